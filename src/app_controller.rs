@@ -1,10 +1,55 @@
+use crate::app_overlays::cross_section_overlay;
+use crate::app_overlays::cut_line_overlay;
+use crate::app_overlays::density_overlay;
+use crate::app_overlays::drc_overlay;
+use crate::app_overlays::fill_overlay;
+use crate::app_overlays::lint_overlay;
+use crate::app_overlays::net_overlay;
+use crate::app_overlays::placeholder_overlay;
+use crate::app_overlays::ruler_overlay;
+use crate::app_overlays::search_overlay;
+use crate::app_overlays::OverlayShape;
+use crate::app_overlays::PixelAxes;
+use crate::app_overlays::LINT_MARKER_SIZE;
 use crate::app_shaders::FRAGMENT_SHADER;
+use crate::app_shaders::INSTANCED_VERTEX_SHADER;
 use crate::app_shaders::VERTEX_SHADER;
+use crate::core::split_derived_layer_definition;
+use crate::core::transform_bounds;
+use crate::core::CellDefId;
+use crate::core::CellId;
+use crate::core::CellLint;
+use crate::core::Connectivity;
+use crate::core::CrossSection;
+use crate::core::DensityMap;
+use crate::core::DensityOptions;
+use crate::core::DrcReport;
+use crate::core::DrcRule;
+use crate::core::DrcViolation;
+use crate::core::Edit;
+use crate::core::EditChange;
+use crate::core::EditOutcome;
+use crate::core::FillOptions;
+use crate::core::FillResult;
+use crate::core::InstanceStep;
 use crate::core::Layer;
+use crate::core::LayerStack;
+use crate::core::LayoutPlacement;
+use crate::core::LintKind;
+use crate::core::LintOptions;
+use crate::core::Net;
+use crate::core::Netlist;
 use crate::core::PickResult;
+use crate::core::Placeholder;
+use crate::core::Ruler;
+use crate::core::SearchMatch;
+use crate::core::SearchPattern;
+use crate::core::ShapeInfo;
+use crate::graphics::BoundingBox;
 use crate::graphics::Camera;
 use crate::graphics::Geometry;
 use crate::graphics::Material;
+use crate::graphics::MaterialId;
 use crate::graphics::Mesh;
 use crate::graphics::MeshId;
 use crate::graphics::Renderer;
@@ -12,11 +57,123 @@ use crate::graphics::Scene;
 use crate::graphics::Viewport;
 use crate::Project;
 
+use anyhow::anyhow;
+use anyhow::Result;
+use geo::AffineTransform;
+use geo::Coord;
+use geo::Rect;
 use geo::TriangulateEarcut;
+use indexmap::IndexMap;
 use nalgebra::Point3;
 use nalgebra::Vector4;
+use rstar::primitives::GeomWithData;
+use rstar::RTree;
+use rstar::AABB;
+use std::collections::HashMap;
 
 type Point = nalgebra::Point3<f32>;
+type Polygon = geo::Polygon<f64>;
+
+const DENSITY_OVERLAY: &str = "density";
+const DRC_OVERLAY: &str = "drc";
+const FILL_OVERLAY: &str = "fill";
+const NET_OVERLAY: &str = "net";
+const RULER_OVERLAY: &str = "rulers";
+const LINT_OVERLAY: &str = "lint";
+const SECTION_OVERLAY: &str = "cross-section";
+const SEARCH_OVERLAY: &str = "search";
+const PLACEHOLDER_OVERLAY: &str = "placeholders";
+
+/// Issues beyond this many are not marked in the viewer.
+const MAX_LINT_MARKERS: usize = 10000;
+
+/// Searches stop after this many matches.
+pub const MAX_SEARCH_MATCHES: usize = 10000;
+
+/// Instances with more shapes than this are highlighted by their bounds.
+const MAX_HIGHLIGHT_POLYGONS: usize = 20000;
+
+/// Distance in pixels within which ruler endpoints snap to geometry.
+const SNAP_DISTANCE: f64 = 8.0;
+
+/// Colors multiplying the layers of overlaid layouts, in the order the
+/// layouts are added.
+pub const LAYOUT_TINTS: [[f32; 3]; 4] = [
+    [1.0, 0.55, 0.55],
+    [0.55, 1.0, 0.55],
+    [0.55, 0.7, 1.0],
+    [1.0, 0.9, 0.45],
+];
+
+/// Distance in pixels that the arrow keys move shapes and instances by,
+/// rounded to whole database units.
+const NUDGE_PIXELS: f64 = 10.0;
+
+/// Named set of shapes drawn on top of the layout, along with the meshes
+/// currently representing them in the scene.
+struct Overlay {
+    shapes: Vec<OverlayShape>,
+    meshes: Vec<MeshId>,
+}
+
+/// Result of a design rule check, along with the violation being inspected.
+struct DrcState {
+    report: DrcReport,
+    current: Option<usize>,
+}
+
+/// What clicking on the layout does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PointerMode {
+    Pan,
+    Ruler,
+    Cut,
+}
+
+/// Cut line of a cross-section. While the line is pending, its end follows
+/// the cursor.
+struct CutState {
+    line: Ruler,
+    pending: bool,
+    section: Option<CrossSection>,
+}
+
+/// Lint results along with the top level locations of their markers.
+struct LintState {
+    options: LintOptions,
+    results: Vec<CellLint>,
+    locations: Vec<(LintKind, Coord<f64>)>,
+    /// Index of the locations, so that only markers in view are drawn.
+    location_tree: RTree<GeomWithData<[f64; 2], usize>>,
+}
+
+/// Matches of the last search, along with the one that was stepped to.
+struct SearchState {
+    pattern: SearchPattern,
+    matches: Vec<SearchMatch>,
+    current: Option<usize>,
+}
+
+/// Nets extracted from the project, along with the highlighted net.
+struct NetState {
+    connectivity: Connectivity,
+    netlist: Netlist,
+    highlighted: Option<usize>,
+}
+
+/// Layout drawn over the main project, e.g. to compare two versions of a
+/// chip or to assemble a die and its package. Only the main project can be
+/// picked, edited and analyzed.
+pub struct OverlaidLayout {
+    pub name: String,
+    pub project: Project,
+    /// Position relative to the main project.
+    pub placement: LayoutPlacement,
+    /// Multiplies the color of every layer, to tell the layouts apart.
+    pub tint: Vector4<f32>,
+    pub visible: bool,
+    meshes: Option<LayerMeshes>,
+}
 
 /// Encapsulates high-level application logic common to all platforms.
 pub struct AppController {
@@ -24,13 +181,41 @@ pub struct AppController {
     renderer: Renderer,
     camera: Camera,
     scene: Scene,
+    layer_meshes: Option<LayerMeshes>,
+    layouts: Vec<OverlaidLayout>,
     is_dragging: bool,
     last_mouse_pos: Option<(u32, u32)>,
     zoom_speed: f32,
     needs_render: bool,
     project: Option<Project>,
     hovered_cell: Option<PickResult>,
+    /// Placeholder under the cursor, when there is no shape under it.
+    hovered_placeholder: Option<Placeholder>,
+    /// Last cursor position, where the hover is picked again after edits.
+    hover_position: Option<(u32, u32)>,
+    /// Outlines every shape of the hovered instance rather than one shape.
+    highlight_instance: bool,
     outline_mesh: MeshId,
+    overlays: IndexMap<String, Overlay>,
+    overlay_material: Option<MaterialId>,
+    density: Option<(i16, DensityOptions)>,
+    /// Name of the filled cell, which is looked up again on reload, and the
+    /// fill settings.
+    fill: Option<(String, FillOptions)>,
+    drc: Option<DrcState>,
+    nets: Option<NetState>,
+    lint: Option<LintState>,
+    search: Option<SearchState>,
+    pointer_mode: PointerMode,
+    rulers: Vec<Ruler>,
+    /// Ruler being placed, whose end follows the cursor.
+    pending_ruler: Option<Ruler>,
+    layer_stack: Option<LayerStack>,
+    cut: Option<CutState>,
+    section_panel: bool,
+    /// Contexts that were descended from, by cell name so that they survive
+    /// a reload, along with the camera to restore on ascending.
+    context_stack: Vec<(Option<String>, Camera)>,
 }
 
 impl AppController {
@@ -47,13 +232,33 @@ impl AppController {
             renderer,
             camera,
             scene,
+            layer_meshes: None,
+            layouts: Vec::new(),
             is_dragging: false,
             last_mouse_pos: None,
             zoom_speed: 0.05,
             needs_render: true,
             project: None,
             hovered_cell: None,
+            hovered_placeholder: None,
+            hover_position: None,
+            highlight_instance: false,
             outline_mesh: MeshId(0),
+            overlays: IndexMap::new(),
+            overlay_material: None,
+            density: None,
+            fill: None,
+            drc: None,
+            nets: None,
+            lint: None,
+            search: None,
+            pointer_mode: PointerMode::Pan,
+            rulers: Vec::new(),
+            pending_ruler: None,
+            layer_stack: None,
+            cut: None,
+            section_panel: true,
+            context_stack: Vec::new(),
         }
     }
 
@@ -64,15 +269,26 @@ impl AppController {
         log::info!("Number of paths: {}", stats.path_count);
         log::info!("Highest layer: {}", project.highest_layer());
 
-        let mut alpha = 0.6; // looks ok for 4004 & 6502
-        if project.layers().len() > 10 {
-            alpha = 0.05;
-        }
-        for layer in project.layers_mut() {
-            layer.color.w = alpha;
+        apply_default_alpha(&mut project);
+
+        self.overlays.clear();
+        self.density = None;
+        self.fill = None;
+        self.drc = None;
+        self.nets = None;
+        self.lint = None;
+        self.search = None;
+        self.rulers.clear();
+        self.pending_ruler = None;
+        self.cut = None;
+        self.context_stack.clear();
+        for layout in self.layouts.drain(..) {
+            if let Some(meshes) = layout.meshes {
+                meshes.destroy(&mut self.scene, self.renderer.gl());
+            }
         }
 
-        populate_scene(project.layers(), &mut self.scene);
+        self.layer_meshes = Some(populate_scene(&project, &mut self.scene));
 
         self.create_outline_mesh();
 
@@ -80,21 +296,1227 @@ impl AppController {
         self.camera.fit_to_bounds(self.window_size, bounds);
 
         self.project = Some(project);
+        self.update_placeholder_overlay();
+
+        self.render();
+    }
+
+    /// Swaps in a freshly parsed project, e.g. after the file changed on disk.
+    /// Unlike [Self::set_project], this leaves the camera alone and carries
+    /// over layer visibility and colors from the previous project.
+    pub fn reload_project(&mut self, mut project: Project) {
+        apply_default_alpha(&mut project);
+
+        if let Some(old_project) = self.project.take() {
+            for derived in old_project.derived_layers() {
+                if let Err(err) = project.add_derived_layer(&derived.name, &derived.expression) {
+                    log::warn!("Dropping derived layer {}: {}", derived.name, err);
+                }
+            }
+            project.copy_layer_styles(old_project.layers());
+            if old_project.is_merged() {
+                project.merge_layers();
+            }
+            if old_project.display_depth().is_some() {
+                project.set_display_depth(old_project.display_depth());
+            }
+            if let Some(context) = old_project.context() {
+                let name = old_project.struct_name(context);
+                match project.find_cell_def(name) {
+                    Some(cell_def_id) => project.set_context(Some(cell_def_id)),
+                    None => log::warn!("Cell '{}' no longer exists, showing the top cells", name),
+                }
+            }
+        }
+
+        self.project = Some(project);
+        self.rebuild_scene();
+        self.refresh_analyses();
+    }
+
+    /// Recomputes the analyses that are shown, after the geometry changed.
+    fn refresh_analyses(&mut self) {
+        if let Some((layer, options)) = self.density.take() {
+            if let Err(err) = self.show_density(layer, options) {
+                log::warn!("Dropping density map: {}", err);
+            }
+        }
+        if let Some((name, options)) = self.fill.take() {
+            let top = self.project.as_ref().and_then(|p| p.find_cell_def(&name));
+            let result = match top {
+                Some(top) => self.show_fill(top, options).map(|_| ()),
+                None => Err(anyhow!("Cell '{}' not found", name)),
+            };
+            if let Err(err) = result {
+                log::warn!("Dropping fill: {}", err);
+            }
+        }
+        if let Some(drc) = self.drc.take() {
+            self.show_drc(drc.report.rules);
+        }
+        if let Some(nets) = self.nets.take() {
+            self.set_connectivity(nets.connectivity);
+        }
+        if let Some(lint) = self.lint.take() {
+            self.show_lint(lint.options);
+        }
+        if let Some(search) = self.search.take() {
+            self.search(search.pattern);
+        }
+        if let Some(cut) = self.cut.take() {
+            if !cut.pending {
+                self.set_cut_line(cut.line.start, cut.line.end);
+            }
+        }
+    }
+
+    /// Shows or hides an instance, see [Project::set_instance_visible].
+    pub fn set_instance_visible(&mut self, cell_id: CellId, visible: bool) {
+        let Some(project) = self.project.as_mut() else {
+            return;
+        };
+        project.set_instance_visible(cell_id, visible);
+        self.refresh_cell_visibility();
+    }
+
+    /// Shows or hides every instance of a cell.
+    pub fn set_cell_def_visible(&mut self, cell_def_id: CellDefId, visible: bool) {
+        let Some(project) = self.project.as_mut() else {
+            return;
+        };
+        project.set_cell_def_visible(cell_def_id, visible);
+        self.refresh_cell_visibility();
+    }
+
+    fn refresh_cell_visibility(&mut self) {
+        if let Some(project) = self.project.as_mut() {
+            project.refresh_layers();
+        }
+        self.rebuild_scene();
+        self.refresh_analyses();
+    }
+
+    /// Shows a cell as if it were the top cell and fits the view to it. The
+    /// previous context and camera are restored by [Self::ascend].
+    pub fn descend(&mut self, cell_def_id: CellDefId) {
+        let Some(project) = self.project.as_mut() else {
+            return;
+        };
+        if project.context() == Some(cell_def_id) {
+            return;
+        }
+        let previous = project
+            .context()
+            .map(|c| project.struct_name(c).to_string());
+        self.context_stack.push((previous, self.camera.clone()));
+        project.set_context(Some(cell_def_id));
+        self.apply_context();
+        let bounds = self.project.as_ref().unwrap().bounds();
+        self.camera.fit_to_bounds(self.window_size, bounds);
+        self.refresh_screen_overlays();
+        self.render();
+    }
+
+    /// Descends into the cell of the instance under the cursor. Returns
+    /// whether there was one.
+    pub fn descend_hovered(&mut self) -> bool {
+        let cell_def_id = match (&self.project, &self.hovered_cell) {
+            (Some(project), Some(hovered)) => project.picked_cell_def(hovered),
+            _ => self.hovered_placeholder.as_ref().map(|p| p.cell_def_id),
+        };
+        match cell_def_id {
+            Some(cell_def_id) => {
+                self.descend(cell_def_id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns to the context that was shown before the last
+    /// [Self::descend], with its camera. Returns false at the top.
+    pub fn ascend(&mut self) -> bool {
+        let Some(project) = self.project.as_mut() else {
+            return false;
+        };
+        let Some((previous, camera)) = self.context_stack.pop() else {
+            return false;
+        };
+        let context = previous.and_then(|name| project.find_cell_def(&name));
+        project.set_context(context);
+        self.apply_context();
+        self.camera = camera;
+        self.refresh_screen_overlays();
+        self.render();
+        true
+    }
+
+    /// Returns the cell shown as the top cell, if not the whole library.
+    pub fn context(&self) -> Option<CellDefId> {
+        self.project.as_ref()?.context()
+    }
+
+    /// Draws instances nested deeper than the given depth as labelled boxes,
+    /// see [Project::set_display_depth].
+    pub fn set_display_depth(&mut self, depth: Option<usize>) {
+        let Some(project) = self.project.as_mut() else {
+            return;
+        };
+        project.set_display_depth(depth);
+        self.rebuild_scene();
+        self.refresh_analyses();
+    }
+
+    pub fn display_depth(&self) -> Option<usize> {
+        self.project.as_ref()?.display_depth()
+    }
+
+    /// Shows more or fewer levels of the hierarchy. Going past the deepest
+    /// level shows everything, and going down from everything starts at
+    /// the level above the deepest one.
+    pub fn step_display_depth(&mut self, step: isize) -> Option<usize> {
+        let project = self.project.as_ref()?;
+        let deepest = project.hierarchy_depth();
+        let current = project.display_depth().unwrap_or(deepest);
+        let depth = current.saturating_add_signed(step);
+        let depth = (depth < deepest).then_some(depth);
+        self.set_display_depth(depth);
+        depth
+    }
+
+    fn apply_context(&mut self) {
+        self.rebuild_scene();
+        self.refresh_analyses();
+    }
+
+    /// Fits the view to an instance given by its path from a top cell, see
+    /// [Project::instance_path_bounds].
+    pub fn zoom_to_instance(&mut self, path: &[InstanceStep]) {
+        let Some(bounds) = self
+            .project
+            .as_ref()
+            .and_then(|project| project.instance_path_bounds(path))
+        else {
+            return;
+        };
+        let margin = bounds.width().max(bounds.height()) * 0.05;
+        self.camera
+            .fit_to_bounds(self.window_size, bounds.inflate(margin.max(1.0)));
+        self.refresh_screen_overlays();
+        self.render();
+    }
+
+    /// Checks the geometry of every cell and marks the issues at each
+    /// placement of the affected cells. The check is repeated when the
+    /// project is reloaded.
+    pub fn show_lint(&mut self, options: LintOptions) -> Option<&[CellLint]> {
+        let project = self.project.as_ref()?;
+        let results = project.lint(&options);
+        let locations = project.lint_locations(&results, MAX_LINT_MARKERS);
+        let location_tree = RTree::bulk_load(
+            locations
+                .iter()
+                .enumerate()
+                .map(|(index, (_, location))| GeomWithData::new([location.x, location.y], index))
+                .collect(),
+        );
+        self.lint = Some(LintState {
+            options,
+            results,
+            locations,
+            location_tree,
+        });
+        self.update_lint_overlay();
+        self.lint.as_ref().map(|lint| lint.results.as_slice())
+    }
+
+    pub fn hide_lint(&mut self) {
+        self.clear_overlay(LINT_OVERLAY);
+        self.lint = None;
+    }
+
+    pub fn lint_shown(&self) -> bool {
+        self.lint.is_some()
+    }
+
+    fn update_lint_overlay(&mut self) {
+        let Some(lint) = &self.lint else {
+            return;
+        };
+        // Markers keep their size on screen, so those centered just outside
+        // of the view still show.
+        let axes = self.pixel_axes();
+        let view = self
+            .view_bounds()
+            .inflate(axes.pixel_size() * LINT_MARKER_SIZE);
+        let envelope = AABB::from_corners([view.min_x, view.min_y], [view.max_x, view.max_y]);
+        let locations: Vec<(LintKind, Coord<f64>)> = lint
+            .location_tree
+            .locate_in_envelope(&envelope)
+            .map(|item| lint.locations[item.data])
+            .collect();
+        let shapes = lint_overlay(&locations, &axes);
+        self.set_overlay(LINT_OVERLAY, shapes);
+    }
+
+    /// Finds the instances and labels matching a pattern and frames them.
+    /// The search is repeated when the project is reloaded or the top cell
+    /// changes.
+    pub fn search(&mut self, pattern: SearchPattern) -> Option<&[SearchMatch]> {
+        let project = self.project.as_ref()?;
+        let matches = project.search(&pattern, MAX_SEARCH_MATCHES);
+        self.search = Some(SearchState {
+            pattern,
+            matches,
+            current: None,
+        });
+        self.update_search_overlay();
+        self.render();
+        self.search_matches()
+    }
 
+    pub fn search_matches(&self) -> Option<&[SearchMatch]> {
+        self.search.as_ref().map(|search| search.matches.as_slice())
+    }
+
+    /// Index of the match that was stepped to last.
+    pub fn current_search_match(&self) -> Option<usize> {
+        self.search.as_ref()?.current
+    }
+
+    pub fn clear_search(&mut self) {
+        if self.search.take().is_some() {
+            self.clear_overlay(SEARCH_OVERLAY);
+            self.render();
+        }
+    }
+
+    /// Moves to the next or previous search match, wrapping around, and
+    /// fits the view to it.
+    pub fn step_search(&mut self, step: isize) -> Option<(usize, &SearchMatch)> {
+        let search = self.search.as_ref()?;
+        let count = search.matches.len() as isize;
+        if count == 0 {
+            return None;
+        }
+        let index = match search.current {
+            Some(current) => (current as isize + step).rem_euclid(count),
+            None if step < 0 => count - 1,
+            None => 0,
+        } as usize;
+        self.show_search_match(index)
+    }
+
+    /// Fits the view to a search match and highlights it.
+    pub fn show_search_match(&mut self, index: usize) -> Option<(usize, &SearchMatch)> {
+        let search = self.search.as_mut()?;
+        let bounds = search.matches.get(index)?.bounds;
+        search.current = Some(index);
+
+        // Labels are points, so they are shown with some of the layout
+        // around them.
+        let layout = self.project.as_ref()?.bounds();
+        let margin = (bounds.width().max(bounds.height()) * 0.05)
+            .max(layout.width().max(layout.height()) * 0.01)
+            .max(1.0);
+        self.camera
+            .fit_to_bounds(self.window_size, bounds.inflate(margin));
+        self.refresh_screen_overlays();
+        self.render();
+
+        let search = self.search.as_ref()?;
+        Some((index, &search.matches[index]))
+    }
+
+    fn update_search_overlay(&mut self) {
+        let Some(search) = &self.search else {
+            return;
+        };
+        let shapes = search_overlay(&search.matches, search.current, &self.pixel_axes());
+        self.set_overlay(SEARCH_OVERLAY, shapes);
+    }
+
+    /// Extracts nets from the project so that they can be highlighted. The
+    /// nets are extracted again when the project is reloaded.
+    pub fn set_connectivity(&mut self, connectivity: Connectivity) -> Option<&Netlist> {
+        let project = self.project.as_mut()?;
+        project.flatten_layers();
+        let netlist = project.extract_nets(&connectivity);
+        self.clear_overlay(NET_OVERLAY);
+        self.nets = Some(NetState {
+            connectivity,
+            netlist,
+            highlighted: None,
+        });
+        self.nets.as_ref().map(|nets| &nets.netlist)
+    }
+
+    /// Highlights every shape connected to the shape under the cursor, or
+    /// clears the highlight if there is no conducting shape there. Returns
+    /// the net along with its index.
+    pub fn highlight_hovered_net(&mut self) -> Option<(usize, &Net)> {
+        let hovered = self.hovered_cell.as_ref();
+        let net_index = self.nets.as_ref().and_then(|nets| {
+            let hovered = hovered?;
+            let polygon = self.project.as_ref()?.flat_polygon_index(hovered)?;
+            nets.netlist.net_of((hovered.layer, polygon))
+        });
+        match net_index {
+            Some(index) => self.highlight_net(index),
+            None => {
+                self.clear_net_highlight();
+                None
+            }
+        }
+    }
+
+    pub fn highlight_net(&mut self, index: usize) -> Option<(usize, &Net)> {
+        let project = self.project.as_ref()?;
+        let nets = self.nets.as_mut()?;
+        let net = nets.netlist.nets.get(index)?;
+        nets.highlighted = Some(index);
+        let shapes = net_overlay(project, net);
+        self.set_overlay(NET_OVERLAY, shapes);
+
+        let nets = self.nets.as_ref()?;
+        Some((index, &nets.netlist.nets[index]))
+    }
+
+    pub fn clear_net_highlight(&mut self) {
+        if let Some(nets) = self.nets.as_mut() {
+            nets.highlighted = None;
+        }
+        self.clear_overlay(NET_OVERLAY);
+    }
+
+    /// Checks the project against the given rules and draws markers for all
+    /// violations. The check is repeated when the project is reloaded.
+    pub fn show_drc(&mut self, rules: Vec<DrcRule>) -> Option<&DrcReport> {
+        let project = self.project.as_mut()?;
+        project.flatten_layers();
+        let report = project.check_drc(&rules);
+        self.drc = Some(DrcState {
+            report,
+            current: None,
+        });
+        self.update_drc_overlay();
+        self.drc.as_ref().map(|drc| &drc.report)
+    }
+
+    pub fn hide_drc(&mut self) {
+        self.clear_overlay(DRC_OVERLAY);
+        self.drc = None;
+        self.release_flat_layers();
+    }
+
+    /// Moves to the next (or with a negative step, previous) violation,
+    /// highlighting it and zooming in on it. Returns the violation along with
+    /// its index.
+    pub fn step_violation(&mut self, step: isize) -> Option<(usize, &DrcViolation)> {
+        let drc = self.drc.as_mut()?;
+        let count = drc.report.violations.len() as isize;
+        if count == 0 {
+            return None;
+        }
+        let index = match drc.current {
+            Some(current) => (current as isize + step).rem_euclid(count),
+            None if step < 0 => count - 1,
+            None => 0,
+        } as usize;
+        drc.current = Some(index);
+
+        let bounds = drc.report.violations[index].bounds;
+        self.update_drc_overlay();
+        self.zoom_to(&bounds);
+
+        let drc = self.drc.as_ref()?;
+        Some((index, &drc.report.violations[index]))
+    }
+
+    /// Frames the given bounds with some margin around them.
+    pub fn zoom_to(&mut self, bounds: &BoundingBox) {
+        let margin = (bounds.width().max(bounds.height()) * 2.0).max(1.0);
+        self.camera
+            .fit_to_bounds(self.window_size, bounds.inflate(margin));
+        self.refresh_screen_overlays();
+        self.render();
+    }
+
+    fn update_drc_overlay(&mut self) {
+        let Some(drc) = &self.drc else {
+            return;
+        };
+        let shapes = drc_overlay(&drc.report, drc.current);
+        self.set_overlay(DRC_OVERLAY, shapes);
+    }
+
+    /// Computes the density of a layer and draws it as a heatmap, which is
+    /// recomputed when the project is reloaded.
+    pub fn show_density(&mut self, layer: i16, options: DensityOptions) -> Result<DensityMap> {
+        let Some(project) = self.project.as_mut() else {
+            return Err(anyhow!("No layout loaded"));
+        };
+        project.flatten_layers();
+        let map = project.compute_density(layer, &options)?;
+        self.set_overlay(DENSITY_OVERLAY, density_overlay(&map));
+        self.density = Some((layer, options));
+        Ok(map)
+    }
+
+    pub fn hide_density(&mut self) {
+        self.clear_overlay(DENSITY_OVERLAY);
+        self.density = None;
+        self.release_flat_layers();
+    }
+
+    /// Returns the layer whose density is currently shown.
+    pub fn density_layer(&self) -> Option<i16> {
+        self.density.as_ref().map(|(layer, _)| *layer)
+    }
+
+    /// Generates dummy fill for a cell and draws it over the layout. The fill
+    /// is regenerated when the project is reloaded.
+    pub fn show_fill(&mut self, top: CellDefId, options: FillOptions) -> Result<FillResult> {
+        let Some(project) = self.project.as_ref() else {
+            return Err(anyhow!("No layout loaded"));
+        };
+        let fill = project.generate_fill(top, &options)?;
+        let shapes = fill_overlay(project, &fill);
+        let name = project.struct_name(top).to_string();
+        self.set_overlay(FILL_OVERLAY, shapes);
+        self.fill = Some((name, options));
+        Ok(fill)
+    }
+
+    pub fn hide_fill(&mut self) {
+        self.clear_overlay(FILL_OVERLAY);
+        self.fill = None;
+    }
+
+    /// Adds a layer defined as `NAME=EXPRESSION`, see [LayerExpr].
+    ///
+    /// [LayerExpr]: crate::core::LayerExpr
+    pub fn add_derived_layer(&mut self, definition: &str) -> Result<()> {
+        let Some(project) = self.project.as_mut() else {
+            return Err(anyhow!("No layout loaded"));
+        };
+        let (name, expression) = split_derived_layer_definition(definition)?;
+        project.add_derived_layer(name, expression)?;
+        self.rebuild_scene();
+        Ok(())
+    }
+
+    /// Switches between drawing the merged and the original polygons of each
+    /// layer. Merging is computed on demand since it can take a while for
+    /// large layouts.
+    pub fn set_merge_layers(&mut self, merge: bool) {
+        let Some(project) = self.project.as_mut() else {
+            return;
+        };
+        if merge {
+            project.merge_layers();
+        } else {
+            project.unmerge_layers();
+        }
+        self.release_flat_layers();
+        self.rebuild_scene();
+    }
+
+    /// Drops the flattened layers once none of the analyses that read them
+    /// is shown, so that showing and hiding instances or editing no longer
+    /// flattens the layout, see [Project::release_flat_layers].
+    fn release_flat_layers(&mut self) {
+        let in_use = self.density.is_some()
+            || self.drc.is_some()
+            || self.nets.is_some()
+            || self.cut.is_some();
+        if let (Some(project), false) = (self.project.as_mut(), in_use) {
+            project.release_flat_layers();
+        }
+    }
+
+    /// Applies the visibility and color of a layer to its meshes, after they
+    /// were changed through [Project::layers_mut].
+    pub fn update_layer_style(&mut self, index: usize) {
+        let Some(layer) = self.project.as_ref().and_then(|p| p.layers().get(index)) else {
+            return;
+        };
+        if let Some(meshes) = &self.layer_meshes {
+            meshes.apply_style(&mut self.scene, layer);
+        }
         self.render();
     }
 
+    /// Describes the shape under the cursor, see [Project::inspect].
+    pub fn inspect_hovered(&self) -> Option<ShapeInfo> {
+        self.project.as_ref()?.inspect(self.hovered_cell.as_ref()?)
+    }
+
+    /// Applies an edit to the project and updates the affected meshes, see
+    /// [Project::apply_edit].
+    pub fn apply_edit(&mut self, edit: Edit) -> Result<EditOutcome> {
+        let Some(project) = self.project.as_mut() else {
+            return Err(anyhow!("No layout loaded"));
+        };
+        let outcome = project.apply_edit(edit)?;
+        self.update_after_edit(outcome.change);
+        Ok(outcome)
+    }
+
+    /// Reverts the most recent edit. Returns None if there is nothing to
+    /// undo.
+    pub fn undo(&mut self) -> Result<Option<EditOutcome>> {
+        let Some(project) = self.project.as_mut() else {
+            return Ok(None);
+        };
+        let outcome = project.undo()?;
+        if let Some(outcome) = &outcome {
+            self.update_after_edit(outcome.change);
+        }
+        Ok(outcome)
+    }
+
+    /// Applies the most recently undone edit again. Returns None if there is
+    /// nothing to redo.
+    pub fn redo(&mut self) -> Result<Option<EditOutcome>> {
+        let Some(project) = self.project.as_mut() else {
+            return Ok(None);
+        };
+        let outcome = project.redo()?;
+        if let Some(outcome) = &outcome {
+            self.update_after_edit(outcome.change);
+        }
+        Ok(outcome)
+    }
+
+    /// Deletes the shape under the cursor from its cell.
+    pub fn delete_hovered_shape(&mut self) -> Result<EditOutcome> {
+        let pick = self.hovered_shape()?;
+        self.apply_edit(Edit::DeleteShape {
+            cell_def_id: pick.cell_def_id,
+            shape: pick.shape,
+        })
+    }
+
+    /// Moves the shape under the cursor, or the innermost instance it
+    /// belongs to, by a few pixels in the given screen direction, where y
+    /// points down.
+    pub fn nudge_hovered(&mut self, dx: i32, dy: i32, instance: bool) -> Result<EditOutcome> {
+        let pick = self.hovered_shape()?;
+        let axes = self.pixel_axes();
+        let step = (NUDGE_PIXELS * axes.pixel_size()).round().max(1.0);
+        let direction = Coord {
+            x: axes.right.x * dx as f64 + axes.down.x * dy as f64,
+            y: axes.right.y * dx as f64 + axes.down.y * dy as f64,
+        };
+        let length = direction.x.hypot(direction.y);
+        let world_delta = Coord {
+            x: direction.x / length * step,
+            y: direction.y / length * step,
+        };
+        // Convert the delta into the coordinates of the cell that is edited:
+        // the owning cell for shapes, or the parent cell for instances.
+        let transform = match instance {
+            true => {
+                let cell_id = self.hovered_instance(&pick)?;
+                let project = self.project.as_ref().unwrap();
+                let element = pick.path.last().and_then(|step| step.element);
+                let placement = project.cell(cell_id).element_placement(element);
+                placement
+                    .inverse()
+                    .map(|inverse| inverse.compose(&pick.transform))
+            }
+            false => Some(pick.transform),
+        };
+        let inverse = transform
+            .and_then(|transform| transform.inverse())
+            .ok_or_else(|| anyhow!("The hovered instance cannot be moved"))?;
+        let origin = inverse.apply(Coord { x: 0.0, y: 0.0 });
+        let moved = inverse.apply(world_delta);
+        let (dx, dy) = (
+            (moved.x - origin.x).round() as i32,
+            (moved.y - origin.y).round() as i32,
+        );
+        let edit = match instance {
+            true => Edit::MoveInstance {
+                cell_id: self.hovered_instance(&pick)?,
+                dx,
+                dy,
+            },
+            false => Edit::MoveShape {
+                cell_def_id: pick.cell_def_id,
+                shape: pick.shape,
+                dx,
+                dy,
+            },
+        };
+        self.apply_edit(edit)
+    }
+
+    /// Rotates the instance under the cursor counterclockwise by 90 degrees.
+    pub fn rotate_hovered_instance(&mut self) -> Result<EditOutcome> {
+        let pick = self.hovered_shape()?;
+        let cell_id = self.hovered_instance(&pick)?;
+        self.apply_edit(Edit::RotateInstance {
+            cell_id,
+            quarter_turns: 1,
+        })
+    }
+
+    /// Mirrors the instance under the cursor about its x axis.
+    pub fn mirror_hovered_instance(&mut self) -> Result<EditOutcome> {
+        let pick = self.hovered_shape()?;
+        let cell_id = self.hovered_instance(&pick)?;
+        self.apply_edit(Edit::MirrorInstance { cell_id })
+    }
+
+    /// Deletes the innermost instance under the cursor from its parent.
+    pub fn delete_hovered_instance(&mut self) -> Result<EditOutcome> {
+        let pick = self.hovered_shape()?;
+        let cell_id = self.hovered_instance(&pick)?;
+        self.apply_edit(Edit::DeleteInstance { cell_id })
+    }
+
+    /// Moves the shape under the cursor to the next or previous layer.
+    pub fn step_hovered_layer(&mut self, step: i16) -> Result<EditOutcome> {
+        let pick = self.hovered_shape()?;
+        self.apply_edit(Edit::SetShapeLayer {
+            cell_def_id: pick.cell_def_id,
+            shape: pick.shape,
+            layer: pick.layer.saturating_add(step),
+        })
+    }
+
+    fn hovered_shape(&self) -> Result<PickResult> {
+        self.hovered_cell
+            .clone()
+            .ok_or_else(|| anyhow!("Nothing under the cursor"))
+    }
+
+    /// Instance that a picked shape belongs to. Shapes of the top cells do
+    /// not belong to an instance.
+    fn hovered_instance(&self, pick: &PickResult) -> Result<CellId> {
+        let project = self
+            .project
+            .as_ref()
+            .ok_or_else(|| anyhow!("No layout loaded"))?;
+        project
+            .picked_cell_def(pick)
+            .map(|_| pick.cell_id)
+            .ok_or_else(|| anyhow!("The shape under the cursor belongs to a top cell"))
+    }
+
+    /// Updates the meshes affected by an edit, along with the hover and the
+    /// analyses that are shown. Merged and derived layers are computed from
+    /// the flattened geometry, so they require rebuilding the scene.
+    fn update_after_edit(&mut self, change: EditChange) {
+        let Some(project) = &self.project else {
+            return;
+        };
+        if project.layers().iter().any(is_flat) {
+            self.rebuild_scene();
+        } else if let Some(meshes) = self.layer_meshes.as_mut() {
+            let gl = self.renderer.gl();
+            match change {
+                EditChange::Shapes(cell_def_id) => {
+                    meshes.update_cell_shapes(project, &mut self.scene, gl, cell_def_id)
+                }
+                EditChange::Placements => meshes.update_placements(project, &mut self.scene, gl),
+            }
+            self.update_placeholder_overlay();
+        }
+
+        self.hovered_cell = None;
+        self.hovered_placeholder = None;
+        self.get_outline_mesh().visible = false;
+        if let Some((x, y)) = self.hover_position {
+            self.update_hover(x, y);
+        }
+
+        self.refresh_analyses();
+        self.render();
+    }
+
+    /// Draws another layout over the main project, tinted with the next
+    /// color of [LAYOUT_TINTS]. Returns its index in [Self::layouts].
+    pub fn add_layout(&mut self, name: &str, mut project: Project) -> usize {
+        apply_default_alpha(&mut project);
+        let index = self.layouts.len();
+        let [r, g, b] = LAYOUT_TINTS[index % LAYOUT_TINTS.len()];
+        let mut layout = OverlaidLayout {
+            name: name.to_string(),
+            project,
+            placement: LayoutPlacement::default(),
+            tint: Vector4::new(r, g, b, 1.0),
+            visible: true,
+            meshes: None,
+        };
+        layout.meshes = Some(populate_layout(&layout, &mut self.scene));
+        self.layouts.push(layout);
+        self.render();
+        index
+    }
+
+    pub fn remove_layout(&mut self, index: usize) {
+        if index >= self.layouts.len() {
+            return;
+        }
+        if let Some(meshes) = self.layouts.remove(index).meshes {
+            meshes.destroy(&mut self.scene, self.renderer.gl());
+        }
+        self.render();
+    }
+
+    /// Layouts drawn over the main project, in the order they were added.
+    pub fn layouts(&self) -> &[OverlaidLayout] {
+        &self.layouts
+    }
+
+    /// Gives access to a layout for changing its style, placement or
+    /// layers, which takes effect with [Self::update_layout_style] or
+    /// [Self::update_layout_placement].
+    pub fn layout_mut(&mut self, index: usize) -> Option<&mut OverlaidLayout> {
+        self.layouts.get_mut(index)
+    }
+
+    /// Applies the visibility and tint of a layout, and the visibility and
+    /// color of each of its layers, to its meshes.
+    pub fn update_layout_style(&mut self, index: usize) {
+        let Some(layout) = self.layouts.get_mut(index) else {
+            return;
+        };
+        if let Some(meshes) = layout.meshes.as_mut() {
+            meshes.tint = layout.tint;
+            meshes.visible = layout.visible;
+            for layer in layout.project.layers() {
+                meshes.apply_style(&mut self.scene, layer);
+            }
+        }
+        self.render();
+    }
+
+    /// Redraws a layout after its placement or its layers changed.
+    pub fn update_layout_placement(&mut self, index: usize) {
+        let Some(layout) = self.layouts.get_mut(index) else {
+            return;
+        };
+        if let Some(meshes) = layout.meshes.as_mut() {
+            meshes.clear(&mut self.scene, self.renderer.gl());
+            meshes.transform = Some(layout.placement.transform());
+            meshes.tint = layout.tint;
+            meshes.visible = layout.visible;
+            meshes.populate(&layout.project, &mut self.scene);
+        }
+        self.render();
+    }
+
+    /// Bounds of the main project and every visible layout.
+    pub fn combined_bounds(&self) -> BoundingBox {
+        let mut bounds = self
+            .project
+            .as_ref()
+            .map(|project| project.bounds())
+            .unwrap_or_default();
+        for layout in self.layouts.iter().filter(|layout| layout.visible) {
+            let layout_bounds = layout.project.bounds();
+            if !layout_bounds.is_empty() {
+                bounds.encompass(&transform_bounds(
+                    &layout_bounds,
+                    &layout.placement.transform(),
+                ));
+            }
+        }
+        bounds
+    }
+
+    /// Recreates all meshes from the current project, keeping the camera.
+    fn rebuild_scene(&mut self) {
+        self.scene.destroy(self.renderer.gl());
+        self.outline_mesh = MeshId(0);
+        self.overlay_material = None;
+        self.hovered_cell = None;
+        self.hovered_placeholder = None;
+
+        self.layer_meshes = self
+            .project
+            .as_ref()
+            .map(|project| populate_scene(project, &mut self.scene));
+        for layout in &mut self.layouts {
+            layout.meshes = Some(populate_layout(layout, &mut self.scene));
+        }
+
+        self.create_outline_mesh();
+
+        let names: Vec<String> = self.overlays.keys().cloned().collect();
+        for name in names {
+            self.create_overlay_meshes(&name);
+        }
+        self.update_placeholder_overlay();
+
+        self.render();
+    }
+
+    /// Draws the given shapes on top of the layout, replacing any overlay
+    /// previously set under the same name.
+    pub fn set_overlay(&mut self, name: &str, shapes: Vec<OverlayShape>) {
+        self.clear_overlay(name);
+        self.overlays.insert(
+            name.to_string(),
+            Overlay {
+                shapes,
+                meshes: Vec::new(),
+            },
+        );
+        self.create_overlay_meshes(name);
+        self.render();
+    }
+
+    pub fn clear_overlay(&mut self, name: &str) {
+        let Some(overlay) = self.overlays.shift_remove(name) else {
+            return;
+        };
+        for mesh_id in overlay.meshes {
+            self.scene.remove_mesh(self.renderer.gl(), mesh_id);
+        }
+        self.render();
+    }
+
+    pub fn has_overlay(&self, name: &str) -> bool {
+        self.overlays.contains_key(name)
+    }
+
+    fn create_overlay_meshes(&mut self, name: &str) {
+        let material_id = *self.overlay_material.get_or_insert_with(|| {
+            let mut material = Material::new(VERTEX_SHADER, FRAGMENT_SHADER);
+            material.set_blending(true);
+            self.scene.add_material(material)
+        });
+        let Some(overlay) = self.overlays.get_mut(name) else {
+            return;
+        };
+        overlay.meshes.clear();
+        for shape in overlay.shapes.iter().filter(|s| !s.polygons.is_empty()) {
+            let geometry = create_polygons_geometry(&shape.polygons);
+            let geometry_id = self.scene.add_geometry(geometry);
+            let mut mesh = Mesh::new(geometry_id, material_id);
+            mesh.set_vec4("color", shape.color);
+            overlay.meshes.push(self.scene.add_mesh(mesh));
+        }
+    }
+
+    /// In ruler mode, clicks place rulers instead of panning the view. The
+    /// first click starts a ruler and the second one ends it.
+    pub fn set_ruler_mode(&mut self, enabled: bool) {
+        self.set_pointer_mode(if enabled {
+            PointerMode::Ruler
+        } else {
+            PointerMode::Pan
+        });
+    }
+
+    pub fn ruler_mode(&self) -> bool {
+        self.pointer_mode == PointerMode::Ruler
+    }
+
+    /// Whether hovering outlines every shape of the instance that owns the
+    /// shape under the cursor, on all visible layers, instead of the shape.
+    pub fn set_highlight_instance(&mut self, enabled: bool) {
+        self.highlight_instance = enabled;
+        if let Some(pick) = self.hovered_cell.clone() {
+            self.update_outline_mesh(pick);
+            self.needs_render = true;
+        }
+    }
+
+    pub fn highlight_instance(&self) -> bool {
+        self.highlight_instance
+    }
+
+    /// Chain of instances under the cursor from its top cell, e.g.
+    /// `top/core/alu#3/nand2`, see [Project::describe_instance_path].
+    pub fn hovered_path(&self) -> Option<String> {
+        let project = self.project.as_ref()?;
+        let (top, path) = match (&self.hovered_cell, &self.hovered_placeholder) {
+            (Some(pick), _) => (pick.top, pick.path.clone()),
+            (None, Some(placeholder)) => project.node_path(placeholder.node),
+            (None, None) => return None,
+        };
+        Some(project.describe_instance_path(top, &path))
+    }
+
+    /// In cut mode, clicks draw the line along which a cross-section is
+    /// taken. The first click starts the line and the second one ends it.
+    pub fn set_cut_mode(&mut self, enabled: bool) {
+        self.set_pointer_mode(if enabled {
+            PointerMode::Cut
+        } else {
+            PointerMode::Pan
+        });
+    }
+
+    pub fn cut_mode(&self) -> bool {
+        self.pointer_mode == PointerMode::Cut
+    }
+
+    fn set_pointer_mode(&mut self, mode: PointerMode) {
+        self.pointer_mode = mode;
+        if mode != PointerMode::Ruler && self.pending_ruler.take().is_some() {
+            self.update_ruler_overlay();
+        }
+        if mode != PointerMode::Cut && self.cut.as_ref().is_some_and(|cut| cut.pending) {
+            self.clear_cross_section();
+        }
+    }
+
+    /// Sets the layer stack used for cross-sections. Without one, every
+    /// layer with geometry is stacked on top of the previous one.
+    pub fn set_layer_stack(&mut self, stack: LayerStack) {
+        self.layer_stack = Some(stack);
+        if let Some(cut) = self.cut.take() {
+            if !cut.pending {
+                self.set_cut_line(cut.line.start, cut.line.end);
+            }
+        }
+    }
+
+    /// Whether cross-sections are drawn in a panel on top of the layout.
+    /// Platforms that show the section elsewhere can turn this off.
+    pub fn set_section_panel(&mut self, enabled: bool) {
+        self.section_panel = enabled;
+        self.update_cut_overlay();
+    }
+
+    /// Takes a cross-section along the given line.
+    pub fn set_cut_line(&mut self, start: Coord<f64>, end: Coord<f64>) -> Option<&CrossSection> {
+        let project = self.project.as_mut()?;
+        project.flatten_layers();
+        let stack = match &self.layer_stack {
+            Some(stack) => stack.clone(),
+            None => project.default_layer_stack(),
+        };
+        let section = project.cross_section(&stack, start, end);
+        self.cut = Some(CutState {
+            line: Ruler { start, end },
+            pending: false,
+            section: Some(section),
+        });
+        self.update_cut_overlay();
+        self.cross_section()
+    }
+
+    pub fn cross_section(&self) -> Option<&CrossSection> {
+        self.cut.as_ref()?.section.as_ref()
+    }
+
+    pub fn clear_cross_section(&mut self) {
+        self.cut = None;
+        self.release_flat_layers();
+        self.update_cut_overlay();
+    }
+
+    fn update_cut_overlay(&mut self) {
+        let (Some(project), Some(cut)) = (&self.project, &self.cut) else {
+            self.clear_overlay(SECTION_OVERLAY);
+            self.render();
+            return;
+        };
+        let axes = self.pixel_axes();
+        let mut shapes = vec![cut_line_overlay(cut.line.start, cut.line.end, &axes)];
+        if let (Some(section), true) = (&cut.section, self.section_panel) {
+            shapes.extend(cross_section_overlay(
+                section,
+                project.layers(),
+                &axes,
+                self.window_size,
+            ));
+        }
+        self.set_overlay(SECTION_OVERLAY, shapes);
+    }
+
+    pub fn rulers(&self) -> &[Ruler] {
+        &self.rulers
+    }
+
+    pub fn clear_rulers(&mut self) {
+        self.rulers.clear();
+        self.pending_ruler = None;
+        self.update_ruler_overlay();
+    }
+
+    /// Converts a screen position to world space, snapping to nearby
+    /// vertices and edges.
+    fn snapped_point(&self, x: u32, y: u32) -> Coord<f64> {
+        let (world_x, world_y) = self.screen_to_world(x, y);
+        let radius = SNAP_DISTANCE * self.pixel_axes().pixel_size();
+        self.project
+            .as_ref()
+            .and_then(|project| project.snap_point(world_x, world_y, radius))
+            .unwrap_or(Coord {
+                x: world_x,
+                y: world_y,
+            })
+    }
+
+    fn pixel_axes(&self) -> PixelAxes {
+        const SPAN: u32 = 100;
+        let (x0, y0) = self.screen_to_world(0, 0);
+        let (x1, y1) = self.screen_to_world(SPAN, 0);
+        let (x2, y2) = self.screen_to_world(0, SPAN);
+        let span = SPAN as f64;
+        PixelAxes {
+            origin: Coord { x: x0, y: y0 },
+            right: Coord {
+                x: (x1 - x0) / span,
+                y: (y1 - y0) / span,
+            },
+            down: Coord {
+                x: (x2 - x0) / span,
+                y: (y2 - y0) / span,
+            },
+        }
+    }
+
+    /// Rulers, lint markers and cut lines have a fixed size on screen, so
+    /// they need to be rebuilt whenever the zoom level changes.
+    fn refresh_screen_overlays(&mut self) {
+        if !self.rulers.is_empty() || self.pending_ruler.is_some() {
+            self.update_ruler_overlay();
+        }
+        self.update_lint_overlay();
+        self.update_search_overlay();
+        if self.cut.is_some() {
+            self.update_cut_overlay();
+        }
+        self.update_placeholder_overlay();
+    }
+
+    /// Draws the placeholders on screen, which are rebuilt as the view
+    /// changes so that their frames stay thin and labels stay readable.
+    fn update_placeholder_overlay(&mut self) {
+        let Some(project) = &self.project else {
+            return;
+        };
+        if project.placeholders().is_empty() {
+            if self.has_overlay(PLACEHOLDER_OVERLAY) {
+                self.clear_overlay(PLACEHOLDER_OVERLAY);
+            }
+            return;
+        }
+        let placeholders: Vec<(&Placeholder, &str)> = project
+            .placeholders_in(&self.view_bounds())
+            .map(|placeholder| (placeholder, project.struct_name(placeholder.cell_def_id)))
+            .collect();
+        let shapes = placeholder_overlay(&placeholders, &self.pixel_axes());
+        self.set_overlay(PLACEHOLDER_OVERLAY, shapes);
+    }
+
+    /// World bounds of the area shown on screen.
+    fn view_bounds(&self) -> BoundingBox {
+        let (width, height) = self.window_size;
+        let mut bounds = BoundingBox::new();
+        for (x, y) in [(0, 0), (width, 0), (0, height), (width, height)] {
+            let (x, y) = self.screen_to_world(x, y);
+            bounds.encompass(&BoundingBox {
+                min_x: x,
+                min_y: y,
+                max_x: x,
+                max_y: y,
+            });
+        }
+        bounds
+    }
+
+    fn update_ruler_overlay(&mut self) {
+        let Some(project) = &self.project else {
+            return;
+        };
+        let rulers: Vec<Ruler> = self
+            .rulers
+            .iter()
+            .chain(&self.pending_ruler)
+            .copied()
+            .collect();
+        if rulers.is_empty() {
+            self.clear_overlay(RULER_OVERLAY);
+            self.render();
+            return;
+        }
+        let labels: Vec<String> = rulers
+            .iter()
+            .map(|ruler| project.format_length(ruler.length()))
+            .collect();
+        let shapes = ruler_overlay(&rulers, &labels, &self.pixel_axes());
+        self.set_overlay(RULER_OVERLAY, shapes);
+    }
+
     pub fn handle_mouse_press(&mut self, x: u32, y: u32) {
+        if self.pointer_mode == PointerMode::Cut {
+            let point = self.snapped_point(x, y);
+            match self.cut.take() {
+                Some(cut) if cut.pending => {
+                    self.set_cut_line(cut.line.start, point);
+                }
+                _ => {
+                    self.cut = Some(CutState {
+                        line: Ruler {
+                            start: point,
+                            end: point,
+                        },
+                        pending: true,
+                        section: None,
+                    });
+                    self.update_cut_overlay();
+                }
+            }
+            return;
+        }
+        if self.pointer_mode == PointerMode::Ruler {
+            let point = self.snapped_point(x, y);
+            match self.pending_ruler.take() {
+                Some(mut ruler) => {
+                    ruler.end = point;
+                    self.rulers.push(ruler);
+                }
+                None => {
+                    self.pending_ruler = Some(Ruler {
+                        start: point,
+                        end: point,
+                    });
+                }
+            }
+            self.update_ruler_overlay();
+            return;
+        }
         self.is_dragging = true;
         self.last_mouse_pos = Some((x, y));
     }
 
     pub fn handle_mouse_release(&mut self) {
+        // Placeholders and lint markers are only drawn within the view, so
+        // panning may reveal new ones.
+        if self.is_dragging {
+            self.update_lint_overlay();
+            self.update_placeholder_overlay();
+        }
         self.is_dragging = false;
         self.last_mouse_pos = None;
     }
 
     pub fn handle_mouse_move(&mut self, x: u32, y: u32) {
+        if self.pending_ruler.is_some() {
+            let point = self.snapped_point(x, y);
+            if let Some(ruler) = self.pending_ruler.as_mut() {
+                ruler.end = point;
+            }
+            self.update_ruler_overlay();
+        }
+        if self.cut.as_ref().is_some_and(|cut| cut.pending) {
+            let point = self.snapped_point(x, y);
+            if let Some(cut) = self.cut.as_mut() {
+                cut.line.end = point;
+            }
+            self.update_cut_overlay();
+        }
         if self.is_dragging {
             if let Some((last_x, last_y)) = self.last_mouse_pos {
                 let p1 = self.screen_to_world(x, y);
@@ -108,18 +1530,48 @@ impl AppController {
                 self.camera.position = pos;
             }
             self.last_mouse_pos = Some((x, y));
+            // The cross-section panel is anchored to the screen.
+            if self.section_panel && self.cross_section().is_some() {
+                self.update_cut_overlay();
+            }
         }
 
+        self.hover_position = Some((x, y));
+        self.update_hover(x, y);
+    }
+
+    /// Picks the shape or placeholder under the cursor and outlines it.
+    fn update_hover(&mut self, x: u32, y: u32) {
         // Convert screen coordinates to world space
         let (world_x, world_y) = self.screen_to_world(x, y);
         if let Some(project) = self.project() {
             if let Some(result) = project.pick_cell(world_x, world_y) {
+                self.hovered_placeholder = None;
                 if self.hovered_cell != Some(result.clone()) {
                     self.hovered_cell = Some(result.clone());
                     self.update_outline_mesh(result);
                 }
-            } else if self.hovered_cell.is_some() {
+            } else if let Some(placeholder) = project.pick_placeholder(world_x, world_y).cloned() {
+                self.hovered_cell = None;
+                let hovered = self.hovered_placeholder.as_ref().map(|p| p.cell_id);
+                if hovered != Some(placeholder.cell_id) {
+                    let polygon = Rect::new(
+                        Coord {
+                            x: placeholder.bounds.min_x,
+                            y: placeholder.bounds.min_y,
+                        },
+                        Coord {
+                            x: placeholder.bounds.max_x,
+                            y: placeholder.bounds.max_y,
+                        },
+                    )
+                    .to_polygon();
+                    self.show_outlines(&[polygon]);
+                    self.hovered_placeholder = Some(placeholder);
+                }
+            } else if self.hovered_cell.is_some() || self.hovered_placeholder.is_some() {
                 self.hovered_cell = None;
+                self.hovered_placeholder = None;
                 self.get_outline_mesh().visible = false;
             }
         }
@@ -154,6 +1606,8 @@ impl AppController {
         // Adjust camera position to keep cursor point stable
         self.camera.position.x += (world_x - new_world_x) as f32;
         self.camera.position.y += (world_y - new_world_y) as f32;
+
+        self.refresh_screen_overlays();
     }
 
     /// Requests a render to occur during the next tick.
@@ -185,6 +1639,7 @@ impl AppController {
             let bounds = project.bounds();
             self.camera.fit_to_bounds(self.window_size, bounds);
         }
+        self.refresh_screen_overlays();
     }
 
     pub fn destroy(&mut self) {
@@ -236,28 +1691,53 @@ impl AppController {
     }
 
     fn update_outline_mesh(&mut self, selection: PickResult) {
-        let Some(project) = self.project() else {
-            log::error!("No project");
+        let Some(project) = self.project.as_ref() else {
             return;
         };
-        let layer = &project.layers()[selection.layer as usize];
-        let polygon = &layer.polygons[selection.polygon];
-
-        let triangles = polygon.earcut_triangles_raw();
+        if !self.highlight_instance {
+            self.show_outlines(&[selection.polygon]);
+            return;
+        }
+        let polygons = project
+            .instance_polygons(&selection, MAX_HIGHLIGHT_POLYGONS)
+            .or_else(|| {
+                let bounds = match selection.path.is_empty() {
+                    true => project.bounds(),
+                    false => project.instance_path_bounds(&selection.path)?,
+                };
+                let rect = Rect::new(
+                    Coord {
+                        x: bounds.min_x,
+                        y: bounds.min_y,
+                    },
+                    Coord {
+                        x: bounds.max_x,
+                        y: bounds.max_y,
+                    },
+                );
+                Some(vec![rect.to_polygon()])
+            })
+            .unwrap_or_else(|| vec![selection.polygon]);
+        self.show_outlines(&polygons);
+    }
 
+    fn show_outlines(&mut self, polygons: &[geo::Polygon<f64>]) {
         let mut geometry = Geometry::new();
+        for polygon in polygons {
+            let triangles = polygon.earcut_triangles_raw();
+            let offset = (geometry.positions.len() / 3) as u32;
+            geometry.positions.reserve(3 * triangles.vertices.len() / 2);
+            geometry.indices.reserve(triangles.triangle_indices.len());
 
-        geometry.positions.reserve(3 * triangles.vertices.len() / 2);
-        geometry.indices.reserve(triangles.triangle_indices.len());
-
-        for coord in triangles.vertices.chunks(2) {
-            geometry.positions.push(coord[0] as f32);
-            geometry.positions.push(coord[1] as f32);
-            geometry.positions.push(0.0);
-        }
+            for coord in triangles.vertices.chunks(2) {
+                geometry.positions.push(coord[0] as f32);
+                geometry.positions.push(coord[1] as f32);
+                geometry.positions.push(0.0);
+            }
 
-        for index in triangles.triangle_indices {
-            geometry.indices.push(index as u32);
+            for index in triangles.triangle_indices {
+                geometry.indices.push(offset + index as u32);
+            }
         }
 
         let mesh = self.get_outline_mesh();
@@ -275,31 +1755,297 @@ impl Drop for AppController {
     }
 }
 
-pub fn populate_scene(layers: &[Layer], scene: &mut Scene) {
-    let mut material = Material::new(VERTEX_SHADER, FRAGMENT_SHADER);
+/// Meshes drawing the layers of a project, so that they can be restyled, and
+/// updated after edits without rebuilding the scene.
+pub struct LayerMeshes {
+    material_id: MaterialId,
+    instanced_material_id: MaterialId,
+    /// Transform applied on top of the placements, for overlaid layouts.
+    transform: Option<AffineTransform>,
+    /// Multiplies the color of every layer.
+    tint: Vector4<f32>,
+    visible: bool,
+    /// Meshes of each layer, by layer index.
+    by_layer: Vec<Vec<MeshId>>,
+    /// Instanced meshes of each placed cell, along with their layers.
+    by_cell: HashMap<CellDefId, Vec<(i16, MeshId)>>,
+}
 
-    material.set_blending(true);
+impl LayerMeshes {
+    fn new(scene: &mut Scene, transform: Option<AffineTransform>) -> Self {
+        let mut material = Material::new(VERTEX_SHADER, FRAGMENT_SHADER);
+        material.set_blending(true);
+        let material_id = scene.add_material(material);
 
-    let material_id = scene.add_material(material);
+        let mut material = Material::new(INSTANCED_VERTEX_SHADER, FRAGMENT_SHADER);
+        material.set_blending(true);
+        let instanced_material_id = scene.add_material(material);
 
-    for layer in layers {
-        let geometry = create_layer_geometry(layer);
+        Self {
+            material_id,
+            instanced_material_id,
+            transform,
+            tint: Vector4::new(1.0, 1.0, 1.0, 1.0),
+            visible: true,
+            by_layer: Vec::new(),
+            by_cell: HashMap::new(),
+        }
+    }
+
+    /// Meshes drawing a layer.
+    pub fn layer(&self, index: usize) -> &[MeshId] {
+        self.by_layer.get(index).map_or(&[], Vec::as_slice)
+    }
+
+    /// Creates the meshes of every layer. Each cell is triangulated once per
+    /// layer and drawn at all of its placements with instancing. Merged and
+    /// derived layers only exist flattened, so they get a single mesh each.
+    fn populate(&mut self, project: &Project, scene: &mut Scene) {
+        let layers = project.layers();
+
+        let mut geometries: Vec<Vec<(Option<CellDefId>, Geometry)>> =
+            layers.iter().map(|_| Vec::new()).collect();
+        for (cell_def_id, transforms) in placement_transforms(project) {
+            let instances = self.instance_data(&transforms);
+            for (layer, geometry) in cell_geometries(project, cell_def_id, &instances) {
+                geometries[layer as usize].push((Some(cell_def_id), geometry));
+            }
+        }
+        for (layer, geometries) in layers.iter().zip(&mut geometries) {
+            if is_flat(layer) {
+                let mut geometry = create_polygons_geometry(layer.render_polygons());
+                if self.transform.is_some() {
+                    geometry.set_instances(self.instance_data(&[&AffineTransform::identity()]));
+                }
+                geometries.push((None, geometry));
+            }
+        }
+
+        // Meshes are added in layer order, which is the order they are drawn
+        // in.
+        for (layer, geometries) in layers.iter().zip(geometries) {
+            for (cell_def_id, geometry) in geometries {
+                self.add(scene, layer, geometry, cell_def_id);
+            }
+        }
+    }
+
+    /// Removes every mesh from the scene.
+    fn clear(&mut self, scene: &mut Scene, gl: &glow::Context) {
+        for mesh_id in self.by_layer.drain(..).flatten() {
+            scene.remove_mesh(gl, mesh_id);
+        }
+        self.by_cell.clear();
+    }
+
+    /// Removes every mesh and both materials from the scene.
+    fn destroy(mut self, scene: &mut Scene, gl: &glow::Context) {
+        self.clear(scene, gl);
+        scene.remove_material(gl, self.material_id);
+        scene.remove_material(gl, self.instanced_material_id);
+    }
+
+    fn add(
+        &mut self,
+        scene: &mut Scene,
+        layer: &Layer,
+        geometry: Geometry,
+        cell_def_id: Option<CellDefId>,
+    ) {
+        let material_id = match geometry.instances.is_empty() {
+            true => self.material_id,
+            false => self.instanced_material_id,
+        };
         let geometry_id = scene.add_geometry(geometry);
         let mut mesh = Mesh::new(geometry_id, material_id);
 
         // Set the color uniform using the layer's color
-        mesh.set_vec4("color", layer.color);
+        mesh.set_vec4("color", layer.color.component_mul(&self.tint));
+        mesh.visible = self.visible && layer.visible;
+
+        let mesh_id = scene.add_mesh(mesh);
+        let index = layer.index() as usize;
+        if self.by_layer.len() <= index {
+            self.by_layer.resize(index + 1, Vec::new());
+        }
+        self.by_layer[index].push(mesh_id);
+        if let Some(cell_def_id) = cell_def_id {
+            self.by_cell
+                .entry(cell_def_id)
+                .or_default()
+                .push((layer.index(), mesh_id));
+        }
+    }
+
+    /// Applies the visibility and color of a layer to its meshes.
+    fn apply_style(&self, scene: &mut Scene, layer: &Layer) {
+        for mesh_id in self.layer(layer.index() as usize) {
+            if let Some(mesh) = scene.get_mesh_mut(mesh_id) {
+                mesh.set_vec4("color", layer.color.component_mul(&self.tint));
+                mesh.visible = self.visible && layer.visible;
+            }
+        }
+    }
+
+    /// Removes the meshes of a cell from the scene.
+    fn remove_cell(&mut self, scene: &mut Scene, gl: &glow::Context, cell_def_id: CellDefId) {
+        for (layer, mesh_id) in self.by_cell.remove(&cell_def_id).unwrap_or_default() {
+            self.by_layer[layer as usize].retain(|&id| id != mesh_id);
+            scene.remove_mesh(gl, mesh_id);
+        }
+    }
 
-        scene.add_mesh(mesh);
+    /// Replaces the meshes of a cell after its shapes changed.
+    fn update_cell_shapes(
+        &mut self,
+        project: &Project,
+        scene: &mut Scene,
+        gl: &glow::Context,
+        cell_def_id: CellDefId,
+    ) {
+        self.remove_cell(scene, gl, cell_def_id);
+        let transforms = placement_transforms(project);
+        if let Some(transforms) = transforms.get(&cell_def_id) {
+            let instances = self.instance_data(transforms);
+            for (layer, geometry) in cell_geometries(project, cell_def_id, &instances) {
+                let layer = &project.layers()[layer as usize];
+                self.add(scene, layer, geometry, Some(cell_def_id));
+            }
+        }
+    }
+
+    /// Moves the instances of every cell after the placements changed,
+    /// adding meshes for cells that are newly placed and removing those of
+    /// cells that no longer are.
+    fn update_placements(&mut self, project: &Project, scene: &mut Scene, gl: &glow::Context) {
+        let transforms = placement_transforms(project);
+        let removed: Vec<CellDefId> = self
+            .by_cell
+            .keys()
+            .filter(|cell_def_id| !transforms.contains_key(*cell_def_id))
+            .copied()
+            .collect();
+        for cell_def_id in removed {
+            self.remove_cell(scene, gl, cell_def_id);
+        }
+        for (cell_def_id, transforms) in transforms {
+            let instances = self.instance_data(&transforms);
+            match self.by_cell.get(&cell_def_id) {
+                Some(meshes) => {
+                    for (_, mesh_id) in meshes {
+                        let Some(mesh) = scene.get_mesh(mesh_id) else {
+                            continue;
+                        };
+                        let geometry_id = mesh.geometry_id;
+                        if let Some(geometry) = scene.get_geometry_mut(&geometry_id) {
+                            geometry.set_instances(instances.clone());
+                        }
+                    }
+                }
+                None => {
+                    for (layer, geometry) in cell_geometries(project, cell_def_id, &instances) {
+                        let layer = &project.layers()[layer as usize];
+                        self.add(scene, layer, geometry, Some(cell_def_id));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Packs transforms in the layout of [Geometry::instances], after
+    /// applying the transform of the layout.
+    fn instance_data(&self, transforms: &[&AffineTransform]) -> Vec<f32> {
+        transforms
+            .iter()
+            .map(|t| match &self.transform {
+                Some(transform) => t.compose(transform),
+                None => **t,
+            })
+            .flat_map(|t| [t.a(), t.b(), t.xoff(), t.d(), t.e(), t.yoff()])
+            .map(|value| value as f32)
+            .collect()
+    }
+}
+
+/// Creates the meshes of an overlaid layout.
+fn populate_layout(layout: &OverlaidLayout, scene: &mut Scene) -> LayerMeshes {
+    let mut meshes = LayerMeshes::new(scene, Some(layout.placement.transform()));
+    meshes.tint = layout.tint;
+    meshes.visible = layout.visible;
+    meshes.populate(&layout.project, scene);
+    meshes
+}
+
+/// Creates the meshes of every layer of the main project.
+pub fn populate_scene(project: &Project, scene: &mut Scene) -> LayerMeshes {
+    let mut meshes = LayerMeshes::new(scene, None);
+    meshes.populate(project, scene);
+    meshes
+}
+
+/// Merged and derived layers are drawn from their flattened polygons.
+fn is_flat(layer: &Layer) -> bool {
+    layer.is_derived() || layer.is_merged()
+}
+
+/// Transforms of the placements of each drawn cell.
+fn placement_transforms(project: &Project) -> IndexMap<CellDefId, Vec<&AffineTransform>> {
+    let mut transforms: IndexMap<CellDefId, Vec<&AffineTransform>> = IndexMap::new();
+    for placement in project.placements() {
+        transforms
+            .entry(placement.cell_def_id)
+            .or_default()
+            .push(&placement.transform);
+    }
+    transforms
+}
+
+/// Triangulates the shapes of a cell, one geometry per layer, each drawn with
+/// the given instance data.
+fn cell_geometries(
+    project: &Project,
+    cell_def_id: CellDefId,
+    instances: &[f32],
+) -> Vec<(i16, Geometry)> {
+    let layers = project.layers();
+    let mut by_layer: IndexMap<i16, Vec<&Polygon>> = IndexMap::new();
+    for shape in project.cell_def_shapes(cell_def_id) {
+        if !is_flat(&layers[shape.layer as usize]) {
+            by_layer
+                .entry(shape.layer)
+                .or_default()
+                .push(&shape.polygon);
+        }
+    }
+    by_layer
+        .into_iter()
+        .map(|(layer, polygons)| {
+            let mut geometry = create_polygons_geometry(polygons);
+            geometry.set_instances(instances.to_vec());
+            (layer, geometry)
+        })
+        .collect()
+}
+
+fn apply_default_alpha(project: &mut Project) {
+    let mut alpha = 0.6; // looks ok for 4004 & 6502
+    if project.layers().len() > 10 {
+        alpha = 0.05;
+    }
+    for layer in project.layers_mut() {
+        if layer.is_derived() {
+            continue;
+        }
+        layer.color.w = alpha;
     }
 }
 
 /// Triangulates polygons and appends them to a vertex buffer.
-fn create_layer_geometry(layer: &Layer) -> Geometry {
+fn create_polygons_geometry<'a>(polygons: impl IntoIterator<Item = &'a Polygon>) -> Geometry {
     let mut geometry = Geometry::new();
 
     // Process each polygon in the layer
-    for polygon in &layer.polygons {
+    for polygon in polygons {
         let triangles = polygon.earcut_triangles_raw();
 
         let vertex_offset = geometry.positions.len() as u32 / 3;
@@ -0,0 +1,598 @@
+use crate::core::CrossSection;
+use crate::core::DensityMap;
+use crate::core::DrcReport;
+use crate::core::FillResult;
+use crate::core::Layer;
+use crate::core::LintKind;
+use crate::core::Net;
+use crate::core::Placeholder;
+use crate::core::Ruler;
+use crate::core::SearchMatch;
+use crate::graphics::BoundingBox;
+use crate::rsutils::heatmap_color;
+use crate::Project;
+use geo::Coord;
+use geo::LineString;
+use nalgebra::Vector4;
+
+type Polygon = geo::Polygon<f64>;
+
+/// Number of distinct colors used to draw a density map.
+const DENSITY_BUCKETS: usize = 16;
+
+/// Sizes of ruler decorations, in pixels.
+const RULER_LINE_WIDTH: f64 = 2.0;
+const RULER_TICK_LENGTH: f64 = 8.0;
+const RULER_TEXT_HEIGHT: f64 = 14.0;
+
+/// Sizes of placeholder frames and labels, in pixels.
+const PLACEHOLDER_LINE_WIDTH: f64 = 1.0;
+const PLACEHOLDER_TEXT_HEIGHT: f64 = 12.0;
+const PLACEHOLDER_TEXT_MARGIN: f64 = 4.0;
+/// Labels are left out beyond this many, to keep zooming responsive.
+const MAX_PLACEHOLDER_LABELS: usize = 500;
+
+/// Size of lint markers, in pixels.
+pub const LINT_MARKER_SIZE: f64 = 12.0;
+
+/// Sizes of search match frames, in pixels. Labels and instances smaller
+/// than the marker are framed by a marker of this size instead.
+const SEARCH_LINE_WIDTH: f64 = 2.0;
+const SEARCH_MARKER_SIZE: f64 = 16.0;
+
+/// Cross-section panels cover this fraction of the height of the screen.
+const SECTION_PANEL_HEIGHT: f64 = 0.3;
+const SECTION_PANEL_MARGIN: f64 = 12.0;
+
+/// World space offsets corresponding to moving one pixel to the right and
+/// one pixel down on screen. Used to draw decorations that keep a constant
+/// size and stay upright regardless of zoom and orientation.
+#[derive(Debug, Clone, Copy)]
+pub struct PixelAxes {
+    /// World space position of the top left corner of the screen.
+    pub origin: Coord<f64>,
+    pub right: Coord<f64>,
+    pub down: Coord<f64>,
+}
+
+impl PixelAxes {
+    /// Offsets a world space point by the given number of pixels.
+    fn offset(&self, origin: Coord<f64>, x: f64, y: f64) -> Coord<f64> {
+        origin + self.right * x + self.down * y
+    }
+
+    /// Converts a screen position in pixels to world space.
+    pub fn screen_to_world(&self, x: f64, y: f64) -> Coord<f64> {
+        self.offset(self.origin, x, y)
+    }
+
+    /// Converts a world space point to a screen position in pixels.
+    pub fn world_to_screen(&self, point: Coord<f64>) -> Coord<f64> {
+        let delta = point - self.origin;
+        let dot = |a: Coord<f64>, b: Coord<f64>| a.x * b.x + a.y * b.y;
+        Coord {
+            x: dot(delta, self.right) / dot(self.right, self.right),
+            y: dot(delta, self.down) / dot(self.down, self.down),
+        }
+    }
+
+    /// World space length of a pixel.
+    pub fn pixel_size(&self) -> f64 {
+        self.right.x.hypot(self.right.y)
+    }
+}
+
+/// Polygons drawn in a single color on top of the layout, used to visualize
+/// analysis results such as density maps.
+#[derive(Debug, Clone)]
+pub struct OverlayShape {
+    pub polygons: Vec<Polygon>,
+    pub color: Vector4<f32>,
+}
+
+impl OverlayShape {
+    pub fn new(color: Vector4<f32>) -> Self {
+        Self {
+            polygons: Vec::new(),
+            color,
+        }
+    }
+
+    pub fn add_rect(&mut self, bounds: &BoundingBox) {
+        let ring = vec![
+            (bounds.min_x, bounds.min_y),
+            (bounds.max_x, bounds.min_y),
+            (bounds.max_x, bounds.max_y),
+            (bounds.min_x, bounds.max_y),
+        ];
+        self.polygons
+            .push(Polygon::new(LineString::from(ring), vec![]));
+    }
+
+    /// Adds a line segment of the given width with square caps.
+    pub fn add_line(&mut self, start: Coord<f64>, end: Coord<f64>, width: f64) {
+        let delta = end - start;
+        let length = delta.x.hypot(delta.y);
+        if length == 0.0 {
+            return;
+        }
+        let along = delta * (width / 2.0 / length);
+        let across = Coord {
+            x: -along.y,
+            y: along.x,
+        };
+        let ring = vec![
+            start - along - across,
+            end + along - across,
+            end + along + across,
+            start - along + across,
+        ];
+        self.polygons
+            .push(Polygon::new(LineString::from(ring), vec![]));
+    }
+
+    /// Adds upright text with its top left corner at the given point, drawn
+    /// with a segmented font. Digits, uppercase letters, a few lowercase
+    /// letters and punctuation are supported, other characters are left
+    /// blank.
+    pub fn add_text(&mut self, text: &str, origin: Coord<f64>, axes: &PixelAxes, height: f64) {
+        let scale = height / 2.0;
+        let stroke = axes.pixel_size() * (height / 7.0).max(1.0);
+        let mut x = 0.0;
+        for c in text.chars() {
+            for &((x0, y0), (x1, y1)) in glyph_strokes(c) {
+                let start = axes.offset(origin, (x + x0) * scale, y0 * scale);
+                let end = axes.offset(origin, (x + x1) * scale, y1 * scale);
+                self.add_line(start, end, stroke);
+            }
+            x += glyph_advance(c);
+        }
+    }
+
+    /// Adds the outline of a rectangle, drawn inside of its bounds.
+    pub fn add_frame(&mut self, bounds: &BoundingBox, thickness: f64) {
+        let t = thickness
+            .min(bounds.width() / 2.0)
+            .min(bounds.height() / 2.0);
+        let BoundingBox {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        } = *bounds;
+        for (x0, y0, x1, y1) in [
+            (min_x, min_y, max_x, min_y + t),
+            (min_x, max_y - t, max_x, max_y),
+            (min_x, min_y + t, min_x + t, max_y - t),
+            (max_x - t, min_y + t, max_x, max_y - t),
+        ] {
+            self.add_rect(&BoundingBox {
+                min_x: x0,
+                min_y: y0,
+                max_x: x1,
+                max_y: y1,
+            });
+        }
+    }
+}
+
+/// Draws all violations of a report, with the current one highlighted.
+pub fn drc_overlay(report: &DrcReport, current: Option<usize>) -> Vec<OverlayShape> {
+    let mut others = OverlayShape::new(Vector4::new(1.0, 0.2, 0.2, 0.5));
+    let mut highlighted = OverlayShape::new(Vector4::new(1.0, 1.0, 0.0, 0.9));
+    for (index, violation) in report.violations.iter().enumerate() {
+        let shape = if Some(index) == current {
+            &mut highlighted
+        } else {
+            &mut others
+        };
+        shape.polygons.extend(violation.region.iter().cloned());
+        // Small violations would be invisible when zoomed out, so frame them.
+        let b = &violation.bounds;
+        let size = b.width().max(b.height());
+        shape.add_frame(&b.inflate(size), size / 4.0);
+    }
+    vec![others, highlighted]
+}
+
+/// Draws each window of a density map as a tile centered on the window,
+/// colored by its density, with frames around failing windows.
+pub fn density_overlay(map: &DensityMap) -> Vec<OverlayShape> {
+    let mut shapes: Vec<OverlayShape> = (0..DENSITY_BUCKETS)
+        .map(|bucket| {
+            let t = bucket as f32 / (DENSITY_BUCKETS - 1) as f32;
+            let (r, g, b) = heatmap_color(t);
+            OverlayShape::new(Vector4::new(r, g, b, 0.5))
+        })
+        .collect();
+
+    let tile = map.step.min(map.window);
+    let tile_bounds = |col: usize, row: usize| {
+        let window = map.window_bounds(col, row);
+        let center_x = (window.min_x + window.max_x) / 2.0;
+        let center_y = (window.min_y + window.max_y) / 2.0;
+        BoundingBox {
+            min_x: center_x - tile / 2.0,
+            min_y: center_y - tile / 2.0,
+            max_x: center_x + tile / 2.0,
+            max_y: center_y + tile / 2.0,
+        }
+    };
+
+    for row in 0..map.rows {
+        for col in 0..map.cols {
+            let value = map.value(col, row).clamp(0.0, 1.0);
+            let bucket = (value * (DENSITY_BUCKETS - 1) as f64).round() as usize;
+            shapes[bucket].add_rect(&tile_bounds(col, row));
+        }
+    }
+
+    let mut failing = OverlayShape::new(Vector4::new(1.0, 1.0, 1.0, 0.9));
+    for violation in map.violations() {
+        failing.add_frame(&tile_bounds(violation.col, violation.row), tile * 0.08);
+    }
+    shapes.push(failing);
+    shapes
+}
+
+/// Draws the fill shapes in the color of their layer, but opaque so that they
+/// stand out from the existing geometry.
+pub fn fill_overlay(project: &Project, fill: &FillResult) -> Vec<OverlayShape> {
+    let color = project
+        .layers()
+        .get(fill.layer as usize)
+        .map_or(Vector4::new(1.0, 1.0, 1.0, 1.0), |layer| layer.color);
+    let mut shape = OverlayShape::new(Vector4::new(color.x, color.y, color.z, 0.9));
+    shape.polygons.extend(fill.polygons.iter().cloned());
+    vec![shape]
+}
+
+/// Draws every shape of a net in a single bright color.
+pub fn net_overlay(project: &Project, net: &Net) -> Vec<OverlayShape> {
+    let mut shape = OverlayShape::new(Vector4::new(0.0, 1.0, 1.0, 0.6));
+    for &(layer, polygon) in &net.shapes {
+        shape
+            .polygons
+            .push(project.layers()[layer as usize].polygons[polygon].clone());
+    }
+    vec![shape]
+}
+
+/// Draws each ruler as a line with ticks at both ends and a label with its
+/// length centered above it.
+pub fn ruler_overlay(rulers: &[Ruler], labels: &[String], axes: &PixelAxes) -> Vec<OverlayShape> {
+    let mut shape = OverlayShape::new(Vector4::new(1.0, 0.85, 0.2, 0.95));
+    let pixel = axes.pixel_size();
+    for (ruler, label) in rulers.iter().zip(labels) {
+        let width = RULER_LINE_WIDTH * pixel;
+        shape.add_line(ruler.start, ruler.end, width);
+
+        let length = ruler.length();
+        if length > 0.0 {
+            let direction = (ruler.end - ruler.start) / length;
+            let tick = Coord {
+                x: -direction.y,
+                y: direction.x,
+            } * (RULER_TICK_LENGTH * pixel);
+            for point in [ruler.start, ruler.end] {
+                shape.add_line(point - tick, point + tick, width);
+            }
+        }
+
+        let middle = (ruler.start + ruler.end) / 2.0;
+        let text_width = text_width(label) * RULER_TEXT_HEIGHT / 2.0;
+        let origin = axes.offset(
+            middle,
+            -text_width / 2.0,
+            -RULER_TEXT_HEIGHT - RULER_TICK_LENGTH,
+        );
+        shape.add_text(label, origin, axes, RULER_TEXT_HEIGHT);
+    }
+    vec![shape]
+}
+
+/// Draws each placeholder as a thin frame, labelled with its cell name in
+/// the top left corner where the name fits inside.
+pub fn placeholder_overlay(
+    placeholders: &[(&Placeholder, &str)],
+    axes: &PixelAxes,
+) -> Vec<OverlayShape> {
+    let mut frames = OverlayShape::new(Vector4::new(0.8, 0.8, 0.8, 0.8));
+    let mut labels = OverlayShape::new(Vector4::new(1.0, 1.0, 1.0, 0.9));
+    let pixel = axes.pixel_size();
+    let mut label_count = 0;
+    for (placeholder, name) in placeholders {
+        let bounds = &placeholder.bounds;
+        frames.add_frame(bounds, PLACEHOLDER_LINE_WIDTH * pixel);
+        if label_count >= MAX_PLACEHOLDER_LABELS {
+            continue;
+        }
+
+        let corners = [
+            (bounds.min_x, bounds.min_y),
+            (bounds.max_x, bounds.min_y),
+            (bounds.max_x, bounds.max_y),
+            (bounds.min_x, bounds.max_y),
+        ]
+        .map(|(x, y)| axes.world_to_screen(Coord { x, y }));
+        let left = corners.iter().map(|c| c.x).fold(f64::MAX, f64::min);
+        let top = corners.iter().map(|c| c.y).fold(f64::MAX, f64::min);
+        let right = corners.iter().map(|c| c.x).fold(f64::MIN, f64::max);
+        let bottom = corners.iter().map(|c| c.y).fold(f64::MIN, f64::max);
+
+        let label = name.to_uppercase();
+        let label_width = text_width(&label) * PLACEHOLDER_TEXT_HEIGHT / 2.0;
+        let fits_width = label_width + 2.0 * PLACEHOLDER_TEXT_MARGIN <= right - left;
+        let fits_height = PLACEHOLDER_TEXT_HEIGHT + 2.0 * PLACEHOLDER_TEXT_MARGIN <= bottom - top;
+        if fits_width && fits_height {
+            let origin = axes.screen_to_world(
+                left + PLACEHOLDER_TEXT_MARGIN,
+                top + PLACEHOLDER_TEXT_MARGIN,
+            );
+            labels.add_text(&label, origin, axes, PLACEHOLDER_TEXT_HEIGHT);
+            label_count += 1;
+        }
+    }
+    vec![frames, labels]
+}
+
+/// Draws a frame of a fixed size on screen around each lint location,
+/// colored by the kind of issue.
+pub fn lint_overlay(locations: &[(LintKind, Coord<f64>)], axes: &PixelAxes) -> Vec<OverlayShape> {
+    let color = |kind| match kind {
+        LintKind::OffGrid => Vector4::new(1.0, 0.3, 1.0, 0.9),
+        LintKind::AcuteAngle => Vector4::new(1.0, 0.6, 0.1, 0.9),
+        LintKind::DisallowedAngle => Vector4::new(0.3, 0.8, 1.0, 0.9),
+        LintKind::SelfIntersection => Vector4::new(1.0, 0.2, 0.2, 0.9),
+    };
+    let half = axes.pixel_size() * LINT_MARKER_SIZE / 2.0;
+    LintKind::ALL
+        .iter()
+        .map(|&kind| {
+            let mut shape = OverlayShape::new(color(kind));
+            for (_, location) in locations.iter().filter(|(k, _)| *k == kind) {
+                let bounds = BoundingBox {
+                    min_x: location.x - half,
+                    min_y: location.y - half,
+                    max_x: location.x + half,
+                    max_y: location.y + half,
+                };
+                shape.add_frame(&bounds, half / 3.0);
+            }
+            shape
+        })
+        .collect()
+}
+
+/// Frames every search match, with the current one highlighted.
+pub fn search_overlay(
+    matches: &[SearchMatch],
+    current: Option<usize>,
+    axes: &PixelAxes,
+) -> Vec<OverlayShape> {
+    let mut others = OverlayShape::new(Vector4::new(1.0, 0.5, 0.0, 0.7));
+    let mut highlighted = OverlayShape::new(Vector4::new(1.0, 1.0, 0.0, 0.9));
+    let pixel = axes.pixel_size();
+    let half = pixel * SEARCH_MARKER_SIZE / 2.0;
+    for (index, found) in matches.iter().enumerate() {
+        let shape = if Some(index) == current {
+            &mut highlighted
+        } else {
+            &mut others
+        };
+        let b = &found.bounds;
+        let bounds = if b.width().max(b.height()) < 2.0 * half {
+            let center_x = (b.min_x + b.max_x) / 2.0;
+            let center_y = (b.min_y + b.max_y) / 2.0;
+            BoundingBox {
+                min_x: center_x - half,
+                min_y: center_y - half,
+                max_x: center_x + half,
+                max_y: center_y + half,
+            }
+        } else {
+            *b
+        };
+        shape.add_frame(&bounds, pixel * SEARCH_LINE_WIDTH);
+    }
+    vec![others, highlighted]
+}
+
+/// Draws a cut line with ticks at both ends.
+pub fn cut_line_overlay(start: Coord<f64>, end: Coord<f64>, axes: &PixelAxes) -> OverlayShape {
+    let mut shape = OverlayShape::new(Vector4::new(1.0, 1.0, 1.0, 0.9));
+    let pixel = axes.pixel_size();
+    let width = RULER_LINE_WIDTH * pixel;
+    shape.add_line(start, end, width);
+    let delta = end - start;
+    let length = delta.x.hypot(delta.y);
+    if length > 0.0 {
+        let tick = Coord {
+            x: -delta.y,
+            y: delta.x,
+        } * (RULER_TICK_LENGTH * pixel / length);
+        for point in [start, end] {
+            shape.add_line(point - tick, point + tick, width);
+        }
+    }
+    shape
+}
+
+/// Draws the side view of a cross-section in a panel along the bottom of
+/// the screen, scaled to fit. Each layer is drawn in its own color.
+pub fn cross_section_overlay(
+    section: &CrossSection,
+    layers: &[Layer],
+    axes: &PixelAxes,
+    screen_size: (u32, u32),
+) -> Vec<OverlayShape> {
+    let (screen_width, screen_height) = (screen_size.0 as f64, screen_size.1 as f64);
+    let margin = SECTION_PANEL_MARGIN;
+    let panel_top = screen_height * (1.0 - SECTION_PANEL_HEIGHT);
+    let rect = |x0: f64, y0: f64, x1: f64, y1: f64| {
+        let corners = [(x0, y0), (x1, y0), (x1, y1), (x0, y1)];
+        let ring: Vec<Coord<f64>> = corners
+            .iter()
+            .map(|&(x, y)| axes.screen_to_world(x, y))
+            .collect();
+        Polygon::new(LineString::from(ring), vec![])
+    };
+
+    let mut background = OverlayShape::new(Vector4::new(0.1, 0.1, 0.1, 0.85));
+    background.polygons.push(rect(
+        margin,
+        panel_top,
+        screen_width - margin,
+        screen_height - margin,
+    ));
+    let mut shapes = vec![background];
+
+    // Inner area, with the distance along the cut mapped to x and the height
+    // mapped to y.
+    let (left, right) = (2.0 * margin, screen_width - 2.0 * margin);
+    let (top, bottom) = (panel_top + margin, screen_height - 2.0 * margin);
+    let height = section.top - section.bottom;
+    if section.length <= 0.0 || height <= 0.0 || right <= left || bottom <= top {
+        return shapes;
+    }
+    let x_scale = (right - left) / section.length;
+    let y_scale = (bottom - top) / height;
+
+    let mut by_layer: Vec<(i16, OverlayShape)> = Vec::new();
+    for section_rect in &section.rects {
+        let index = match by_layer.iter().position(|(l, _)| *l == section_rect.layer) {
+            Some(index) => index,
+            None => {
+                let mut color = layers
+                    .get(section_rect.layer as usize)
+                    .map(|layer| layer.color)
+                    .unwrap_or(Vector4::new(0.5, 0.5, 0.5, 1.0));
+                color.w = 0.9;
+                by_layer.push((section_rect.layer, OverlayShape::new(color)));
+                by_layer.len() - 1
+            }
+        };
+        let z0 = section_rect.z - section.bottom;
+        let z1 = z0 + section_rect.thickness;
+        by_layer[index].1.polygons.push(rect(
+            left + section_rect.from * x_scale,
+            bottom - z1 * y_scale,
+            left + section_rect.to * x_scale,
+            bottom - z0 * y_scale,
+        ));
+    }
+    shapes.extend(by_layer.into_iter().map(|(_, shape)| shape));
+    shapes
+}
+
+type Stroke = ((f64, f64), (f64, f64));
+
+// Segments of a seven-segment display on a grid one unit wide and two units
+// high, with y pointing down.
+const SEG_A: Stroke = ((0.0, 0.0), (1.0, 0.0));
+const SEG_B: Stroke = ((1.0, 0.0), (1.0, 1.0));
+const SEG_C: Stroke = ((1.0, 1.0), (1.0, 2.0));
+const SEG_D: Stroke = ((0.0, 2.0), (1.0, 2.0));
+const SEG_E: Stroke = ((0.0, 1.0), (0.0, 2.0));
+const SEG_F: Stroke = ((0.0, 0.0), (0.0, 1.0));
+const SEG_G: Stroke = ((0.0, 1.0), (1.0, 1.0));
+
+// Additional strokes for letters: the halves of the middle segment, the
+// vertical center line and diagonals.
+const SEG_G1: Stroke = ((0.0, 1.0), (0.5, 1.0));
+const SEG_G2: Stroke = ((0.5, 1.0), (1.0, 1.0));
+const SEG_H: Stroke = ((0.5, 0.0), (0.5, 1.0));
+const SEG_J: Stroke = ((0.5, 1.0), (0.5, 2.0));
+const DIAG_TOP_LEFT: Stroke = ((0.0, 0.0), (0.5, 1.0));
+const DIAG_TOP_RIGHT: Stroke = ((1.0, 0.0), (0.5, 1.0));
+const DIAG_BOTTOM_LEFT: Stroke = ((0.0, 2.0), (0.5, 1.0));
+const DIAG_BOTTOM_RIGHT: Stroke = ((1.0, 2.0), (0.5, 1.0));
+const DIAG_RISING: Stroke = ((0.0, 2.0), (1.0, 0.0));
+const DIAG_FALLING: Stroke = ((0.0, 0.0), (1.0, 2.0));
+
+fn glyph_strokes(c: char) -> &'static [Stroke] {
+    match c {
+        '0' => &[SEG_A, SEG_B, SEG_C, SEG_D, SEG_E, SEG_F],
+        '1' => &[SEG_B, SEG_C],
+        '2' => &[SEG_A, SEG_B, SEG_G, SEG_E, SEG_D],
+        '3' => &[SEG_A, SEG_B, SEG_G, SEG_C, SEG_D],
+        '4' => &[SEG_F, SEG_G, SEG_B, SEG_C],
+        '5' => &[SEG_A, SEG_F, SEG_G, SEG_C, SEG_D],
+        '6' => &[SEG_A, SEG_F, SEG_G, SEG_E, SEG_C, SEG_D],
+        '7' => &[SEG_A, SEG_B, SEG_C],
+        '8' => &[SEG_A, SEG_B, SEG_C, SEG_D, SEG_E, SEG_F, SEG_G],
+        '9' => &[SEG_A, SEG_B, SEG_C, SEG_D, SEG_F, SEG_G],
+        '-' => &[SEG_G],
+        '.' => &[((0.0, 1.9), (0.0, 2.0))],
+        'b' => &[SEG_C, SEG_D, SEG_E, SEG_F, SEG_G],
+        'd' => &[SEG_B, SEG_C, SEG_D, SEG_E, SEG_G],
+        'm' => &[SEG_C, SEG_E, SEG_G, ((0.5, 1.0), (0.5, 2.0))],
+        'n' => &[SEG_C, SEG_E, SEG_G],
+        'u' => &[SEG_C, SEG_D, SEG_E],
+        'A' => &[SEG_A, SEG_B, SEG_C, SEG_E, SEG_F, SEG_G],
+        'B' => &[SEG_A, SEG_B, SEG_C, SEG_D, SEG_G2, SEG_H, SEG_J],
+        'C' => &[SEG_A, SEG_D, SEG_E, SEG_F],
+        'D' => &[SEG_A, SEG_B, SEG_C, SEG_D, SEG_H, SEG_J],
+        'E' => &[SEG_A, SEG_D, SEG_E, SEG_F, SEG_G1],
+        'F' => &[SEG_A, SEG_E, SEG_F, SEG_G1],
+        'G' => &[SEG_A, SEG_C, SEG_D, SEG_E, SEG_F, SEG_G2],
+        'H' => &[SEG_B, SEG_C, SEG_E, SEG_F, SEG_G],
+        'I' => &[SEG_A, SEG_D, SEG_H, SEG_J],
+        'J' => &[SEG_B, SEG_C, SEG_D, SEG_E],
+        'K' => &[SEG_E, SEG_F, SEG_G1, DIAG_TOP_RIGHT, DIAG_BOTTOM_RIGHT],
+        'L' => &[SEG_D, SEG_E, SEG_F],
+        'M' => &[SEG_B, SEG_C, SEG_E, SEG_F, DIAG_TOP_LEFT, DIAG_TOP_RIGHT],
+        'N' => &[SEG_B, SEG_C, SEG_E, SEG_F, DIAG_FALLING],
+        'O' => &[SEG_A, SEG_B, SEG_C, SEG_D, SEG_E, SEG_F],
+        'P' => &[SEG_A, SEG_B, SEG_E, SEG_F, SEG_G],
+        'Q' => &[SEG_A, SEG_B, SEG_C, SEG_D, SEG_E, SEG_F, DIAG_BOTTOM_RIGHT],
+        'R' => &[SEG_A, SEG_B, SEG_E, SEG_F, SEG_G, DIAG_BOTTOM_RIGHT],
+        'S' => &[SEG_A, SEG_C, SEG_D, SEG_F, SEG_G],
+        'T' => &[SEG_A, SEG_H, SEG_J],
+        'U' => &[SEG_B, SEG_C, SEG_D, SEG_E, SEG_F],
+        'V' => &[((0.0, 0.0), (0.5, 2.0)), ((1.0, 0.0), (0.5, 2.0))],
+        'W' => &[
+            SEG_B,
+            SEG_C,
+            SEG_E,
+            SEG_F,
+            DIAG_BOTTOM_LEFT,
+            DIAG_BOTTOM_RIGHT,
+        ],
+        'X' => &[DIAG_RISING, DIAG_FALLING],
+        'Y' => &[DIAG_TOP_LEFT, DIAG_TOP_RIGHT, SEG_J],
+        'Z' => &[SEG_A, SEG_D, DIAG_RISING],
+        '_' => &[SEG_D],
+        '/' => &[DIAG_RISING],
+        '$' => &[SEG_A, SEG_C, SEG_D, SEG_F, SEG_G, SEG_H, SEG_J],
+        '[' => &[
+            ((0.5, 0.0), (0.0, 0.0)),
+            SEG_F,
+            SEG_E,
+            ((0.0, 2.0), (0.5, 2.0)),
+        ],
+        ']' => &[
+            ((0.5, 0.0), (1.0, 0.0)),
+            SEG_B,
+            SEG_C,
+            ((1.0, 2.0), (0.5, 2.0)),
+        ],
+        _ => &[],
+    }
+}
+
+fn glyph_advance(c: char) -> f64 {
+    if c == '.' {
+        0.5
+    } else {
+        1.5
+    }
+}
+
+/// Width of text drawn by [OverlayShape::add_text], in units of half its
+/// height.
+fn text_width(text: &str) -> f64 {
+    let advance: f64 = text.chars().map(glyph_advance).sum();
+    // The last glyph does not need the spacing after it.
+    (advance - 0.5).max(0.0)
+}
@@ -32,6 +32,50 @@ void main() {
 }
 "#;
 
+/// Places each instance of a cell with its own 2x3 affine transform, see
+/// [Geometry::instances](crate::graphics::Geometry::instances).
+#[cfg(target_arch = "wasm32")]
+pub const INSTANCED_VERTEX_SHADER: &str = r#"#version 100
+attribute vec3 position;
+attribute vec3 instance_x;
+attribute vec3 instance_y;
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 projection;
+uniform vec4 color;
+
+varying vec4 v_color;
+
+void main() {
+    vec3 local = vec3(position.xy, 1.0);
+    vec4 world = vec4(dot(instance_x, local), dot(instance_y, local), position.z, 1.0);
+    gl_Position = projection * view * model * world;
+    v_color = color;
+}
+"#;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub const INSTANCED_VERTEX_SHADER: &str = r#"#version 330
+layout (location = 0) in vec3 position;
+layout (location = 1) in vec3 instance_x;
+layout (location = 2) in vec3 instance_y;
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 projection;
+uniform vec4 color;
+
+out vec4 v_color;
+
+void main() {
+    vec3 local = vec3(position.xy, 1.0);
+    vec4 world = vec4(dot(instance_x, local), dot(instance_y, local), position.z, 1.0);
+    gl_Position = projection * view * model * world;
+    v_color = color;
+}
+"#;
+
 #[cfg(target_arch = "wasm32")]
 pub const FRAGMENT_SHADER: &str = r#"#version 100
 precision mediump float;
@@ -1,6 +1,18 @@
 use crate::app_controller::AppController;
+use crate::core::CellDefId;
+use crate::core::Connectivity;
+use crate::core::DensityOptions;
+use crate::core::DrcRule;
+use crate::core::EditOutcome;
+use crate::core::FillOptions;
+use crate::core::LayerStack;
+use crate::core::LayoutPlacement;
+use crate::core::LintOptions;
+use crate::core::SearchPattern;
+use crate::generate_cross_section::generate_cross_section_svg;
 use crate::graphics::Renderer;
 use crate::graphics::Scene;
+use crate::rsutils::FileWatcher;
 use crate::Project;
 
 use glutin::config::ConfigTemplateBuilder;
@@ -13,7 +25,10 @@ use glutin::surface::SurfaceAttributesBuilder;
 use glutin::surface::WindowSurface;
 use glutin_winit::DisplayBuilder;
 use raw_window_handle::HasRawWindowHandle;
+use std::fs;
 use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::sync::mpsc;
 use std::time::Duration;
 use std::time::Instant;
 use winit::dpi::PhysicalPosition;
@@ -21,15 +36,55 @@ use winit::event::Event;
 use winit::event::WindowEvent;
 use winit::event_loop::ControlFlow;
 use winit::event_loop::EventLoop;
+use winit::keyboard::KeyCode;
+use winit::keyboard::ModifiersState;
 use winit::window::WindowBuilder;
 
 const INITIAL_WINDOW_WIDTH: u32 = 800;
 const INITIAL_WINDOW_HEIGHT: u32 = 600;
+const WINDOW_TITLE: &str = "Layout Viewer";
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
 
-pub fn spawn_window(project: Project) -> anyhow::Result<()> {
+/// Options for [spawn_window].
+#[derive(Debug, Clone, Default)]
+pub struct WindowOptions {
+    /// File to re-parse in the background whenever it changes on disk.
+    pub watch_path: Option<PathBuf>,
+    /// Layer and settings of a density heatmap to draw over the layout.
+    pub density: Option<(i16, DensityOptions)>,
+    /// Cell and settings of dummy fill to draw over the layout.
+    pub fill: Option<(CellDefId, FillOptions)>,
+    /// Rules to check, with violations marked in the viewer. N and P step
+    /// through the violations.
+    pub drc_rules: Option<Vec<DrcRule>>,
+    /// Connectivity used to extract nets. H highlights the net under the
+    /// cursor.
+    pub connectivity: Option<Connectivity>,
+    /// Settings of a geometry lint pass whose issues are marked in the
+    /// viewer.
+    pub lint: Option<LintOptions>,
+    /// Layer stack for cross-sections. X toggles drawing cut lines.
+    pub layer_stack: Option<LayerStack>,
+    /// File that each cross-section is written to as an SVG.
+    pub cross_section_svg: Option<PathBuf>,
+    /// Cell to show as the top cell initially. Double-clicking an instance
+    /// descends into its cell, and U ascends back out. The brackets show
+    /// fewer or more levels of the hierarchy.
+    pub context: Option<CellDefId>,
+    /// GDS files drawn over the project at the given placements. Keys 1 to 9
+    /// toggle them.
+    pub layouts: Vec<(PathBuf, LayoutPlacement)>,
+    /// Pattern whose matches are framed in the viewer. F3 and Shift+F3 step
+    /// through them.
+    pub search: Option<SearchPattern>,
+}
+
+/// Opens a GL window showing the given project.
+pub fn spawn_window(project: Project, options: WindowOptions) -> anyhow::Result<()> {
     let event_loop = EventLoop::new()?;
     let window_builder = WindowBuilder::new()
-        .with_title("Layout Viewer")
+        .with_title(WINDOW_TITLE)
         .with_inner_size(winit::dpi::LogicalSize::new(
             INITIAL_WINDOW_WIDTH,
             INITIAL_WINDOW_HEIGHT,
@@ -103,8 +158,59 @@ pub fn spawn_window(project: Project) -> anyhow::Result<()> {
 
     controller.set_project(project);
     controller.resize(window_size.width, window_size.height);
+    if let Some((layer, density_options)) = options.density {
+        controller.show_density(layer, density_options)?;
+    }
+    if let Some((top, fill_options)) = options.fill {
+        controller.show_fill(top, fill_options)?;
+    }
+    if let Some(rules) = options.drc_rules {
+        controller.show_drc(rules);
+    }
+    if let Some(connectivity) = options.connectivity {
+        controller.set_connectivity(connectivity);
+    }
+    if let Some(lint_options) = options.lint {
+        controller.show_lint(lint_options);
+    }
+    if let Some(pattern) = options.search {
+        controller.search(pattern);
+    }
+    if let Some(stack) = options.layer_stack {
+        controller.set_layer_stack(stack);
+    }
+    if let Some(context) = options.context {
+        controller.descend(context);
+    }
+    for (path, placement) in options.layouts {
+        let layout = Project::from_bytes(&fs::read(&path)?)?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let index = controller.add_layout(&name, layout);
+        if !placement.is_identity() {
+            if let Some(layout) = controller.layout_mut(index) {
+                layout.placement = placement;
+            }
+            controller.update_layout_placement(index);
+        }
+    }
+    window.set_title(&view_title(&controller));
+    let cross_section_svg = options.cross_section_svg;
+
+    let (reload_sender, reload_receiver) = mpsc::channel::<anyhow::Result<Project>>();
+    let _watcher = options.watch_path.map(|path| {
+        FileWatcher::new(path, WATCH_INTERVAL, move |path| {
+            log::info!("Reloading {}...", path.display());
+            let result = fs::read(path)
+                .map_err(anyhow::Error::from)
+                .and_then(|bytes| Project::from_bytes(&bytes));
+            let _ = reload_sender.send(result);
+        })
+    });
 
     let mut current_cursor_pos: Option<PhysicalPosition<f64>> = None;
+    let mut hovered_path: Option<String> = None;
+    let mut last_click: Option<Instant> = None;
+    let mut modifiers = ModifiersState::empty();
     let mut next_tick = Instant::now();
     let tick_interval = Duration::from_millis(16);
 
@@ -115,6 +221,18 @@ pub fn spawn_window(project: Project) -> anyhow::Result<()> {
 
         match event {
             Event::AboutToWait => {
+                while let Ok(result) = reload_receiver.try_recv() {
+                    match result {
+                        Ok(project) => {
+                            controller.reload_project(project);
+                            window.set_title(&view_title(&controller));
+                        }
+                        Err(err) => {
+                            log::error!("Unable to reload: {}", err);
+                            window.set_title(&format!("{} (reload failed: {})", WINDOW_TITLE, err));
+                        }
+                    }
+                }
                 let now = Instant::now();
                 if now >= next_tick {
                     if controller.tick() {
@@ -129,13 +247,120 @@ pub fn spawn_window(project: Project) -> anyhow::Result<()> {
                     window_target.exit();
                 }
                 WindowEvent::KeyboardInput { event, .. } => {
-                    use winit::keyboard::KeyCode;
                     use winit::keyboard::PhysicalKey;
                     if let PhysicalKey::Code(code) = event.physical_key {
                         if code == KeyCode::Escape || code == KeyCode::KeyQ {
                             controller.destroy();
                             window_target.exit();
                         }
+                        let step = match code {
+                            KeyCode::KeyN => 1,
+                            KeyCode::KeyP => -1,
+                            _ => 0,
+                        };
+                        if step != 0 && event.state.is_pressed() {
+                            if let Some((index, violation)) = controller.step_violation(step) {
+                                window.set_title(&format!(
+                                    "{} (violation {}: {})",
+                                    WINDOW_TITLE,
+                                    index + 1,
+                                    violation.message
+                                ));
+                            }
+                        }
+                        if code == KeyCode::F3 && event.state.is_pressed() {
+                            let step = if modifiers.shift_key() { -1 } else { 1 };
+                            let count = controller.search_matches().map_or(0, |m| m.len());
+                            if let Some((index, found)) = controller.step_search(step) {
+                                let found = found.clone();
+                                if let Some(project) = controller.project() {
+                                    window.set_title(&format!(
+                                        "{} (match {} of {}: {})",
+                                        WINDOW_TITLE,
+                                        index + 1,
+                                        count,
+                                        project.describe_search_match(&found)
+                                    ));
+                                }
+                            }
+                        }
+                        if code == KeyCode::KeyR && event.state.is_pressed() {
+                            let enabled = !controller.ruler_mode();
+                            controller.set_ruler_mode(enabled);
+                            if enabled {
+                                window.set_title(&format!("{} (ruler)", WINDOW_TITLE));
+                            } else {
+                                window.set_title(WINDOW_TITLE);
+                            }
+                        }
+                        if code == KeyCode::KeyX && event.state.is_pressed() {
+                            let enabled = !controller.cut_mode();
+                            controller.set_cut_mode(enabled);
+                            if enabled {
+                                window.set_title(&format!("{} (cross-section)", WINDOW_TITLE));
+                            } else {
+                                controller.clear_cross_section();
+                                window.set_title(WINDOW_TITLE);
+                            }
+                        }
+                        if code == KeyCode::KeyC && event.state.is_pressed() {
+                            controller.clear_rulers();
+                        }
+                        if code == KeyCode::KeyA && event.state.is_pressed() {
+                            let enabled = !controller.highlight_instance();
+                            controller.set_highlight_instance(enabled);
+                        }
+                        if code == KeyCode::KeyU && event.state.is_pressed() && controller.ascend()
+                        {
+                            window.set_title(&view_title(&controller));
+                        }
+                        let depth_step = match code {
+                            KeyCode::BracketLeft => -1,
+                            KeyCode::BracketRight => 1,
+                            _ => 0,
+                        };
+                        if depth_step != 0 && event.state.is_pressed() {
+                            controller.step_display_depth(depth_step);
+                            window.set_title(&view_title(&controller));
+                        }
+                        if event.state.is_pressed() {
+                            match handle_edit_key(&mut controller, code, modifiers) {
+                                Some(Ok(message)) => {
+                                    log::info!("{}", message);
+                                    window.set_title(&format!("{} ({})", WINDOW_TITLE, message));
+                                }
+                                Some(Err(err)) => log::warn!("Unable to edit: {}", err),
+                                None => {}
+                            }
+                        }
+                        if let Some(index) = layout_key(code).filter(|_| event.state.is_pressed()) {
+                            if let Some(layout) = controller.layout_mut(index) {
+                                layout.visible = !layout.visible;
+                                let title = format!(
+                                    "{} ({} {})",
+                                    WINDOW_TITLE,
+                                    layout.name,
+                                    if layout.visible { "shown" } else { "hidden" }
+                                );
+                                controller.update_layout_style(index);
+                                window.set_title(&title);
+                            }
+                        }
+                        if code == KeyCode::KeyI && event.state.is_pressed() {
+                            print_hovered_shape(&controller);
+                        }
+                        if code == KeyCode::KeyH && event.state.is_pressed() {
+                            match controller.highlight_hovered_net() {
+                                Some((index, net)) => window.set_title(&format!(
+                                    "{} (net {}: {} shapes on layers {:?})",
+                                    WINDOW_TITLE,
+                                    index,
+                                    net.shapes.len(),
+                                    net.layers()
+                                )),
+                                None => window.set_title(WINDOW_TITLE),
+                            }
+                        }
                     }
                 }
                 WindowEvent::MouseInput { state, button, .. } => {
@@ -143,9 +368,36 @@ pub fn spawn_window(project: Project) -> anyhow::Result<()> {
                     if button == MouseButton::Left {
                         match state {
                             winit::event::ElementState::Pressed => {
-                                if let Some(pos) = current_cursor_pos {
+                                let now = Instant::now();
+                                let double_click = last_click
+                                    .is_some_and(|last| now - last < DOUBLE_CLICK_INTERVAL);
+                                last_click = (!double_click).then_some(now);
+                                if double_click && controller.descend_hovered() {
+                                    window.set_title(&view_title(&controller));
+                                } else if let Some(pos) = current_cursor_pos {
+                                    let cut_line = |controller: &AppController| {
+                                        controller.cross_section().map(|s| (s.start, s.end))
+                                    };
+                                    let previous_cut = cut_line(&controller);
                                     controller.handle_mouse_press(pos.x as u32, pos.y as u32);
                                     controller.render();
+                                    let new_cut = cut_line(&controller) != previous_cut;
+                                    if let (true, Some(section), Some(path)) =
+                                        (new_cut, controller.cross_section(), &cross_section_svg)
+                                    {
+                                        let layers = controller.project().unwrap().layers();
+                                        let svg = generate_cross_section_svg(section, layers);
+                                        match fs::write(path, svg) {
+                                            Ok(()) => log::info!(
+                                                "Cross-section written to {}",
+                                                path.display()
+                                            ),
+                                            Err(err) => log::error!(
+                                                "Unable to write cross-section: {}",
+                                                err
+                                            ),
+                                        }
+                                    }
                                 }
                             }
                             winit::event::ElementState::Released => {
@@ -165,6 +417,9 @@ pub fn spawn_window(project: Project) -> anyhow::Result<()> {
                         controller.render();
                     }
                 }
+                WindowEvent::ModifiersChanged(new_modifiers) => {
+                    modifiers = new_modifiers.state();
+                }
                 WindowEvent::CursorMoved { position, .. } => {
                     current_cursor_pos = Some(position);
                     let x = position.x as u32;
@@ -172,6 +427,15 @@ pub fn spawn_window(project: Project) -> anyhow::Result<()> {
 
                     controller.handle_mouse_move(x, y);
                     controller.render();
+
+                    // Show the chain of instances under the cursor, keeping
+                    // the last one when the cursor leaves the geometry.
+                    if let Some(path) = controller.hovered_path() {
+                        if hovered_path.as_ref() != Some(&path) {
+                            window.set_title(&format!("{} - {}", view_title(&controller), path));
+                            hovered_path = Some(path);
+                        }
+                    }
                 }
                 WindowEvent::Resized(size) => {
                     surface.resize(
@@ -194,3 +458,87 @@ pub fn spawn_window(project: Project) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Prints everything known about the shape under the cursor.
+fn print_hovered_shape(controller: &AppController) {
+    let (Some(project), Some(info)) = (controller.project(), controller.inspect_hovered()) else {
+        println!("Nothing under the cursor");
+        return;
+    };
+    let rows = info.rows(project.units().db_unit());
+    let width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    for (label, value) in rows {
+        println!("{:width$}  {}", label, value, width = width);
+    }
+    println!();
+}
+
+/// Applies the editing shortcut bound to a key, if any, returning a message
+/// describing what was done.
+fn handle_edit_key(
+    controller: &mut AppController,
+    code: KeyCode,
+    modifiers: ModifiersState,
+) -> Option<anyhow::Result<String>> {
+    let shift = modifiers.shift_key();
+    let history = |outcome: Option<EditOutcome>, verb: &str| match outcome {
+        Some(outcome) => format!("{} {}", verb, outcome.description),
+        None => format!("nothing to {}", verb.to_lowercase()),
+    };
+    let result = match code {
+        KeyCode::KeyZ if modifiers.control_key() && shift => {
+            controller.redo().map(|o| history(o, "redo"))
+        }
+        KeyCode::KeyZ if modifiers.control_key() => controller.undo().map(|o| history(o, "undo")),
+        KeyCode::KeyY if modifiers.control_key() => controller.redo().map(|o| history(o, "redo")),
+        KeyCode::Delete if shift => controller.delete_hovered_instance().map(|o| o.description),
+        KeyCode::Delete => controller.delete_hovered_shape().map(|o| o.description),
+        KeyCode::ArrowLeft => controller
+            .nudge_hovered(-1, 0, shift)
+            .map(|o| o.description),
+        KeyCode::ArrowRight => controller.nudge_hovered(1, 0, shift).map(|o| o.description),
+        KeyCode::ArrowUp => controller
+            .nudge_hovered(0, -1, shift)
+            .map(|o| o.description),
+        KeyCode::ArrowDown => controller.nudge_hovered(0, 1, shift).map(|o| o.description),
+        KeyCode::KeyO => controller.rotate_hovered_instance().map(|o| o.description),
+        KeyCode::KeyM => controller.mirror_hovered_instance().map(|o| o.description),
+        KeyCode::PageUp => controller.step_hovered_layer(1).map(|o| o.description),
+        KeyCode::PageDown => controller.step_hovered_layer(-1).map(|o| o.description),
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// Window title naming the cell shown as the top cell and the display
+/// depth, if set.
+/// Index of the overlaid layout that a digit key toggles.
+fn layout_key(code: KeyCode) -> Option<usize> {
+    let digits = [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+        KeyCode::Digit7,
+        KeyCode::Digit8,
+        KeyCode::Digit9,
+    ];
+    digits.iter().position(|digit| *digit == code)
+}
+
+fn view_title(controller: &AppController) -> String {
+    let mut details = Vec::new();
+    if let (Some(project), Some(context)) = (controller.project(), controller.context()) {
+        details.push(project.struct_name(context).to_string());
+    }
+    if let Some(depth) = controller.display_depth() {
+        details.push(format!("depth {}", depth));
+    }
+    if details.is_empty() {
+        WINDOW_TITLE.to_string()
+    } else {
+        format!("{} ({})", WINDOW_TITLE, details.join(", "))
+    }
+}
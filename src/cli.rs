@@ -2,26 +2,415 @@ use anyhow::anyhow;
 use anyhow::Result;
 use clap::Parser;
 use colored::*;
-use layout_viewer::generate_svg;
+use geo::Coord;
+use layout_viewer::find_layout_files;
+use layout_viewer::gallery_stem;
+use layout_viewer::generate_cell_usage_csv;
+use layout_viewer::generate_cell_usage_json;
+use layout_viewer::generate_cross_section_svg;
+use layout_viewer::generate_density_csv;
+use layout_viewer::generate_density_png;
+use layout_viewer::generate_drc_json;
+use layout_viewer::generate_drc_text;
+use layout_viewer::generate_extracted_gds;
+use layout_viewer::generate_fill_gds;
+use layout_viewer::generate_flat_gds;
+use layout_viewer::generate_gallery_html;
+use layout_viewer::generate_project_png;
+use layout_viewer::generate_project_svg;
+use layout_viewer::generate_svg_with_options;
+use layout_viewer::split_derived_layer_definition;
+use layout_viewer::BoundingBox;
+use layout_viewer::CellDefId;
+use layout_viewer::DensityOptions;
+use layout_viewer::DrcReport;
+use layout_viewer::EdgeAngles;
+use layout_viewer::FillOptions;
+use layout_viewer::FillResult;
+use layout_viewer::FlattenOptions;
+use layout_viewer::GalleryEntry;
+use layout_viewer::GalleryItem;
+use layout_viewer::LayoutPlacement;
+use layout_viewer::LintKind;
+use layout_viewer::LintOptions;
+use layout_viewer::Netlist;
+use layout_viewer::PngOptions;
 use layout_viewer::Project;
+use layout_viewer::SearchPattern;
+use layout_viewer::SvgOptions;
+use layout_viewer::SvgOrientation;
+use layout_viewer::WindowOptions;
+use std::collections::HashSet;
 use std::fs;
+use std::panic;
 use std::path::Path;
 use std::path::PathBuf;
 
+const THUMBNAIL_SIZE: u32 = 256;
+const FULL_PNG_SIZE: u32 = 2048;
+const DENSITY_PNG_SIZE: u32 = 512;
+
+/// Number of nets listed by --connectivity, largest first.
+const NET_SUMMARY_COUNT: usize = 10;
+
+/// Number of cells listed by --lint, most issues first.
+const LINT_SUMMARY_COUNT: usize = 20;
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
-    /// Input GDSII file to process
+    /// Input GDSII file to process (or a directory or glob with --gallery)
     #[arg(required = true)]
     pub input: PathBuf,
 
-    /// Optional output SVG file to generate
+    /// Optional output SVG file to generate (or GDS file with --flatten or
+    /// --extract)
     #[arg(value_name = "OUTPUT.svg")]
     pub output: Option<PathBuf>,
 
     /// Request OpenGL window with interactive visualization
     #[arg(long)]
     pub gl: bool,
+
+    /// Do not reload the OpenGL window when the input file changes
+    #[arg(long)]
+    pub no_watch: bool,
+
+    /// Draw another GDS file over the input in the OpenGL window, optionally
+    /// offset, rotated and mirrored as in "die.gds@1000,-500,90,M"; may be
+    /// repeated. Keys 1 to 9 toggle the overlaid layouts
+    #[arg(long, value_name = "FILE[@DX,DY[,ANGLE][,M]]", requires = "gl")]
+    pub overlay: Vec<String>,
+
+    /// Generate a thumbnail gallery of all layouts in the input directory or
+    /// glob, writing index.html and exports to the given directory
+    #[arg(long, value_name = "OUTPUT_DIR")]
+    pub gallery: Option<PathBuf>,
+
+    /// Define a derived layer from a boolean expression over other layers,
+    /// e.g. "gate=1 AND 3" or "halo=(5 OR 6) GROW 100 ROUND"; earlier derived
+    /// layers are referred to by name; may be repeated
+    #[arg(long, value_name = "NAME=EXPR")]
+    pub derive: Vec<String>,
+
+    /// Compute windowed pattern density of the given layer
+    #[arg(long, value_name = "LAYER")]
+    pub density: Option<i16>,
+
+    /// Size of each density window, defaults to 1/20 of the layout size
+    #[arg(long, value_name = "SIZE", requires = "density")]
+    pub density_window: Option<f64>,
+
+    /// Distance between density windows, defaults to half the window size
+    #[arg(long, value_name = "SIZE", requires = "density")]
+    pub density_step: Option<f64>,
+
+    /// Report windows with a density below this fraction
+    #[arg(long, value_name = "FRACTION", requires = "density")]
+    pub density_min: Option<f64>,
+
+    /// Report windows with a density above this fraction
+    #[arg(long, value_name = "FRACTION", requires = "density")]
+    pub density_max: Option<f64>,
+
+    /// Write the density of every window to a CSV file
+    #[arg(long, value_name = "OUTPUT.csv", requires = "density")]
+    pub density_csv: Option<PathBuf>,
+
+    /// Write the density map as a heatmap image
+    #[arg(long, value_name = "OUTPUT.png", requires = "density")]
+    pub density_png: Option<PathBuf>,
+
+    /// Generate dummy fill on the given layer
+    #[arg(long, value_name = "LAYER")]
+    pub fill: Option<i16>,
+
+    /// Density that each fill window is filled up to
+    #[arg(
+        long,
+        value_name = "FRACTION",
+        default_value_t = 0.3,
+        requires = "fill"
+    )]
+    pub fill_density: f64,
+
+    /// Side length of the square fill shapes, defaults to 1/20 of the window
+    #[arg(long, value_name = "SIZE", requires = "fill")]
+    pub fill_size: Option<f64>,
+
+    /// Gap between fill shapes, defaults to half their size
+    #[arg(long, value_name = "SIZE", requires = "fill")]
+    pub fill_spacing: Option<f64>,
+
+    /// Distance between fill shapes and existing geometry, defaults to their
+    /// size
+    #[arg(long, value_name = "SIZE", requires = "fill")]
+    pub fill_keep_out: Option<f64>,
+
+    /// Size of the windows that are filled up to the target density,
+    /// defaults to 1/20 of the layout size
+    #[arg(long, value_name = "SIZE", requires = "fill")]
+    pub fill_window: Option<f64>,
+
+    /// Comma-separated list of other layers that fill must keep out of
+    #[arg(long, value_delimiter = ',', value_name = "LAYERS", requires = "fill")]
+    pub fill_blocking: Vec<i16>,
+
+    /// Datatype of the fill shapes in the output GDS
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "fill")]
+    pub fill_datatype: i16,
+
+    /// Write the cell given by --top, or the first root cell, together with
+    /// the fill to a GDS file
+    #[arg(long, value_name = "OUTPUT.gds", requires = "fill")]
+    pub fill_gds: Option<PathBuf>,
+
+    /// Print how often each cell is placed and how much area it adds to the
+    /// flattened layout, largest first
+    #[arg(long)]
+    pub cell_usage: bool,
+
+    /// Write the usage and area of every cell to a CSV or JSON report
+    #[arg(long, value_name = "OUTPUT.{csv,json}", requires = "cell_usage")]
+    pub cell_usage_report: Option<PathBuf>,
+
+    /// List the instances whose cell name matches, and the labels that
+    /// match, with their world bounds. Text without * or ? is looked for
+    /// within names, and /EXPR/ is a regular expression. With --gl, F3 and
+    /// Shift+F3 step through the matches
+    #[arg(long, value_name = "PATTERN")]
+    pub search: Option<String>,
+
+    /// Maximum number of search matches to list
+    #[arg(long, value_name = "N", default_value_t = 1000, requires = "search")]
+    pub search_limit: usize,
+
+    /// Check the layout against the design rules in the given file
+    #[arg(long, value_name = "RULES")]
+    pub drc: Option<PathBuf>,
+
+    /// Write every rule violation to a text or JSON report
+    #[arg(long, value_name = "OUTPUT.{txt,json}", requires = "drc")]
+    pub drc_report: Option<PathBuf>,
+
+    /// Extract nets using the layer connectivity in the given file
+    #[arg(long, value_name = "FILE")]
+    pub connectivity: Option<PathBuf>,
+
+    /// Check every cell for off-grid vertices, acute angles, disallowed edge
+    /// angles and self-intersecting boundaries
+    #[arg(long)]
+    pub lint: bool,
+
+    /// Manufacturing grid in database units
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "lint")]
+    pub lint_grid: i32,
+
+    /// Report corners sharper than this many degrees
+    #[arg(
+        long,
+        value_name = "DEGREES",
+        default_value_t = 90.0,
+        requires = "lint"
+    )]
+    pub lint_min_angle: f64,
+
+    /// Comma-separated list of layers that only allow horizontal and vertical
+    /// edges
+    #[arg(long, value_delimiter = ',', value_name = "LAYERS", requires = "lint")]
+    pub lint_manhattan: Vec<i16>,
+
+    /// Comma-separated list of layers that also allow 45 degree edges
+    #[arg(long, value_delimiter = ',', value_name = "LAYERS", requires = "lint")]
+    pub lint_octilinear: Vec<i16>,
+
+    /// Layer stack for cross-sections, with a layer, height and thickness per
+    /// line
+    #[arg(long, value_name = "FILE")]
+    pub stack: Option<PathBuf>,
+
+    /// Line to take a cross-section along
+    #[arg(
+        long,
+        value_delimiter = ',',
+        allow_negative_numbers = true,
+        value_name = "X0,Y0,X1,Y1",
+        requires = "cross_section"
+    )]
+    pub cut: Option<Vec<f64>>,
+
+    /// Write the cross-section along --cut, or along lines drawn in the
+    /// viewer, to an SVG file
+    #[arg(long, value_name = "OUTPUT.svg")]
+    pub cross_section: Option<PathBuf>,
+
+    /// Flatten the cell hierarchy before writing the output
+    #[arg(long)]
+    pub flatten: bool,
+
+    /// Cell to flatten or fill, defaults to --cell or the first root cell
+    #[arg(long, value_name = "NAME")]
+    pub top: Option<String>,
+
+    /// Show only the named cell and its descendants, as if it were the top
+    /// cell, for statistics, SVG output and the viewer
+    #[arg(long, value_name = "NAME")]
+    pub cell: Option<String>,
+
+    /// Draw instances nested deeper than this as boxes labelled with the
+    /// cell name, in SVG output and the viewer. The top cell is at depth 0
+    #[arg(long, value_name = "N")]
+    pub display_depth: Option<usize>,
+
+    /// Keep instances nested deeper than this as references when flattening
+    #[arg(long, value_name = "N")]
+    pub depth: Option<usize>,
+
+    /// Merge overlapping polygons within each layer. This applies to every
+    /// output at once: the layers drawn in SVG output and the viewer, the
+    /// printed area of each layer, and the polygons written to GDS output by
+    /// --flatten
+    #[arg(long)]
+    pub merge: bool,
+
+    /// Write the named cell and all cells it references to the output GDS
+    #[arg(long, value_name = "NAME", conflicts_with = "flatten")]
+    pub extract: Option<String>,
+
+    /// Prefix prepended to every cell name when extracting
+    #[arg(long, requires = "extract")]
+    pub prefix: Option<String>,
+
+    /// Comma-separated list of layers to include in the SVG
+    #[arg(long, value_delimiter = ',', value_name = "LAYERS")]
+    pub svg_layers: Option<Vec<i16>>,
+
+    /// World-space window to crop the SVG to
+    #[arg(
+        long,
+        value_delimiter = ',',
+        allow_negative_numbers = true,
+        value_name = "MIN_X,MIN_Y,MAX_X,MAX_Y"
+    )]
+    pub svg_window: Option<Vec<f64>>,
+
+    /// SVG orientation: transposed (as in the viewer), y-up or y-down
+    #[arg(long, default_value = "transposed")]
+    pub svg_orientation: SvgOrientation,
+
+    /// SVG background color, or "none" for a transparent background
+    #[arg(long, default_value = "#2D2D2D")]
+    pub svg_background: String,
+
+    /// Opacity of each layer in the SVG
+    #[arg(long, default_value_t = 0.5)]
+    pub svg_opacity: f64,
+
+    /// Outline color for SVG polygons
+    #[arg(long)]
+    pub svg_stroke: Option<String>,
+
+    /// Outline width for SVG polygons and placeholder boxes, in world units
+    #[arg(long, default_value_t = 1.0)]
+    pub svg_stroke_width: f64,
+
+    /// SVG coordinates are rounded to a multiple of this value
+    #[arg(long, default_value_t = 0.0001)]
+    pub svg_precision: f64,
+}
+
+impl Args {
+    /// Returns whether any of the requested reports or outputs read the
+    /// flattened layers, see [Project::flatten_layers].
+    fn needs_flat_layers(&self) -> bool {
+        self.density.is_some()
+            || self.drc.is_some()
+            || self.connectivity.is_some()
+            || self.cut.is_some()
+    }
+
+    fn flatten_options(&self) -> FlattenOptions {
+        FlattenOptions {
+            max_depth: self.depth,
+            merge: self.merge,
+            layers: None,
+        }
+    }
+
+    fn lint_options(&self) -> LintOptions {
+        let mut options = LintOptions {
+            grid: self.lint_grid,
+            min_angle: self.lint_min_angle,
+            ..LintOptions::default()
+        };
+        for &layer in &self.lint_octilinear {
+            options.edge_angles.insert(layer, EdgeAngles::Octilinear);
+        }
+        for &layer in &self.lint_manhattan {
+            options.edge_angles.insert(layer, EdgeAngles::Manhattan);
+        }
+        options
+    }
+
+    fn density_options(&self, project: &Project) -> DensityOptions {
+        let mut options = DensityOptions::for_bounds(&project.bounds());
+        if let Some(window) = self.density_window {
+            options.window = window;
+            options.step = window / 2.0;
+        }
+        if let Some(step) = self.density_step {
+            options.step = step;
+        }
+        options.min_density = self.density_min;
+        options.max_density = self.density_max;
+        options
+    }
+
+    fn fill_options(&self, project: &Project, layer: i16) -> FillOptions {
+        let mut options = FillOptions::for_bounds(layer, self.fill_density, &project.bounds());
+        if let Some(window) = self.fill_window {
+            options.density.window = window;
+            options.size = (window / 20.0).max(1.0).round();
+            options.spacing = (options.size / 2.0).round();
+            options.keep_out = options.size;
+        }
+        if let Some(size) = self.fill_size {
+            options.size = size;
+            options.spacing = (size / 2.0).round();
+            options.keep_out = size;
+        }
+        if let Some(spacing) = self.fill_spacing {
+            options.spacing = spacing;
+        }
+        if let Some(keep_out) = self.fill_keep_out {
+            options.keep_out = keep_out;
+        }
+        options.blocking_layers = self.fill_blocking.clone();
+        options
+    }
+
+    fn svg_options(&self) -> Result<SvgOptions> {
+        let window = match self.svg_window.as_deref() {
+            None => None,
+            Some([x0, y0, x1, y1]) => Some(BoundingBox {
+                min_x: x0.min(*x1),
+                min_y: y0.min(*y1),
+                max_x: x0.max(*x1),
+                max_y: y0.max(*y1),
+            }),
+            Some(_) => return Err(anyhow!("--svg-window expects four comma-separated values")),
+        };
+        Ok(SvgOptions {
+            layers: self.svg_layers.clone(),
+            window,
+            orientation: self.svg_orientation,
+            background: (self.svg_background != "none").then(|| self.svg_background.clone()),
+            opacity: self.svg_opacity,
+            stroke: self.svg_stroke.clone(),
+            stroke_width: self.svg_stroke_width,
+            precision: self.svg_precision,
+        })
+    }
 }
 
 fn verify_file_extension(path: &Path, expected: &str) -> Result<()> {
@@ -49,10 +438,24 @@ pub fn run_cli() -> Result<()> {
 
     let args = Args::parse();
 
+    if let Some(ref output_dir) = args.gallery {
+        return build_gallery(&args.input, output_dir);
+    }
+
     // Verify file extensions
     verify_file_extension(&args.input, "gds")?;
     if let Some(ref output_path) = args.output {
-        verify_file_extension(output_path, "svg")?;
+        if args.extract.is_some() {
+            verify_file_extension(output_path, "gds")?;
+        } else if !args.flatten || verify_file_extension(output_path, "gds").is_err() {
+            verify_file_extension(output_path, "svg")?;
+        }
+    }
+    if args.depth.is_some() && !args.flatten {
+        return Err(anyhow!("--depth requires --flatten"));
+    }
+    if args.top.is_some() && !args.flatten && args.fill.is_none() {
+        return Err(anyhow!("--top requires --flatten or --fill"));
     }
 
     println!(
@@ -62,7 +465,17 @@ pub fn run_cli() -> Result<()> {
 
     // Read and process the GDSII file
     let file_content = fs::read(&args.input)?;
-    let project = Project::from_bytes(&file_content)?;
+    let mut project = Project::from_bytes(&file_content)?;
+    if let Some(ref name) = args.cell {
+        let context = find_top_cell(&project, Some(name))?;
+        project.set_context(Some(context));
+    }
+    if args.display_depth.is_some() {
+        project.set_display_depth(args.display_depth);
+    }
+    if args.needs_flat_layers() {
+        project.flatten_layers();
+    }
 
     let stats = project.stats();
     println!(
@@ -108,19 +521,592 @@ pub fn run_cli() -> Result<()> {
         println!("{}", "No root cell found".color(Color::Red));
     }
 
-    // Generate and save SVG if output path is provided
-    if let Some(ref output_path) = args.output {
-        let svg_content = generate_svg(project.layers());
+    if let Some(depth) = project.display_depth() {
+        println!(
+            "{:<12} {} instances below depth {}",
+            "Placeholders".color(Color::BrightYellow),
+            project.placeholders().len(),
+            depth
+        );
+    }
+
+    for definition in &args.derive {
+        let (name, expression) = split_derived_layer_definition(definition)?;
+        let index = project.add_derived_layer(name, expression)?;
+        println!(
+            "{:<12} {} = layer {} ({} polygons)",
+            "Derived".color(Color::BrightYellow),
+            name,
+            index,
+            project.layers()[index as usize].polygons.len()
+        );
+    }
+
+    if args.merge {
+        project.merge_layers();
+        for layer in project.layers() {
+            if layer.polygons.is_empty() {
+                continue;
+            }
+            println!(
+                "{:<12} {} polygons merged into {}, area {}",
+                layer.display_name().color(Color::Green),
+                layer.polygons.len(),
+                layer.render_polygons().len(),
+                pretty_print_float(layer.area())
+            );
+        }
+    }
+
+    let mut density = None;
+    if let Some(layer) = args.density {
+        let options = args.density_options(&project);
+        report_density(&project, layer, &options, &args)?;
+        density = Some((layer, options));
+    }
+
+    let mut fill = None;
+    if let Some(layer) = args.fill {
+        let top = find_top_cell(&project, args.top.as_deref())?;
+        let options = args.fill_options(&project, layer);
+        let result = project.generate_fill(top, &options)?;
+        report_fill(&result);
+        if let Some(ref path) = args.fill_gds {
+            verify_file_extension(path, "gds")?;
+            fs::write(
+                path,
+                generate_fill_gds(&project, &result, args.fill_datatype)?,
+            )?;
+            println!("GDS file written to: {}", path.display());
+        }
+        fill = Some((top, options));
+    }
+
+    let mut drc_rules = None;
+    if let Some(ref path) = args.drc {
+        let rules = project.parse_drc_rules(&fs::read_to_string(path)?)?;
+        let report = project.check_drc(&rules);
+        report_drc(&report, &args)?;
+        drc_rules = Some(rules);
+    }
+
+    if args.cell_usage {
+        report_cell_usage(&project, &args)?;
+    }
+
+    let search = match args.search {
+        Some(ref pattern) => Some(SearchPattern::parse(pattern)?),
+        None => None,
+    };
+    if let Some(ref pattern) = search {
+        report_search(&project, pattern, args.search_limit);
+    }
+
+    let mut connectivity = None;
+    if let Some(ref path) = args.connectivity {
+        let definition = project.parse_connectivity(&fs::read_to_string(path)?)?;
+        report_nets(&project.extract_nets(&definition));
+        connectivity = Some(definition);
+    }
+
+    let lint = args.lint.then(|| args.lint_options());
+    if let Some(ref options) = lint {
+        report_lint(&project, options);
+    }
+
+    let layer_stack = match args.stack {
+        Some(ref path) => Some(project.parse_layer_stack(&fs::read_to_string(path)?)?),
+        None => None,
+    };
+    if let Some(ref cut) = args.cut {
+        let [x0, y0, x1, y1] = cut[..] else {
+            return Err(anyhow!("--cut expects X0,Y0,X1,Y1"));
+        };
+        let stack = layer_stack
+            .clone()
+            .unwrap_or_else(|| project.default_layer_stack());
+        let section = project.cross_section(&stack, Coord { x: x0, y: y0 }, Coord { x: x1, y: y1 });
+        println!(
+            "{:<12} {} segments along {}",
+            "Section".color(Color::BrightYellow),
+            section.rects.len(),
+            pretty_print_float(section.length)
+        );
+        if let Some(ref path) = args.cross_section {
+            verify_file_extension(path, "svg")?;
+            fs::write(path, generate_cross_section_svg(&section, project.layers()))?;
+            println!("SVG file written to: {}", path.display());
+        }
+    }
+
+    if let Some(ref name) = args.extract {
+        let cell_def_id = find_top_cell(&project, Some(name))?;
+        let cell_count = project.collect_dependencies(&[cell_def_id]).len();
+        println!(
+            "{:<12} {} cells",
+            "Extracted".color(Color::BrightYellow),
+            cell_count
+        );
+
+        let output_path = args
+            .output
+            .as_ref()
+            .ok_or_else(|| anyhow!("--extract requires an output GDS file"))?;
+        let gds_content = generate_extracted_gds(&project, cell_def_id, args.prefix.as_deref())?;
+        fs::write(output_path, gds_content)?;
+        println!("GDS file written to: {}", output_path.display());
+    } else if args.flatten {
+        let top = find_top_cell(&project, args.top.as_deref())?;
+        let flat = project.flatten(top, &args.flatten_options());
+        let polygon_count: usize = flat.layers.iter().map(|l| l.polygons.len()).sum();
+        println!(
+            "{:<12} {} polygons, {} references",
+            "Flattened".color(Color::BrightYellow),
+            polygon_count,
+            flat.references.len()
+        );
+
+        if let Some(ref output_path) = args.output {
+            if verify_file_extension(output_path, "gds").is_ok() {
+                fs::write(output_path, generate_flat_gds(&project, &flat)?)?;
+                println!("GDS file written to: {}", output_path.display());
+            } else {
+                let svg_content = generate_svg_with_options(&flat.layers, &args.svg_options()?);
+                fs::write(output_path, svg_content)?;
+                println!("SVG file written to: {}", output_path.display());
+            }
+        }
+    } else if let Some(ref output_path) = args.output {
+        // Generate and save SVG if output path is provided
+        let placeholders: Vec<(BoundingBox, String)> = project
+            .placeholders()
+            .iter()
+            .map(|p| (p.bounds, project.struct_name(p.cell_def_id).to_string()))
+            .collect();
+        let svg_content = generate_project_svg(&project, &placeholders, &args.svg_options()?);
 
         fs::write(output_path, svg_content)?;
         println!("SVG file written to: {}", output_path.display());
     }
 
+    let layouts = args
+        .overlay
+        .iter()
+        .map(|spec| parse_overlay(spec))
+        .collect::<Result<Vec<_>>>()?;
+
     println!();
 
     if args.gl {
-        layout_viewer::spawn_window(project)?;
+        // The viewer descends into the cell itself, so that it can ascend
+        // back to the whole library.
+        let context = project.context();
+        if context.is_some() {
+            project.set_context(None);
+        }
+        let options = WindowOptions {
+            watch_path: (!args.no_watch).then(|| args.input.clone()),
+            density,
+            fill,
+            drc_rules,
+            connectivity,
+            lint,
+            layer_stack,
+            cross_section_svg: args.cross_section.clone(),
+            context,
+            layouts,
+            search,
+        };
+        layout_viewer::spawn_window(project, options)?;
+    }
+
+    Ok(())
+}
+
+fn report_density(
+    project: &Project,
+    layer: i16,
+    options: &DensityOptions,
+    args: &Args,
+) -> Result<()> {
+    let map = project.compute_density(layer, options)?;
+    println!(
+        "{:<12} layer {}, {} x {} windows of {} every {}",
+        "Density".color(Color::BrightYellow),
+        layer,
+        map.cols,
+        map.rows,
+        pretty_print_float(map.window),
+        pretty_print_float(map.step)
+    );
+    println!(
+        "{:<12} min {:.3}, max {:.3}, mean {:.3}",
+        "",
+        map.min(),
+        map.max(),
+        map.mean()
+    );
+
+    if map.min_density.is_some() || map.max_density.is_some() {
+        let violations = map.violations();
+        if violations.is_empty() {
+            println!("{:<12} none", "Failing".color(Color::Green));
+        } else {
+            println!(
+                "{:<12} {} failing windows",
+                "Failing".color(Color::Red),
+                violations.len()
+            );
+            for violation in violations {
+                let bounds = map.window_bounds(violation.col, violation.row);
+                println!(
+                    "{:<12} ({}, {}) to ({}, {}): {:.3}",
+                    "",
+                    pretty_print_float(bounds.min_x),
+                    pretty_print_float(bounds.min_y),
+                    pretty_print_float(bounds.max_x),
+                    pretty_print_float(bounds.max_y),
+                    violation.density
+                );
+            }
+        }
+    }
+
+    if let Some(ref path) = args.density_csv {
+        verify_file_extension(path, "csv")?;
+        fs::write(path, generate_density_csv(&map))?;
+        println!("CSV file written to: {}", path.display());
+    }
+    if let Some(ref path) = args.density_png {
+        verify_file_extension(path, "png")?;
+        fs::write(path, generate_density_png(&map, DENSITY_PNG_SIZE))?;
+        println!("PNG file written to: {}", path.display());
+    }
+
+    Ok(())
+}
+
+fn report_fill(fill: &FillResult) {
+    println!(
+        "{:<12} layer {}, {} shapes in {} x {} windows of {}",
+        "Fill".color(Color::BrightYellow),
+        fill.layer,
+        fill.polygons.len(),
+        fill.after.cols,
+        fill.after.rows,
+        pretty_print_float(fill.after.window)
+    );
+    for (label, map) in [("Before", &fill.before), ("After", &fill.after)] {
+        println!(
+            "{:<12} min {:.3}, max {:.3}, mean {:.3}",
+            label,
+            map.min(),
+            map.max(),
+            map.mean()
+        );
+    }
+}
+
+/// Number of cells listed by --cell-usage, the rest go to the report file.
+const CELL_USAGE_ROWS: usize = 10;
+
+fn report_cell_usage(project: &Project, args: &Args) -> Result<()> {
+    let usage = project.cell_usage();
+    let total_area: f64 = usage.iter().map(|cell| cell.total_area).sum();
+    println!(
+        "{:<12} {} cells, {} of shapes",
+        "Cell usage".color(Color::BrightYellow),
+        usage.len(),
+        project.format_area(total_area)
+    );
+    for cell in usage.iter().take(CELL_USAGE_ROWS) {
+        let share = if total_area > 0.0 {
+            cell.total_area / total_area
+        } else {
+            0.0
+        };
+        println!(
+            "{:<12} {} x{} ({} direct), {} each, {} total ({:.1}%)",
+            "",
+            cell.name,
+            cell.flattened_instances,
+            cell.direct_instances,
+            project.format_area(cell.own_area),
+            project.format_area(cell.total_area),
+            share * 100.0
+        );
+    }
+    let unreferenced: Vec<&str> = usage
+        .iter()
+        .filter(|cell| cell.is_unreferenced())
+        .map(|cell| cell.name.as_str())
+        .collect();
+    println!(
+        "{:<12} {}",
+        "Unreferenced".color(Color::BrightYellow),
+        unreferenced.join(", ")
+    );
+
+    if let Some(ref path) = args.cell_usage_report {
+        let content = if verify_file_extension(path, "json").is_ok() {
+            generate_cell_usage_json(&usage)
+        } else {
+            verify_file_extension(path, "csv")?;
+            generate_cell_usage_csv(&usage)
+        };
+        fs::write(path, content)?;
+        println!("Cell usage report written to: {}", path.display());
+    }
+
+    Ok(())
+}
+
+fn report_search(project: &Project, pattern: &SearchPattern, limit: usize) {
+    let matches = project.search(pattern, limit);
+    let label_count = matches.iter().filter(|found| found.label.is_some()).count();
+    println!(
+        "{:<12} {} instances, {} labels{}",
+        "Search".color(Color::BrightYellow),
+        matches.len() - label_count,
+        label_count,
+        if matches.len() == limit {
+            format!(" (stopped after {})", limit)
+        } else {
+            String::new()
+        }
+    );
+    for found in &matches {
+        println!(
+            "{:<12} {}, ({}, {}) to ({}, {})",
+            "",
+            project.describe_search_match(found),
+            pretty_print_float(found.bounds.min_x),
+            pretty_print_float(found.bounds.min_y),
+            pretty_print_float(found.bounds.max_x),
+            pretty_print_float(found.bounds.max_y)
+        );
+    }
+}
+
+fn report_drc(report: &DrcReport, args: &Args) -> Result<()> {
+    for (rule, count) in report.rules.iter().zip(report.counts()) {
+        let label = if count == 0 {
+            "Passed".color(Color::Green)
+        } else {
+            "Failed".color(Color::Red)
+        };
+        println!("{:<12} {} ({} violations)", label, rule, count);
+    }
+
+    if let Some(ref path) = args.drc_report {
+        let content = if verify_file_extension(path, "json").is_ok() {
+            generate_drc_json(report)
+        } else {
+            verify_file_extension(path, "txt")?;
+            generate_drc_text(report)
+        };
+        fs::write(path, content)?;
+        println!("DRC report written to: {}", path.display());
     }
 
     Ok(())
 }
+
+fn report_nets(netlist: &Netlist) {
+    let shape_count: usize = netlist.nets.iter().map(|net| net.shapes.len()).sum();
+    println!(
+        "{:<12} {} nets, {} shapes",
+        "Nets".color(Color::BrightYellow),
+        netlist.nets.len(),
+        shape_count
+    );
+    for (index, net) in netlist.nets.iter().take(NET_SUMMARY_COUNT).enumerate() {
+        println!(
+            "{:<12} {} shapes on layers {:?}, ({}, {}) to ({}, {})",
+            format!("Net {}", index),
+            net.shapes.len(),
+            net.layers(),
+            pretty_print_float(net.bounds.min_x),
+            pretty_print_float(net.bounds.min_y),
+            pretty_print_float(net.bounds.max_x),
+            pretty_print_float(net.bounds.max_y)
+        );
+    }
+}
+
+fn report_lint(project: &Project, options: &LintOptions) {
+    let mut results = project.lint(options);
+    if results.is_empty() {
+        println!("{:<12} no issues", "Lint".color(Color::Green));
+        return;
+    }
+    results.sort_by_key(|result| std::cmp::Reverse(result.issues.len()));
+
+    let issue_count: usize = results.iter().map(|result| result.issues.len()).sum();
+    println!(
+        "{:<12} {} issues in {} cells",
+        "Lint".color(Color::Red),
+        issue_count,
+        results.len()
+    );
+    for kind in LintKind::ALL {
+        let count: usize = results.iter().map(|result| result.count(kind)).sum();
+        if count > 0 {
+            println!("{:<12} {} {}", "", count, kind);
+        }
+    }
+    for result in results.iter().take(LINT_SUMMARY_COUNT) {
+        let counts: Vec<String> = LintKind::ALL
+            .iter()
+            .map(|&kind| (kind, result.count(kind)))
+            .filter(|(_, count)| *count > 0)
+            .map(|(kind, count)| format!("{} {}", count, kind))
+            .collect();
+        println!(
+            "{:<12} {}",
+            project.struct_name(result.cell_def_id).color(Color::Yellow),
+            counts.join(", ")
+        );
+    }
+    if results.len() > LINT_SUMMARY_COUNT {
+        println!(
+            "{:<12} ... and {} more cells",
+            "",
+            results.len() - LINT_SUMMARY_COUNT
+        );
+    }
+}
+
+fn find_top_cell(project: &Project, name: Option<&str>) -> Result<CellDefId> {
+    match name {
+        Some(name) => project
+            .find_cell_def(name)
+            .ok_or_else(|| anyhow!("Cell '{}' not found", name)),
+        None => project
+            .context()
+            .or_else(|| project.find_roots().into_iter().next())
+            .ok_or_else(|| anyhow!("No root cell found to flatten")),
+    }
+}
+
+fn build_gallery(input: &Path, output_dir: &Path) -> Result<()> {
+    let files = find_layout_files(input)?;
+    if files.is_empty() {
+        return Err(anyhow!("No GDS files found in '{}'", input.display()));
+    }
+    fs::create_dir_all(output_dir)?;
+
+    let root = if input.is_dir() { input } else { Path::new("") };
+    let mut entries = Vec::new();
+    let mut stems = HashSet::new();
+    for path in files {
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+        let name = relative.to_string_lossy().replace('\\', "/");
+        let stem = gallery_stem(&name, &mut stems);
+
+        let outcome = load_project(&path)
+            .and_then(|project| export_gallery_item(&project, output_dir, &stem));
+        match &outcome {
+            Ok(_) => println!("{:<8} {}", "OK".color(Color::Green), name),
+            Err(err) => println!("{:<8} {}: {}", "FAILED".color(Color::Red), name, err),
+        }
+        let outcome = outcome.map_err(|err| err.to_string());
+        entries.push(GalleryEntry { name, outcome });
+    }
+
+    let title = format!("Layouts in {}", input.display());
+    let index_path = output_dir.join("index.html");
+    fs::write(&index_path, generate_gallery_html(&title, &entries))?;
+    println!("Gallery written to: {}", index_path.display());
+
+    Ok(())
+}
+
+/// Splits an --overlay argument into the file and its placement. The text
+/// after the last '@' is only taken as a placement if it parses as one, so
+/// that file names may contain '@'.
+fn parse_overlay(spec: &str) -> Result<(PathBuf, LayoutPlacement)> {
+    if let Some((path, placement)) = spec.rsplit_once('@') {
+        if let Ok(placement) = LayoutPlacement::parse(placement) {
+            return Ok((PathBuf::from(path), placement));
+        }
+    }
+    let path = PathBuf::from(spec);
+    if !path.is_file() {
+        return Err(anyhow!("Overlay file not found: {}", spec));
+    }
+    Ok((path, LayoutPlacement::default()))
+}
+
+/// Reads and parses a GDS file, converting panics in the parser into errors
+/// so that one bad file does not abort a batch.
+fn load_project(path: &Path) -> Result<Project> {
+    let bytes = fs::read(path)?;
+    panic::catch_unwind(|| Project::from_bytes(&bytes))
+        .unwrap_or_else(|_| Err(anyhow!("Parser panicked")))
+}
+
+fn export_gallery_item(project: &Project, output_dir: &Path, stem: &str) -> Result<GalleryItem> {
+    let svg = format!("{}.svg", stem);
+    fs::write(
+        output_dir.join(&svg),
+        generate_project_svg(project, &[], &SvgOptions::default()),
+    )?;
+
+    let thumbnail = format!("{}.thumb.png", stem);
+    let options = PngOptions {
+        size: THUMBNAIL_SIZE,
+        ..Default::default()
+    };
+    fs::write(
+        output_dir.join(&thumbnail),
+        generate_project_png(project, &options),
+    )?;
+
+    let png = format!("{}.png", stem);
+    let options = PngOptions {
+        size: FULL_PNG_SIZE,
+        ..Default::default()
+    };
+    fs::write(
+        output_dir.join(&png),
+        generate_project_png(project, &options),
+    )?;
+
+    Ok(GalleryItem {
+        thumbnail,
+        svg,
+        png,
+        stats: stats_table(project),
+    })
+}
+
+fn stats_table(project: &Project) -> Vec<(String, String)> {
+    let stats = project.stats();
+    let bounds = project.bounds();
+    let roots: Vec<&str> = project
+        .find_roots()
+        .into_iter()
+        .map(|root_id| project.struct_name(root_id))
+        .collect();
+    vec![
+        ("Structs".to_string(), stats.struct_count.to_string()),
+        ("Boundaries".to_string(), stats.polygon_count.to_string()),
+        ("Paths".to_string(), stats.path_count.to_string()),
+        ("SRefs".to_string(), stats.sref_count.to_string()),
+        ("ARefs".to_string(), stats.aref_count.to_string()),
+        (
+            "Layers".to_string(),
+            ((project.highest_layer() + 1) as usize).to_string(),
+        ),
+        (
+            "Size".to_string(),
+            format!(
+                "{} x {}",
+                pretty_print_float(bounds.width()),
+                pretty_print_float(bounds.height())
+            ),
+        ),
+        ("Roots".to_string(), roots.join(", ")),
+    ]
+}
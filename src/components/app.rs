@@ -9,12 +9,15 @@ pub enum Route {
     Home,
     #[at("/:id")]
     Viewer { id: String },
+    #[at("/:id/:cell")]
+    ViewerCell { id: String, cell: String },
 }
 
 fn switch(routes: Route) -> Html {
     match routes {
         Route::Home => html! { <HomePage /> },
         Route::Viewer { id } => html! { <ViewerPage id={id} /> },
+        Route::ViewerCell { id, cell } => html! { <ViewerPage id={id} cell={cell} /> },
     }
 }
 
@@ -0,0 +1,176 @@
+use std::cmp::Ordering;
+
+use yew::prelude::*;
+
+use crate::core::format_area;
+use crate::core::CellDefId;
+use crate::core::CellUsage;
+use crate::generate_cell_usage::generate_cell_usage_csv;
+
+#[derive(Properties, PartialEq)]
+pub struct CellUsagePanelProps {
+    /// Usage of every cell, see [Project::cell_usage](crate::Project::cell_usage).
+    pub usage: Vec<CellUsage>,
+    /// Size of a database unit in meters, for formatting areas.
+    pub db_unit: f64,
+    /// Called with the cell whose name was clicked.
+    pub on_select: Callback<CellDefId>,
+    pub on_close: Callback<()>,
+}
+
+/// Column that the table is sorted by.
+#[derive(Clone, Copy, PartialEq)]
+pub enum CellUsageColumn {
+    Name,
+    Direct,
+    Flattened,
+    OwnArea,
+    TotalArea,
+}
+
+impl CellUsageColumn {
+    const ALL: [CellUsageColumn; 5] = [
+        CellUsageColumn::Name,
+        CellUsageColumn::Direct,
+        CellUsageColumn::Flattened,
+        CellUsageColumn::OwnArea,
+        CellUsageColumn::TotalArea,
+    ];
+
+    fn title(&self) -> &'static str {
+        match self {
+            CellUsageColumn::Name => "Cell",
+            CellUsageColumn::Direct => "Direct",
+            CellUsageColumn::Flattened => "Flat",
+            CellUsageColumn::OwnArea => "Own area",
+            CellUsageColumn::TotalArea => "Total area",
+        }
+    }
+
+    fn tooltip(&self) -> &'static str {
+        match self {
+            CellUsageColumn::Name => "Click a cell to show it as the top cell",
+            CellUsageColumn::Direct => "References from other cells",
+            CellUsageColumn::Flattened => "Copies once the top cells are flattened",
+            CellUsageColumn::OwnArea => "Area of the shapes of the cell itself",
+            CellUsageColumn::TotalArea => "Area that all copies add to the layout",
+        }
+    }
+
+    fn compare(&self, a: &CellUsage, b: &CellUsage) -> Ordering {
+        match self {
+            CellUsageColumn::Name => a.name.cmp(&b.name),
+            CellUsageColumn::Direct => a.direct_instances.cmp(&b.direct_instances),
+            CellUsageColumn::Flattened => a.flattened_instances.cmp(&b.flattened_instances),
+            CellUsageColumn::OwnArea => a.own_area.total_cmp(&b.own_area),
+            CellUsageColumn::TotalArea => a.total_area.total_cmp(&b.total_area),
+        }
+    }
+}
+
+pub enum CellUsagePanelMsg {
+    SortBy(CellUsageColumn),
+}
+
+/// Table of how often each cell is used and how much area it takes, sorted
+/// by clicking the column headers.
+pub struct CellUsagePanel {
+    column: CellUsageColumn,
+    descending: bool,
+}
+
+impl Component for CellUsagePanel {
+    type Message = CellUsagePanelMsg;
+    type Properties = CellUsagePanelProps;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            column: CellUsageColumn::TotalArea,
+            descending: true,
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let props = ctx.props();
+        let mut usage: Vec<&CellUsage> = props.usage.iter().collect();
+        usage.sort_by(|a, b| {
+            let order = self.column.compare(a, b);
+            if self.descending {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+        let total_area: f64 = props.usage.iter().map(|cell| cell.total_area).sum();
+        let on_close = props.on_close.reform(|_: MouseEvent| ());
+        let href = format!(
+            "data:text/csv;charset=utf-8,{}",
+            String::from(js_sys::encode_uri_component(&generate_cell_usage_csv(
+                &props.usage
+            )))
+        );
+
+        html! {
+            <div class="cell-usage-panel">
+                <div class="inspector-header">
+                    <span>{format!("Cell usage, {} total", format_area(total_area, props.db_unit))}</span>
+                    <a href={href} download="cell-usage.csv" title="Download CSV">
+                        <i class="fas fa-download"></i>
+                    </a>
+                    <i class="fas fa-times" title="Close" onclick={on_close}></i>
+                </div>
+                <div class="cell-usage-list">
+                    <table class="cell-usage-table">
+                        <tr>
+                            {CellUsageColumn::ALL.iter().map(|&column| {
+                                let sort = ctx.link().callback(move |_| CellUsagePanelMsg::SortBy(column));
+                                let arrow = match (column == self.column, self.descending) {
+                                    (false, _) => "",
+                                    (true, true) => " ▼",
+                                    (true, false) => " ▲",
+                                };
+                                html! {
+                                    <th onclick={sort} title={column.tooltip()}>
+                                        {format!("{}{}", column.title(), arrow)}
+                                    </th>
+                                }
+                            }).collect::<Html>()}
+                        </tr>
+                        {usage.iter().map(|cell| {
+                            let cell_def_id = cell.cell_def_id;
+                            let select = props.on_select.reform(move |_: MouseEvent| cell_def_id);
+                            html! {
+                                <tr key={cell.name.clone()}>
+                                    <td class="cell-usage-name" onclick={select}>
+                                        {cell.name.clone()}
+                                        {cell.is_unreferenced().then(|| html! {
+                                            <span class="cell-usage-tag" title="Not placed by any cell">{"unreferenced"}</span>
+                                        })}
+                                    </td>
+                                    <td>{cell.direct_instances.to_string()}</td>
+                                    <td>{cell.flattened_instances.to_string()}</td>
+                                    <td>{format_area(cell.own_area, props.db_unit)}</td>
+                                    <td>{format_area(cell.total_area, props.db_unit)}</td>
+                                </tr>
+                            }
+                        }).collect::<Html>()}
+                    </table>
+                </div>
+            </div>
+        }
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            CellUsagePanelMsg::SortBy(column) => {
+                // Names read best A to Z, numbers largest first.
+                self.descending = match column == self.column {
+                    true => !self.descending,
+                    false => column != CellUsageColumn::Name,
+                };
+                self.column = column;
+                true
+            }
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use std::collections::HashSet;
+
+use web_sys::HtmlSelectElement;
+use yew::prelude::*;
+
+use crate::core::CellDefId;
+use crate::core::CellId;
+use crate::core::InstanceStep;
+use crate::Project;
+
+/// Instances listed per cell before the rest are summarized in one row.
+const MAX_INSTANCE_ROWS: usize = 100;
+
+/// One line of the hierarchy tree, see [create_hierarchy_rows].
+#[derive(Clone, PartialEq)]
+pub struct HierarchyRow {
+    /// Identifies the node across rebuilds, for remembering which nodes are
+    /// expanded.
+    pub key: String,
+    pub depth: usize,
+    pub name: String,
+    pub kind: HierarchyRowKind,
+    /// Whether the children are shown, or None if there are none.
+    pub expanded: Option<bool>,
+}
+
+#[derive(Clone, PartialEq)]
+pub enum HierarchyRowKind {
+    Top,
+    /// Instances of a cell within one parent. Toggling hides the cell
+    /// everywhere.
+    Cell {
+        cell_def_id: CellDefId,
+        count: usize,
+        visible: bool,
+    },
+    /// Single instance, with the instances leading to it from the top cell.
+    /// Array instances are listed as a whole.
+    Instance {
+        path: Vec<InstanceStep>,
+        visible: bool,
+    },
+    /// Instances that were left out of a long list.
+    More(usize),
+}
+
+/// Lists the top cells, or the cell descended into, and below each expanded
+/// node the cells placed in it grouped by name, and below each expanded group
+/// its instances.
+pub fn create_hierarchy_rows(project: &Project, expanded: &HashSet<String>) -> Vec<HierarchyRow> {
+    let mut rows = Vec::new();
+    for top in project.top_cells() {
+        let key = project.struct_name(top).to_string();
+        let has_children = !project.child_groups(top).is_empty();
+        let is_expanded = expanded.contains(&key);
+        rows.push(HierarchyRow {
+            key: key.clone(),
+            depth: 0,
+            name: project.struct_name(top).to_string(),
+            kind: HierarchyRowKind::Top,
+            expanded: has_children.then_some(is_expanded),
+        });
+        if is_expanded {
+            push_child_rows(project, top, &[], &key, 1, expanded, &mut rows);
+        }
+    }
+    rows
+}
+
+fn push_child_rows(
+    project: &Project,
+    cell_def_id: CellDefId,
+    path: &[InstanceStep],
+    parent_key: &str,
+    depth: usize,
+    expanded: &HashSet<String>,
+    rows: &mut Vec<HierarchyRow>,
+) {
+    for group in project.child_groups(cell_def_id) {
+        let name = project.struct_name(group.cell_def_id).to_string();
+        let group_key = format!("{}/{}", parent_key, name);
+        let group_expanded = expanded.contains(&group_key);
+        rows.push(HierarchyRow {
+            key: group_key.clone(),
+            depth,
+            name: name.clone(),
+            kind: HierarchyRowKind::Cell {
+                cell_def_id: group.cell_def_id,
+                count: group.instances.len(),
+                visible: project.is_cell_def_visible(group.cell_def_id),
+            },
+            expanded: Some(group_expanded),
+        });
+        if !group_expanded {
+            continue;
+        }
+
+        let has_children = !project.child_groups(group.cell_def_id).is_empty();
+        for (index, &cell_id) in group.instances.iter().take(MAX_INSTANCE_ROWS).enumerate() {
+            let key = format!("{}#{}", group_key, index);
+            let mut instance_path = path.to_vec();
+            instance_path.push(cell_id.into());
+            let is_expanded = expanded.contains(&key);
+            rows.push(HierarchyRow {
+                key: key.clone(),
+                depth: depth + 1,
+                name: format!("{} #{}", name, index + 1),
+                kind: HierarchyRowKind::Instance {
+                    path: instance_path.clone(),
+                    visible: project.is_instance_visible(cell_id),
+                },
+                expanded: has_children.then_some(is_expanded),
+            });
+            if is_expanded {
+                push_child_rows(
+                    project,
+                    group.cell_def_id,
+                    &instance_path,
+                    &key,
+                    depth + 2,
+                    expanded,
+                    rows,
+                );
+            }
+        }
+        if group.instances.len() > MAX_INSTANCE_ROWS {
+            rows.push(HierarchyRow {
+                key: format!("{}#more", group_key),
+                depth: depth + 1,
+                name: String::new(),
+                kind: HierarchyRowKind::More(group.instances.len() - MAX_INSTANCE_ROWS),
+                expanded: None,
+            });
+        }
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct HierarchyPanelProps {
+    pub rows: Vec<HierarchyRow>,
+    pub toggle_expanded: Callback<String>,
+    pub set_cell_visible: Callback<(CellDefId, bool)>,
+    pub set_instance_visible: Callback<(CellId, bool)>,
+    pub zoom_to_instance: Callback<Vec<InstanceStep>>,
+    /// Levels drawn in full, or None for all, see
+    /// [Project::set_display_depth].
+    pub display_depth: Option<usize>,
+    /// Number of levels of instances below the top cell.
+    pub hierarchy_depth: usize,
+    pub set_display_depth: Callback<Option<usize>>,
+}
+
+pub enum HierarchyPanelMsg {
+    ToggleCollapsed,
+}
+
+/// Collapsible tree of cells and their instances, with visibility toggles.
+/// Clicking an instance zooms to it.
+pub struct HierarchyPanel {
+    collapsed: bool,
+}
+
+impl Component for HierarchyPanel {
+    type Message = HierarchyPanelMsg;
+    type Properties = HierarchyPanelProps;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self { collapsed: false }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let toggle_collapsed = ctx.link().callback(|_| HierarchyPanelMsg::ToggleCollapsed);
+        let rows = (!self.collapsed).then(|| {
+            html! {
+                <div class="hierarchy-list">
+                    {ctx.props().rows.iter().map(|row| self.view_row(ctx, row)).collect::<Html>()}
+                </div>
+            }
+        });
+
+        html! {
+            <div class="hierarchy-panel">
+                <div class="hierarchy-header" onclick={toggle_collapsed}>
+                    <i class={format!("fas fa-chevron-{}", if self.collapsed { "right" } else { "down" })}></i>
+                    <span>{"Cells"}</span>
+                    {self.view_depth_select(ctx)}
+                </div>
+                {rows}
+            </div>
+        }
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            HierarchyPanelMsg::ToggleCollapsed => {
+                self.collapsed = !self.collapsed;
+                true
+            }
+        }
+    }
+}
+
+impl HierarchyPanel {
+    /// Selects how many levels are drawn in full, with deeper instances drawn
+    /// as labelled boxes.
+    fn view_depth_select(&self, ctx: &Context<Self>) -> Html {
+        let props = ctx.props();
+        let onchange = props.set_display_depth.reform(|e: Event| {
+            let select: HtmlSelectElement = e.target_unchecked_into();
+            select.value().parse::<usize>().ok()
+        });
+        let selected = props.display_depth;
+        html! {
+            <select
+                class="hierarchy-depth"
+                title="Levels drawn in full, deeper instances are drawn as boxes"
+                onclick={|e: MouseEvent| e.stop_propagation()}
+                onchange={onchange}
+            >
+                <option value="all" selected={selected.is_none()}>{"All levels"}</option>
+                {(0..props.hierarchy_depth).map(|depth| html! {
+                    <option value={depth.to_string()} selected={selected == Some(depth)}>
+                        {format!("Depth {}", depth)}
+                    </option>
+                }).collect::<Html>()}
+            </select>
+        }
+    }
+
+    fn view_row(&self, ctx: &Context<Self>, row: &HierarchyRow) -> Html {
+        let key = row.key.clone();
+        let toggle_expanded = ctx.props().toggle_expanded.reform(move |e: MouseEvent| {
+            e.stop_propagation();
+            key.clone()
+        });
+        let chevron = match row.expanded {
+            Some(expanded) => html! {
+                <i
+                    class={format!("fas fa-chevron-{}", if expanded { "down" } else { "right" })}
+                    onclick={toggle_expanded.clone()}
+                ></i>
+            },
+            None => html! { <i></i> },
+        };
+        let indent = format!("padding-left: {}rem", row.depth as f32 * 0.75);
+
+        let (eye, label, onclick) = match &row.kind {
+            HierarchyRowKind::Top => (
+                html! {},
+                html! { <span class="hierarchy-name">{row.name.clone()}</span> },
+                Some(toggle_expanded),
+            ),
+            HierarchyRowKind::Cell {
+                cell_def_id,
+                count,
+                visible,
+            } => {
+                let (cell_def_id, visible) = (*cell_def_id, *visible);
+                let toggle = ctx.props().set_cell_visible.reform(move |e: MouseEvent| {
+                    e.stop_propagation();
+                    (cell_def_id, !visible)
+                });
+                (
+                    eye_icon(visible, "Show or hide every instance of this cell", toggle),
+                    html! {
+                        <>
+                            <span class="hierarchy-name">{row.name.clone()}</span>
+                            <span class="hierarchy-count">{format!("×{}", count)}</span>
+                        </>
+                    },
+                    Some(toggle_expanded),
+                )
+            }
+            HierarchyRowKind::Instance { path, visible } => {
+                let cell_id = path.last().unwrap().cell_id;
+                let visible = *visible;
+                let toggle = ctx
+                    .props()
+                    .set_instance_visible
+                    .reform(move |e: MouseEvent| {
+                        e.stop_propagation();
+                        (cell_id, !visible)
+                    });
+                let eye = eye_icon(visible, "Show or hide this instance", toggle);
+                let path = path.clone();
+                let zoom = ctx.props().zoom_to_instance.reform(move |_| path.clone());
+                (
+                    eye,
+                    html! { <span class="hierarchy-name" title="Zoom to instance">{row.name.clone()}</span> },
+                    Some(zoom),
+                )
+            }
+            HierarchyRowKind::More(count) => (
+                html! {},
+                html! { <span class="hierarchy-count">{format!("{} more", count)}</span> },
+                None,
+            ),
+        };
+
+        html! {
+            <div class="hierarchy-item" key={row.key.clone()} style={indent} onclick={onclick}>
+                {chevron}
+                {eye}
+                {label}
+            </div>
+        }
+    }
+}
+
+fn eye_icon(visible: bool, title: &'static str, onclick: Callback<MouseEvent>) -> Html {
+    html! {
+        <i
+            class={format!("fas fa-eye{}", if visible { "" } else { "-slash" })}
+            title={title}
+            onclick={onclick}
+        ></i>
+    }
+}
@@ -0,0 +1,31 @@
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct InspectorPanelProps {
+    /// Labelled lines describing the inspected shape, see
+    /// [ShapeInfo::rows](crate::core::ShapeInfo::rows).
+    pub rows: Vec<(String, String)>,
+    pub on_close: Callback<()>,
+}
+
+/// Lists the details of the shape that was inspected last.
+#[function_component(InspectorPanel)]
+pub fn inspector_panel(props: &InspectorPanelProps) -> Html {
+    let on_close = props.on_close.reform(|_: MouseEvent| ());
+    html! {
+        <div class="inspector-panel">
+            <div class="inspector-header">
+                <span>{"Shape"}</span>
+                <i class="fas fa-times" title="Close (Esc)" onclick={on_close}></i>
+            </div>
+            <table class="inspector-table">
+                {props.rows.iter().map(|(label, value)| html! {
+                    <tr>
+                        <th>{label.clone()}</th>
+                        <td>{value.clone()}</td>
+                    </tr>
+                }).collect::<Html>()}
+            </table>
+        </div>
+    }
+}
@@ -1,11 +1,19 @@
 pub mod app;
+pub mod cell_usage;
+pub mod hierarchy;
 pub mod home_page;
+pub mod inspector;
+pub mod search_panel;
 pub mod sidebar;
 pub mod toast;
 pub mod viewer_page;
 
 pub use app::*;
+pub use cell_usage::*;
+pub use hierarchy::*;
 pub use home_page::*;
+pub use inspector::*;
+pub use search_panel::*;
 pub use sidebar::*;
 pub use toast::*;
 pub use viewer_page::*;
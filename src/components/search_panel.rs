@@ -0,0 +1,137 @@
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+/// Match of a search as listed in the [SearchPanel].
+#[derive(Clone, PartialEq)]
+pub struct SearchRow {
+    /// Instance path, followed by the label for label matches.
+    pub description: String,
+    /// World bounds of the instance, or the position of the label.
+    pub bounds: String,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct SearchPanelProps {
+    pub rows: Vec<SearchRow>,
+    /// Match that was stepped to last.
+    pub current: Option<usize>,
+    /// Whether the search stopped before finding every match.
+    pub truncated: bool,
+    /// Called with the pattern when the search is submitted.
+    pub on_search: Callback<String>,
+    /// Called with the index of the match that was clicked.
+    pub on_select: Callback<usize>,
+    /// Called with 1 or -1 to move to the next or previous match.
+    pub on_step: Callback<isize>,
+    pub on_close: Callback<()>,
+}
+
+pub enum SearchPanelMsg {
+    UpdateQuery(String),
+    Submit,
+}
+
+/// Search box for cell names and labels, listing the matching instances.
+pub struct SearchPanel {
+    query: String,
+    input_ref: NodeRef,
+}
+
+impl Component for SearchPanel {
+    type Message = SearchPanelMsg;
+    type Properties = SearchPanelProps;
+
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self {
+            query: String::new(),
+            input_ref: NodeRef::default(),
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let props = ctx.props();
+        let update_query = ctx.link().callback(|e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            SearchPanelMsg::UpdateQuery(input.value())
+        });
+        let submit = ctx.link().callback(|e: SubmitEvent| {
+            e.prevent_default();
+            SearchPanelMsg::Submit
+        });
+        let previous = props.on_step.reform(|_: MouseEvent| -1);
+        let next = props.on_step.reform(|_: MouseEvent| 1);
+        let on_close = props.on_close.reform(|_: MouseEvent| ());
+        let summary = match (props.current, props.truncated) {
+            (Some(current), _) => format!("{} of {}", current + 1, props.rows.len()),
+            (None, true) => format!("First {} matches", props.rows.len()),
+            (None, false) => format!("{} matches", props.rows.len()),
+        };
+
+        html! {
+            <div class="search-panel">
+                <div class="inspector-header">
+                    <span>{"Search"}</span>
+                    <i class="fas fa-times" title="Close" onclick={on_close}></i>
+                </div>
+                <form class="search-form" onsubmit={submit}>
+                    <input
+                        ref={self.input_ref.clone()}
+                        type="text"
+                        placeholder="alu, nand2_*, /^reg\\d+$/"
+                        title="Cell names and labels: text is looked for within names, * and ? make a glob and /EXPR/ a regular expression"
+                        value={self.query.clone()}
+                        oninput={update_query}
+                    />
+                    <button type="submit">{"Find"}</button>
+                </form>
+                <div class="search-nav">
+                    <span>{summary}</span>
+                    <button onclick={previous} title="Previous match (Shift+F3)">
+                        <i class="fas fa-chevron-up"></i>
+                    </button>
+                    <button onclick={next} title="Next match (F3)">
+                        <i class="fas fa-chevron-down"></i>
+                    </button>
+                </div>
+                <div class="search-list">
+                    {props.rows.iter().enumerate().map(|(index, row)| {
+                        let select = props.on_select.reform(move |_: MouseEvent| index);
+                        html! {
+                            <div
+                                class={classes!("search-row", (Some(index) == props.current).then_some("active"))}
+                                onclick={select}
+                            >
+                                <span class="search-row-path">{row.description.clone()}</span>
+                                <span class="search-row-bounds">{row.bounds.clone()}</span>
+                            </div>
+                        }
+                    }).collect::<Html>()}
+                </div>
+            </div>
+        }
+    }
+
+    fn rendered(&mut self, _ctx: &Context<Self>, first_render: bool) {
+        if first_render {
+            if let Some(input) = self.input_ref.cast::<HtmlInputElement>() {
+                let _ = input.focus();
+            }
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            SearchPanelMsg::UpdateQuery(query) => {
+                self.query = query;
+                false
+            }
+            SearchPanelMsg::Submit => {
+                if self.query.trim().is_empty() {
+                    return false;
+                }
+                ctx.props().on_search.emit(self.query.clone());
+                false
+            }
+        }
+    }
+}
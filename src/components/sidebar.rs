@@ -1,106 +1,152 @@
+use crate::core::LayoutPlacement;
+use wasm_bindgen_futures::JsFuture;
 use web_sys::HtmlInputElement;
+use web_sys::HtmlSelectElement;
 use yew::prelude::*;
 
 #[derive(Clone, PartialEq)]
 pub struct LayerProxy {
+    /// Overlaid layout that the layer belongs to, or None for the main
+    /// project.
+    pub layout: Option<usize>,
     pub index: usize,
+    pub name: String,
     pub visible: bool,
     pub opacity: f32,
     pub color: String,
 }
 
+/// Layout drawn over the main project, with its own layers.
+#[derive(Clone, PartialEq)]
+pub struct LayoutProxy {
+    pub index: usize,
+    pub name: String,
+    pub visible: bool,
+    pub tint: String,
+    pub placement: LayoutPlacement,
+    pub layers: Vec<LayerProxy>,
+}
+
 #[derive(Properties, PartialEq)]
 pub struct SidebarProps {
     pub layers: Vec<LayerProxy>,
     pub update_layer: Callback<LayerProxy>,
+    pub layouts: Vec<LayoutProxy>,
+    pub update_layout: Callback<LayoutProxy>,
+    pub remove_layout: Callback<usize>,
+    /// Receives the name and content of a GDS file to overlay.
+    pub add_layout: Callback<(String, Vec<u8>)>,
+    pub add_derived_layer: Callback<String>,
+    pub merged: bool,
+    pub set_merged: Callback<bool>,
+    pub density_layer: Option<usize>,
+    pub toggle_density: Callback<usize>,
 }
 
 pub enum SidebarMsg {
     HideAll,
     ShowAll,
-    ToggleLayer(usize),
-    UpdateOpacity(usize, f32),
-    UpdateColor(usize, String),
+    ToggleLayer(Option<usize>, usize),
+    UpdateOpacity(Option<usize>, usize, f32),
+    UpdateColor(Option<usize>, usize, String),
+    UpdateDerivedInput(String),
+    AddDerivedLayer,
 }
 
-pub struct Sidebar;
+pub struct Sidebar {
+    derived_input: String,
+}
 
 impl Component for Sidebar {
     type Message = SidebarMsg;
     type Properties = SidebarProps;
 
     fn create(_ctx: &Context<Self>) -> Self {
-        Self
+        Self {
+            derived_input: String::new(),
+        }
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
         let hide_all = ctx.link().callback(|_| SidebarMsg::HideAll);
         let show_all = ctx.link().callback(|_| SidebarMsg::ShowAll);
+        let merged = ctx.props().merged;
+        let toggle_merge = ctx.props().set_merged.reform(move |_: MouseEvent| !merged);
+        let update_derived_input = ctx.link().callback(|e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            SidebarMsg::UpdateDerivedInput(input.value())
+        });
+        let add_derived_layer = ctx.link().callback(|e: SubmitEvent| {
+            e.prevent_default();
+            SidebarMsg::AddDerivedLayer
+        });
+        let add_layout = {
+            let add_layout = ctx.props().add_layout.clone();
+            Callback::from(move |e: Event| {
+                let input: HtmlInputElement = e.target_unchecked_into();
+                let Some(file) = input.files().and_then(|files| files.get(0)) else {
+                    return;
+                };
+                // Lets the same file be chosen again.
+                input.set_value("");
+                let name = file.name();
+                let array_buffer = file.array_buffer();
+                let add_layout = add_layout.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    if let Ok(buffer) = JsFuture::from(array_buffer).await {
+                        let content = js_sys::Uint8Array::new(&buffer).to_vec();
+                        add_layout.emit((name, content));
+                    }
+                });
+            })
+        };
 
         html! {
             <div class="sidebar">
                 <div class="sidebar-header">
                     <button onclick={hide_all}>{"Hide All"}</button>
                     <button onclick={show_all}>{"Show All"}</button>
+                    <button
+                        onclick={toggle_merge}
+                        title="Draw the union of each layer so that overlaps are not darker"
+                    >
+                        {if merged { "Unmerge" } else { "Merge" }}
+                    </button>
                 </div>
+                <form class="derived-layer-form" onsubmit={add_derived_layer}>
+                    <input
+                        type="text"
+                        placeholder="gate = 1 AND 3"
+                        title="Derived layer: NAME = EXPRESSION using AND, OR, XOR, NOT, GROW, SHRINK (with optional SQUARE, ROUND or OCTAGONAL corners)"
+                        value={self.derived_input.clone()}
+                        oninput={update_derived_input}
+                    />
+                    <button type="submit">{"Add"}</button>
+                </form>
                 <div class="layer-list">
-                    {ctx.props().layers.iter().map(|layer| {
-                        let index = layer.index;
-                        let toggle_layer = ctx.link().callback(move |_| SidebarMsg::ToggleLayer(index));
-                        let update_opacity = ctx.link().callback(move |e: InputEvent| {
-                            let input: HtmlInputElement = e.target_unchecked_into();
-                            let opacity = input.value().parse::<f32>().unwrap_or(1.0);
-                            SidebarMsg::UpdateOpacity(index, opacity)
-                        });
-                        let update_color = ctx.link().callback(move |e: InputEvent| {
-                            let input: HtmlInputElement = e.target_unchecked_into();
-                            SidebarMsg::UpdateColor(index, input.value())
-                        });
-                        let prevent_toggle = |e: MouseEvent| {
-                            e.stop_propagation();
-                        };
-
-                        html! {
-                            <div
-                                class="layer-item"
-                                key={layer.index}
-                                onclick={toggle_layer}
-                                style={format!(
-                                    "background-color: {}",
-                                    if layer.visible { "#3d3d3d" } else { "#2d2d2d" }
-                                )}
-                            >
-                                <i class={format!("fas fa-eye{}", if layer.visible { "" } else { "-slash" })}></i>
-                                <div class="color-picker-container" onclick={prevent_toggle}>
-                                    <span class="layer-color" style={format!("background-color: {}", layer.color)}></span>
-                                    <input
-                                        type="color"
-                                        value={layer.color.clone()}
-                                        oninput={update_color}
-                                        class="color-picker"
-                                    />
-                                </div>
-                                <span class="layer-index">{format!("Layer {}", layer.index)}</span>
-                                <input
-                                    type="range"
-                                    min="0"
-                                    max="1"
-                                    step="0.01"
-                                    value={layer.opacity.to_string()}
-                                    oninput={update_opacity}
-                                    onclick={prevent_toggle}
-                                />
-                            </div>
-                        }
-                    }).collect::<Html>()}
+                    {ctx.props().layers.iter().map(|layer| self.view_layer(ctx, layer)).collect::<Html>()}
                 </div>
+                {ctx.props().layouts.iter().map(|layout| self.view_layout(ctx, layout)).collect::<Html>()}
+                <label class="overlay-input" title="Draw another GDS file over this one">
+                    <i class="fas fa-layer-group"></i>
+                    {"Overlay GDS"}
+                    <input type="file" accept=".gds,.gds2,.gdsii" onchange={add_layout} />
+                </label>
             </div>
         }
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
-        let find_layer_proxy =
-            |index: usize| ctx.props().layers.iter().find(|layer| layer.index == index);
+        let find_layer_proxy = |layout: Option<usize>, index: usize| {
+            let layers = match layout {
+                Some(layout) => {
+                    let layout = ctx.props().layouts.iter().find(|l| l.index == layout)?;
+                    &layout.layers
+                }
+                None => &ctx.props().layers,
+            };
+            layers.iter().find(|layer| layer.index == index)
+        };
         match msg {
             SidebarMsg::HideAll => {
                 for layer in &ctx.props().layers {
@@ -118,30 +164,216 @@ impl Component for Sidebar {
                 }
                 true
             }
-            SidebarMsg::ToggleLayer(index) => {
-                if let Some(layer) = find_layer_proxy(index) {
+            SidebarMsg::ToggleLayer(layout, index) => {
+                if let Some(layer) = find_layer_proxy(layout, index) {
                     let mut layer = layer.clone();
                     layer.visible = !layer.visible;
                     ctx.props().update_layer.emit(layer.clone());
                 }
                 true
             }
-            SidebarMsg::UpdateOpacity(index, opacity) => {
-                if let Some(layer) = find_layer_proxy(index) {
+            SidebarMsg::UpdateOpacity(layout, index, opacity) => {
+                if let Some(layer) = find_layer_proxy(layout, index) {
                     let mut layer = layer.clone();
                     layer.opacity = opacity;
                     ctx.props().update_layer.emit(layer.clone());
                 }
                 true
             }
-            SidebarMsg::UpdateColor(index, color) => {
-                if let Some(layer) = find_layer_proxy(index) {
+            SidebarMsg::UpdateColor(layout, index, color) => {
+                if let Some(layer) = find_layer_proxy(layout, index) {
                     let mut layer = layer.clone();
                     layer.color = color.clone();
                     ctx.props().update_layer.emit(layer.clone());
                 }
                 true
             }
+            SidebarMsg::UpdateDerivedInput(text) => {
+                self.derived_input = text;
+                false
+            }
+            SidebarMsg::AddDerivedLayer => {
+                if self.derived_input.trim().is_empty() {
+                    return false;
+                }
+                let definition = std::mem::take(&mut self.derived_input);
+                ctx.props().add_derived_layer.emit(definition);
+                true
+            }
+        }
+    }
+}
+
+impl Sidebar {
+    fn view_layer(&self, ctx: &Context<Self>, layer: &LayerProxy) -> Html {
+        let (layout, index) = (layer.layout, layer.index);
+        let toggle_layer = ctx
+            .link()
+            .callback(move |_| SidebarMsg::ToggleLayer(layout, index));
+        let update_opacity = ctx.link().callback(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let opacity = input.value().parse::<f32>().unwrap_or(1.0);
+            SidebarMsg::UpdateOpacity(layout, index, opacity)
+        });
+        let update_color = ctx.link().callback(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            SidebarMsg::UpdateColor(layout, index, input.value())
+        });
+        let prevent_toggle = |e: MouseEvent| {
+            e.stop_propagation();
+        };
+        // Density maps are only computed for the main project.
+        let density_toggle = layout.is_none().then(|| {
+            let toggle_density = ctx.props().toggle_density.reform(move |e: MouseEvent| {
+                e.stop_propagation();
+                index
+            });
+            let density_shown = ctx.props().density_layer == Some(index);
+            html! {
+                <i
+                    class={format!("fas fa-th density-toggle{}", if density_shown { " active" } else { "" })}
+                    title="Show density heatmap"
+                    onclick={toggle_density}
+                ></i>
+            }
+        });
+
+        html! {
+            <div
+                class="layer-item"
+                key={layer.index}
+                onclick={toggle_layer}
+                style={format!(
+                    "background-color: {}",
+                    if layer.visible { "#3d3d3d" } else { "#2d2d2d" }
+                )}
+            >
+                <i class={format!("fas fa-eye{}", if layer.visible { "" } else { "-slash" })}></i>
+                <div class="color-picker-container" onclick={prevent_toggle}>
+                    <span class="layer-color" style={format!("background-color: {}", layer.color)}></span>
+                    <input
+                        type="color"
+                        value={layer.color.clone()}
+                        oninput={update_color}
+                        class="color-picker"
+                    />
+                </div>
+                <span class="layer-index">{layer.name.clone()}</span>
+                <input
+                    type="range"
+                    min="0"
+                    max="1"
+                    step="0.01"
+                    value={layer.opacity.to_string()}
+                    oninput={update_opacity}
+                    onclick={prevent_toggle}
+                />
+                {density_toggle}
+            </div>
+        }
+    }
+
+    /// Header with the visibility, tint and placement of an overlaid layout,
+    /// followed by its layers.
+    fn view_layout(&self, ctx: &Context<Self>, layout: &LayoutProxy) -> Html {
+        let update_layout = &ctx.props().update_layout;
+        let toggle_layout = {
+            let mut layout = layout.clone();
+            layout.visible = !layout.visible;
+            update_layout.reform(move |_: MouseEvent| layout.clone())
+        };
+        let update_tint = {
+            let layout = layout.clone();
+            update_layout.reform(move |e: InputEvent| {
+                let input: HtmlInputElement = e.target_unchecked_into();
+                let mut layout = layout.clone();
+                layout.tint = input.value();
+                layout
+            })
+        };
+        let update_placement = |edit: fn(&mut LayoutPlacement, &str)| {
+            let layout = layout.clone();
+            update_layout.reform(move |e: Event| {
+                let value = match e.target_dyn_into::<HtmlSelectElement>() {
+                    Some(select) => select.value(),
+                    None => {
+                        let input: HtmlInputElement = e.target_unchecked_into();
+                        match input.type_().as_str() {
+                            "checkbox" => input.checked().to_string(),
+                            _ => input.value(),
+                        }
+                    }
+                };
+                let mut layout = layout.clone();
+                edit(&mut layout.placement, &value);
+                layout
+            })
+        };
+        let update_dx = update_placement(|p, value| p.dx = value.parse().unwrap_or(p.dx));
+        let update_dy = update_placement(|p, value| p.dy = value.parse().unwrap_or(p.dy));
+        let update_angle = update_placement(|p, value| p.angle = value.parse().unwrap_or(p.angle));
+        let update_mirrored = update_placement(|p, value| p.mirrored = value == "true");
+        let index = layout.index;
+        let remove_layout = ctx.props().remove_layout.reform(move |_: MouseEvent| index);
+        let angle = layout.placement.angle.rem_euclid(360.0);
+
+        html! {
+            <div class="layout-section" key={format!("layout-{}", layout.index)}>
+                <div class="layout-header">
+                    <i
+                        class={format!("fas fa-eye{}", if layout.visible { "" } else { "-slash" })}
+                        onclick={toggle_layout}
+                    ></i>
+                    <div class="color-picker-container" title="Tint">
+                        <span class="layer-color" style={format!("background-color: {}", layout.tint)}></span>
+                        <input
+                            type="color"
+                            value={layout.tint.clone()}
+                            oninput={update_tint}
+                            class="color-picker"
+                        />
+                    </div>
+                    <span class="layout-name" title={layout.name.clone()}>{layout.name.clone()}</span>
+                    <i class="fas fa-times" title="Remove layout" onclick={remove_layout}></i>
+                </div>
+                <div class="layout-placement">
+                    <label>{"X"}
+                        <input
+                            type="number"
+                            value={layout.placement.dx.to_string()}
+                            onchange={update_dx}
+                        />
+                    </label>
+                    <label>{"Y"}
+                        <input
+                            type="number"
+                            value={layout.placement.dy.to_string()}
+                            onchange={update_dy}
+                        />
+                    </label>
+                    <select title="Rotation" onchange={update_angle}>
+                        {[0, 90, 180, 270].iter().map(|degrees| html! {
+                            <option
+                                value={degrees.to_string()}
+                                selected={angle == *degrees as f64}
+                            >
+                                {format!("{}°", degrees)}
+                            </option>
+                        }).collect::<Html>()}
+                    </select>
+                    <label title="Mirror about the x axis">
+                        <input
+                            type="checkbox"
+                            checked={layout.placement.mirrored}
+                            onchange={update_mirrored}
+                        />
+                        {"Mirror"}
+                    </label>
+                </div>
+                <div class="layer-list">
+                    {layout.layers.iter().map(|layer| self.view_layer(ctx, layer)).collect::<Html>()}
+                </div>
+            </div>
         }
     }
 }
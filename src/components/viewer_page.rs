@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use wasm_bindgen::closure::Closure;
 use wasm_bindgen::JsCast;
 use wasm_bindgen_futures::JsFuture;
@@ -13,13 +15,30 @@ use yew::prelude::*;
 use yew_router::prelude::*;
 
 use crate::app_controller::AppController;
+use crate::app_controller::MAX_SEARCH_MATCHES;
+use crate::components::create_hierarchy_rows;
 use crate::components::take_dropped_file;
+use crate::components::CellUsagePanel;
+use crate::components::HierarchyPanel;
+use crate::components::HierarchyRow;
+use crate::components::InspectorPanel;
 use crate::components::LayerProxy;
+use crate::components::LayoutProxy;
 use crate::components::Route;
+use crate::components::SearchPanel;
+use crate::components::SearchRow;
 use crate::components::Sidebar;
 use crate::components::ToastContainer;
 use crate::components::ToastManager;
-use crate::graphics::MeshId;
+use crate::core::CellDefId;
+use crate::core::CellId;
+use crate::core::CellUsage;
+use crate::core::DensityOptions;
+use crate::core::EditOutcome;
+use crate::core::InstanceStep;
+use crate::core::LintOptions;
+use crate::core::SearchPattern;
+use crate::generate_cross_section::generate_cross_section_svg;
 use crate::graphics::Renderer;
 use crate::graphics::Scene;
 use crate::rsutils::hex_to_rgb;
@@ -30,6 +49,9 @@ use crate::Project;
 #[derive(Properties, PartialEq)]
 pub struct ViewerProps {
     pub id: String,
+    /// Cell to show as the top cell once the layout is loaded.
+    #[prop_or_default]
+    pub cell: Option<String>,
 }
 
 pub enum ViewerMsg {
@@ -44,6 +66,47 @@ pub enum ViewerMsg {
     Tick,
     RemoveToast(usize),
     UpdateLayer(LayerProxy),
+    AddLayout((String, Vec<u8>)),
+    UpdateLayout(LayoutProxy),
+    RemoveLayout(usize),
+    AddDerivedLayer(String),
+    SetMergeLayers(bool),
+    ToggleDensity(usize),
+    ToggleRuler,
+    ClearRulers,
+    ToggleHighlightInstance,
+    ToggleLint,
+    ToggleCut,
+    ToggleHierarchyNode(String),
+    SetCellVisible((CellDefId, bool)),
+    SetInstanceVisible((CellId, bool)),
+    ZoomToInstance(Vec<InstanceStep>),
+    Descend,
+    Ascend,
+    SetDisplayDepth(Option<usize>),
+    Edit(EditAction),
+    Inspect,
+    CloseInspector,
+    ToggleCellUsage,
+    SelectCell(CellDefId),
+    ToggleSearch,
+    Search(String),
+    SelectSearchMatch(usize),
+    StepSearch(isize),
+}
+
+/// Editing shortcuts, which act on the shape or instance under the cursor.
+#[derive(Debug, Clone, Copy)]
+pub enum EditAction {
+    Undo,
+    Redo,
+    DeleteShape,
+    DeleteInstance,
+    /// Moves the shape, or its instance if set, in a screen direction.
+    Nudge(i32, i32, bool),
+    Rotate,
+    Mirror,
+    StepLayer(i16),
 }
 
 pub struct ViewerPage {
@@ -52,6 +115,23 @@ pub struct ViewerPage {
     status: String,
     toast_manager: ToastManager,
     layer_proxies: Vec<LayerProxy>,
+    /// Layouts drawn over the main project, with their layers.
+    layout_proxies: Vec<LayoutProxy>,
+    merged: bool,
+    /// SVG of the current cross-section, shown in a panel.
+    section_svg: Option<String>,
+    /// Keys of the expanded nodes of the hierarchy tree.
+    hierarchy_expanded: HashSet<String>,
+    hierarchy_rows: Vec<HierarchyRow>,
+    hierarchy_depth: usize,
+    /// Details of the shape that was inspected last, shown in a panel.
+    inspector_rows: Option<Vec<(String, String)>>,
+    /// Usage and area of every cell, shown in a table while set.
+    cell_usage: Option<Vec<CellUsage>>,
+    /// Chain of instances under the cursor, shown in the status bar.
+    hover_path: String,
+    /// Matches of the last search, shown in a panel while set.
+    search_rows: Option<Vec<SearchRow>>,
 }
 
 impl Component for ViewerPage {
@@ -93,6 +173,16 @@ impl Component for ViewerPage {
             status,
             toast_manager,
             layer_proxies,
+            layout_proxies: Vec::new(),
+            merged: false,
+            section_svg: None,
+            hierarchy_expanded: HashSet::new(),
+            hierarchy_rows: Vec::new(),
+            hierarchy_depth: 0,
+            inspector_rows: None,
+            cell_usage: None,
+            hover_path: String::new(),
+            search_rows: None,
         }
     }
 
@@ -113,6 +203,7 @@ impl Component for ViewerPage {
         });
 
         let onmouseup = ctx.link().callback(|_| ViewerMsg::MouseRelease);
+        let ondblclick = ctx.link().callback(|_| ViewerMsg::Descend);
 
         let onmousemove = ctx.link().callback(|e: MouseEvent| {
             let x = e.offset_x() as u32;
@@ -134,8 +225,149 @@ impl Component for ViewerPage {
             ViewerMsg::MouseWheel(x as u32, y as u32, delta)
         });
 
+        let onkeydown = ctx.link().batch_callback(|e: KeyboardEvent| {
+            if let Some(action) = edit_action(&e) {
+                // Keeps arrows from scrolling the page.
+                e.prevent_default();
+                return Some(ViewerMsg::Edit(action));
+            }
+            match e.key().as_str() {
+                "F3" => {
+                    // Keeps the browser from searching the page.
+                    e.prevent_default();
+                    Some(ViewerMsg::StepSearch(if e.shift_key() { -1 } else { 1 }))
+                }
+                "/" => {
+                    e.prevent_default();
+                    Some(ViewerMsg::ToggleSearch)
+                }
+                "r" => Some(ViewerMsg::ToggleRuler),
+                "c" => Some(ViewerMsg::ClearRulers),
+                "a" => Some(ViewerMsg::ToggleHighlightInstance),
+                "u" => Some(ViewerMsg::Ascend),
+                "i" => Some(ViewerMsg::Inspect),
+                "Escape" => Some(ViewerMsg::CloseInspector),
+                _ => None,
+            }
+        });
+        let toggle_ruler = ctx.link().callback(|_| ViewerMsg::ToggleRuler);
+        let toggle_lint = ctx.link().callback(|_| ViewerMsg::ToggleLint);
+        let toggle_cut = ctx.link().callback(|_| ViewerMsg::ToggleCut);
+        let toggle_highlight_instance = ctx.link().callback(|_| ViewerMsg::ToggleHighlightInstance);
+        let highlight_instance = self
+            .controller
+            .as_ref()
+            .is_some_and(|controller| controller.highlight_instance());
+        let context_name = self.controller.as_ref().and_then(|controller| {
+            let project = controller.project()?;
+            Some(project.struct_name(controller.context()?).to_string())
+        });
+        let ascend_button = context_name.map(|name| {
+            let ascend = ctx.link().callback(|_| ViewerMsg::Ascend);
+            html! {
+                <button
+                    class="floating-button"
+                    title={format!("Showing {}. Ascend to the previous cell (U)", name)}
+                    onclick={ascend}
+                >
+                    <i class="fas fa-level-up-alt fa-lg"></i>
+                </button>
+            }
+        });
+        let cut_mode = self
+            .controller
+            .as_ref()
+            .is_some_and(|controller| controller.cut_mode());
+        let section_panel = self.section_svg.as_ref().map(|svg| {
+            let href = format!(
+                "data:image/svg+xml;charset=utf-8,{}",
+                String::from(js_sys::encode_uri_component(svg))
+            );
+            html! {
+                <div class="cross-section-panel">
+                    {Html::from_html_unchecked(AttrValue::from(svg.clone()))}
+                    <a href={href} download="cross-section.svg">{"Download SVG"}</a>
+                </div>
+            }
+        });
+        let lint_shown = self
+            .controller
+            .as_ref()
+            .is_some_and(|controller| controller.lint_shown());
+        let ruler_mode = self
+            .controller
+            .as_ref()
+            .is_some_and(|controller| controller.ruler_mode());
+
+        let inspector_panel = self.inspector_rows.as_ref().map(|rows| {
+            let on_close = ctx.link().callback(|_| ViewerMsg::CloseInspector);
+            html! { <InspectorPanel rows={rows.clone()} on_close={on_close} /> }
+        });
+
+        let toggle_cell_usage = ctx.link().callback(|_| ViewerMsg::ToggleCellUsage);
+        let cell_usage_panel = self.cell_usage.as_ref().map(|usage| {
+            let db_unit = self
+                .controller
+                .as_ref()
+                .and_then(|controller| controller.project())
+                .map_or(0.0, |project| project.units().db_unit());
+            let on_select = ctx.link().callback(ViewerMsg::SelectCell);
+            let on_close = ctx.link().callback(|_| ViewerMsg::ToggleCellUsage);
+            html! {
+                <CellUsagePanel
+                    usage={usage.clone()}
+                    db_unit={db_unit}
+                    on_select={on_select}
+                    on_close={on_close}
+                />
+            }
+        });
+
+        let toggle_search = ctx.link().callback(|_| ViewerMsg::ToggleSearch);
+        let search_panel = self.search_rows.as_ref().map(|rows| {
+            let current = self
+                .controller
+                .as_ref()
+                .and_then(|controller| controller.current_search_match());
+            let on_search = ctx.link().callback(ViewerMsg::Search);
+            let on_select = ctx.link().callback(ViewerMsg::SelectSearchMatch);
+            let on_step = ctx.link().callback(ViewerMsg::StepSearch);
+            let on_close = ctx.link().callback(|_| ViewerMsg::ToggleSearch);
+            html! {
+                <SearchPanel
+                    rows={rows.clone()}
+                    current={current}
+                    truncated={rows.len() == MAX_SEARCH_MATCHES}
+                    on_search={on_search}
+                    on_select={on_select}
+                    on_step={on_step}
+                    on_close={on_close}
+                />
+            }
+        });
+
         let on_remove_toast = ctx.link().callback(ViewerMsg::RemoveToast);
         let update_layer = ctx.link().callback(ViewerMsg::UpdateLayer);
+        let add_layout = ctx.link().callback(ViewerMsg::AddLayout);
+        let update_layout = ctx.link().callback(ViewerMsg::UpdateLayout);
+        let remove_layout = ctx.link().callback(ViewerMsg::RemoveLayout);
+        let add_derived_layer = ctx.link().callback(ViewerMsg::AddDerivedLayer);
+        let set_merged = ctx.link().callback(ViewerMsg::SetMergeLayers);
+        let toggle_density = ctx.link().callback(ViewerMsg::ToggleDensity);
+        let toggle_hierarchy_node = ctx.link().callback(ViewerMsg::ToggleHierarchyNode);
+        let set_cell_visible = ctx.link().callback(ViewerMsg::SetCellVisible);
+        let set_instance_visible = ctx.link().callback(ViewerMsg::SetInstanceVisible);
+        let zoom_to_instance = ctx.link().callback(ViewerMsg::ZoomToInstance);
+        let set_display_depth = ctx.link().callback(ViewerMsg::SetDisplayDepth);
+        let display_depth = self
+            .controller
+            .as_ref()
+            .and_then(|controller| controller.display_depth());
+        let density_layer = self
+            .controller
+            .as_ref()
+            .and_then(|controller| controller.density_layer())
+            .map(|layer| layer as usize);
 
         html! {
             <>
@@ -143,8 +375,11 @@ impl Component for ViewerPage {
                     <canvas
                         class="viewer-canvas"
                         ref={self.canvas_ref.clone()}
+                        tabindex="0"
+                        onkeydown={onkeydown}
                         onmousedown={onmousedown}
                         onmouseup={onmouseup}
+                        ondblclick={ondblclick}
                         onmousemove={onmousemove}
                         onwheel={onwheel}
                     />
@@ -152,10 +387,88 @@ impl Component for ViewerPage {
                         <Link<Route> to={Route::Home} classes="floating-button">
                             <i class="fas fa-arrow-left fa-lg"></i>
                         </Link<Route>>
+                        <button
+                            class={classes!("floating-button", ruler_mode.then_some("active"))}
+                            title="Measure distances (R), clear rulers (C)"
+                            onclick={toggle_ruler}
+                        >
+                            <i class="fas fa-ruler fa-lg"></i>
+                        </button>
+                        <button
+                            class={classes!("floating-button", lint_shown.then_some("active"))}
+                            title="Mark acute angles and self-intersections"
+                            onclick={toggle_lint}
+                        >
+                            <i class="fas fa-exclamation-triangle fa-lg"></i>
+                        </button>
+                        <button
+                            class={classes!("floating-button", cut_mode.then_some("active"))}
+                            title="Draw a cut line to see a cross-section"
+                            onclick={toggle_cut}
+                        >
+                            <i class="fas fa-cut fa-lg"></i>
+                        </button>
+                        <button
+                            class={classes!("floating-button", self.cell_usage.is_some().then_some("active"))}
+                            title="List how often each cell is used and how much area it takes"
+                            onclick={toggle_cell_usage}
+                        >
+                            <i class="fas fa-table fa-lg"></i>
+                        </button>
+                        <button
+                            class={classes!("floating-button", self.search_rows.is_some().then_some("active"))}
+                            title="Search cell names and labels (/), step through matches with F3"
+                            onclick={toggle_search}
+                        >
+                            <i class="fas fa-search fa-lg"></i>
+                        </button>
+                        {ascend_button}
                         <span class="status-text">{self.status.clone()}</span>
                     </div>
+                    {section_panel}
+                    {inspector_panel}
+                    {cell_usage_panel}
+                    {search_panel}
+                    <HierarchyPanel
+                        rows={self.hierarchy_rows.clone()}
+                        toggle_expanded={toggle_hierarchy_node}
+                        set_cell_visible={set_cell_visible}
+                        set_instance_visible={set_instance_visible}
+                        zoom_to_instance={zoom_to_instance}
+                        display_depth={display_depth}
+                        hierarchy_depth={self.hierarchy_depth}
+                        set_display_depth={set_display_depth}
+                    />
+                    <div class="status-bar">
+                        <span
+                            class="status-bar-path"
+                            title="Instances under the cursor"
+                        >
+                            {self.hover_path.clone()}
+                        </span>
+                        <label title="Highlight every shape of the instance under the cursor (A)">
+                            <input
+                                type="checkbox"
+                                checked={highlight_instance}
+                                onchange={toggle_highlight_instance}
+                            />
+                            {"Whole instance"}
+                        </label>
+                    </div>
                 </div>
-                <Sidebar layers={self.layer_proxies.clone()} update_layer={update_layer} />
+                <Sidebar
+                    layers={self.layer_proxies.clone()}
+                    update_layer={update_layer}
+                    layouts={self.layout_proxies.clone()}
+                    update_layout={update_layout}
+                    remove_layout={remove_layout}
+                    add_layout={add_layout}
+                    add_derived_layer={add_derived_layer}
+                    merged={self.merged}
+                    set_merged={set_merged}
+                    density_layer={density_layer}
+                    toggle_density={toggle_density}
+                />
                 <ToastContainer toasts={self.toast_manager.toasts().to_vec()} on_remove={on_remove_toast} />
             </>
         }
@@ -269,7 +582,14 @@ impl Component for ViewerPage {
             ViewerMsg::MousePress(x, y) => {
                 controller.handle_mouse_press(x, y);
                 controller.render();
-                false
+                if !controller.cut_mode() {
+                    return false;
+                }
+                self.section_svg = controller.cross_section().map(|section| {
+                    let layers = controller.project().map(|p| p.layers()).unwrap_or(&[]);
+                    generate_cross_section_svg(section, layers)
+                });
+                true
             }
             ViewerMsg::MouseRelease => {
                 controller.handle_mouse_release();
@@ -279,7 +599,14 @@ impl Component for ViewerPage {
             ViewerMsg::MouseMove(x, y) => {
                 controller.handle_mouse_move(x, y);
                 controller.render();
-                false
+                // Keeps the last path when the cursor leaves the geometry.
+                match controller.hovered_path() {
+                    Some(path) if path != self.hover_path => {
+                        self.hover_path = path;
+                        true
+                    }
+                    _ => false,
+                }
             }
             ViewerMsg::MouseWheel(x, y, delta) => {
                 controller.handle_mouse_wheel(x, y, -delta);
@@ -292,37 +619,35 @@ impl Component for ViewerPage {
                     return false;
                 };
                 controller.set_project(*project);
+                controller.set_section_panel(false);
+                self.merged = false;
+                self.section_svg = None;
+                self.cell_usage = None;
+                self.search_rows = None;
+                self.hover_path.clear();
                 self.status.clear();
                 self.toast_manager
                     .show("Zoom and pan like a map".to_string());
 
+                if let Some(name) = &context.props().cell {
+                    let cell_def_id = controller.project().and_then(|p| p.find_cell_def(name));
+                    match cell_def_id {
+                        Some(cell_def_id) => controller.descend(cell_def_id),
+                        None => self
+                            .toast_manager
+                            .show(format!("Cell '{}' not found", name)),
+                    }
+                }
+
                 // Update layer proxies
                 if let Some(project) = controller.project() {
-                    self.layer_proxies = project
-                        .layers()
-                        .iter()
-                        .enumerate()
-                        .filter_map(|(index, layer)| {
-                            if layer.polygons.is_empty() {
-                                return None;
-                            }
-                            let color = if index == project.layers().len() - 1 {
-                                // Make highest layer white
-                                rgb_to_hex(1.0, 1.0, 1.0)
-                            } else {
-                                rgb_to_hex(layer.color.x, layer.color.y, layer.color.z)
-                            };
-                            Some(LayerProxy {
-                                index,
-                                visible: layer.visible,
-                                opacity: layer.color.w,
-                                color,
-                            })
-                        })
-                        .collect();
+                    self.layer_proxies = create_layer_proxies(project, None);
                 }
+                self.layout_proxies.clear();
 
                 controller.render();
+                self.hierarchy_expanded.clear();
+                self.update_hierarchy_rows();
                 true
             }
             ViewerMsg::ParsingGds => {
@@ -337,8 +662,12 @@ impl Component for ViewerPage {
                 let Some(controller) = &mut self.controller else {
                     return false;
                 };
-                let color = {
-                    let Some(project) = controller.project_mut() else {
+                {
+                    let project = match layer_proxy.layout {
+                        Some(layout) => controller.layout_mut(layout).map(|l| &mut l.project),
+                        None => controller.project_mut(),
+                    };
+                    let Some(project) = project else {
                         return false;
                     };
                     let Some(layer) = project.layers_mut().get_mut(layer_proxy.index) else {
@@ -351,15 +680,23 @@ impl Component for ViewerPage {
                         layer.color.y = g;
                         layer.color.z = b;
                     }
-                    layer.color
+                }
+                let layer_proxies = match layer_proxy.layout {
+                    Some(layout) => {
+                        controller.update_layout_style(layout);
+                        self.layout_proxies
+                            .iter_mut()
+                            .find(|l| l.index == layout)
+                            .map(|l| &mut l.layers)
+                    }
+                    None => {
+                        controller.update_layer_style(layer_proxy.index);
+                        Some(&mut self.layer_proxies)
+                    }
                 };
-                let mesh_id = MeshId(1 + layer_proxy.index);
-                let mesh = controller.scene().get_mesh_mut(&mesh_id).unwrap();
-                mesh.set_vec4("color", color);
-                mesh.visible = layer_proxy.visible;
-                if let Some(layer) = self
-                    .layer_proxies
-                    .iter_mut()
+                if let Some(layer) = layer_proxies
+                    .into_iter()
+                    .flatten()
                     .find(|layer| layer.index == layer_proxy.index)
                 {
                     *layer = layer_proxy.clone();
@@ -367,8 +704,411 @@ impl Component for ViewerPage {
                 controller.render();
                 true
             }
+            ViewerMsg::AddLayout((name, content)) => {
+                match Project::from_bytes(&content) {
+                    Ok(project) => {
+                        controller.add_layout(&name, project);
+                        self.layout_proxies = create_layout_proxies(controller);
+                    }
+                    Err(err) => self
+                        .toast_manager
+                        .show(format!("Unable to overlay {}: {}", name, err)),
+                }
+                true
+            }
+            ViewerMsg::UpdateLayout(layout_proxy) => {
+                let index = layout_proxy.index;
+                let Some(layout) = controller.layout_mut(index) else {
+                    return false;
+                };
+                layout.visible = layout_proxy.visible;
+                if let Some((r, g, b)) = hex_to_rgb(&layout_proxy.tint) {
+                    layout.tint.x = r;
+                    layout.tint.y = g;
+                    layout.tint.z = b;
+                }
+                let moved = layout.placement != layout_proxy.placement;
+                layout.placement = layout_proxy.placement;
+                if moved {
+                    controller.update_layout_placement(index);
+                } else {
+                    controller.update_layout_style(index);
+                }
+                self.layout_proxies = create_layout_proxies(controller);
+                true
+            }
+            ViewerMsg::RemoveLayout(index) => {
+                controller.remove_layout(index);
+                self.layout_proxies = create_layout_proxies(controller);
+                true
+            }
+            ViewerMsg::AddDerivedLayer(definition) => {
+                let Some(controller) = &mut self.controller else {
+                    return false;
+                };
+                if let Err(err) = controller.add_derived_layer(&definition) {
+                    self.toast_manager.show(err.to_string());
+                    return true;
+                }
+                if let Some(project) = controller.project() {
+                    self.layer_proxies = create_layer_proxies(project, None);
+                }
+                true
+            }
+            ViewerMsg::ToggleDensity(index) => {
+                let Some(controller) = &mut self.controller else {
+                    return false;
+                };
+                if controller.density_layer() == Some(index as i16) {
+                    controller.hide_density();
+                    return true;
+                }
+                let Some(project) = controller.project() else {
+                    return false;
+                };
+                let options = DensityOptions::for_bounds(&project.bounds());
+                match controller.show_density(index as i16, options) {
+                    Ok(map) => self.toast_manager.show(format!(
+                        "Density min {:.3}, max {:.3}, mean {:.3}",
+                        map.min(),
+                        map.max(),
+                        map.mean()
+                    )),
+                    Err(err) => self.toast_manager.show(err.to_string()),
+                }
+                true
+            }
+            ViewerMsg::SetMergeLayers(merged) => {
+                let Some(controller) = &mut self.controller else {
+                    return false;
+                };
+                controller.set_merge_layers(merged);
+                self.merged = merged;
+                true
+            }
+            ViewerMsg::ToggleRuler => {
+                let enabled = !controller.ruler_mode();
+                controller.set_ruler_mode(enabled);
+                self.status = if enabled {
+                    "Click twice to measure, C to clear".to_string()
+                } else {
+                    String::new()
+                };
+                true
+            }
+            ViewerMsg::ClearRulers => {
+                controller.clear_rulers();
+                false
+            }
+            ViewerMsg::ToggleHighlightInstance => {
+                let enabled = !controller.highlight_instance();
+                controller.set_highlight_instance(enabled);
+                controller.render();
+                true
+            }
+            ViewerMsg::ToggleCut => {
+                let enabled = !controller.cut_mode();
+                controller.set_cut_mode(enabled);
+                if !enabled {
+                    controller.clear_cross_section();
+                    self.section_svg = None;
+                }
+                true
+            }
+            ViewerMsg::ToggleHierarchyNode(key) => {
+                if !self.hierarchy_expanded.remove(&key) {
+                    self.hierarchy_expanded.insert(key);
+                }
+                self.update_hierarchy_rows();
+                true
+            }
+            ViewerMsg::SetCellVisible((cell_def_id, visible)) => {
+                controller.set_cell_def_visible(cell_def_id, visible);
+                self.update_hierarchy_rows();
+                true
+            }
+            ViewerMsg::SetInstanceVisible((cell_id, visible)) => {
+                controller.set_instance_visible(cell_id, visible);
+                self.update_hierarchy_rows();
+                true
+            }
+            ViewerMsg::ZoomToInstance(path) => {
+                controller.zoom_to_instance(&path);
+                false
+            }
+            ViewerMsg::Descend => {
+                if !controller.descend_hovered() {
+                    return false;
+                }
+                // Layers that are empty within the new context are not listed.
+                if let Some(project) = controller.project() {
+                    self.layer_proxies = create_layer_proxies(project, None);
+                }
+                self.hierarchy_expanded.clear();
+                self.update_hierarchy_rows();
+                self.refresh_cell_usage();
+                self.refresh_search_rows();
+                true
+            }
+            ViewerMsg::Ascend => {
+                if !controller.ascend() {
+                    return false;
+                }
+                if let Some(project) = controller.project() {
+                    self.layer_proxies = create_layer_proxies(project, None);
+                }
+                self.hierarchy_expanded.clear();
+                self.update_hierarchy_rows();
+                self.refresh_cell_usage();
+                self.refresh_search_rows();
+                true
+            }
+            ViewerMsg::SetDisplayDepth(depth) => {
+                controller.set_display_depth(depth);
+                if let Some(project) = controller.project() {
+                    self.layer_proxies = create_layer_proxies(project, None);
+                }
+                true
+            }
+            ViewerMsg::Edit(action) => {
+                let history = |outcome: Option<EditOutcome>, verb: &str| match outcome {
+                    Some(outcome) => format!("{}: {}", verb, outcome.description),
+                    None => format!("Nothing to {}", verb.to_lowercase()),
+                };
+                let result = match action {
+                    EditAction::Undo => controller.undo().map(|o| Some(history(o, "Undo"))),
+                    EditAction::Redo => controller.redo().map(|o| Some(history(o, "Redo"))),
+                    EditAction::DeleteShape => controller.delete_hovered_shape().map(|_| None),
+                    EditAction::DeleteInstance => {
+                        controller.delete_hovered_instance().map(|_| None)
+                    }
+                    EditAction::Nudge(dx, dy, instance) => {
+                        controller.nudge_hovered(dx, dy, instance).map(|_| None)
+                    }
+                    EditAction::Rotate => controller.rotate_hovered_instance().map(|_| None),
+                    EditAction::Mirror => controller.mirror_hovered_instance().map(|_| None),
+                    EditAction::StepLayer(step) => {
+                        controller.step_hovered_layer(step).map(|_| None)
+                    }
+                };
+                match result {
+                    Ok(Some(message)) => self.toast_manager.show(message),
+                    Ok(None) => {}
+                    Err(err) => self.toast_manager.show(err.to_string()),
+                }
+                // Edits may empty or fill layers and remove instances.
+                if let Some(project) = controller.project() {
+                    self.layer_proxies = create_layer_proxies(project, None);
+                }
+                self.refresh_cell_usage();
+                self.refresh_search_rows();
+                self.update_hierarchy_rows();
+                true
+            }
+            ViewerMsg::Inspect => {
+                let rows = controller.inspect_hovered().and_then(|info| {
+                    let db_unit = controller.project()?.units().db_unit();
+                    Some(info.rows(db_unit))
+                });
+                match rows {
+                    Some(rows) => self.inspector_rows = Some(rows),
+                    None => self
+                        .toast_manager
+                        .show("Hover over a shape and press I to inspect it".to_string()),
+                }
+                true
+            }
+            ViewerMsg::CloseInspector => self.inspector_rows.take().is_some(),
+            ViewerMsg::ToggleCellUsage => {
+                self.cell_usage = match self.cell_usage {
+                    Some(_) => None,
+                    None => controller.project().map(|project| project.cell_usage()),
+                };
+                true
+            }
+            ViewerMsg::SelectCell(cell_def_id) => {
+                controller.descend(cell_def_id);
+                if let Some(project) = controller.project() {
+                    self.layer_proxies = create_layer_proxies(project, None);
+                }
+                self.hierarchy_expanded.clear();
+                self.update_hierarchy_rows();
+                self.refresh_cell_usage();
+                self.refresh_search_rows();
+                true
+            }
+            ViewerMsg::ToggleSearch => {
+                if self.search_rows.take().is_some() {
+                    controller.clear_search();
+                } else {
+                    self.search_rows = Some(Vec::new());
+                }
+                true
+            }
+            ViewerMsg::Search(text) => {
+                let pattern = match SearchPattern::parse(&text) {
+                    Ok(pattern) => pattern,
+                    Err(err) => {
+                        self.toast_manager.show(err.to_string());
+                        return true;
+                    }
+                };
+                let count = controller
+                    .search(pattern)
+                    .map_or(0, |matches| matches.len());
+                if count == 0 {
+                    self.toast_manager
+                        .show(format!("No cells or labels match '{}'", text.trim()));
+                }
+                self.refresh_search_rows();
+                // Fits the view to the first match right away.
+                if let Some(controller) = &mut self.controller {
+                    controller.step_search(1);
+                }
+                true
+            }
+            ViewerMsg::SelectSearchMatch(index) => {
+                controller.show_search_match(index);
+                true
+            }
+            ViewerMsg::StepSearch(step) => controller.step_search(step).is_some(),
+            ViewerMsg::ToggleLint => {
+                if controller.lint_shown() {
+                    controller.hide_lint();
+                    return true;
+                }
+                if let Some(results) = controller.show_lint(LintOptions::default()) {
+                    let issue_count: usize = results.iter().map(|r| r.issues.len()).sum();
+                    self.toast_manager.show(format!(
+                        "{} lint issues in {} cells",
+                        issue_count,
+                        results.len()
+                    ));
+                }
+                true
+            }
+        }
+    }
+}
+
+impl ViewerPage {
+    /// Recomputes the cell usage table if it is shown, since flattened counts
+    /// depend on the cell descended into.
+    fn refresh_cell_usage(&mut self) {
+        if self.cell_usage.is_none() {
+            return;
+        }
+        let project = self.controller.as_ref().and_then(|c| c.project());
+        self.cell_usage = project.map(|project| project.cell_usage());
+    }
+
+    /// Lists the current search matches, which are found again when the
+    /// cell descended into changes or the layout is edited.
+    fn refresh_search_rows(&mut self) {
+        if self.search_rows.is_none() {
+            return;
         }
+        let Some(controller) = &self.controller else {
+            return;
+        };
+        let (Some(project), Some(matches)) = (controller.project(), controller.search_matches())
+        else {
+            self.search_rows = Some(Vec::new());
+            return;
+        };
+        let rows = matches
+            .iter()
+            .map(|found| {
+                let b = &found.bounds;
+                let bounds = if found.label.is_some() {
+                    format!("at ({}, {})", b.min_x, b.min_y)
+                } else {
+                    format!("({}, {}) to ({}, {})", b.min_x, b.min_y, b.max_x, b.max_y)
+                };
+                SearchRow {
+                    description: project.describe_search_match(found),
+                    bounds,
+                }
+            })
+            .collect();
+        self.search_rows = Some(rows);
     }
+
+    fn update_hierarchy_rows(&mut self) {
+        let project = self.controller.as_ref().and_then(|c| c.project());
+        self.hierarchy_rows = match project {
+            Some(project) => create_hierarchy_rows(project, &self.hierarchy_expanded),
+            None => Vec::new(),
+        };
+        self.hierarchy_depth = project.map_or(0, |project| project.hierarchy_depth());
+    }
+}
+
+/// Maps a key press to an editing shortcut, matching the native viewer.
+fn edit_action(e: &KeyboardEvent) -> Option<EditAction> {
+    let ctrl = e.ctrl_key() || e.meta_key();
+    let shift = e.shift_key();
+    let action = match e.key().as_str() {
+        "z" | "Z" if ctrl && shift => EditAction::Redo,
+        "z" if ctrl => EditAction::Undo,
+        "y" if ctrl => EditAction::Redo,
+        "Delete" if shift => EditAction::DeleteInstance,
+        "Delete" => EditAction::DeleteShape,
+        "ArrowLeft" => EditAction::Nudge(-1, 0, shift),
+        "ArrowRight" => EditAction::Nudge(1, 0, shift),
+        "ArrowUp" => EditAction::Nudge(0, -1, shift),
+        "ArrowDown" => EditAction::Nudge(0, 1, shift),
+        "o" if !ctrl => EditAction::Rotate,
+        "m" if !ctrl => EditAction::Mirror,
+        "PageUp" => EditAction::StepLayer(1),
+        "PageDown" => EditAction::StepLayer(-1),
+        _ => return None,
+    };
+    Some(action)
+}
+
+fn create_layer_proxies(project: &Project, layout: Option<usize>) -> Vec<LayerProxy> {
+    let highest_layer = project.highest_layer() as usize;
+    project
+        .layers()
+        .iter()
+        .enumerate()
+        .filter_map(|(index, layer)| {
+            if layer.is_empty() && !layer.is_derived() {
+                return None;
+            }
+            let color = if index == highest_layer {
+                // Make highest layer white
+                rgb_to_hex(1.0, 1.0, 1.0)
+            } else {
+                rgb_to_hex(layer.color.x, layer.color.y, layer.color.z)
+            };
+            Some(LayerProxy {
+                layout,
+                index,
+                name: layer.display_name(),
+                visible: layer.visible,
+                opacity: layer.color.w,
+                color,
+            })
+        })
+        .collect()
+}
+
+fn create_layout_proxies(controller: &AppController) -> Vec<LayoutProxy> {
+    controller
+        .layouts()
+        .iter()
+        .enumerate()
+        .map(|(index, layout)| LayoutProxy {
+            index,
+            name: layout.name.clone(),
+            visible: layout.visible,
+            tint: rgb_to_hex(layout.tint.x, layout.tint.y, layout.tint.z),
+            placement: layout.placement,
+            layers: create_layer_proxies(&layout.project, Some(index)),
+        })
+        .collect()
 }
 
 // Helper function to fetch GDS file
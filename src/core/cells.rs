@@ -1,9 +1,21 @@
+use crate::graphics::BoundingBox;
 use crate::rsutils::IdMapKey;
-use gds21::GdsBoundary;
-use gds21::GdsPath;
+use gds21::GdsDateTimes;
+use gds21::GdsElemFlags;
+use gds21::GdsElement;
+use gds21::GdsPlex;
 use gds21::GdsPoint;
+use gds21::GdsPresentation;
+use gds21::GdsProperty;
 use gds21::GdsStrans;
 use geo::AffineTransform;
+use geo::BoundingRect;
+use geo::Coord;
+use rstar::primitives::GeomWithData;
+use rstar::primitives::Rectangle;
+use rstar::RTree;
+
+type Polygon = geo::Polygon<f64>;
 
 /// Simple integer ID for cells, guaranteed to be unique within a project.
 #[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
@@ -19,14 +31,79 @@ impl IdMapKey for CellId {
     }
 }
 
+/// Step along a chain of instances: an instance, and for array references
+/// the element that the chain passes through.
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub struct InstanceStep {
+    pub cell_id: CellId,
+    /// Column and row of the array element, or None for single instances
+    /// and for an array as a whole.
+    pub element: Option<(i16, i16)>,
+}
+
+impl From<CellId> for InstanceStep {
+    fn from(cell_id: CellId) -> Self {
+        Self {
+            cell_id,
+            element: None,
+        }
+    }
+}
+
 /// Renderable instance of a [CellDef], positioned in the world.
 pub(crate) struct Cell {
     pub cell_def_id: CellDefId,
+    /// Cell that places this instance.
+    pub parent: CellDefId,
     pub xy: GdsPoint,
     pub local_transform: Option<GdsStrans>,
-    pub world_transform: AffineTransform, // derived from local_transform by traversing the hierarchy
     pub visible: bool,
     pub array: Option<ArrayProperties>,
+    pub metadata: ElementMetadata,
+}
+
+impl Cell {
+    /// Returns the transform from this cell's coordinate system into the
+    /// coordinate system of its parent.
+    pub fn placement(&self) -> AffineTransform {
+        let translate = AffineTransform::translate(self.xy.x as f64, self.xy.y as f64);
+        let mut rotate = AffineTransform::identity();
+        let mut scale = AffineTransform::identity();
+
+        if let Some(local_transform) = &self.local_transform {
+            if let Some(angle) = &local_transform.angle {
+                rotate = AffineTransform::rotate(*angle, Coord::zero());
+            }
+            if local_transform.reflected {
+                scale = AffineTransform::scale(1.0, -1.0, Coord::zero());
+            }
+        }
+
+        scale.compose(&rotate).compose(&translate)
+    }
+
+    /// Like [Self::placement], for the given element of an array reference.
+    /// Single instances and None give the placement of the first element.
+    pub fn element_placement(&self, element: Option<(i16, i16)>) -> AffineTransform {
+        match (&self.array, element) {
+            (Some(array), Some((col, row))) => {
+                let (x, y) = array.element_offset(col, row);
+                self.placement().compose(&AffineTransform::translate(x, y))
+            }
+            _ => self.placement(),
+        }
+    }
+
+    /// Every element of an array reference as (column, row), row by row, or
+    /// a single None for other instances.
+    pub fn elements(&self) -> Vec<Option<(i16, i16)>> {
+        match &self.array {
+            Some(array) => (0..array.rows)
+                .flat_map(|row| (0..array.cols).map(move |col| Some((col, row))))
+                .collect(),
+            None => vec![None],
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -35,27 +112,150 @@ pub(crate) struct ArrayProperties {
     pub cols: i16,
     pub width: f64,
     pub height: f64,
+    /// The three reference points of the GDSII AREF element.
+    pub xy: [GdsPoint; 3],
+}
+
+impl ArrayProperties {
+    /// Returns the translation of the given array element relative to the
+    /// first element, in the coordinate system of the parent cell.
+    pub fn element_offset(&self, col: i16, row: i16) -> (f64, f64) {
+        let [origin, col_corner, row_corner] = &self.xy;
+        let cols = self.cols.max(1) as f64;
+        let rows = self.rows.max(1) as f64;
+        let col_step = (
+            (col_corner.x - origin.x) as f64 / cols,
+            (col_corner.y - origin.y) as f64 / cols,
+        );
+        let row_step = (
+            (row_corner.x - origin.x) as f64 / rows,
+            (row_corner.y - origin.y) as f64 / rows,
+        );
+        (
+            col as f64 * col_step.0 + row as f64 * row_step.0,
+            col as f64 * col_step.1 + row as f64 * row_step.1,
+        )
+    }
+}
+
+/// GDSII element data that the viewer does not interpret, kept so that it
+/// can be inspected and written back.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ElementMetadata {
+    pub elflags: Option<GdsElemFlags>,
+    pub plex: Option<GdsPlex>,
+    /// PROPATTR and PROPVALUE pairs.
+    pub properties: Vec<GdsProperty>,
+}
+
+/// Boundary or path element of a cell, converted to a polygon once in the
+/// coordinate system of the cell.
+#[derive(Debug, Clone)]
+pub(crate) struct Shape {
+    pub layer: i16,
+    pub datatype: i16,
+    pub polygon: Polygon,
+    /// Spine of the path that the polygon was stroked from, kept so that
+    /// paths can be written back as paths. None for boundaries.
+    pub path: Option<PathSpine>,
+    pub metadata: ElementMetadata,
+}
+
+/// Text element of a cell, see [Project::search](crate::Project::search).
+/// Attributes that only affect presentation are kept so that the element
+/// can be written back.
+#[derive(Debug, Clone)]
+pub(crate) struct Label {
+    pub text: String,
+    pub layer: i16,
+    pub texttype: i16,
+    /// Anchor of the text in the coordinate system of the cell.
+    pub position: Coord,
+    pub presentation: Option<GdsPresentation>,
+    pub path_type: Option<i16>,
+    pub width: Option<i32>,
+    pub strans: Option<GdsStrans>,
+    pub metadata: ElementMetadata,
+}
+
+impl Shape {
+    /// Returns the outline as GDSII points, closed as in boundary elements.
+    pub fn boundary_xy(&self) -> Vec<GdsPoint> {
+        self.polygon
+            .exterior()
+            .coords()
+            .map(|c| GdsPoint::new(c.x.round() as i32, c.y.round() as i32))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct PathSpine {
+    pub xy: Vec<GdsPoint>,
+    pub width: Option<i32>,
+    pub path_type: Option<i16>,
+    pub begin_extn: Option<i32>,
+    pub end_extn: Option<i32>,
 }
 
 /// Instanceable template definition of a cell.
 /// Corresponds to a single GDSII struct.
 #[derive(Debug, Clone)]
 pub(crate) struct CellDef {
-    pub boundary_elements: Vec<GdsBoundary>,
-    pub path_elements: Vec<GdsPath>,
+    pub shapes: Vec<Shape>,
+    /// Bounding boxes of the shapes, indexing into `shapes`.
+    pub shape_tree: RTree<GeomWithData<Rectangle<[f64; 2]>, usize>>,
+    /// Bounds of the shapes alone, see [CellDef::update_shape_tree].
+    pub shape_bounds: Option<BoundingBox>,
+    /// Bounds of the shapes and all descendants, regardless of visibility.
+    /// Filled in by [Project::update_cell_def_bounds](crate::Project).
+    pub bounds: Option<BoundingBox>,
     pub cell_elements: Vec<CellId>,
     pub instances: Vec<CellId>,
+    /// Text elements, which are not drawn but can be searched.
+    pub labels: Vec<Label>,
+    /// Node and box elements, which are neither drawn nor searched but
+    /// written back unchanged.
+    pub raw_elements: Vec<GdsElement>,
     pub root_instance: Option<CellId>,
+    /// Creation and modification dates of the GDSII struct.
+    pub dates: GdsDateTimes,
 }
 
 impl CellDef {
     pub fn new() -> Self {
         Self {
             instances: vec![],
-            boundary_elements: Vec::new(),
-            path_elements: Vec::new(),
+            shapes: Vec::new(),
+            shape_tree: RTree::new(),
+            shape_bounds: None,
+            bounds: None,
             cell_elements: Vec::new(),
+            labels: Vec::new(),
+            raw_elements: Vec::new(),
             root_instance: None,
+            dates: GdsDateTimes::default(),
         }
     }
+
+    /// Rebuilds the spatial index of the shapes after they changed.
+    pub fn update_shape_tree(&mut self) {
+        let mut bounds = BoundingBox::new();
+        let items = self
+            .shapes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, shape)| {
+                let rect = shape.polygon.bounding_rect()?;
+                bounds.encompass(&BoundingBox::from(rect));
+                let rectangle = Rectangle::from_corners(
+                    [rect.min().x, rect.min().y],
+                    [rect.max().x, rect.max().y],
+                );
+                Some(GeomWithData::new(rectangle, index))
+            })
+            .collect();
+        self.shape_tree = RTree::bulk_load(items);
+        self.shape_bounds = (!bounds.is_empty()).then_some(bounds);
+    }
 }
@@ -0,0 +1,347 @@
+use crate::core::rule_lines;
+use crate::graphics::BoundingBox;
+use crate::Project;
+use anyhow::Result;
+use geo::BoundingRect;
+use geo::Intersects;
+use indexmap::IndexSet;
+use rstar::primitives::GeomWithData;
+use rstar::primitives::Rectangle;
+use rstar::RTree;
+use rstar::RTreeObject;
+use std::collections::HashMap;
+
+type Polygon = geo::Polygon<f64>;
+
+/// A via layer joining shapes on the layers below and above it wherever the
+/// three overlap.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Via {
+    pub layer: i16,
+    pub below: i16,
+    pub above: i16,
+}
+
+/// Describes which layers conduct and how they are connected to each other.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Connectivity {
+    /// Layers whose touching shapes are electrically connected, including
+    /// the via layers.
+    pub conductors: IndexSet<i16>,
+    pub vias: Vec<Via>,
+}
+
+/// Parses a connectivity definition with one statement per line.
+///
+/// ```text
+/// conduct 1 3 5       # diffusion, poly and metal conduct
+/// via 4 1 5           # contacts on layer 4 join diffusion and metal
+/// via 4 3 5
+/// ```
+///
+/// Via layers and the layers they join conduct implicitly. See [rule_lines]
+/// for comments and how layers are given.
+pub fn parse_connectivity(
+    text: &str,
+    resolve: impl Fn(&str) -> Option<i16>,
+) -> Result<Connectivity> {
+    let mut connectivity = Connectivity::default();
+    for line in rule_lines(text) {
+        let layer = |field: &str| line.layer(field, &resolve);
+        match line.fields.as_slice() {
+            ["conduct", layers @ ..] if !layers.is_empty() => {
+                for field in layers {
+                    connectivity.conductors.insert(layer(field)?);
+                }
+            }
+            ["via", via, below, above] => {
+                let via = Via {
+                    layer: layer(via)?,
+                    below: layer(below)?,
+                    above: layer(above)?,
+                };
+                connectivity.conductors.insert(via.layer);
+                connectivity.conductors.insert(via.below);
+                connectivity.conductors.insert(via.above);
+                connectivity.vias.push(via);
+            }
+            [kind, ..] => {
+                return Err(line.error(&format!(
+                    "expected 'conduct' with one or more layers or 'via' with three layers, \
+                     found '{}'",
+                    kind
+                )))
+            }
+            [] => unreachable!(),
+        }
+    }
+    Ok(connectivity)
+}
+
+/// Identifies a polygon by its layer and its index within
+/// [Layer::polygons](crate::core::Layer::polygons), as in
+/// [PickResult](crate::core::PickResult).
+pub type ShapeRef = (i16, usize);
+
+/// A set of shapes that are electrically connected.
+#[derive(Debug, Clone)]
+pub struct Net {
+    pub shapes: Vec<ShapeRef>,
+    pub bounds: BoundingBox,
+}
+
+impl Net {
+    /// Returns the distinct layers of the shapes in this net, in ascending
+    /// order.
+    pub fn layers(&self) -> Vec<i16> {
+        let mut layers: Vec<i16> = self.shapes.iter().map(|(layer, _)| *layer).collect();
+        layers.sort_unstable();
+        layers.dedup();
+        layers
+    }
+}
+
+/// Result of [Project::extract_nets]. Nets are sorted by decreasing size.
+#[derive(Debug, Clone, Default)]
+pub struct Netlist {
+    pub nets: Vec<Net>,
+    net_of: HashMap<ShapeRef, usize>,
+}
+
+impl Netlist {
+    /// Returns the index of the net containing the given shape, if the shape
+    /// is on a conducting layer.
+    pub fn net_of(&self, shape: ShapeRef) -> Option<usize> {
+        self.net_of.get(&shape).copied()
+    }
+}
+
+impl Project {
+    /// Parses a connectivity file, resolving derived layers of this project
+    /// by name. See [parse_connectivity] for the format.
+    pub fn parse_connectivity(&self, text: &str) -> Result<Connectivity> {
+        parse_connectivity(text, |name| self.find_derived_layer(name))
+    }
+
+    /// Groups the flattened polygons on conducting layers into nets. Shapes
+    /// on the same layer are connected when they touch or overlap, and via
+    /// shapes connect to the shapes they overlap on the layers they join.
+    pub fn extract_nets(&self, connectivity: &Connectivity) -> Netlist {
+        let mut shapes: Vec<ShapeRef> = Vec::new();
+        let mut trees: HashMap<i16, LayerTree> = HashMap::new();
+        for &layer in &connectivity.conductors {
+            let Some(source) = self.layers().get(layer as usize) else {
+                continue;
+            };
+            let items: Vec<TreeItem> = source
+                .polygons
+                .iter()
+                .enumerate()
+                .filter_map(|(index, polygon)| {
+                    let rect = polygon.bounding_rect()?;
+                    let rectangle = Rectangle::from_corners(
+                        [rect.min().x, rect.min().y],
+                        [rect.max().x, rect.max().y],
+                    );
+                    shapes.push((layer, index));
+                    Some(GeomWithData::new(rectangle, shapes.len() - 1))
+                })
+                .collect();
+            trees.insert(layer, RTree::bulk_load(items));
+        }
+
+        let polygon = |(layer, index): ShapeRef| -> &Polygon {
+            &self.layers()[layer as usize].polygons[index]
+        };
+        let mut sets = DisjointSets::new(shapes.len());
+        let connect = |from: i16, to: i16, sets: &mut DisjointSets| {
+            let (Some(from_tree), Some(to_tree)) = (trees.get(&from), trees.get(&to)) else {
+                return;
+            };
+            for item in from_tree.iter() {
+                let envelope = item.geom().envelope();
+                for other in to_tree.locate_in_envelope_intersecting(&envelope) {
+                    // Same layer pairs only need to be tested once.
+                    if from == to && other.data <= item.data {
+                        continue;
+                    }
+                    if sets.find(item.data) == sets.find(other.data) {
+                        continue;
+                    }
+                    if polygon(shapes[item.data]).intersects(polygon(shapes[other.data])) {
+                        sets.union(item.data, other.data);
+                    }
+                }
+            }
+        };
+        for &layer in &connectivity.conductors {
+            connect(layer, layer, &mut sets);
+        }
+        for via in &connectivity.vias {
+            connect(via.layer, via.below, &mut sets);
+            connect(via.layer, via.above, &mut sets);
+        }
+
+        let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+        for index in 0..shapes.len() {
+            groups.entry(sets.find(index)).or_default().push(index);
+        }
+        let mut nets: Vec<Net> = groups
+            .into_values()
+            .map(|members| {
+                let mut bounds = BoundingBox::new();
+                for &member in &members {
+                    if let Some(rect) = polygon(shapes[member]).bounding_rect() {
+                        bounds.encompass(&BoundingBox::from(rect));
+                    }
+                }
+                Net {
+                    shapes: members.into_iter().map(|member| shapes[member]).collect(),
+                    bounds,
+                }
+            })
+            .collect();
+        nets.sort_by(|a, b| {
+            b.shapes
+                .len()
+                .cmp(&a.shapes.len())
+                .then_with(|| a.shapes[0].cmp(&b.shapes[0]))
+        });
+
+        let mut net_of = HashMap::new();
+        for (net_index, net) in nets.iter().enumerate() {
+            for &shape in &net.shapes {
+                net_of.insert(shape, net_index);
+            }
+        }
+        Netlist { nets, net_of }
+    }
+}
+
+type TreeItem = GeomWithData<Rectangle<[f64; 2]>, usize>;
+type LayerTree = RTree<TreeItem>;
+
+/// Union-find over shape indices, with path halving.
+struct DisjointSets {
+    parents: Vec<usize>,
+}
+
+impl DisjointSets {
+    fn new(count: usize) -> Self {
+        Self {
+            parents: (0..count).collect(),
+        }
+    }
+
+    fn find(&mut self, mut index: usize) -> usize {
+        while self.parents[index] != index {
+            self.parents[index] = self.parents[self.parents[index]];
+            index = self.parents[index];
+        }
+        index
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let a = self.find(a);
+        let b = self.find(b);
+        if a != b {
+            self.parents[a.max(b)] = a.min(b);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::test_layouts::*;
+
+    #[test]
+    fn test_parse_connectivity_definition() {
+        let text = "conduct 1 3  # comment\n\nvia 4 1 5\n";
+        let connectivity = parse_connectivity(text, |_| None).unwrap();
+        let conductors: Vec<i16> = connectivity.conductors.iter().copied().collect();
+        assert_eq!(conductors, vec![1, 3, 4, 5]);
+        assert_eq!(
+            connectivity.vias,
+            vec![Via {
+                layer: 4,
+                below: 1,
+                above: 5
+            }]
+        );
+        assert!(parse_connectivity("via 4 1", |_| None).is_err());
+        assert!(parse_connectivity("conduct metal", |_| None).is_err());
+    }
+
+    #[test]
+    fn test_disjoint_sets_join_transitively() {
+        let mut sets = DisjointSets::new(4);
+        sets.union(0, 1);
+        sets.union(3, 1);
+        assert_eq!(sets.find(3), sets.find(0));
+        assert_ne!(sets.find(2), sets.find(0));
+    }
+
+    #[test]
+    fn test_extract_nets_through_vias() {
+        // Metal 1 on layer 1 and metal 2 on layer 3 are joined by a via on
+        // layer 2. A pad on metal 1 is placed twice at the same spot.
+        let mut project = project(vec![
+            gds_struct("pad", vec![rect(1, (50, 0), (52, 2))]),
+            gds_struct(
+                "top",
+                vec![
+                    rect(1, (0, 0), (10, 2)),
+                    rect(2, (8, 0), (10, 2)),
+                    rect(3, (8, 0), (20, 2)),
+                    rect(1, (30, 0), (40, 2)),
+                    sref("pad", (0, 0)),
+                    sref("pad", (0, 0)),
+                ],
+            ),
+        ]);
+        project.flatten_layers();
+        let connectivity = parse_connectivity("via 2 1 3", |_| None).unwrap();
+        let netlist = project.extract_nets(&connectivity);
+        let mut nets: Vec<Vec<ShapeRef>> = netlist
+            .nets
+            .iter()
+            .map(|net| {
+                let mut shapes = net.shapes.clone();
+                shapes.sort();
+                shapes
+            })
+            .collect();
+        nets.sort();
+        // The pads overlap each other, so they form one net.
+        assert_eq!(
+            nets,
+            vec![
+                vec![(1, 0), (2, 0), (3, 0)],
+                vec![(1, 1)],
+                vec![(1, 2), (1, 3)]
+            ]
+        );
+
+        let pick = project.pick_cell(9.0, 1.0).unwrap();
+        assert_eq!(pick.layer, 3);
+        let index = project.flat_polygon_index(&pick).unwrap();
+        assert_eq!(netlist.net_of((3, index)), netlist.net_of((1, 0)));
+
+        let pick = project.pick_cell(51.0, 1.0).unwrap();
+        let index = project.flat_polygon_index(&pick).unwrap();
+        assert_eq!(project.layers()[1].polygons[index], pick.polygon);
+        let mut other = pick.clone();
+        other.node = project
+            .placements()
+            .iter()
+            .find(|placement| {
+                placement.cell_def_id == pick.cell_def_id && placement.node != pick.node
+            })
+            .unwrap()
+            .node;
+        let other_index = project.flat_polygon_index(&other).unwrap();
+        assert_ne!(other_index, index);
+        assert_eq!(project.layers()[1].polygons[other_index], pick.polygon);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::core::rule_lines;
+use crate::Project;
+use anyhow::Result;
+use geo::BooleanOps;
+use geo::BoundingRect;
+use geo::Coord;
+use geo::Intersects;
+use geo::Line;
+use geo::LineString;
+use geo::MultiLineString;
+
+/// Vertical placement of a layer in the process, in database units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StackLayer {
+    pub layer: i16,
+    pub z: f64,
+    pub thickness: f64,
+}
+
+/// Describes how layers are stacked on top of each other, for drawing
+/// cross-sections.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LayerStack {
+    pub layers: Vec<StackLayer>,
+}
+
+impl LayerStack {
+    /// Parses a layer stack with one layer per line, given as the layer,
+    /// the height of its bottom and its thickness.
+    ///
+    /// ```text
+    /// 1  0    300   # diffusion
+    /// 3  300  200   # poly
+    /// 5  700  500   # metal
+    /// ```
+    ///
+    /// See [rule_lines] for comments and how layers are given.
+    pub fn parse(text: &str, resolve: impl Fn(&str) -> Option<i16>) -> Result<Self> {
+        let mut layers = Vec::new();
+        for line in rule_lines(text) {
+            let [layer, z, thickness] = line.fields.as_slice() else {
+                return Err(line.error("expected a layer, a height and a thickness"));
+            };
+            let layer = line.layer(layer, &resolve)?;
+            let number = |field: &str| {
+                field
+                    .parse::<f64>()
+                    .map_err(|_| line.error(&format!("invalid number '{}'", field)))
+            };
+            let thickness = number(thickness)?;
+            if thickness <= 0.0 {
+                return Err(line.error("thickness must be positive"));
+            }
+            layers.push(StackLayer {
+                layer,
+                z: number(z)?,
+                thickness,
+            });
+        }
+        Ok(Self { layers })
+    }
+
+    pub fn top(&self) -> f64 {
+        self.layers
+            .iter()
+            .map(|layer| layer.z + layer.thickness)
+            .fold(0.0, f64::max)
+    }
+
+    pub fn bottom(&self) -> f64 {
+        self.layers.iter().map(|layer| layer.z).fold(0.0, f64::min)
+    }
+}
+
+/// Part of a layer cut by the section line, spanning the given distances
+/// along the line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SectionRect {
+    pub layer: i16,
+    pub from: f64,
+    pub to: f64,
+    pub z: f64,
+    pub thickness: f64,
+}
+
+/// Side view of the layout along a line, produced by
+/// [Project::cross_section].
+#[derive(Debug, Clone)]
+pub struct CrossSection {
+    pub start: Coord<f64>,
+    pub end: Coord<f64>,
+    pub length: f64,
+    /// Rectangles in the order of the layer stack, so that later layers are
+    /// drawn on top.
+    pub rects: Vec<SectionRect>,
+    pub top: f64,
+    pub bottom: f64,
+}
+
+impl CrossSection {
+    /// Factor to stretch heights by so that the section is drawn with at
+    /// least the given height to width ratio. Layers are usually far
+    /// thinner than the distances along the cut.
+    pub fn vertical_scale(&self, min_aspect: f64) -> f64 {
+        let height = self.top - self.bottom;
+        if height <= 0.0 || self.length <= 0.0 {
+            return 1.0;
+        }
+        (self.length * min_aspect / height).max(1.0)
+    }
+}
+
+impl Project {
+    /// Stacks every layer that has any geometry on top of the previous one,
+    /// in order of the layer number, for when no layer stack is given.
+    pub fn default_layer_stack(&self) -> LayerStack {
+        const THICKNESS: f64 = 100.0;
+        let layers = self
+            .layers()
+            .iter()
+            .filter(|layer| !layer.is_empty())
+            .enumerate()
+            .map(|(position, layer)| StackLayer {
+                layer: layer.index(),
+                z: position as f64 * THICKNESS,
+                thickness: THICKNESS,
+            })
+            .collect();
+        LayerStack { layers }
+    }
+
+    /// Parses a layer stack, resolving derived layers of this project by
+    /// name. See [LayerStack::parse] for the format.
+    pub fn parse_layer_stack(&self, text: &str) -> Result<LayerStack> {
+        LayerStack::parse(text, |name| self.find_derived_layer(name))
+    }
+
+    /// Computes where the polygons of each layer in the stack cross the line
+    /// from `start` to `end`.
+    pub fn cross_section(
+        &self,
+        stack: &LayerStack,
+        start: Coord<f64>,
+        end: Coord<f64>,
+    ) -> CrossSection {
+        let line = Line::new(start, end);
+        let length = (end - start).x.hypot((end - start).y);
+        let cut = MultiLineString::new(vec![LineString::from(line)]);
+        let line_bounds = line.bounding_rect();
+
+        let mut rects = Vec::new();
+        for entry in &stack.layers {
+            let Some(layer) = self.layers().get(entry.layer as usize) else {
+                continue;
+            };
+            let mut intervals = Vec::new();
+            for polygon in &layer.polygons {
+                let overlaps = polygon
+                    .bounding_rect()
+                    .is_some_and(|rect| rect.intersects(&line_bounds));
+                if !overlaps {
+                    continue;
+                }
+                for piece in polygon.clip(&cut, false) {
+                    let distances = piece
+                        .coords()
+                        .map(|coord| distance_along(start, end, length, *coord));
+                    let (from, to) =
+                        distances.fold((f64::MAX, f64::MIN), |(lo, hi), d| (lo.min(d), hi.max(d)));
+                    if to > from {
+                        intervals.push((from, to));
+                    }
+                }
+            }
+            for (from, to) in merge_intervals(intervals) {
+                rects.push(SectionRect {
+                    layer: entry.layer,
+                    from,
+                    to,
+                    z: entry.z,
+                    thickness: entry.thickness,
+                });
+            }
+        }
+
+        CrossSection {
+            start,
+            end,
+            length,
+            rects,
+            top: stack.top(),
+            bottom: stack.bottom(),
+        }
+    }
+}
+
+fn distance_along(start: Coord<f64>, end: Coord<f64>, length: f64, point: Coord<f64>) -> f64 {
+    if length == 0.0 {
+        return 0.0;
+    }
+    let direction = (end - start) / length;
+    let offset = point - start;
+    (offset.x * direction.x + offset.y * direction.y).clamp(0.0, length)
+}
+
+/// Joins overlapping and abutting intervals.
+fn merge_intervals(mut intervals: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    intervals.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut merged: Vec<(f64, f64)> = Vec::new();
+    for (from, to) in intervals {
+        match merged.last_mut() {
+            Some(last) if from <= last.1 => last.1 = last.1.max(to),
+            _ => merged.push((from, to)),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stack() {
+        let stack = LayerStack::parse("1 0 300 # diff\n\n5 700 500\n", |_| None).unwrap();
+        assert_eq!(stack.layers.len(), 2);
+        assert_eq!(stack.top(), 1200.0);
+        assert!(LayerStack::parse("1 0", |_| None).is_err());
+        assert!(LayerStack::parse("1 0 -5", |_| None).is_err());
+    }
+
+    #[test]
+    fn test_merges_intervals() {
+        let merged = merge_intervals(vec![(5.0, 6.0), (0.0, 2.0), (1.0, 3.0), (3.0, 4.0)]);
+        assert_eq!(merged, vec![(0.0, 4.0), (5.0, 6.0)]);
+    }
+}
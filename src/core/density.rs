@@ -0,0 +1,275 @@
+use crate::core::union_polygons;
+use crate::graphics::BoundingBox;
+use crate::Project;
+use anyhow::anyhow;
+use anyhow::Result;
+use geo::BoundingRect;
+use geo::LineString;
+
+type Polygon = geo::Polygon<f64>;
+
+/// Options for [Project::compute_density].
+#[derive(Debug, Clone)]
+pub struct DensityOptions {
+    /// Side length of each square window, in database units.
+    pub window: f64,
+    /// Distance between the origins of adjacent windows.
+    pub step: f64,
+    /// Windows below this density are reported as failing.
+    pub min_density: Option<f64>,
+    /// Windows above this density are reported as failing.
+    pub max_density: Option<f64>,
+}
+
+impl DensityOptions {
+    /// Picks a window that divides the given area into roughly 20 windows
+    /// along its longer side, with windows overlapping by half.
+    pub fn for_bounds(bounds: &BoundingBox) -> Self {
+        let window = (bounds.width().max(bounds.height()) / 20.0).max(1.0);
+        Self {
+            window,
+            step: window / 2.0,
+            min_density: None,
+            max_density: None,
+        }
+    }
+}
+
+/// Fraction of each window covered by a layer. Windows are laid out on a
+/// regular grid starting at the lower left corner of the layout, and the
+/// last row and column may extend past its bounds.
+#[derive(Debug, Clone)]
+pub struct DensityMap {
+    pub layer: i16,
+    pub origin_x: f64,
+    pub origin_y: f64,
+    pub window: f64,
+    pub step: f64,
+    pub cols: usize,
+    pub rows: usize,
+    /// Densities between 0 and 1 in row-major order, starting at the bottom.
+    pub values: Vec<f64>,
+    pub min_density: Option<f64>,
+    pub max_density: Option<f64>,
+}
+
+/// A window whose density is outside of the configured limits.
+#[derive(Debug, Clone, Copy)]
+pub struct DensityViolation {
+    pub col: usize,
+    pub row: usize,
+    pub density: f64,
+}
+
+impl DensityMap {
+    pub fn value(&self, col: usize, row: usize) -> f64 {
+        self.values[row * self.cols + col]
+    }
+
+    pub fn window_bounds(&self, col: usize, row: usize) -> BoundingBox {
+        let min_x = self.origin_x + col as f64 * self.step;
+        let min_y = self.origin_y + row as f64 * self.step;
+        BoundingBox {
+            min_x,
+            min_y,
+            max_x: min_x + self.window,
+            max_y: min_y + self.window,
+        }
+    }
+
+    pub fn min(&self) -> f64 {
+        self.values.iter().copied().fold(f64::MAX, f64::min)
+    }
+
+    pub fn max(&self) -> f64 {
+        self.values.iter().copied().fold(f64::MIN, f64::max)
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.values.iter().sum::<f64>() / self.values.len() as f64
+    }
+
+    pub fn violations(&self) -> Vec<DensityViolation> {
+        let mut violations = Vec::new();
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                let density = self.value(col, row);
+                let too_low = self.min_density.is_some_and(|min| density < min);
+                let too_high = self.max_density.is_some_and(|max| density > max);
+                if too_low || too_high {
+                    violations.push(DensityViolation { col, row, density });
+                }
+            }
+        }
+        violations
+    }
+}
+
+impl Project {
+    /// Computes the density of a layer over a grid of windows covering the
+    /// whole layout. Overlapping polygons are merged first so that they are
+    /// not counted twice.
+    pub fn compute_density(&self, layer: i16, options: &DensityOptions) -> Result<DensityMap> {
+        if !(options.window > 0.0 && options.step > 0.0) {
+            return Err(anyhow!("Density window and step must be positive"));
+        }
+        let source = self
+            .layers()
+            .get(layer as usize)
+            .ok_or_else(|| anyhow!("Layer {} does not exist", layer))?;
+
+        let merged;
+        let polygons = if source.is_merged() {
+            source.render_polygons()
+        } else {
+            merged = union_polygons(&source.polygons);
+            &merged
+        };
+        Ok(density_map(layer, polygons, &self.bounds(), options))
+    }
+}
+
+/// Computes the density of the given polygons, which must not overlap, over
+/// a grid of windows covering the given bounds.
+pub(crate) fn density_map(
+    layer: i16,
+    polygons: &[Polygon],
+    bounds: &BoundingBox,
+    options: &DensityOptions,
+) -> DensityMap {
+    let (origin_x, origin_y) = if bounds.is_empty() {
+        (0.0, 0.0)
+    } else {
+        (bounds.min_x, bounds.min_y)
+    };
+    let count = |extent: f64| {
+        let extent = if bounds.is_empty() { 0.0 } else { extent };
+        ((extent - options.window) / options.step).ceil().max(0.0) as usize + 1
+    };
+    let cols = count(bounds.width());
+    let rows = count(bounds.height());
+
+    let mut areas = vec![0.0; cols * rows];
+    for polygon in polygons {
+        let Some(rect) = polygon.bounding_rect() else {
+            continue;
+        };
+        let col_range = window_range(rect.min().x, rect.max().x, origin_x, options, cols);
+        let row_range = window_range(rect.min().y, rect.max().y, origin_y, options, rows);
+        for row in row_range {
+            for col in col_range.clone() {
+                let x = origin_x + col as f64 * options.step;
+                let y = origin_y + row as f64 * options.step;
+                let window = [x, y, x + options.window, y + options.window];
+                areas[row * cols + col] += clipped_area(polygon, window);
+            }
+        }
+    }
+
+    let window_area = options.window * options.window;
+    DensityMap {
+        layer,
+        origin_x,
+        origin_y,
+        window: options.window,
+        step: options.step,
+        cols,
+        rows,
+        values: areas.iter().map(|area| area / window_area).collect(),
+        min_density: options.min_density,
+        max_density: options.max_density,
+    }
+}
+
+/// Returns the indices of the windows along one axis that overlap the
+/// interval from `min` to `max`.
+fn window_range(
+    min: f64,
+    max: f64,
+    origin: f64,
+    options: &DensityOptions,
+    count: usize,
+) -> std::ops::Range<usize> {
+    let first = ((min - origin - options.window) / options.step).floor() + 1.0;
+    let last = ((max - origin) / options.step).ceil();
+    let first = first.max(0.0) as usize;
+    let last = (last.max(0.0) as usize).min(count);
+    first..last.max(first)
+}
+
+/// Area of a polygon within an axis-aligned window given as
+/// `[min_x, min_y, max_x, max_y]`.
+fn clipped_area(polygon: &Polygon, window: [f64; 4]) -> f64 {
+    let holes: f64 = polygon
+        .interiors()
+        .iter()
+        .map(|ring| clipped_ring_area(ring, window))
+        .sum();
+    (clipped_ring_area(polygon.exterior(), window) - holes).max(0.0)
+}
+
+/// Clips a ring against each side of the window in turn (Sutherland-Hodgman)
+/// and returns the area of the result. The clipped ring may contain
+/// degenerate edges when the ring is concave, but its area is still exact.
+fn clipped_ring_area(ring: &LineString<f64>, window: [f64; 4]) -> f64 {
+    let [min_x, min_y, max_x, max_y] = window;
+    let mut points: Vec<(f64, f64)> = ring.coords().map(|c| (c.x, c.y)).collect();
+    if points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+
+    // Each side is described by the coordinate axis, the limit and whether
+    // points inside have a larger value than the limit.
+    let sides = [
+        (0, min_x, true),
+        (0, max_x, false),
+        (1, min_y, true),
+        (1, max_y, false),
+    ];
+    for (axis, limit, keep_above) in sides {
+        let coord = |p: (f64, f64)| if axis == 0 { p.0 } else { p.1 };
+        let inside = |p: (f64, f64)| (coord(p) >= limit) == keep_above || coord(p) == limit;
+        let mut clipped = Vec::with_capacity(points.len() + 4);
+        for i in 0..points.len() {
+            let current = points[i];
+            let previous = points[(i + points.len() - 1) % points.len()];
+            if inside(current) != inside(previous) {
+                let t = (limit - coord(previous)) / (coord(current) - coord(previous));
+                clipped.push((
+                    previous.0 + t * (current.0 - previous.0),
+                    previous.1 + t * (current.1 - previous.1),
+                ));
+            }
+            if inside(current) {
+                clipped.push(current);
+            }
+        }
+        points = clipped;
+        if points.is_empty() {
+            return 0.0;
+        }
+    }
+
+    let mut twice_area = 0.0;
+    for i in 0..points.len() {
+        let (x0, y0) = points[i];
+        let (x1, y1) = points[(i + 1) % points.len()];
+        twice_area += x0 * y1 - x1 * y0;
+    }
+    twice_area.abs() / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clipped_area_of_square_with_hole() {
+        let exterior = LineString::from(vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]);
+        let hole = LineString::from(vec![(2.0, 2.0), (4.0, 2.0), (4.0, 4.0), (2.0, 4.0)]);
+        let polygon = Polygon::new(exterior, vec![hole]);
+        assert_eq!(clipped_area(&polygon, [0.0, 0.0, 5.0, 5.0]), 21.0);
+        assert_eq!(clipped_area(&polygon, [5.0, 5.0, 20.0, 20.0]), 25.0);
+        assert_eq!(clipped_area(&polygon, [20.0, 20.0, 30.0, 30.0]), 0.0);
+    }
+}
@@ -0,0 +1,444 @@
+use crate::core::difference_polygons;
+use crate::core::intersect_polygons;
+use crate::core::size_polygons;
+use crate::core::union_polygons;
+use crate::core::xor_polygons;
+use crate::core::CornerStyle;
+use crate::core::Layer;
+use crate::rsutils::hsv_to_rgb;
+use crate::Project;
+use anyhow::anyhow;
+use anyhow::Result;
+use nalgebra::Vector4;
+
+type Polygon = geo::Polygon<f64>;
+
+/// Boolean expression over layers, e.g. `(1 AND 3) GROW 50`.
+///
+/// `AND` and `NOT` bind tighter than `OR` and `XOR`, and `GROW` / `SHRINK`
+/// bind tightest of all. Distances are in database units, and may be followed
+/// by `SQUARE`, `ROUND` or `OCTAGONAL` to pick the corner style, e.g.
+/// `1 GROW 50 ROUND`. Corners are square by default. Layers read from the
+/// file are referred to by number and derived layers by name.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LayerExpr {
+    Layer(i16),
+    /// Derived layer referred to by name, as its layer index.
+    Derived(i16),
+    And(Box<LayerExpr>, Box<LayerExpr>),
+    Or(Box<LayerExpr>, Box<LayerExpr>),
+    Xor(Box<LayerExpr>, Box<LayerExpr>),
+    Not(Box<LayerExpr>, Box<LayerExpr>),
+    Grow(Box<LayerExpr>, f64, CornerStyle),
+    Shrink(Box<LayerExpr>, f64, CornerStyle),
+}
+
+/// Named layer computed from other layers.
+#[derive(Debug, Clone)]
+pub struct DerivedLayer {
+    pub name: String,
+    /// Source text of the expression, which refers to derived layers by name
+    /// and can be re-parsed if layer indices change.
+    pub expression: String,
+    pub expr: LayerExpr,
+}
+
+impl LayerExpr {
+    /// Parses an expression, using the given function to look up layers
+    /// that are referred to by name. Layer numbers above `highest_layer`
+    /// are rejected, since those belong to derived layers.
+    pub fn parse(
+        text: &str,
+        highest_layer: i16,
+        resolve: impl Fn(&str) -> Option<i16>,
+    ) -> Result<LayerExpr> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            highest_layer,
+            resolve: &resolve,
+        };
+        let expr = parser.parse_or()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(anyhow!("Unexpected '{}' in layer expression", token)),
+        }
+    }
+
+    /// Computes the polygons of this expression from the given layers. A
+    /// layer number only refers to a layer read from the file, never to a
+    /// derived layer.
+    /// Adds the indices of the layers that the expression reads.
+    pub fn collect_inputs(&self, inputs: &mut Vec<i16>) {
+        match self {
+            LayerExpr::Layer(index) | LayerExpr::Derived(index) => inputs.push(*index),
+            LayerExpr::And(a, b)
+            | LayerExpr::Or(a, b)
+            | LayerExpr::Xor(a, b)
+            | LayerExpr::Not(a, b) => {
+                a.collect_inputs(inputs);
+                b.collect_inputs(inputs);
+            }
+            LayerExpr::Grow(a, ..) | LayerExpr::Shrink(a, ..) => a.collect_inputs(inputs),
+        }
+    }
+
+    pub fn evaluate(&self, layers: &[Layer]) -> Vec<Polygon> {
+        match self {
+            LayerExpr::Layer(index) => layers
+                .get(*index as usize)
+                .filter(|layer| layer.name.is_none())
+                .map(|layer| layer.polygons.clone())
+                .unwrap_or_default(),
+            LayerExpr::Derived(index) => layers
+                .get(*index as usize)
+                .filter(|layer| layer.name.is_some())
+                .map(|layer| layer.polygons.clone())
+                .unwrap_or_default(),
+            LayerExpr::And(a, b) => intersect_polygons(&a.evaluate(layers), &b.evaluate(layers)),
+            LayerExpr::Or(a, b) => {
+                let mut polygons = a.evaluate(layers);
+                polygons.extend(b.evaluate(layers));
+                union_polygons(&polygons)
+            }
+            LayerExpr::Xor(a, b) => xor_polygons(&a.evaluate(layers), &b.evaluate(layers)),
+            LayerExpr::Not(a, b) => difference_polygons(&a.evaluate(layers), &b.evaluate(layers)),
+            LayerExpr::Grow(a, distance, corners) => {
+                size_polygons(&a.evaluate(layers), *distance, *corners)
+            }
+            LayerExpr::Shrink(a, distance, corners) => {
+                size_polygons(&a.evaluate(layers), -distance, *corners)
+            }
+        }
+    }
+}
+
+/// Splits a definition of the form `NAME = EXPRESSION`.
+pub fn split_derived_layer_definition(definition: &str) -> Result<(&str, &str)> {
+    let (name, expression) = definition
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Derived layer must be defined as NAME=EXPRESSION"))?;
+    Ok((name.trim(), expression.trim()))
+}
+
+impl Project {
+    pub fn derived_layers(&self) -> &[DerivedLayer] {
+        &self.derived_layers
+    }
+
+    /// Defines a new layer from an expression over existing layers, which
+    /// may include earlier derived layers by name. Returns the index of the
+    /// new layer, which follows all layers read from the file.
+    pub fn add_derived_layer(&mut self, name: &str, expression: &str) -> Result<i16> {
+        if !is_valid_name(name) {
+            return Err(anyhow!("Invalid derived layer name '{}'", name));
+        }
+        if self.derived_layers.iter().any(|layer| layer.name == name) {
+            return Err(anyhow!("Derived layer '{}' already exists", name));
+        }
+        let expr = LayerExpr::parse(expression, self.highest_layer(), |name| {
+            self.find_derived_layer(name)
+        })?;
+
+        self.flatten_layers();
+        self.derived_layers.push(DerivedLayer {
+            name: name.to_string(),
+            expression: expression.to_string(),
+            expr,
+        });
+        let ordinal = self.derived_layers.len() - 1;
+        let mut layer =
+            evaluate_derived_layer(self.layers(), &self.derived_layers[ordinal], ordinal);
+        if self.is_merged() {
+            layer.merge();
+        }
+        let index = layer.index();
+        self.push_layer(layer);
+
+        Ok(index)
+    }
+
+    /// Returns the layer index of the derived layer with the given name.
+    pub fn find_derived_layer(&self, name: &str) -> Option<i16> {
+        let ordinal = self.derived_layers.iter().position(|d| d.name == name)?;
+        Some(self.highest_layer() + 1 + ordinal as i16)
+    }
+}
+
+/// Appends all derived layers to a set of layers, in definition order so that
+/// each can refer to the ones before it. Layers whose index is not included
+/// are left empty.
+pub(crate) fn append_derived_layers(
+    layers: &mut Vec<Layer>,
+    derived_layers: &[DerivedLayer],
+    include: impl Fn(i16) -> bool,
+) {
+    for (ordinal, derived) in derived_layers.iter().enumerate() {
+        let index = layers.len() as i16;
+        let layer = match include(index) {
+            true => evaluate_derived_layer(layers, derived, ordinal),
+            false => {
+                let mut layer = Layer::new(index);
+                layer.name = Some(derived.name.clone());
+                layer
+            }
+        };
+        layers.push(layer);
+    }
+}
+
+fn evaluate_derived_layer(layers: &[Layer], derived: &DerivedLayer, ordinal: usize) -> Layer {
+    let mut layer = Layer::new(layers.len() as i16);
+    layer.name = Some(derived.name.clone());
+    layer.polygons = derived.expr.evaluate(layers);
+    layer.update_bounds();
+
+    // Spread hues using the golden ratio so that derived layers stand out
+    // from each other regardless of how many there are.
+    let hue = (0.1 + ordinal as f32 * 0.618) % 1.0;
+    let (r, g, b) = hsv_to_rgb(hue, 1.0, 1.0);
+    layer.color = Vector4::new(r, g, b, 0.6);
+    layer
+}
+
+fn is_valid_name(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && Keyword::from_str(name).is_none()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Keyword {
+    And,
+    Or,
+    Xor,
+    Not,
+    Grow,
+    Shrink,
+    Square,
+    Round,
+    Octagonal,
+}
+
+impl Keyword {
+    fn from_str(s: &str) -> Option<Keyword> {
+        match s.to_ascii_uppercase().as_str() {
+            "AND" => Some(Keyword::And),
+            "OR" => Some(Keyword::Or),
+            "XOR" => Some(Keyword::Xor),
+            "NOT" => Some(Keyword::Not),
+            "GROW" => Some(Keyword::Grow),
+            "SHRINK" => Some(Keyword::Shrink),
+            "SQUARE" => Some(Keyword::Square),
+            "ROUND" => Some(Keyword::Round),
+            "OCTAGONAL" => Some(Keyword::Octagonal),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Keyword(Keyword),
+    Open,
+    Close,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "{}", value),
+            Token::Name(name) => write!(f, "{}", name),
+            Token::Keyword(keyword) => write!(f, "{:?}", keyword),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' {
+            chars.next();
+            tokens.push(Token::Open);
+        } else if c == ')' {
+            chars.next();
+            tokens.push(Token::Close);
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+                number.push(c);
+                chars.next();
+            }
+            let value = number
+                .parse()
+                .map_err(|_| anyhow!("Invalid number '{}' in layer expression", number))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+                word.push(c);
+                chars.next();
+            }
+            match Keyword::from_str(&word) {
+                Some(keyword) => tokens.push(Token::Keyword(keyword)),
+                None => tokens.push(Token::Name(word)),
+            }
+        } else {
+            return Err(anyhow!("Unexpected character '{}' in layer expression", c));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    highest_layer: i16,
+    resolve: &'a dyn Fn(&str) -> Option<i16>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn next_keyword(&mut self, keywords: &[Keyword]) -> Option<Keyword> {
+        match self.peek() {
+            Some(Token::Keyword(keyword)) if keywords.contains(keyword) => {
+                let keyword = *keyword;
+                self.pos += 1;
+                Some(keyword)
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<LayerExpr> {
+        let mut expr = self.parse_and()?;
+        while let Some(keyword) = self.next_keyword(&[Keyword::Or, Keyword::Xor]) {
+            let rhs = Box::new(self.parse_and()?);
+            expr = match keyword {
+                Keyword::Or => LayerExpr::Or(Box::new(expr), rhs),
+                _ => LayerExpr::Xor(Box::new(expr), rhs),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<LayerExpr> {
+        let mut expr = self.parse_sizing()?;
+        while let Some(keyword) = self.next_keyword(&[Keyword::And, Keyword::Not]) {
+            let rhs = Box::new(self.parse_sizing()?);
+            expr = match keyword {
+                Keyword::And => LayerExpr::And(Box::new(expr), rhs),
+                _ => LayerExpr::Not(Box::new(expr), rhs),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn parse_sizing(&mut self) -> Result<LayerExpr> {
+        let mut expr = self.parse_primary()?;
+        while let Some(keyword) = self.next_keyword(&[Keyword::Grow, Keyword::Shrink]) {
+            let Some(Token::Number(distance)) = self.next() else {
+                return Err(anyhow!("Expected a distance after {:?}", keyword));
+            };
+            let corners =
+                match self.next_keyword(&[Keyword::Square, Keyword::Round, Keyword::Octagonal]) {
+                    Some(Keyword::Round) => CornerStyle::Round,
+                    Some(Keyword::Octagonal) => CornerStyle::Octagonal,
+                    _ => CornerStyle::Square,
+                };
+            expr = match keyword {
+                Keyword::Grow => LayerExpr::Grow(Box::new(expr), distance, corners),
+                _ => LayerExpr::Shrink(Box::new(expr), distance, corners),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn parse_primary(&mut self) -> Result<LayerExpr> {
+        match self.next() {
+            Some(Token::Number(value)) => {
+                if value.fract() != 0.0 || value > i16::MAX as f64 {
+                    return Err(anyhow!("Invalid layer number {}", value));
+                }
+                if value > self.highest_layer as f64 {
+                    return Err(anyhow!(
+                        "Layer {} is not in the file, derived layers are referred to by name",
+                        value
+                    ));
+                }
+                Ok(LayerExpr::Layer(value as i16))
+            }
+            Some(Token::Name(name)) => (self.resolve)(&name)
+                .map(LayerExpr::Derived)
+                .ok_or_else(|| anyhow!("Unknown layer '{}'", name)),
+            Some(Token::Open) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err(anyhow!("Missing ')' in layer expression")),
+                }
+            }
+            Some(token) => Err(anyhow!("Unexpected '{}' in layer expression", token)),
+            None => Err(anyhow!("Unexpected end of layer expression")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_respects_precedence() {
+        let expr = LayerExpr::parse("1 OR 2 AND gate GROW 5", 6, |name| {
+            (name == "gate").then_some(7)
+        })
+        .unwrap();
+        let grown = LayerExpr::Grow(Box::new(LayerExpr::Derived(7)), 5.0, CornerStyle::Square);
+        let and = LayerExpr::And(Box::new(LayerExpr::Layer(2)), Box::new(grown));
+        assert_eq!(
+            expr,
+            LayerExpr::Or(Box::new(LayerExpr::Layer(1)), Box::new(and))
+        );
+    }
+
+    #[test]
+    fn test_parse_reports_errors() {
+        let resolve = |_: &str| None;
+        assert!(LayerExpr::parse("1 AND", 6, resolve).is_err());
+        assert!(LayerExpr::parse("(1 OR 2", 6, resolve).is_err());
+        assert!(LayerExpr::parse("metal1", 6, resolve).is_err());
+        assert!(LayerExpr::parse("1 GROW", 6, resolve).is_err());
+        assert!(LayerExpr::parse("1 GROW ROUND", 6, resolve).is_err());
+        assert!(LayerExpr::parse("1 OR 7", 6, resolve).is_err());
+    }
+
+    #[test]
+    fn test_parse_corner_styles() {
+        let expr = LayerExpr::parse("1 GROW 5 round SHRINK 2", 6, |_| None).unwrap();
+        let grown = LayerExpr::Grow(Box::new(LayerExpr::Layer(1)), 5.0, CornerStyle::Round);
+        assert_eq!(
+            expr,
+            LayerExpr::Shrink(Box::new(grown), 2.0, CornerStyle::Square)
+        );
+    }
+}
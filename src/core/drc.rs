@@ -0,0 +1,516 @@
+use crate::core::closest_point_on_line;
+use crate::core::difference_polygons;
+use crate::core::intersect_polygons;
+use crate::core::offset_polygons;
+use crate::core::rule_lines;
+use crate::core::union_polygons;
+use crate::graphics::BoundingBox;
+use crate::Project;
+use anyhow::Result;
+use geo::Area;
+use geo::BoundingRect;
+use geo::Coord;
+use geo::Line;
+use geo::Winding;
+use rstar::primitives::GeomWithData;
+use rstar::primitives::Rectangle;
+use rstar::RTree;
+use rstar::RTreeObject;
+use rstar::AABB;
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+type Polygon = geo::Polygon<f64>;
+
+/// Regions smaller than this, in square database units, are considered to be
+/// numerical noise from polygon operations rather than violations.
+const MIN_REGION_AREA: f64 = 1.0;
+
+/// A single design rule, with distances and areas in database units.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrcRule {
+    /// Every shape on the layer must be at least this wide.
+    Width { layer: i16, min: f64 },
+    /// Shapes on the layer must be at least this far apart, which includes
+    /// notches between two parts of the same shape.
+    Spacing { layer: i16, min: f64 },
+    /// Every shape on the layer must cover at least this area.
+    Area { layer: i16, min: f64 },
+    /// Shapes on the inner layer must be covered by the outer layer with at
+    /// least this margin on all sides.
+    Enclosure { outer: i16, inner: i16, min: f64 },
+    /// Where the layer crosses the other layer, it must extend past it by at
+    /// least this distance, e.g. poly end caps past diffusion.
+    Extension { layer: i16, past: i16, min: f64 },
+}
+
+impl Display for DrcRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DrcRule::Width { layer, min } => write!(f, "width {} {}", layer, min),
+            DrcRule::Spacing { layer, min } => write!(f, "spacing {} {}", layer, min),
+            DrcRule::Area { layer, min } => write!(f, "area {} {}", layer, min),
+            DrcRule::Enclosure { outer, inner, min } => {
+                write!(f, "enclosure {} {} {}", outer, inner, min)
+            }
+            DrcRule::Extension { layer, past, min } => {
+                write!(f, "extension {} {} {}", layer, past, min)
+            }
+        }
+    }
+}
+
+/// A location where a rule is not met.
+#[derive(Debug, Clone)]
+pub struct DrcViolation {
+    /// Index of the rule in [DrcReport::rules].
+    pub rule: usize,
+    pub message: String,
+    pub bounds: BoundingBox,
+    /// Area that is in violation, for drawing markers.
+    pub region: Vec<Polygon>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DrcReport {
+    pub rules: Vec<DrcRule>,
+    pub violations: Vec<DrcViolation>,
+}
+
+impl DrcReport {
+    /// Number of violations of each rule, in rule order.
+    pub fn counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.rules.len()];
+        for violation in &self.violations {
+            counts[violation.rule] += 1;
+        }
+        counts
+    }
+}
+
+/// Parses a rule file with one rule per line:
+///
+/// ```text
+/// # Comments start with a hash.
+/// width 3 140
+/// spacing 3 140
+/// area 3 20000
+/// enclosure 3 4 50    # layer 3 encloses layer 4 by 50
+/// extension 5 1 130   # layer 5 extends past layer 1 by 130
+/// ```
+///
+/// See [rule_lines] for comments and how layers are given.
+pub fn parse_drc_rules(text: &str, resolve: impl Fn(&str) -> Option<i16>) -> Result<Vec<DrcRule>> {
+    let mut rules = Vec::new();
+    for line in rule_lines(text) {
+        let layer = |field: &str| line.layer(field, &resolve);
+        let value = |field: &str| -> Result<f64> {
+            field
+                .parse::<f64>()
+                .ok()
+                .filter(|value| *value > 0.0)
+                .ok_or_else(|| line.error(&format!("invalid value '{}'", field)))
+        };
+
+        let rule = match line.fields.as_slice() {
+            ["width", l, min] => DrcRule::Width {
+                layer: layer(l)?,
+                min: value(min)?,
+            },
+            ["spacing", l, min] => DrcRule::Spacing {
+                layer: layer(l)?,
+                min: value(min)?,
+            },
+            ["area", l, min] => DrcRule::Area {
+                layer: layer(l)?,
+                min: value(min)?,
+            },
+            ["enclosure", outer, inner, min] => DrcRule::Enclosure {
+                outer: layer(outer)?,
+                inner: layer(inner)?,
+                min: value(min)?,
+            },
+            ["extension", l, past, min] => DrcRule::Extension {
+                layer: layer(l)?,
+                past: layer(past)?,
+                min: value(min)?,
+            },
+            [kind, ..] => {
+                return Err(line.error(&format!(
+                    "expected 'width', 'spacing' or 'area' with a layer and a value, \
+                     or 'enclosure' or 'extension' with two layers and a value, \
+                     found '{}'",
+                    kind
+                )))
+            }
+            [] => unreachable!(),
+        };
+        rules.push(rule);
+    }
+    Ok(rules)
+}
+
+impl Project {
+    /// Parses a rule file, resolving derived layers of this project by name.
+    /// See [parse_drc_rules] for the format.
+    pub fn parse_drc_rules(&self, text: &str) -> Result<Vec<DrcRule>> {
+        parse_drc_rules(text, |name| self.find_derived_layer(name))
+    }
+
+    /// Checks the flattened geometry against the given rules. Shapes on each
+    /// layer are merged first, so rules apply to the combined shapes rather
+    /// than to individual polygons.
+    pub fn check_drc(&self, rules: &[DrcRule]) -> DrcReport {
+        let mut merged_layers: Vec<Option<Vec<Polygon>>> = vec![None; self.layers().len()];
+        let mut merged = |layer: i16| -> Vec<Polygon> {
+            let Some(source) = self.layers().get(layer as usize) else {
+                return Vec::new();
+            };
+            merged_layers[layer as usize]
+                .get_or_insert_with(|| union_polygons(&source.polygons))
+                .clone()
+        };
+
+        let mut report = DrcReport {
+            rules: rules.to_vec(),
+            violations: Vec::new(),
+        };
+        for (index, rule) in rules.iter().enumerate() {
+            let mut add = |message: String, region: Vec<Polygon>| {
+                let mut bounds = BoundingBox::new();
+                for polygon in &region {
+                    if let Some(rect) = polygon.bounding_rect() {
+                        bounds.encompass(&BoundingBox::from(rect));
+                    }
+                }
+                report.violations.push(DrcViolation {
+                    rule: index,
+                    message,
+                    bounds,
+                    region,
+                });
+            };
+
+            match *rule {
+                DrcRule::Width { layer, min } => {
+                    for region in narrow_regions(&merged(layer), min) {
+                        add(
+                            format!("Width below {} on layer {}", min, layer),
+                            vec![region],
+                        );
+                    }
+                }
+                DrcRule::Spacing { layer, min } => {
+                    for (distance, region) in spacing_violations(&merged(layer), min) {
+                        let message =
+                            format!("Spacing {:.1} below {} on layer {}", distance, min, layer);
+                        add(message, vec![region]);
+                    }
+                }
+                DrcRule::Area { layer, min } => {
+                    for polygon in merged(layer) {
+                        let area = polygon.unsigned_area();
+                        if area < min {
+                            let message =
+                                format!("Area {:.1} below {} on layer {}", area, min, layer);
+                            add(message, vec![polygon]);
+                        }
+                    }
+                }
+                DrcRule::Enclosure { outer, inner, min } => {
+                    let grown = offset_polygons(&merged(inner), min);
+                    for region in significant(difference_polygons(&grown, &merged(outer))) {
+                        let message = format!(
+                            "Layer {} not enclosed by layer {} with margin {}",
+                            inner, outer, min
+                        );
+                        add(message, vec![region]);
+                    }
+                }
+                DrcRule::Extension { layer, past, min } => {
+                    let other = merged(past);
+                    let outside = difference_polygons(&merged(layer), &other);
+                    // Only consider the parts that touch the other layer.
+                    let touching_zone = offset_polygons(&other, 1.0);
+                    let extensions: Vec<Polygon> = outside
+                        .into_iter()
+                        .filter(|piece| {
+                            !intersect_polygons(std::slice::from_ref(piece), &touching_zone)
+                                .is_empty()
+                        })
+                        .collect();
+                    for region in narrow_regions(&extensions, min) {
+                        let message = format!(
+                            "Layer {} extends past layer {} by less than {}",
+                            layer, past, min
+                        );
+                        add(message, vec![region]);
+                    }
+                }
+            }
+        }
+        report
+    }
+}
+
+/// Finds the parts of the polygons that are narrower than the given width,
+/// as the difference between the polygons and their morphological opening.
+fn narrow_regions(polygons: &[Polygon], min: f64) -> Vec<Polygon> {
+    let opened = offset_polygons(&offset_polygons(polygons, -min / 2.0), min / 2.0);
+    significant(difference_polygons(polygons, &opened))
+}
+
+fn significant(polygons: Vec<Polygon>) -> Vec<Polygon> {
+    polygons
+        .into_iter()
+        .filter(|polygon| polygon.unsigned_area() >= MIN_REGION_AREA)
+        .collect()
+}
+
+/// Edge of a merged polygon, as indexed by [spacing_violations].
+struct Edge {
+    line: Line<f64>,
+    polygon: usize,
+    /// Ring within the polygon, 0 for the exterior.
+    ring: usize,
+    /// Position within the ring, and the number of edges of the ring.
+    index: usize,
+    ring_len: usize,
+    /// Whether the inside of the polygon lies to the left of the edge.
+    inside_left: bool,
+}
+
+impl Edge {
+    fn is_adjacent(&self, other: &Edge) -> bool {
+        if self.polygon != other.polygon || self.ring != other.ring {
+            return false;
+        }
+        let next = |index: usize| (index + 1) % self.ring_len;
+        next(self.index) == other.index || next(other.index) == self.index
+    }
+
+    /// Unit normal pointing into the polygon, None for an empty edge.
+    fn inward(&self) -> Option<Coord<f64>> {
+        let delta = self.line.delta();
+        let length = delta.x.hypot(delta.y);
+        if length == 0.0 {
+            return None;
+        }
+        let left = Coord {
+            x: -delta.y / length,
+            y: delta.x / length,
+        };
+        Some(if self.inside_left { left } else { -left })
+    }
+
+    /// Whether this edge and the other one face each other across empty
+    /// space, with `a` and `b` the closest point on each. Their normals must
+    /// point against each other, which rules out two edges around a
+    /// chamfered corner.
+    fn faces(&self, other: &Edge, a: Coord<f64>, b: Coord<f64>) -> bool {
+        let (Some(inward), Some(other_inward)) = (self.inward(), other.inward()) else {
+            return false;
+        };
+        let dot = |u: Coord<f64>, v: Coord<f64>| u.x * v.x + u.y * v.y;
+        let epsilon = 1e-9 * (b - a).x.hypot((b - a).y);
+        dot(inward, other_inward) < -1e-9
+            && dot(b - a, inward) < -epsilon
+            && dot(a - b, other_inward) < -epsilon
+    }
+}
+
+/// Finds places where the polygons are closer than the given distance,
+/// either to each other or to themselves across a notch, returning the
+/// distance and a rectangle spanning the closest points.
+///
+/// Edges are indexed rather than polygons, so that long polygons are not
+/// compared edge by edge against everything nearby, and notches within a
+/// polygon are found the same way as gaps between polygons. The spatial
+/// indices of the project cannot be reused for this, since they hold the
+/// shapes of each cell before merging, while the rule applies to the merged
+/// outlines. Two edges only violate the rule if they face each other across
+/// empty space, which rules out edges around a corner and the two sides of a
+/// narrow shape, the latter being a width rule.
+fn spacing_violations(polygons: &[Polygon], min: f64) -> Vec<(f64, Polygon)> {
+    let mut edges = Vec::new();
+    for (polygon_index, polygon) in polygons.iter().enumerate() {
+        let rings = std::iter::once(polygon.exterior()).chain(polygon.interiors());
+        for (ring, line_string) in rings.enumerate() {
+            let lines: Vec<Line<f64>> = line_string.lines().collect();
+            let inside_left = (ring == 0) == line_string.is_ccw();
+            for (index, &line) in lines.iter().enumerate() {
+                edges.push(Edge {
+                    line,
+                    polygon: polygon_index,
+                    ring,
+                    index,
+                    ring_len: lines.len(),
+                    inside_left,
+                });
+            }
+        }
+    }
+    let items: Vec<GeomWithData<Rectangle<[f64; 2]>, usize>> = edges
+        .iter()
+        .enumerate()
+        .map(|(index, edge)| {
+            let (start, end) = (edge.line.start, edge.line.end);
+            let rectangle = Rectangle::from_corners([start.x, start.y], [end.x, end.y]);
+            GeomWithData::new(rectangle, index)
+        })
+        .collect();
+    let tree = RTree::bulk_load(items);
+
+    let mut violations = Vec::new();
+    let mut seen = HashSet::new();
+    for item in tree.iter() {
+        let index = item.data;
+        let edge = &edges[index];
+        let envelope = item.geom().envelope();
+        let [min_x, min_y] = envelope.lower();
+        let [max_x, max_y] = envelope.upper();
+        let search = AABB::from_corners([min_x - min, min_y - min], [max_x + min, max_y + min]);
+        for neighbor in tree.locate_in_envelope_intersecting(&search) {
+            // Visit each pair once.
+            if neighbor.data <= index {
+                continue;
+            }
+            let other = &edges[neighbor.data];
+            if edge.is_adjacent(other) {
+                continue;
+            }
+            let (distance, a, b) = closest_points(&edge.line, &other.line);
+            if distance >= min {
+                continue;
+            }
+            // Touching polygons always violate the rule, while the closest
+            // points of separate edges must face each other.
+            if distance > 0.0 && !edge.faces(other, a, b) {
+                continue;
+            }
+            // Edges meeting at the same closest points, such as around a
+            // corner, report a single violation.
+            let key = [a.x, a.y, b.x, b.y].map(f64::to_bits);
+            if !seen.insert(key) {
+                continue;
+            }
+            let rect = geo::Rect::new(a, b);
+            let pad = (min - rect.width().max(rect.height())).max(0.0) / 2.0;
+            let region = geo::Rect::new(
+                Coord {
+                    x: rect.min().x - pad,
+                    y: rect.min().y - pad,
+                },
+                Coord {
+                    x: rect.max().x + pad,
+                    y: rect.max().y + pad,
+                },
+            );
+            violations.push((distance, region.to_polygon()));
+        }
+    }
+    violations
+}
+
+/// Returns the distance between two non-crossing lines, along with the
+/// closest point on each.
+fn closest_points(a: &Line<f64>, b: &Line<f64>) -> (f64, Coord<f64>, Coord<f64>) {
+    let mut best = (f64::MAX, Coord::zero(), Coord::zero());
+    for (point, line, point_first) in [
+        (a.start, b, true),
+        (a.end, b, true),
+        (b.start, a, false),
+        (b.end, a, false),
+    ] {
+        let closest = closest_point_on_line(point, line);
+        let distance = (point - closest).x.hypot((point - closest).y);
+        if distance < best.0 {
+            best = if point_first {
+                (distance, point, closest)
+            } else {
+                (distance, closest, point)
+            };
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::LineString;
+
+    fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> Polygon {
+        Polygon::new(
+            LineString::from(vec![(x0, y0), (x1, y0), (x1, y1), (x0, y1)]),
+            vec![],
+        )
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let text = "# rules\nwidth 1 10\nenclosure 2 gate 5 # comment\n\n";
+        let rules = parse_drc_rules(text, |name| (name == "gate").then_some(9)).unwrap();
+        assert_eq!(
+            rules,
+            vec![
+                DrcRule::Width {
+                    layer: 1,
+                    min: 10.0
+                },
+                DrcRule::Enclosure {
+                    outer: 2,
+                    inner: 9,
+                    min: 5.0
+                }
+            ]
+        );
+        assert!(parse_drc_rules("width 1", |_| None).is_err());
+        assert!(parse_drc_rules("spacing 1 -5", |_| None).is_err());
+    }
+
+    #[test]
+    fn test_width_and_spacing() {
+        let polygons = vec![rect(0.0, 0.0, 100.0, 5.0), rect(0.0, 8.0, 100.0, 30.0)];
+        assert_eq!(narrow_regions(&polygons, 10.0).len(), 1);
+        let violations = spacing_violations(&polygons, 10.0);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].0, 3.0);
+
+        // A U shape with a notch 4 wide, whose arms are 5 wide.
+        let u_shape = Polygon::new(
+            LineString::from(vec![
+                (0.0, 0.0),
+                (14.0, 0.0),
+                (14.0, 20.0),
+                (9.0, 20.0),
+                (9.0, 5.0),
+                (5.0, 5.0),
+                (5.0, 20.0),
+                (0.0, 20.0),
+            ]),
+            vec![],
+        );
+        let violations = spacing_violations(std::slice::from_ref(&u_shape), 6.0);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].0, 4.0);
+        assert!(spacing_violations(&[u_shape], 4.0).is_empty());
+
+        // An L shape whose inner corner is cut at 45 degrees.
+        let l_shape = Polygon::new(
+            LineString::from(vec![
+                (0.0, 0.0),
+                (20.0, 0.0),
+                (20.0, 5.0),
+                (7.0, 5.0),
+                (5.0, 7.0),
+                (5.0, 20.0),
+                (0.0, 20.0),
+            ]),
+            vec![],
+        );
+        assert!(spacing_violations(&[l_shape], 6.0).is_empty());
+    }
+}
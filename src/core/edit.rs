@@ -0,0 +1,835 @@
+use crate::core::boundary_polygon;
+use crate::core::path_polygon;
+use crate::core::Cell;
+use crate::core::CellDefId;
+use crate::core::CellId;
+use crate::core::ElementMetadata;
+use crate::core::PathSpine;
+use crate::core::Shape;
+use crate::Project;
+use anyhow::anyhow;
+use anyhow::Result;
+use gds21::GdsBoundary;
+use gds21::GdsPath;
+use gds21::GdsPoint;
+use gds21::GdsStrans;
+use geo::Translate;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::{self};
+
+/// Change to a project requested by the user, see [Project::apply_edit].
+/// Shapes belong to a cell, so editing a shape affects every placement of
+/// the cell. Coordinates are in database units.
+#[derive(Debug, Clone)]
+pub enum Edit {
+    /// Moves an instance within its parent cell.
+    MoveInstance {
+        cell_id: CellId,
+        dx: i32,
+        dy: i32,
+    },
+    /// Rotates an instance counterclockwise about its origin by multiples of
+    /// 90 degrees.
+    RotateInstance {
+        cell_id: CellId,
+        quarter_turns: i32,
+    },
+    /// Mirrors an instance about the horizontal axis through its origin.
+    MirrorInstance {
+        cell_id: CellId,
+    },
+    DeleteInstance {
+        cell_id: CellId,
+    },
+    AddPolygon {
+        cell_def_id: CellDefId,
+        layer: i16,
+        datatype: i16,
+        xy: Vec<GdsPoint>,
+    },
+    AddPath {
+        cell_def_id: CellDefId,
+        layer: i16,
+        datatype: i16,
+        xy: Vec<GdsPoint>,
+        width: i32,
+    },
+    /// Deletes a shape, given by its index within the cell.
+    DeleteShape {
+        cell_def_id: CellDefId,
+        shape: usize,
+    },
+    MoveShape {
+        cell_def_id: CellDefId,
+        shape: usize,
+        dx: i32,
+        dy: i32,
+    },
+    SetShapeLayer {
+        cell_def_id: CellDefId,
+        shape: usize,
+        layer: i16,
+    },
+}
+
+impl Display for Edit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Edit::MoveInstance { .. } => "move instance",
+            Edit::RotateInstance { .. } => "rotate instance",
+            Edit::MirrorInstance { .. } => "mirror instance",
+            Edit::DeleteInstance { .. } => "delete instance",
+            Edit::AddPolygon { .. } => "add polygon",
+            Edit::AddPath { .. } => "add path",
+            Edit::DeleteShape { .. } => "delete shape",
+            Edit::MoveShape { .. } => "move shape",
+            Edit::SetShapeLayer { .. } => "change layer",
+        };
+        f.write_str(text)
+    }
+}
+
+/// What an edit changed, so that views can update only what is affected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditChange {
+    /// The shapes of a cell changed, wherever it is placed.
+    Shapes(CellDefId),
+    /// An instance was moved, removed or restored, which moves or removes
+    /// every placement below it.
+    Placements,
+}
+
+/// Edit that was applied, undone or redone.
+#[derive(Debug, Clone)]
+pub struct EditOutcome {
+    pub description: String,
+    pub change: EditChange,
+}
+
+/// Primitive change that [Project::execute] can apply, returning the command
+/// that reverts it.
+#[derive(Debug, Clone)]
+pub(crate) enum Command {
+    SetPlacement {
+        cell_id: CellId,
+        xy: GdsPoint,
+        strans: Option<GdsStrans>,
+    },
+    RemoveInstance {
+        cell_id: CellId,
+    },
+    InsertInstance {
+        cell_id: CellId,
+        parent: CellDefId,
+        /// Positions in the cell elements of the parent and in the instances
+        /// of the placed cell.
+        positions: (usize, usize),
+    },
+    InsertShape {
+        cell_def_id: CellDefId,
+        index: usize,
+        shape: Box<Shape>,
+    },
+    RemoveShape {
+        cell_def_id: CellDefId,
+        index: usize,
+    },
+    TranslateShape {
+        cell_def_id: CellDefId,
+        index: usize,
+        dx: i32,
+        dy: i32,
+    },
+    SetShapeLayer {
+        cell_def_id: CellDefId,
+        index: usize,
+        layer: i16,
+    },
+}
+
+impl Command {
+    fn change(&self) -> EditChange {
+        match self {
+            Command::SetPlacement { .. }
+            | Command::RemoveInstance { .. }
+            | Command::InsertInstance { .. } => EditChange::Placements,
+            Command::InsertShape { cell_def_id, .. }
+            | Command::RemoveShape { cell_def_id, .. }
+            | Command::TranslateShape { cell_def_id, .. }
+            | Command::SetShapeLayer { cell_def_id, .. } => EditChange::Shapes(*cell_def_id),
+        }
+    }
+}
+
+/// Entry of the undo or redo stack: the command that reverts an edit, along
+/// with its description.
+#[derive(Debug, Clone)]
+pub(crate) struct HistoryEntry {
+    description: String,
+    command: Command,
+}
+
+/// Undo and redo stacks of a project.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct EditHistory {
+    undo: Vec<HistoryEntry>,
+    redo: Vec<HistoryEntry>,
+}
+
+impl Project {
+    /// Applies an edit and records it for [Self::undo], clearing the redo
+    /// stack. The layers are updated right away, keeping their style.
+    pub fn apply_edit(&mut self, edit: Edit) -> Result<EditOutcome> {
+        let description = edit.to_string();
+        let command = self.edit_command(edit)?;
+        let change = command.change();
+        let inverse = self.execute(command)?;
+        self.history.undo.push(HistoryEntry {
+            description: description.clone(),
+            command: inverse,
+        });
+        self.history.redo.clear();
+        Ok(EditOutcome {
+            description,
+            change,
+        })
+    }
+
+    /// Reverts the most recent edit. Returns None if there is nothing to
+    /// undo.
+    pub fn undo(&mut self) -> Result<Option<EditOutcome>> {
+        let Some(entry) = self.history.undo.pop() else {
+            return Ok(None);
+        };
+        let change = entry.command.change();
+        let inverse = self.execute(entry.command)?;
+        self.history.redo.push(HistoryEntry {
+            description: entry.description.clone(),
+            command: inverse,
+        });
+        Ok(Some(EditOutcome {
+            description: entry.description,
+            change,
+        }))
+    }
+
+    /// Applies the most recently undone edit again. Returns None if there is
+    /// nothing to redo.
+    pub fn redo(&mut self) -> Result<Option<EditOutcome>> {
+        let Some(entry) = self.history.redo.pop() else {
+            return Ok(None);
+        };
+        let change = entry.command.change();
+        let inverse = self.execute(entry.command)?;
+        self.history.undo.push(HistoryEntry {
+            description: entry.description.clone(),
+            command: inverse,
+        });
+        Ok(Some(EditOutcome {
+            description: entry.description,
+            change,
+        }))
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.history.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.history.redo.is_empty()
+    }
+
+    /// Number of shapes in a cell, which is the range of shape indices for
+    /// [Edit].
+    pub fn shape_count(&self, cell_def_id: CellDefId) -> usize {
+        self.cell_def(cell_def_id).shapes.len()
+    }
+
+    /// Checks an edit against the project and turns it into a command.
+    fn edit_command(&self, edit: Edit) -> Result<Command> {
+        let command = match edit {
+            Edit::MoveInstance { cell_id, dx, dy } => {
+                let cell = self.find_instance(cell_id)?;
+                Command::SetPlacement {
+                    cell_id,
+                    xy: GdsPoint::new(cell.xy.x + dx, cell.xy.y + dy),
+                    strans: cell.local_transform.clone(),
+                }
+            }
+            Edit::RotateInstance {
+                cell_id,
+                quarter_turns,
+            } => {
+                let cell = self.find_instance(cell_id)?;
+                Command::SetPlacement {
+                    cell_id,
+                    xy: cell.xy.clone(),
+                    strans: rotated_strans(&cell.local_transform, quarter_turns),
+                }
+            }
+            Edit::MirrorInstance { cell_id } => {
+                let cell = self.find_instance(cell_id)?;
+                Command::SetPlacement {
+                    cell_id,
+                    xy: cell.xy.clone(),
+                    strans: mirrored_strans(&cell.local_transform),
+                }
+            }
+            Edit::DeleteInstance { cell_id } => {
+                self.find_instance(cell_id)?;
+                Command::RemoveInstance { cell_id }
+            }
+            Edit::AddPolygon {
+                cell_def_id,
+                layer,
+                datatype,
+                xy,
+            } => {
+                self.check_cell_def(cell_def_id)?;
+                self.check_layer(layer)?;
+                let boundary = GdsBoundary {
+                    layer,
+                    datatype,
+                    xy,
+                    ..Default::default()
+                };
+                let polygon = boundary_polygon(&boundary)
+                    .ok_or_else(|| anyhow!("A polygon needs at least 3 points"))?;
+                Command::InsertShape {
+                    cell_def_id,
+                    index: self.shape_count(cell_def_id),
+                    shape: Box::new(Shape {
+                        layer,
+                        datatype,
+                        polygon,
+                        path: None,
+                        metadata: ElementMetadata::default(),
+                    }),
+                }
+            }
+            Edit::AddPath {
+                cell_def_id,
+                layer,
+                datatype,
+                xy,
+                width,
+            } => {
+                self.check_cell_def(cell_def_id)?;
+                self.check_layer(layer)?;
+                let path = GdsPath {
+                    layer,
+                    datatype,
+                    xy,
+                    width: Some(width),
+                    ..Default::default()
+                };
+                let polygon =
+                    path_polygon(&path).ok_or_else(|| anyhow!("A path needs at least 2 points"))?;
+                Command::InsertShape {
+                    cell_def_id,
+                    index: self.shape_count(cell_def_id),
+                    shape: Box::new(Shape {
+                        layer,
+                        datatype,
+                        polygon,
+                        path: Some(PathSpine {
+                            xy: path.xy,
+                            width: path.width,
+                            path_type: None,
+                            begin_extn: None,
+                            end_extn: None,
+                        }),
+                        metadata: ElementMetadata::default(),
+                    }),
+                }
+            }
+            Edit::DeleteShape { cell_def_id, shape } => {
+                self.check_shape(cell_def_id, shape)?;
+                Command::RemoveShape {
+                    cell_def_id,
+                    index: shape,
+                }
+            }
+            Edit::MoveShape {
+                cell_def_id,
+                shape,
+                dx,
+                dy,
+            } => {
+                self.check_shape(cell_def_id, shape)?;
+                Command::TranslateShape {
+                    cell_def_id,
+                    index: shape,
+                    dx,
+                    dy,
+                }
+            }
+            Edit::SetShapeLayer {
+                cell_def_id,
+                shape,
+                layer,
+            } => {
+                self.check_shape(cell_def_id, shape)?;
+                self.check_layer(layer)?;
+                Command::SetShapeLayer {
+                    cell_def_id,
+                    index: shape,
+                    layer,
+                }
+            }
+        };
+        Ok(command)
+    }
+
+    /// Applies a command, updating the cached bounds and the layers, and
+    /// returns the command that reverts it.
+    pub(crate) fn execute(&mut self, command: Command) -> Result<Command> {
+        let edited = match &command {
+            Command::SetPlacement { cell_id, .. } | Command::RemoveInstance { cell_id } => {
+                self.find_instance(*cell_id)?.parent
+            }
+            Command::InsertInstance { parent, .. } => *parent,
+            Command::InsertShape { cell_def_id, .. }
+            | Command::RemoveShape { cell_def_id, .. }
+            | Command::TranslateShape { cell_def_id, .. }
+            | Command::SetShapeLayer { cell_def_id, .. } => *cell_def_id,
+        };
+        let shape_bounds = self.cell_def(edited).shape_bounds;
+        let had_shapes = !self.cell_def(edited).shapes.is_empty();
+
+        let inverse = match command {
+            Command::SetPlacement {
+                cell_id,
+                xy,
+                strans,
+            } => {
+                let cell = self.cell_mut(cell_id);
+                let xy = std::mem::replace(&mut cell.xy, xy);
+                let strans = std::mem::replace(&mut cell.local_transform, strans);
+                Command::SetPlacement {
+                    cell_id,
+                    xy,
+                    strans,
+                }
+            }
+            Command::RemoveInstance { cell_id } => {
+                let child = self.cell(cell_id).cell_def_id;
+                let parent = self.cell(cell_id).parent;
+                let parent_elements = &mut self.cell_def_mut(parent).cell_elements;
+                let element_position = parent_elements.iter().position(|&id| id == cell_id);
+                let element_position = element_position.unwrap();
+                parent_elements.remove(element_position);
+                let instances = &mut self.cell_def_mut(child).instances;
+                let instance_position = instances.iter().position(|&id| id == cell_id).unwrap();
+                instances.remove(instance_position);
+                Command::InsertInstance {
+                    cell_id,
+                    parent,
+                    positions: (element_position, instance_position),
+                }
+            }
+            Command::InsertInstance {
+                cell_id,
+                parent,
+                positions: (element_position, instance_position),
+            } => {
+                let child = self.cell(cell_id).cell_def_id;
+                self.cell_def_mut(parent)
+                    .cell_elements
+                    .insert(element_position, cell_id);
+                self.cell_def_mut(child)
+                    .instances
+                    .insert(instance_position, cell_id);
+                Command::RemoveInstance { cell_id }
+            }
+            Command::InsertShape {
+                cell_def_id,
+                index,
+                shape,
+            } => {
+                self.cell_def_mut(cell_def_id).shapes.insert(index, *shape);
+                Command::RemoveShape { cell_def_id, index }
+            }
+            Command::RemoveShape { cell_def_id, index } => {
+                let shape = self.cell_def_mut(cell_def_id).shapes.remove(index);
+                Command::InsertShape {
+                    cell_def_id,
+                    index,
+                    shape: Box::new(shape),
+                }
+            }
+            Command::TranslateShape {
+                cell_def_id,
+                index,
+                dx,
+                dy,
+            } => {
+                let shape = &mut self.cell_def_mut(cell_def_id).shapes[index];
+                shape.polygon.translate_mut(dx as f64, dy as f64);
+                if let Some(path) = &mut shape.path {
+                    for point in &mut path.xy {
+                        point.x += dx;
+                        point.y += dy;
+                    }
+                }
+                Command::TranslateShape {
+                    cell_def_id,
+                    index,
+                    dx: -dx,
+                    dy: -dy,
+                }
+            }
+            Command::SetShapeLayer {
+                cell_def_id,
+                index,
+                layer,
+            } => {
+                let shape = &mut self.cell_def_mut(cell_def_id).shapes[index];
+                let layer = std::mem::replace(&mut shape.layer, layer);
+                Command::SetShapeLayer {
+                    cell_def_id,
+                    index,
+                    layer,
+                }
+            }
+        };
+
+        if let EditChange::Shapes(cell_def_id) = inverse.change() {
+            self.cell_def_mut(cell_def_id).update_shape_tree();
+        }
+
+        // Only the edited cell and the cells placing it change, so their
+        // bounds and the affected placements are updated in place. The layers
+        // are rebuilt when instances are removed or restored, or a cell gains
+        // its first shape or loses its last, since that changes which
+        // instances are drawn. Flattened layers are rebuilt as well, as the
+        // analyses reading them run over the whole layout again anyway.
+        let stale = self.update_cell_def_bounds_above(edited);
+        let has_shapes = !self.cell_def(edited).shapes.is_empty();
+        match &inverse {
+            _ if self.is_flattened() || has_shapes != had_shapes => self.refresh_layers(),
+            Command::SetPlacement { cell_id, .. } => self.update_moved_instance(*cell_id, &stale),
+            Command::RemoveInstance { .. } | Command::InsertInstance { .. } => {
+                self.refresh_layers()
+            }
+            _ => self.update_reshaped_cell(edited, shape_bounds, &stale),
+        }
+        Ok(inverse)
+    }
+
+    fn find_instance(&self, cell_id: CellId) -> Result<&Cell> {
+        self.find_cell(cell_id)
+            .ok_or_else(|| anyhow!("Top cells cannot be edited as instances"))
+    }
+
+    fn check_cell_def(&self, cell_def_id: CellDefId) -> Result<()> {
+        if !self.has_cell_def(cell_def_id) {
+            return Err(anyhow!("Cell {} does not exist", cell_def_id.0));
+        }
+        Ok(())
+    }
+
+    fn check_shape(&self, cell_def_id: CellDefId, shape: usize) -> Result<()> {
+        self.check_cell_def(cell_def_id)?;
+        let count = self.shape_count(cell_def_id);
+        if shape >= count {
+            return Err(anyhow!(
+                "Shape {} does not exist, the cell has {} shapes",
+                shape,
+                count
+            ));
+        }
+        Ok(())
+    }
+
+    /// Shapes can only be placed on layers read from the file, since derived
+    /// layers are numbered after them.
+    fn check_layer(&self, layer: i16) -> Result<()> {
+        if !(0..=self.highest_layer()).contains(&layer) {
+            return Err(anyhow!(
+                "Layer {} is not between 0 and {}",
+                layer,
+                self.highest_layer()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Adds quarter turns to the rotation of a GDSII transformation.
+fn rotated_strans(strans: &Option<GdsStrans>, quarter_turns: i32) -> Option<GdsStrans> {
+    let mut strans = strans.clone().unwrap_or_default();
+    let angle = strans.angle.unwrap_or(0.0) + 90.0 * quarter_turns as f64;
+    strans.angle = Some(angle.rem_euclid(360.0));
+    Some(strans)
+}
+
+/// Mirrors a GDSII transformation about the x axis. Reflection is applied
+/// before rotation, so the rotation is reversed.
+fn mirrored_strans(strans: &Option<GdsStrans>) -> Option<GdsStrans> {
+    let mut strans = strans.clone().unwrap_or_default();
+    strans.reflected = !strans.reflected;
+    strans.angle = Some((-strans.angle.unwrap_or(0.0)).rem_euclid(360.0));
+    Some(strans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::test_layouts::*;
+    use geo::Coord;
+
+    fn cell(strans: Option<GdsStrans>) -> Cell {
+        Cell {
+            cell_def_id: CellDefId(0),
+            parent: CellDefId(1),
+            xy: GdsPoint::new(10, 20),
+            local_transform: strans,
+            visible: true,
+            array: None,
+            metadata: ElementMetadata::default(),
+        }
+    }
+
+    fn apply(strans: &Option<GdsStrans>, point: Coord<f64>) -> Coord<f64> {
+        let mut result = cell(strans.clone()).placement().apply(point);
+        result.x = result.x.round();
+        result.y = result.y.round();
+        result
+    }
+
+    #[test]
+    fn test_rotation_turns_about_origin() {
+        let strans = rotated_strans(&None, 1);
+        assert_eq!(
+            apply(&strans, Coord { x: 1.0, y: 0.0 }),
+            Coord { x: 10.0, y: 21.0 }
+        );
+        let strans = rotated_strans(&strans, 3);
+        assert_eq!(strans.unwrap().angle, Some(0.0));
+    }
+
+    #[test]
+    fn test_mirror_flips_in_parent_coordinates() {
+        let rotated = rotated_strans(&None, 1);
+        let mirrored = mirrored_strans(&rotated);
+        // (1, 0) is placed at (0, 1) relative to the origin, then flipped.
+        assert_eq!(
+            apply(&mirrored, Coord { x: 1.0, y: 0.0 }),
+            Coord { x: 10.0, y: 19.0 }
+        );
+        let restored = mirrored_strans(&mirrored);
+        assert_eq!(
+            apply(&restored, Coord { x: 1.0, y: 0.0 }),
+            Coord { x: 10.0, y: 21.0 }
+        );
+    }
+
+    /// Layer and outline of a shape.
+    type ShapeOutline = (i16, Vec<(i64, i64)>);
+
+    /// State that edits change, rounded so that transforms composed in a
+    /// different order compare equal.
+    #[derive(Debug, PartialEq)]
+    struct Snapshot {
+        shapes: Vec<Vec<ShapeOutline>>,
+        bounds: Vec<Option<[i64; 4]>>,
+        placements: Vec<(CellId, CellDefId, [i64; 6], usize)>,
+        placement_tree: Vec<([i64; 4], usize)>,
+    }
+
+    fn round(value: f64) -> i64 {
+        (value * 1000.0).round() as i64
+    }
+
+    fn snapshot(project: &Project) -> Snapshot {
+        let cell_defs: Vec<_> = project.cell_def_ids().collect();
+        let mut placement_tree: Vec<_> = project
+            .placement_tree_items()
+            .into_iter()
+            .map(|(lower, upper, index)| {
+                let corners = [lower[0], lower[1], upper[0], upper[1]];
+                (corners.map(round), index)
+            })
+            .collect();
+        placement_tree.sort();
+        Snapshot {
+            shapes: cell_defs
+                .iter()
+                .map(|&cell_def_id| {
+                    let shapes = &project.cell_def(cell_def_id).shapes;
+                    shapes
+                        .iter()
+                        .map(|shape| {
+                            let coords = shape.polygon.exterior().coords();
+                            let coords = coords.map(|c| (round(c.x), round(c.y))).collect();
+                            (shape.layer, coords)
+                        })
+                        .collect()
+                })
+                .collect(),
+            bounds: cell_defs
+                .iter()
+                .map(|&cell_def_id| {
+                    let bounds = project.cell_def(cell_def_id).bounds?;
+                    Some([bounds.min_x, bounds.min_y, bounds.max_x, bounds.max_y].map(round))
+                })
+                .collect(),
+            placements: project
+                .placements()
+                .iter()
+                .map(|placement| {
+                    let t = &placement.transform;
+                    let transform = [t.a(), t.b(), t.xoff(), t.d(), t.e(), t.yoff()].map(round);
+                    (
+                        placement.cell_id,
+                        placement.cell_def_id,
+                        transform,
+                        placement.node,
+                    )
+                })
+                .collect(),
+            placement_tree,
+        }
+    }
+
+    /// Checks that the state after an edit matches rebuilding everything.
+    fn assert_matches_full_update(project: &mut Project) {
+        let updated = snapshot(project);
+        project.update_cell_def_bounds();
+        project.update_layers();
+        assert_eq!(updated, snapshot(project));
+    }
+
+    #[test]
+    fn test_edits_undo_and_redo() {
+        let mut project = project(vec![
+            gds_struct("leaf", vec![rect(1, (0, 0), (4, 4))]),
+            gds_struct(
+                "mid",
+                vec![
+                    rect(2, (0, 0), (30, 10)),
+                    sref("leaf", (10, 0)),
+                    aref("leaf", (0, 20), (2, 1), (8, 8)),
+                ],
+            ),
+            gds_struct(
+                "top",
+                vec![
+                    rect(0, (-5, -5), (0, 0)),
+                    sref("mid", (0, 0)),
+                    sref("mid", (100, 50)),
+                ],
+            ),
+        ]);
+        let leaf = project.find_cell_def("leaf").unwrap();
+        let mid = project.find_cell_def("mid").unwrap();
+        let top = project.find_cell_def("top").unwrap();
+        let leaf_instance = project.cell_def(mid).cell_elements[0];
+        let leaf_array = project.cell_def(mid).cell_elements[1];
+        let mid_instance = project.cell_def(top).cell_elements[1];
+        let edits = vec![
+            Edit::MoveInstance {
+                cell_id: leaf_instance,
+                dx: 3,
+                dy: -2,
+            },
+            Edit::MoveInstance {
+                cell_id: leaf_array,
+                dx: 1,
+                dy: 1,
+            },
+            Edit::RotateInstance {
+                cell_id: mid_instance,
+                quarter_turns: 1,
+            },
+            Edit::MirrorInstance {
+                cell_id: mid_instance,
+            },
+            Edit::AddPolygon {
+                cell_def_id: leaf,
+                layer: 2,
+                datatype: 0,
+                xy: vec![
+                    GdsPoint::new(0, 0),
+                    GdsPoint::new(20, 0),
+                    GdsPoint::new(0, 20),
+                ],
+            },
+            Edit::AddPath {
+                cell_def_id: mid,
+                layer: 1,
+                datatype: 0,
+                xy: vec![GdsPoint::new(0, 0), GdsPoint::new(0, 40)],
+                width: 2,
+            },
+            Edit::MoveShape {
+                cell_def_id: mid,
+                shape: 0,
+                dx: -10,
+                dy: 0,
+            },
+            Edit::SetShapeLayer {
+                cell_def_id: leaf,
+                shape: 0,
+                layer: 0,
+            },
+            Edit::DeleteShape {
+                cell_def_id: leaf,
+                shape: 0,
+            },
+            Edit::DeleteInstance {
+                cell_id: leaf_instance,
+            },
+        ];
+        for edit in edits {
+            let description = edit.to_string();
+            let before = snapshot(&project);
+            project.apply_edit(edit).unwrap();
+            assert_matches_full_update(&mut project);
+            let after = snapshot(&project);
+            assert_ne!(before, after, "{}", description);
+
+            project.undo().unwrap().unwrap();
+            assert_matches_full_update(&mut project);
+            assert_eq!(before, snapshot(&project), "undo {}", description);
+
+            project.redo().unwrap().unwrap();
+            assert_matches_full_update(&mut project);
+            assert_eq!(after, snapshot(&project), "redo {}", description);
+        }
+    }
+
+    #[test]
+    fn test_edits_check_the_cell() {
+        let mut project = project(vec![gds_struct("top", vec![rect(1, (0, 0), (4, 4))])]);
+        let missing = CellDefId(99);
+        let xy = vec![
+            GdsPoint::new(0, 0),
+            GdsPoint::new(1, 0),
+            GdsPoint::new(0, 1),
+        ];
+        let edit = Edit::AddPolygon {
+            cell_def_id: missing,
+            layer: 1,
+            datatype: 0,
+            xy: xy.clone(),
+        };
+        assert!(project.apply_edit(edit).is_err());
+        let edit = Edit::AddPath {
+            cell_def_id: missing,
+            layer: 1,
+            datatype: 0,
+            xy,
+            width: 1,
+        };
+        assert!(project.apply_edit(edit).is_err());
+        let edit = Edit::DeleteShape {
+            cell_def_id: missing,
+            shape: 0,
+        };
+        assert!(project.apply_edit(edit).is_err());
+        assert!(!project.can_undo());
+    }
+}
@@ -0,0 +1,242 @@
+use crate::core::density_map;
+use crate::core::offset_polygons;
+use crate::core::union_polygons;
+use crate::core::CellDefId;
+use crate::core::DensityMap;
+use crate::core::DensityOptions;
+use crate::core::FlattenOptions;
+use crate::graphics::BoundingBox;
+use crate::Project;
+use anyhow::anyhow;
+use anyhow::Result;
+use geo::BoundingRect;
+use geo::Coord;
+use geo::Intersects;
+use geo::Rect;
+use rstar::primitives::GeomWithData;
+use rstar::primitives::Rectangle;
+use rstar::RTree;
+use rstar::AABB;
+
+type Polygon = geo::Polygon<f64>;
+
+/// Options for [Project::generate_fill]. Distances are in database units.
+#[derive(Debug, Clone)]
+pub struct FillOptions {
+    pub layer: i16,
+    /// Density that every window is filled up to, between 0 and 1.
+    pub target_density: f64,
+    /// Side length of the square fill shapes.
+    pub size: f64,
+    /// Gap between adjacent fill shapes.
+    pub spacing: f64,
+    /// Minimum distance between fill shapes and existing geometry.
+    pub keep_out: f64,
+    /// Other layers that fill shapes must keep out of, such as the layers
+    /// above and below.
+    pub blocking_layers: Vec<i16>,
+    /// Windows that are filled up to the target density independently. The
+    /// step is ignored, since windows must not overlap.
+    pub density: DensityOptions,
+}
+
+impl FillOptions {
+    /// Picks fill shapes that fit around 20 across each density window.
+    pub fn for_bounds(layer: i16, target_density: f64, bounds: &BoundingBox) -> Self {
+        let density = DensityOptions::for_bounds(bounds);
+        let size = (density.window / 20.0).max(1.0).round();
+        Self {
+            layer,
+            target_density,
+            size,
+            spacing: (size / 2.0).round(),
+            keep_out: size,
+            blocking_layers: Vec::new(),
+            density,
+        }
+    }
+}
+
+/// Fill shapes produced by [Project::generate_fill], along with the density
+/// of the layer before and after adding them.
+#[derive(Debug, Clone)]
+pub struct FillResult {
+    pub cell_def_id: CellDefId,
+    pub layer: i16,
+    /// Fill shapes in the coordinate system of the top cell.
+    pub polygons: Vec<Polygon>,
+    pub before: DensityMap,
+    pub after: DensityMap,
+}
+
+impl Project {
+    /// Places fill squares on a regular grid into the empty regions of the
+    /// flattened top cell, window by window, until each window reaches the
+    /// target density or runs out of room. The grid is shared by all windows,
+    /// but squares crossing the edge between two windows are left out, so
+    /// that strips along the window edges stay empty.
+    pub fn generate_fill(&self, top: CellDefId, options: &FillOptions) -> Result<FillResult> {
+        if !(options.size > 0.0 && options.spacing >= 0.0 && options.keep_out >= 0.0) {
+            return Err(anyhow!(
+                "Fill size must be positive, spacing and keep-out must not be negative"
+            ));
+        }
+        if options.density.window.is_nan() || options.density.window <= 0.0 {
+            return Err(anyhow!("Density window must be positive"));
+        }
+        let mut layers = vec![options.layer];
+        layers.extend(&options.blocking_layers);
+        let flatten_options = FlattenOptions {
+            layers: Some(layers),
+            ..Default::default()
+        };
+        let flat = self.flatten(top, &flatten_options);
+        let layer = flat
+            .layers
+            .get(options.layer as usize)
+            .ok_or_else(|| anyhow!("Layer {} does not exist", options.layer))?;
+        let bounds = self.cell_def_bounds(top).unwrap_or_default();
+
+        // Fill is decided per window, so the windows must not overlap.
+        let density = DensityOptions {
+            step: options.density.window,
+            ..options.density.clone()
+        };
+        let existing = union_polygons(&layer.polygons);
+        let before = density_map(options.layer, &existing, &bounds, &density);
+
+        let mut blockers = existing.clone();
+        for &blocking_layer in &options.blocking_layers {
+            if let Some(layer) = flat.layers.get(blocking_layer as usize) {
+                blockers.extend(layer.polygons.iter().cloned());
+            }
+        }
+        let blocked = offset_polygons(&blockers, options.keep_out);
+        let blocked_tree = RTree::bulk_load(
+            blocked
+                .iter()
+                .enumerate()
+                .filter_map(|(index, polygon)| {
+                    let rect = polygon.bounding_rect()?;
+                    let rectangle = Rectangle::from_corners(
+                        [rect.min().x, rect.min().y],
+                        [rect.max().x, rect.max().y],
+                    );
+                    Some(GeomWithData::new(rectangle, index))
+                })
+                .collect(),
+        );
+        let is_free = |square: &Polygon| {
+            let rect = square.bounding_rect().unwrap();
+            let envelope =
+                AABB::from_corners([rect.min().x, rect.min().y], [rect.max().x, rect.max().y]);
+            !blocked_tree
+                .locate_in_envelope_intersecting(&envelope)
+                .any(|item| blocked[item.data].intersects(square))
+        };
+
+        let pitch = options.size + options.spacing;
+        let window_area = density.window * density.window;
+        let mut polygons = Vec::new();
+        for row in 0..before.rows {
+            for col in 0..before.cols {
+                let missing = options.target_density - before.value(col, row);
+                if missing <= 0.0 {
+                    continue;
+                }
+                polygons.extend(fill_window(
+                    &before.window_bounds(col, row),
+                    &bounds,
+                    options.size,
+                    pitch,
+                    missing * window_area,
+                    is_free,
+                ));
+            }
+        }
+
+        let mut filled = existing;
+        filled.extend(polygons.iter().cloned());
+        let after = density_map(options.layer, &filled, &bounds, &density);
+
+        Ok(FillResult {
+            cell_def_id: top,
+            layer: options.layer,
+            polygons,
+            before,
+            after,
+        })
+    }
+}
+
+/// Places squares on a grid anchored at the lower left corner of the layout
+/// until they cover the needed area. Only squares that lie entirely within
+/// both the window and the layout are considered, so that no square is
+/// counted towards two windows.
+fn fill_window(
+    window: &BoundingBox,
+    layout: &BoundingBox,
+    size: f64,
+    pitch: f64,
+    mut needed: f64,
+    is_free: impl Fn(&Polygon) -> bool,
+) -> Vec<Polygon> {
+    let first = |min: f64, origin: f64| origin + ((min - origin) / pitch).ceil().max(0.0) * pitch;
+    let max_x = window.max_x.min(layout.max_x);
+    let max_y = window.max_y.min(layout.max_y);
+    let mut squares = Vec::new();
+    let mut y = first(window.min_y, layout.min_y);
+    while y + size <= max_y && needed > 0.0 {
+        let mut x = first(window.min_x, layout.min_x);
+        while x + size <= max_x && needed > 0.0 {
+            let square = Rect::new(
+                Coord { x, y },
+                Coord {
+                    x: x + size,
+                    y: y + size,
+                },
+            )
+            .to_polygon();
+            if is_free(&square) {
+                squares.push(square);
+                needed -= size * size;
+            }
+            x += pitch;
+        }
+        y += pitch;
+    }
+    squares
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fills_free_grid_positions() {
+        let layout = BoundingBox {
+            min_x: 0.0,
+            min_y: 0.0,
+            max_x: 100.0,
+            max_y: 100.0,
+        };
+        let window = BoundingBox {
+            max_x: 50.0,
+            max_y: 50.0,
+            ..layout
+        };
+        // Room for 4 x 4 squares, minus those blocked in the lower left.
+        let blocked = |square: &Polygon| {
+            let rect = square.bounding_rect().unwrap();
+            rect.min().x < 20.0 && rect.min().y < 20.0
+        };
+        let squares = fill_window(&window, &layout, 10.0, 12.0, f64::MAX, |s| !blocked(s));
+        assert_eq!(squares.len(), 12);
+        assert!(squares
+            .iter()
+            .all(|s| s.bounding_rect().unwrap().max().x <= 50.0));
+
+        let squares = fill_window(&window, &layout, 10.0, 12.0, 250.0, |_| true);
+        assert_eq!(squares.len(), 3);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::core::append_derived_layers;
+use crate::core::union_polygons;
+use crate::core::CellDefId;
+use crate::core::Layer;
+use crate::Project;
+use geo::AffineTransform;
+use std::collections::HashSet;
+
+/// Options for [Project::flatten].
+#[derive(Debug, Clone, Default)]
+pub struct FlattenOptions {
+    /// Instances nested deeper than this are kept as references rather than
+    /// being flattened. The top cell is at depth 0.
+    pub max_depth: Option<usize>,
+    /// Merge overlapping and abutting polygons within each layer.
+    pub merge: bool,
+    /// Layers to flatten, or None for all. The layers that derived layers
+    /// among them are computed from are flattened as well, and all other
+    /// layers are left empty.
+    pub layers: Option<Vec<i16>>,
+}
+
+/// Single-level version of a cell, produced by [Project::flatten].
+pub struct FlatCell {
+    pub cell_def_id: CellDefId,
+    /// Polygons in the coordinate system of the top cell, indexed by layer.
+    /// Derived layers are evaluated over the flattened polygons.
+    pub layers: Vec<Layer>,
+    /// Instances below the depth limit, which are not flattened.
+    pub references: Vec<FlatReference>,
+}
+
+/// Instance that was left unflattened due to the depth limit.
+pub struct FlatReference {
+    pub cell_def_id: CellDefId,
+    /// Transform from the referenced cell into the coordinate system of the
+    /// top cell.
+    pub transform: AffineTransform,
+}
+
+impl Project {
+    /// Gathers all polygons of the given cell and its descendants into a
+    /// single level, expanding array instances along the way. Visibility
+    /// flags are ignored since this is meant for export.
+    pub fn flatten(&self, top: CellDefId, options: &FlattenOptions) -> FlatCell {
+        let mut flat = FlatCell {
+            cell_def_id: top,
+            layers: (0..=self.highest_layer()).map(Layer::new).collect(),
+            references: Vec::new(),
+        };
+        let included = options
+            .layers
+            .as_ref()
+            .map(|layers| self.layer_inputs(layers));
+        let include = |layer: i16| included.as_ref().is_none_or(|set| set.contains(&layer));
+
+        self.flatten_recurse(
+            top,
+            &AffineTransform::identity(),
+            0,
+            options,
+            &include,
+            &mut flat,
+        );
+
+        for (layer, source) in flat.layers.iter_mut().zip(self.layers()) {
+            layer.color = source.color;
+            if options.merge && !layer.polygons.is_empty() {
+                layer.polygons = union_polygons(&layer.polygons);
+            }
+            layer.update_bounds();
+        }
+        append_derived_layers(&mut flat.layers, &self.derived_layers, include);
+
+        flat
+    }
+
+    /// The given layers along with every layer that the derived layers among
+    /// them are computed from, directly or indirectly.
+    fn layer_inputs(&self, layers: &[i16]) -> HashSet<i16> {
+        let mut inputs = HashSet::new();
+        let mut pending = layers.to_vec();
+        while let Some(layer) = pending.pop() {
+            if !inputs.insert(layer) || layer <= self.highest_layer() {
+                continue;
+            }
+            let ordinal = (layer - self.highest_layer() - 1) as usize;
+            if let Some(derived) = self.derived_layers.get(ordinal) {
+                derived.expr.collect_inputs(&mut pending);
+            }
+        }
+        inputs
+    }
+
+    fn flatten_recurse(
+        &self,
+        cell_def_id: CellDefId,
+        transform: &AffineTransform,
+        depth: usize,
+        options: &FlattenOptions,
+        include: &impl Fn(i16) -> bool,
+        flat: &mut FlatCell,
+    ) {
+        let cell_def = self.cell_def(cell_def_id);
+        for shape in &cell_def.shapes {
+            if include(shape.layer) {
+                flat.layers[shape.layer as usize].add_polygon(&shape.polygon, transform);
+            }
+        }
+
+        for &cell_id in &cell_def.cell_elements {
+            let cell = self.cell(cell_id);
+            for element in cell.elements() {
+                let child_transform = cell.element_placement(element).compose(transform);
+                if options
+                    .max_depth
+                    .is_some_and(|max_depth| depth >= max_depth)
+                {
+                    flat.references.push(FlatReference {
+                        cell_def_id: cell.cell_def_id,
+                        transform: child_transform,
+                    });
+                } else {
+                    self.flatten_recurse(
+                        cell.cell_def_id,
+                        &child_transform,
+                        depth + 1,
+                        options,
+                        include,
+                        flat,
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::test_layouts::*;
+
+    #[test]
+    fn test_flatten_only_the_given_layers() {
+        let mut project = project(vec![
+            gds_struct(
+                "leaf",
+                vec![rect(1, (0, 0), (2, 2)), rect(3, (0, 0), (2, 2))],
+            ),
+            gds_struct(
+                "top",
+                vec![
+                    rect(2, (4, 0), (6, 2)),
+                    sref("leaf", (0, 0)),
+                    sref("leaf", (10, 0)),
+                ],
+            ),
+        ]);
+        let both = project.add_derived_layer("both", "1 OR 2").unwrap();
+        let top = project.find_cell_def("top").unwrap();
+        let options = FlattenOptions {
+            layers: Some(vec![both]),
+            ..Default::default()
+        };
+        let flat = project.flatten(top, &options);
+        let counts: Vec<usize> = flat
+            .layers
+            .iter()
+            .map(|layer| layer.polygons.len())
+            .collect();
+        assert_eq!(counts, vec![0, 2, 1, 0, 3]);
+    }
+}
@@ -0,0 +1,301 @@
+use crate::core::Cell;
+use crate::core::CellDefId;
+use crate::core::CellId;
+use crate::core::InstanceStep;
+use crate::graphics::BoundingBox;
+use crate::Project;
+use geo::AffineTransform;
+use geo::Coord;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+/// Instances of one cell placed within a parent cell, as listed by
+/// [Project::child_groups].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChildGroup {
+    pub cell_def_id: CellDefId,
+    pub instances: Vec<CellId>,
+}
+
+impl Project {
+    /// Groups the instances placed directly in a cell by the cell they
+    /// refer to, in order of first placement.
+    pub fn child_groups(&self, cell_def_id: CellDefId) -> Vec<ChildGroup> {
+        let mut groups: Vec<ChildGroup> = Vec::new();
+        for &cell_id in &self.cell_def(cell_def_id).cell_elements {
+            let child = self.cell(cell_id).cell_def_id;
+            match groups.iter_mut().find(|group| group.cell_def_id == child) {
+                Some(group) => group.instances.push(cell_id),
+                None => groups.push(ChildGroup {
+                    cell_def_id: child,
+                    instances: vec![cell_id],
+                }),
+            }
+        }
+        groups
+    }
+
+    pub fn instance_cell_def(&self, cell_id: CellId) -> CellDefId {
+        self.cell(cell_id).cell_def_id
+    }
+
+    /// Number of times a cell is placed across all of its parents.
+    pub fn instance_count(&self, cell_def_id: CellDefId) -> usize {
+        self.cell_def(cell_def_id).instances.len()
+    }
+
+    pub fn is_instance_visible(&self, cell_id: CellId) -> bool {
+        self.cell(cell_id).visible
+    }
+
+    /// Shows or hides an instance along with everything below it. Since
+    /// instances belong to the parent cell, this applies wherever the parent
+    /// is placed. Call [Self::refresh_layers] afterwards to update the layers.
+    pub fn set_instance_visible(&mut self, cell_id: CellId, visible: bool) {
+        self.cell_mut(cell_id).visible = visible;
+    }
+
+    /// Returns whether any instance of the cell is visible.
+    pub fn is_cell_def_visible(&self, cell_def_id: CellDefId) -> bool {
+        self.cell_def(cell_def_id)
+            .instances
+            .iter()
+            .any(|&cell_id| self.cell(cell_id).visible)
+    }
+
+    /// Shows or hides every instance of a cell, see
+    /// [Self::set_instance_visible].
+    pub fn set_cell_def_visible(&mut self, cell_def_id: CellDefId, visible: bool) {
+        for cell_id in self.cell_def(cell_def_id).instances.clone() {
+            self.set_instance_visible(cell_id, visible);
+        }
+    }
+
+    /// Rebuilds the layers after instances were shown or hidden, keeping the
+    /// visibility, color and merging of each layer.
+    pub fn refresh_layers(&mut self) {
+        let styles: Vec<_> = self
+            .layers()
+            .iter()
+            .map(|layer| (layer.visible, layer.color))
+            .collect();
+        let merged = self.is_merged();
+        self.update_layers();
+        for (layer, (visible, color)) in self.layers_mut().iter_mut().zip(styles) {
+            layer.visible = visible;
+            layer.color = color;
+        }
+        if merged {
+            self.merge_layers();
+        }
+    }
+
+    /// Number of levels of instances below the top cells, or 0 if they
+    /// contain no instances.
+    pub fn hierarchy_depth(&self) -> usize {
+        fn depth(
+            project: &Project,
+            cell_def_id: CellDefId,
+            memo: &mut HashMap<CellDefId, usize>,
+        ) -> usize {
+            if let Some(&depth) = memo.get(&cell_def_id) {
+                return depth;
+            }
+            let result = project
+                .cell_def(cell_def_id)
+                .cell_elements
+                .iter()
+                .map(|&cell_id| 1 + depth(project, project.cell(cell_id).cell_def_id, memo))
+                .max()
+                .unwrap_or(0);
+            memo.insert(cell_def_id, result);
+            result
+        }
+        let mut memo = HashMap::new();
+        self.top_cells()
+            .into_iter()
+            .map(|top| depth(self, top, &mut memo))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Computes the world bounds of an instance given by the chain of
+    /// instances leading to it from a top cell. The path is needed since a
+    /// cell may be placed several times. If the path ends at an array as a
+    /// whole, the bounds cover all of its elements.
+    pub fn instance_path_bounds(&self, path: &[InstanceStep]) -> Option<BoundingBox> {
+        let (last, parents) = path.split_last()?;
+        let cell = self.cell(last.cell_id);
+        let bounds = self.cell_def_bounds(cell.cell_def_id)?;
+        let bounds = match last.element {
+            Some(element) => transform_bounds(&bounds, &cell.element_placement(Some(element))),
+            None => placed_bounds(cell, &bounds),
+        };
+        Some(transform_bounds(
+            &bounds,
+            &self.instance_path_transform(parents),
+        ))
+    }
+
+    /// Composes the placements along a path of instances, from the last
+    /// instance into the coordinate system of the top cell.
+    pub fn instance_path_transform(&self, path: &[InstanceStep]) -> AffineTransform {
+        path.iter()
+            .rev()
+            .fold(AffineTransform::identity(), |transform, step| {
+                transform.compose(&self.cell(step.cell_id).element_placement(step.element))
+            })
+    }
+
+    /// Bounds of all geometry in a cell and its descendants, in the
+    /// coordinate system of the cell, regardless of visibility.
+    pub(crate) fn cell_def_bounds(&self, cell_def_id: CellDefId) -> Option<BoundingBox> {
+        self.cell_def(cell_def_id).bounds
+    }
+
+    /// Recomputes the cached bounds of every cell, see [Self::cell_def_bounds].
+    pub(crate) fn update_cell_def_bounds(&mut self) {
+        let mut memo = HashMap::new();
+        for cell_def_id in self.cell_def_ids().collect::<Vec<_>>() {
+            self.compute_cell_def_bounds(cell_def_id, None, &mut memo);
+        }
+        for (cell_def_id, bounds) in memo {
+            self.cell_def_mut(cell_def_id).bounds = bounds;
+        }
+    }
+
+    /// Recomputes the cached bounds of a cell whose shapes or instances
+    /// changed, and of every cell placing it directly or indirectly, keeping
+    /// the bounds of all other cells. Returns the cells that were updated.
+    pub(crate) fn update_cell_def_bounds_above(
+        &mut self,
+        cell_def_id: CellDefId,
+    ) -> HashSet<CellDefId> {
+        let mut stale = HashSet::new();
+        let mut pending = vec![cell_def_id];
+        while let Some(cell_def_id) = pending.pop() {
+            if stale.insert(cell_def_id) {
+                let instances = &self.cell_def(cell_def_id).instances;
+                pending.extend(instances.iter().map(|&cell_id| self.cell(cell_id).parent));
+            }
+        }
+        let mut memo = HashMap::new();
+        for &cell_def_id in &stale {
+            self.compute_cell_def_bounds(cell_def_id, Some(&stale), &mut memo);
+        }
+        for (cell_def_id, bounds) in memo {
+            self.cell_def_mut(cell_def_id).bounds = bounds;
+        }
+        stale
+    }
+
+    /// Computes the bounds of a cell from those of its children. If a set of
+    /// stale cells is given, the cached bounds of all other cells are used.
+    fn compute_cell_def_bounds(
+        &self,
+        cell_def_id: CellDefId,
+        stale: Option<&HashSet<CellDefId>>,
+        memo: &mut HashMap<CellDefId, Option<BoundingBox>>,
+    ) -> Option<BoundingBox> {
+        if let Some(bounds) = memo.get(&cell_def_id) {
+            return *bounds;
+        }
+        if stale.is_some_and(|stale| !stale.contains(&cell_def_id)) {
+            return self.cell_def_bounds(cell_def_id);
+        }
+        let cell_def = self.cell_def(cell_def_id);
+        let mut bounds = cell_def.shape_bounds.unwrap_or_default();
+        for &cell_id in &cell_def.cell_elements {
+            let cell = self.cell(cell_id);
+            let Some(child) = self.compute_cell_def_bounds(cell.cell_def_id, stale, memo) else {
+                continue;
+            };
+            bounds.encompass(&placed_bounds(cell, &child));
+        }
+        let bounds = (!bounds.is_empty()).then_some(bounds);
+        memo.insert(cell_def_id, bounds);
+        bounds
+    }
+}
+
+/// Bounds of a cell placed by an instance, in the coordinate system of the
+/// parent, covering every element of array instances.
+pub(crate) fn placed_bounds(cell: &Cell, bounds: &BoundingBox) -> BoundingBox {
+    let mut placed = transform_bounds(bounds, &cell.placement());
+    if let Some(array) = &cell.array {
+        let first = placed;
+        let (last_col, last_row) = (array.cols.max(1) - 1, array.rows.max(1) - 1);
+        for (col, row) in [(last_col, 0), (0, last_row), (last_col, last_row)] {
+            let (x, y) = array.element_offset(col, row);
+            placed.encompass(&BoundingBox {
+                min_x: first.min_x + x,
+                min_y: first.min_y + y,
+                max_x: first.max_x + x,
+                max_y: first.max_y + y,
+            });
+        }
+    }
+    placed
+}
+
+pub(crate) fn transform_bounds(bounds: &BoundingBox, transform: &AffineTransform) -> BoundingBox {
+    let mut result = BoundingBox::new();
+    for (x, y) in [
+        (bounds.min_x, bounds.min_y),
+        (bounds.max_x, bounds.min_y),
+        (bounds.max_x, bounds.max_y),
+        (bounds.min_x, bounds.max_y),
+    ] {
+        let point = transform.apply(Coord { x, y });
+        result.encompass(&BoundingBox {
+            min_x: point.x,
+            min_y: point.y,
+            max_x: point.x,
+            max_y: point.y,
+        });
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::test_layouts::*;
+
+    #[test]
+    fn test_hidden_instances_are_not_drawn() {
+        let mut project = project(vec![
+            gds_struct("via", vec![rect(2, (0, 0), (2, 2))]),
+            gds_struct("bit", vec![rect(1, (0, 0), (10, 10)), sref("via", (4, 4))]),
+            gds_struct(
+                "top",
+                vec![
+                    sref("bit", (0, 0)),
+                    sref("via", (50, 0)),
+                    sref("bit", (20, 0)),
+                ],
+            ),
+        ]);
+        let top = project.find_cell_def("top").unwrap();
+        let bit = project.find_cell_def("bit").unwrap();
+        let via = project.find_cell_def("via").unwrap();
+        let groups = project.child_groups(top);
+        let cells: Vec<_> = groups.iter().map(|group| group.cell_def_id).collect();
+        assert_eq!(cells, vec![bit, via]);
+        assert_eq!(groups[0].instances.len(), 2);
+        assert_eq!(project.layers()[2].shape_count, 3);
+
+        let first_bit = groups[0].instances[0];
+        project.set_instance_visible(first_bit, false);
+        project.refresh_layers();
+        assert!(!project.is_instance_visible(first_bit));
+        assert!(project.is_cell_def_visible(bit));
+        assert_eq!(project.layers()[1].shape_count, 1);
+        assert_eq!(project.layers()[2].shape_count, 2);
+
+        project.set_cell_def_visible(via, false);
+        project.refresh_layers();
+        assert!(!project.is_cell_def_visible(via));
+        assert_eq!(project.layers()[2].shape_count, 0);
+        assert_eq!(project.placements().len(), 1);
+    }
+}
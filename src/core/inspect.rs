@@ -0,0 +1,230 @@
+use crate::core::format_area;
+use crate::core::format_length;
+use crate::core::ElementMetadata;
+use crate::core::PickResult;
+use crate::graphics::BoundingBox;
+use crate::Project;
+use gds21::GdsDateTimes;
+use gds21::GdsPoint;
+use gds21::GdsStrans;
+use geo::Area;
+use geo::BoundingRect;
+
+/// Everything known about a picked shape, see [Project::inspect].
+#[derive(Debug, Clone)]
+pub struct ShapeInfo {
+    pub layer: i16,
+    pub datatype: i16,
+    /// Width of the path the shape was stroked from, or None for
+    /// boundaries.
+    pub path_width: Option<i32>,
+    /// Number of distinct vertices of the outline.
+    pub vertex_count: usize,
+    /// Area in square database units.
+    pub area: f64,
+    /// Bounds in world coordinates.
+    pub bounds: BoundingBox,
+    /// Name of the cell that owns the shape.
+    pub cell_name: String,
+    pub cell_dates: GdsDateTimes,
+    /// Placement of the owning cell within its parent, or None if it is a
+    /// top cell.
+    pub instance: Option<InstanceInfo>,
+    pub metadata: ElementMetadata,
+}
+
+/// Reference element that places a cell within its parent.
+#[derive(Debug, Clone)]
+pub struct InstanceInfo {
+    pub xy: GdsPoint,
+    pub strans: Option<GdsStrans>,
+    /// Columns and rows of array references.
+    pub array: Option<(i16, i16)>,
+    pub metadata: ElementMetadata,
+}
+
+impl Project {
+    /// Describes a picked shape. Returns None if the shape no longer exists,
+    /// e.g. after an edit.
+    pub fn inspect(&self, pick: &PickResult) -> Option<ShapeInfo> {
+        let shape = self.cell_def(pick.cell_def_id).shapes.get(pick.shape)?;
+        let instance = self.find_cell(pick.cell_id).map(|cell| InstanceInfo {
+            xy: cell.xy.clone(),
+            strans: cell.local_transform.clone(),
+            array: cell.array.as_ref().map(|array| (array.cols, array.rows)),
+            metadata: cell.metadata.clone(),
+        });
+        // The outline is closed, so its last point repeats the first one.
+        let vertex_count = shape.polygon.exterior().0.len().saturating_sub(1)
+            + shape
+                .polygon
+                .interiors()
+                .iter()
+                .map(|ring| ring.0.len().saturating_sub(1))
+                .sum::<usize>();
+        Some(ShapeInfo {
+            layer: shape.layer,
+            datatype: shape.datatype,
+            path_width: shape.path.as_ref().map(|path| path.width.unwrap_or(0)),
+            vertex_count,
+            area: shape.polygon.unsigned_area(),
+            bounds: pick
+                .polygon
+                .bounding_rect()
+                .map(BoundingBox::from)
+                .unwrap_or_default(),
+            cell_name: self.struct_name(pick.cell_def_id).to_string(),
+            cell_dates: self.cell_def(pick.cell_def_id).dates.clone(),
+            instance,
+            metadata: shape.metadata.clone(),
+        })
+    }
+}
+
+impl ShapeInfo {
+    /// Labelled lines describing the shape, with lengths in physical units
+    /// for the given database unit in meters.
+    pub fn rows(&self, db_unit: f64) -> Vec<(String, String)> {
+        let mut rows = vec![
+            (
+                "Layer".to_string(),
+                format!("{}/{}", self.layer, self.datatype),
+            ),
+            (
+                "Element".to_string(),
+                match self.path_width {
+                    Some(width) => format!("path, width {}", format_length(width as f64, db_unit)),
+                    None => "boundary".to_string(),
+                },
+            ),
+            ("Vertices".to_string(), self.vertex_count.to_string()),
+            ("Area".to_string(), format_area(self.area, db_unit)),
+            (
+                "Bounds".to_string(),
+                format!(
+                    "({}, {}) to ({}, {})",
+                    self.bounds.min_x, self.bounds.min_y, self.bounds.max_x, self.bounds.max_y
+                ),
+            ),
+            (
+                "Size".to_string(),
+                format!(
+                    "{} × {}",
+                    format_length(self.bounds.width(), db_unit),
+                    format_length(self.bounds.height(), db_unit)
+                ),
+            ),
+            ("Cell".to_string(), self.cell_name.clone()),
+            ("Modified".to_string(), self.cell_dates.modified.to_string()),
+            ("Accessed".to_string(), self.cell_dates.accessed.to_string()),
+        ];
+        match &self.instance {
+            Some(instance) => {
+                rows.push(("Instance".to_string(), describe_placement(instance)));
+                if let Some((cols, rows_count)) = instance.array {
+                    rows.push(("Array".to_string(), format!("{} × {}", cols, rows_count)));
+                }
+                push_metadata_rows(&mut rows, "Instance ", &instance.metadata);
+            }
+            None => rows.push(("Instance".to_string(), "top cell".to_string())),
+        }
+        push_metadata_rows(&mut rows, "Element ", &self.metadata);
+        rows
+    }
+}
+
+/// Describes where and how a reference places its cell.
+fn describe_placement(instance: &InstanceInfo) -> String {
+    let mut text = format!("at ({}, {})", instance.xy.x, instance.xy.y);
+    if let Some(strans) = &instance.strans {
+        if let Some(angle) = strans.angle.filter(|angle| *angle != 0.0) {
+            text.push_str(&format!(", rotated {}°", angle));
+        }
+        if strans.reflected {
+            text.push_str(", mirrored");
+        }
+        if let Some(mag) = strans.mag.filter(|mag| *mag != 1.0) {
+            text.push_str(&format!(", magnified {}", mag));
+        }
+    }
+    text
+}
+
+fn push_metadata_rows(rows: &mut Vec<(String, String)>, prefix: &str, metadata: &ElementMetadata) {
+    if let Some(elflags) = &metadata.elflags {
+        let flags: Vec<&str> = [
+            (elflags.external, "external"),
+            (elflags.template, "template"),
+        ]
+        .into_iter()
+        .filter_map(|(set, name)| set.then_some(name))
+        .collect();
+        let flags = match flags.is_empty() {
+            true => "none".to_string(),
+            false => flags.join(", "),
+        };
+        rows.push((format!("{}flags", prefix), flags));
+    }
+    if let Some(plex) = &metadata.plex {
+        rows.push((format!("{}plex", prefix), plex.0.to_string()));
+    }
+    for property in &metadata.properties {
+        rows.push((
+            format!("{}property {}", prefix, property.attr),
+            property.value.clone(),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gds21::GdsProperty;
+
+    #[test]
+    fn test_rows_list_placement_and_properties() {
+        let info = ShapeInfo {
+            layer: 5,
+            datatype: 2,
+            path_width: None,
+            vertex_count: 4,
+            area: 2.5e6,
+            bounds: BoundingBox {
+                min_x: 0.0,
+                min_y: 0.0,
+                max_x: 2000.0,
+                max_y: 1250.0,
+            },
+            cell_name: "nand2".to_string(),
+            cell_dates: GdsDateTimes::default(),
+            instance: Some(InstanceInfo {
+                xy: GdsPoint::new(10, 20),
+                strans: Some(GdsStrans {
+                    reflected: true,
+                    angle: Some(90.0),
+                    ..Default::default()
+                }),
+                array: None,
+                metadata: ElementMetadata::default(),
+            }),
+            metadata: ElementMetadata {
+                properties: vec![GdsProperty {
+                    attr: 1,
+                    value: "net A".to_string(),
+                }],
+                ..Default::default()
+            },
+        };
+        let rows = info.rows(1e-9);
+        let find = |label: &str| {
+            rows.iter()
+                .find(|(l, _)| l == label)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(find("Layer"), Some("5/2"));
+        assert_eq!(find("Area"), Some("2.5 um²"));
+        assert_eq!(find("Size"), Some("2 um × 1.25 um"));
+        assert_eq!(find("Instance"), Some("at (10, 20), rotated 90°, mirrored"));
+        assert_eq!(find("Element property 1"), Some("net A"));
+    }
+}
@@ -0,0 +1,132 @@
+use crate::core::CellDefId;
+use crate::core::CellId;
+use crate::core::InstanceStep;
+use crate::core::PickResult;
+use crate::Project;
+use geo::AffineOps;
+use geo::Polygon;
+
+impl Project {
+    /// Describes a chain of instances below a top cell as cell names joined
+    /// by slashes, e.g. `top/core/alu#3/nand2`. An instance is followed by
+    /// `#` and its index among the instances of the same cell in its parent
+    /// when there are several. An element of an array reference is followed
+    /// by its column and row, e.g. `top/ram[3,1]/bit`, and an array as a
+    /// whole by its size, e.g. `top/ram(4x2 array)`.
+    pub fn describe_instance_path(&self, top: CellDefId, path: &[InstanceStep]) -> String {
+        let mut names = vec![self.struct_name(top).to_string()];
+        let mut parent = top;
+        for step in path {
+            let cell_id = step.cell_id;
+            let Some(cell) = self.find_cell(cell_id) else {
+                break;
+            };
+            let mut name = self.struct_name(cell.cell_def_id).to_string();
+            let siblings: Vec<CellId> = self
+                .cell_def(parent)
+                .cell_elements
+                .iter()
+                .copied()
+                .filter(|&sibling| self.cell(sibling).cell_def_id == cell.cell_def_id)
+                .collect();
+            if siblings.len() > 1 {
+                if let Some(index) = siblings.iter().position(|&sibling| sibling == cell_id) {
+                    name.push_str(&format!("#{}", index));
+                }
+            }
+            match (&cell.array, step.element) {
+                (Some(_), Some((col, row))) => name.push_str(&format!("[{},{}]", col, row)),
+                (Some(array), None) => {
+                    name.push_str(&format!("({}x{} array)", array.cols, array.rows))
+                }
+                (None, _) => {}
+            }
+            names.push(name);
+            parent = cell.cell_def_id;
+        }
+        names.join("/")
+    }
+
+    /// Outlines of every shape on a visible layer within the instance that
+    /// owns a picked shape, including the instances below it, in world
+    /// coordinates. Returns None if there are more than the given number.
+    pub fn instance_polygons(&self, pick: &PickResult, max_count: usize) -> Option<Vec<Polygon>> {
+        let end = self.instance_node(pick.node)?.end;
+        let placements = self.placements();
+        let first = placements.partition_point(|placement| placement.node < pick.node);
+        let last = placements.partition_point(|placement| placement.node < end);
+        let mut polygons = Vec::new();
+        for placement in &placements[first..last] {
+            for shape in self.cell_def_shapes(placement.cell_def_id) {
+                if !self.layers()[shape.layer as usize].visible {
+                    continue;
+                }
+                if polygons.len() == max_count {
+                    return None;
+                }
+                polygons.push(shape.polygon.affine_transform(&placement.transform));
+            }
+        }
+        Some(polygons)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::test_layouts::*;
+    use crate::Project;
+
+    /// `top` places `ram` twice, which is a 4x2 array of `bit`, which places
+    /// one `via`.
+    fn ram_project() -> Project {
+        project(vec![
+            gds_struct("via", vec![rect(2, (2, 2), (4, 4))]),
+            gds_struct("bit", vec![rect(1, (0, 0), (10, 10)), sref("via", (0, 0))]),
+            gds_struct("ram", vec![aref("bit", (0, 0), (4, 2), (20, 20))]),
+            gds_struct("top", vec![sref("ram", (0, 0)), sref("ram", (100, 0))]),
+        ])
+    }
+
+    #[test]
+    fn test_describe_instance_path() {
+        let project = ram_project();
+        let pick = project.pick_cell(68.0, 28.0).unwrap();
+        assert_eq!(
+            project.describe_instance_path(pick.top, &pick.path),
+            "top/ram#0/bit[3,1]"
+        );
+        let pick = project.pick_cell(103.0, 3.0).unwrap();
+        assert_eq!(
+            project.describe_instance_path(pick.top, &pick.path),
+            "top/ram#1/bit[0,0]/via"
+        );
+
+        let mut whole_array = pick.path.clone();
+        whole_array.truncate(2);
+        whole_array[1].element = None;
+        assert_eq!(
+            project.describe_instance_path(pick.top, &whole_array),
+            "top/ram#1/bit(4x2 array)"
+        );
+    }
+
+    #[test]
+    fn test_instance_polygons_covers_one_array_element() {
+        let project = ram_project();
+        let pick = project.pick_cell(168.0, 28.0).unwrap();
+        let polygons = project.instance_polygons(&pick, 10).unwrap();
+        let mut bounds: Vec<_> = polygons
+            .iter()
+            .map(|polygon| {
+                let rect = geo::BoundingRect::bounding_rect(polygon).unwrap();
+                (rect.min().x, rect.min().y, rect.max().x, rect.max().y)
+            })
+            .collect();
+        bounds.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(
+            bounds,
+            vec![(160.0, 20.0, 170.0, 30.0), (162.0, 22.0, 164.0, 24.0)]
+        );
+        assert!(project.instance_polygons(&pick, 1).is_none());
+    }
+}
@@ -1,9 +1,11 @@
+use crate::core::union_polygons;
 use crate::graphics::BoundingBox;
 use gds21::GdsBoundary;
 use gds21::GdsPath;
 use gds21::GdsPoint;
 use geo::AffineOps;
 use geo::AffineTransform;
+use geo::Area;
 use geo::BoundingRect;
 use geo::LineString;
 use geo::Rect;
@@ -18,10 +20,21 @@ type Vec2d = geo::Point<f64>;
 
 pub struct Layer {
     index: i16,
+    /// Name of a derived layer, or None for layers read from the file.
+    pub name: Option<String>,
+    /// Flattened polygons in world coordinates. Only filled in for layers
+    /// read from the file once the project has been flattened.
     pub polygons: Vec<Polygon>,
+    /// Union of the polygons, if merging is enabled. Overlapping shapes are
+    /// drawn once when rendering this instead, while the original polygons
+    /// are kept for picking.
+    merged: Option<Vec<Polygon>>,
     pub bounds: BoundingBox,
-    pub paths: Vec<GdsPath>,
-    pub boundaries: Vec<GdsBoundary>,
+    /// Number of shapes drawn on this layer, counting every placement of a
+    /// cell. Known without flattening, see [Project::flatten_layers].
+    ///
+    /// [Project::flatten_layers]: crate::Project::flatten_layers
+    pub shape_count: usize,
     pub color: Vector4<f32>, // RGBA color for this layer
     pub visible: bool,
 }
@@ -30,10 +43,11 @@ impl Layer {
     pub fn new(index: i16) -> Self {
         Self {
             index,
+            name: None,
             polygons: vec![],
+            merged: None,
             bounds: BoundingBox::new(),
-            paths: Vec::new(),
-            boundaries: Vec::new(),
+            shape_count: 0,
             color: Vector4::new(0.0, 0.0, 0.0, 1.0), // Default to black
             visible: true,
         }
@@ -43,6 +57,51 @@ impl Layer {
         self.index
     }
 
+    pub fn is_derived(&self) -> bool {
+        self.name.is_some()
+    }
+
+    /// Returns whether nothing is drawn on this layer.
+    pub fn is_empty(&self) -> bool {
+        self.shape_count == 0 && self.polygons.is_empty()
+    }
+
+    /// Returns the name shown to users, e.g. in the sidebar.
+    pub fn display_name(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("Layer {}", self.index),
+        }
+    }
+
+    /// Computes the union of all polygons in this layer.
+    pub fn merge(&mut self) {
+        self.merged = Some(union_polygons(&self.polygons));
+    }
+
+    pub fn unmerge(&mut self) {
+        self.merged = None;
+    }
+
+    pub fn is_merged(&self) -> bool {
+        self.merged.is_some()
+    }
+
+    /// Returns the polygons to draw or export: the merged polygons if the
+    /// layer has been merged, otherwise the original polygons.
+    pub fn render_polygons(&self) -> &[Polygon] {
+        self.merged.as_deref().unwrap_or(&self.polygons)
+    }
+
+    /// Total area covered by this layer. Only exact when the layer is merged,
+    /// since overlapping polygons are otherwise counted more than once.
+    pub fn area(&self) -> f64 {
+        self.render_polygons()
+            .iter()
+            .map(|polygon| polygon.unsigned_area())
+            .sum()
+    }
+
     pub fn update_bounds(&mut self) {
         self.bounds = BoundingBox::new();
 
@@ -54,73 +113,70 @@ impl Layer {
         }
     }
 
-    pub fn add_boundary_element(&mut self, boundary: &GdsBoundary, transform: &AffineTransform) {
-        let points: Vec<Vec2d> = boundary.xy.iter().map(gds_to_geo_point).collect();
-        if points.len() < 3 {
-            log::warn!("Boundary has less than 3 points, skipping");
-            return;
-        }
+    pub fn add_polygon(&mut self, polygon: &Polygon, transform: &AffineTransform) {
+        self.polygons.push(polygon.affine_transform(transform));
+    }
+}
 
-        let polygon = Polygon::new(LineString::from(points), vec![]);
-        let transformed = polygon.affine_transform(transform);
-        self.polygons.push(transformed);
+/// Converts a boundary element to a polygon, or None if it is degenerate.
+pub(crate) fn boundary_polygon(boundary: &GdsBoundary) -> Option<Polygon> {
+    let points: Vec<Vec2d> = boundary.xy.iter().map(gds_to_geo_point).collect();
+    if points.len() < 3 {
+        log::warn!("Boundary has less than 3 points, skipping");
+        return None;
     }
+    Some(Polygon::new(LineString::from(points), vec![]))
+}
 
-    pub fn add_path_element(&mut self, path: &GdsPath, transform: &AffineTransform) {
-        if path.xy.len() < 2 {
-            return;
-        }
+/// Strokes a path element into a polygon, or None if it is degenerate.
+pub(crate) fn path_polygon(path: &GdsPath) -> Option<Polygon> {
+    if path.xy.len() < 2 {
+        return None;
+    }
 
-        let half_width = path.width.unwrap_or(0) as f64 / 2.0;
-        let path_type = path
-            .path_type
-            .map(PathType::from)
-            .unwrap_or(PathType::Standard);
-
-        let outline_points = self.create_path_outline(&path.xy, half_width, path_type);
-
-        // Create and transform the polygon
-        let polygon = Polygon::new(LineString::from(outline_points), vec![]);
-        let transformed = polygon.affine_transform(transform);
-        self.polygons.push(transformed);
-    }
-
-    // Private helper functions
-    fn create_path_outline(
-        &mut self,
-        spine_points: &[GdsPoint],
-        half_width: f64,
-        path_type: PathType,
-    ) -> Vec<Vec2d> {
-        let start_cap = match path_type {
-            PathType::Round => LineCap::Round(0.1),
-            PathType::Extended => LineCap::Square,
-            PathType::Standard => LineCap::Butt,
-        };
-
-        let end_cap = match path_type {
-            PathType::Round => LineCap::Round(0.1),
-            PathType::Extended => LineCap::Square,
-            PathType::Standard => LineCap::Butt,
-        };
-
-        let style = StrokeStyle::new(half_width * 2.0)
-            .line_join(LineJoin::Miter(1.0))
-            .start_cap(start_cap)
-            .end_cap(end_cap);
-
-        let spine_points: Vec<[f64; 2]> = spine_points.iter().map(gds_point_to_array).collect();
-        let shapes: Vec<Vec<Vec<[f64; 2]>>> = spine_points.stroke(style, false);
-
-        if let Some(first_shape) = shapes.first() {
-            if let Some(first_contour) = first_shape.first() {
-                return first_contour.iter().map(array_to_geo_point).collect();
-            }
-        }
+    let half_width = path.width.unwrap_or(0) as f64 / 2.0;
+    let path_type = path
+        .path_type
+        .map(PathType::from)
+        .unwrap_or(PathType::Standard);
 
-        log::warn!("Empty contour for path.");
-        vec![]
+    let outline_points = create_path_outline(&path.xy, half_width, path_type);
+    Some(Polygon::new(LineString::from(outline_points), vec![]))
+}
+
+fn create_path_outline(
+    spine_points: &[GdsPoint],
+    half_width: f64,
+    path_type: PathType,
+) -> Vec<Vec2d> {
+    let start_cap = match path_type {
+        PathType::Round => LineCap::Round(0.1),
+        PathType::Extended => LineCap::Square,
+        PathType::Standard => LineCap::Butt,
+    };
+
+    let end_cap = match path_type {
+        PathType::Round => LineCap::Round(0.1),
+        PathType::Extended => LineCap::Square,
+        PathType::Standard => LineCap::Butt,
+    };
+
+    let style = StrokeStyle::new(half_width * 2.0)
+        .line_join(LineJoin::Miter(1.0))
+        .start_cap(start_cap)
+        .end_cap(end_cap);
+
+    let spine_points: Vec<[f64; 2]> = spine_points.iter().map(gds_point_to_array).collect();
+    let shapes: Vec<Vec<Vec<[f64; 2]>>> = spine_points.stroke(style, false);
+
+    if let Some(first_shape) = shapes.first() {
+        if let Some(first_contour) = first_shape.first() {
+            return first_contour.iter().map(array_to_geo_point).collect();
+        }
     }
+
+    log::warn!("Empty contour for path.");
+    vec![]
 }
 
 fn gds_to_geo_point(p: &GdsPoint) -> Vec2d {
@@ -0,0 +1,89 @@
+use anyhow::anyhow;
+use anyhow::Result;
+use geo::AffineTransform;
+use geo::Coord;
+
+/// Position of a layout that is overlaid on another one, in the database
+/// units of the layout underneath. Mirroring is applied first, then
+/// rotation about the origin, then the offset, as in GDSII references.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LayoutPlacement {
+    pub dx: f64,
+    pub dy: f64,
+    /// Counterclockwise rotation in degrees.
+    pub angle: f64,
+    /// Mirrors about the x axis.
+    pub mirrored: bool,
+}
+
+impl LayoutPlacement {
+    /// Parses `DX,DY[,ANGLE][,M]`, where a trailing `M` mirrors the layout.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut fields: Vec<&str> = text.split(',').map(str::trim).collect();
+        let mirrored = fields
+            .last()
+            .is_some_and(|field| field.eq_ignore_ascii_case("m"));
+        if mirrored {
+            fields.pop();
+        }
+        let number = |field: &str| {
+            field
+                .parse::<f64>()
+                .map_err(|_| anyhow!("Invalid number '{}' in placement '{}'", field, text))
+        };
+        let (dx, dy, angle) = match fields.as_slice() {
+            [dx, dy] => (number(dx)?, number(dy)?, 0.0),
+            [dx, dy, angle] => (number(dx)?, number(dy)?, number(angle)?),
+            _ => {
+                return Err(anyhow!(
+                    "Expected DX,DY[,ANGLE][,M] for the placement, got '{}'",
+                    text
+                ))
+            }
+        };
+        Ok(Self {
+            dx,
+            dy,
+            angle,
+            mirrored,
+        })
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Transform from the overlaid layout into the one underneath.
+    pub fn transform(&self) -> AffineTransform {
+        let scale = match self.mirrored {
+            true => AffineTransform::scale(1.0, -1.0, Coord::zero()),
+            false => AffineTransform::identity(),
+        };
+        let rotate = AffineTransform::rotate(self.angle, Coord::zero());
+        let translate = AffineTransform::translate(self.dx, self.dy);
+        scale.compose(&rotate).compose(&translate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_placement() {
+        let placement = LayoutPlacement::parse("100, -50, 90, M").unwrap();
+        assert_eq!(
+            placement,
+            LayoutPlacement {
+                dx: 100.0,
+                dy: -50.0,
+                angle: 90.0,
+                mirrored: true,
+            }
+        );
+        let point = placement.transform().apply(Coord { x: 10.0, y: 0.0 });
+        assert!((point.x - 100.0).abs() < 1e-9 && (point.y + 40.0).abs() < 1e-9);
+        assert!(LayoutPlacement::parse("0,0").unwrap().is_identity());
+        assert!(LayoutPlacement::parse("1,2,3,4").is_err());
+    }
+}
@@ -0,0 +1,440 @@
+use crate::core::CellDefId;
+use crate::Project;
+use gds21::GdsPoint;
+use geo::AffineTransform;
+use geo::Coord;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+/// Edge directions allowed on a layer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EdgeAngles {
+    #[default]
+    Any,
+    /// Horizontal, vertical and 45 degree edges.
+    Octilinear,
+    /// Horizontal and vertical edges only.
+    Manhattan,
+}
+
+/// Options for [Project::lint].
+#[derive(Debug, Clone)]
+pub struct LintOptions {
+    /// Manufacturing grid in database units. Every vertex must be a multiple
+    /// of it.
+    pub grid: i32,
+    /// Corners sharper than this many degrees are reported.
+    pub min_angle: f64,
+    /// Edge directions allowed on specific layers, all directions are allowed
+    /// on layers not listed here.
+    pub edge_angles: HashMap<i16, EdgeAngles>,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        Self {
+            grid: 1,
+            min_angle: 90.0,
+            edge_angles: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LintKind {
+    OffGrid,
+    AcuteAngle,
+    DisallowedAngle,
+    SelfIntersection,
+}
+
+impl LintKind {
+    pub const ALL: [LintKind; 4] = [
+        LintKind::OffGrid,
+        LintKind::AcuteAngle,
+        LintKind::DisallowedAngle,
+        LintKind::SelfIntersection,
+    ];
+}
+
+impl Display for LintKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            LintKind::OffGrid => "off-grid vertex",
+            LintKind::AcuteAngle => "acute angle",
+            LintKind::DisallowedAngle => "disallowed edge angle",
+            LintKind::SelfIntersection => "self-intersection",
+        };
+        f.write_str(text)
+    }
+}
+
+/// Element of a [CellDef](crate::core::CellDef) that an issue was found in,
+/// by the index of its shape within the cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintElement {
+    Boundary(usize),
+    Path(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct LintIssue {
+    pub kind: LintKind,
+    pub layer: i16,
+    pub element: LintElement,
+    /// Location in the coordinate system of the cell.
+    pub location: Coord<f64>,
+}
+
+/// Issues found in the elements of a single cell.
+#[derive(Debug, Clone)]
+pub struct CellLint {
+    pub cell_def_id: CellDefId,
+    pub issues: Vec<LintIssue>,
+}
+
+impl CellLint {
+    pub fn count(&self, kind: LintKind) -> usize {
+        self.issues
+            .iter()
+            .filter(|issue| issue.kind == kind)
+            .count()
+    }
+}
+
+impl Project {
+    /// Checks the boundary and path elements of every cell for geometry that
+    /// foundries typically reject. Each cell is checked once regardless of
+    /// how often it is instanced. Only cells with issues are returned.
+    pub fn lint(&self, options: &LintOptions) -> Vec<CellLint> {
+        let mut results = Vec::new();
+        for cell_def_id in self.cell_def_ids() {
+            let cell_def = self.cell_def(cell_def_id);
+            let mut issues = Vec::new();
+            for (index, shape) in cell_def.shapes.iter().enumerate() {
+                let element = match shape.path {
+                    Some(_) => LintElement::Path(index),
+                    None => LintElement::Boundary(index),
+                };
+                let mut add = |kind, location| {
+                    issues.push(LintIssue {
+                        kind,
+                        layer: shape.layer,
+                        element,
+                        location,
+                    })
+                };
+                let angles = options.edge_angles.get(&shape.layer).copied();
+                match &shape.path {
+                    Some(path) => {
+                        let points = dedup_points(&path.xy);
+                        check_points(
+                            &points,
+                            false,
+                            options,
+                            angles.unwrap_or_default(),
+                            &mut add,
+                        );
+                    }
+                    None => {
+                        let points = ring_points(&shape.boundary_xy());
+                        check_points(&points, true, options, angles.unwrap_or_default(), &mut add);
+                        if let Some(location) = find_self_intersection(&points) {
+                            add(LintKind::SelfIntersection, location);
+                        }
+                    }
+                }
+            }
+            if !issues.is_empty() {
+                results.push(CellLint {
+                    cell_def_id,
+                    issues,
+                });
+            }
+        }
+        results
+    }
+
+    /// Maps the issues of each cell to the top level coordinate system, once
+    /// for every placement of the cell, returning at most `limit` locations.
+    pub fn lint_locations(
+        &self,
+        results: &[CellLint],
+        limit: usize,
+    ) -> Vec<(LintKind, Coord<f64>)> {
+        let targets: HashSet<CellDefId> = results.iter().map(|r| r.cell_def_id).collect();
+        let mut placements: HashMap<CellDefId, Vec<AffineTransform>> = HashMap::new();
+        let mut contains_target = HashMap::new();
+        let mut budget = limit;
+        for root in self.top_cells() {
+            self.collect_placements(
+                root,
+                &AffineTransform::identity(),
+                &targets,
+                &mut contains_target,
+                &mut placements,
+                &mut budget,
+            );
+        }
+
+        let mut locations = Vec::new();
+        for result in results {
+            for transform in placements.get(&result.cell_def_id).into_iter().flatten() {
+                for issue in &result.issues {
+                    if locations.len() >= limit {
+                        return locations;
+                    }
+                    locations.push((issue.kind, transform.apply(issue.location)));
+                }
+            }
+        }
+        locations
+    }
+
+    fn collect_placements(
+        &self,
+        cell_def_id: CellDefId,
+        transform: &AffineTransform,
+        targets: &HashSet<CellDefId>,
+        contains_target: &mut HashMap<CellDefId, bool>,
+        placements: &mut HashMap<CellDefId, Vec<AffineTransform>>,
+        budget: &mut usize,
+    ) {
+        if *budget == 0 || !self.contains_target(cell_def_id, targets, contains_target) {
+            return;
+        }
+        if targets.contains(&cell_def_id) {
+            placements.entry(cell_def_id).or_default().push(*transform);
+            *budget -= 1;
+        }
+        for &cell_id in &self.cell_def(cell_def_id).cell_elements {
+            let cell = self.cell(cell_id);
+            for element in cell.elements() {
+                let child_transform = cell.element_placement(element).compose(transform);
+                self.collect_placements(
+                    cell.cell_def_id,
+                    &child_transform,
+                    targets,
+                    contains_target,
+                    placements,
+                    budget,
+                );
+            }
+        }
+    }
+
+    /// Returns whether the cell or any of its descendants is one of the
+    /// targets, so that unrelated parts of the hierarchy can be skipped.
+    fn contains_target(
+        &self,
+        cell_def_id: CellDefId,
+        targets: &HashSet<CellDefId>,
+        memo: &mut HashMap<CellDefId, bool>,
+    ) -> bool {
+        if let Some(&result) = memo.get(&cell_def_id) {
+            return result;
+        }
+        let result = targets.contains(&cell_def_id)
+            || self
+                .cell_def(cell_def_id)
+                .cell_elements
+                .iter()
+                .any(|&cell_id| {
+                    self.contains_target(self.cell(cell_id).cell_def_id, targets, memo)
+                });
+        memo.insert(cell_def_id, result);
+        result
+    }
+}
+
+type IntPoint = (i64, i64);
+
+fn dedup_points(xy: &[GdsPoint]) -> Vec<IntPoint> {
+    let mut points: Vec<IntPoint> = xy.iter().map(|p| (p.x as i64, p.y as i64)).collect();
+    points.dedup();
+    points
+}
+
+/// Returns the vertices of a boundary without the closing point.
+fn ring_points(xy: &[GdsPoint]) -> Vec<IntPoint> {
+    let mut points = dedup_points(xy);
+    if points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    points
+}
+
+fn to_coord((x, y): IntPoint) -> Coord<f64> {
+    Coord {
+        x: x as f64,
+        y: y as f64,
+    }
+}
+
+/// Checks vertex positions, corner angles and edge directions of a ring or
+/// an open polyline.
+fn check_points(
+    points: &[IntPoint],
+    closed: bool,
+    options: &LintOptions,
+    edge_angles: EdgeAngles,
+    add: &mut impl FnMut(LintKind, Coord<f64>),
+) {
+    let grid = options.grid.max(1) as i64;
+    for &point in points {
+        if point.0 % grid != 0 || point.1 % grid != 0 {
+            add(LintKind::OffGrid, to_coord(point));
+        }
+    }
+
+    let n = points.len();
+    let edge_count = if closed { n } else { n.saturating_sub(1) };
+    if n < 2 || (closed && n < 3) {
+        return;
+    }
+    for i in 0..edge_count {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let allowed = match edge_angles {
+            EdgeAngles::Any => true,
+            EdgeAngles::Octilinear => dx == 0 || dy == 0 || dx.abs() == dy.abs(),
+            EdgeAngles::Manhattan => dx == 0 || dy == 0,
+        };
+        if !allowed {
+            let middle = (to_coord(a) + to_coord(b)) / 2.0;
+            add(LintKind::DisallowedAngle, middle);
+        }
+    }
+
+    let corners = if closed { 0..n } else { 1..n - 1 };
+    for i in corners {
+        let previous = points[(i + n - 1) % n];
+        let current = points[i];
+        let next = points[(i + 1) % n];
+        if corner_angle(previous, current, next) < options.min_angle {
+            add(LintKind::AcuteAngle, to_coord(current));
+        }
+    }
+}
+
+/// Angle in degrees between the two edges meeting at a vertex, from 0 for a
+/// spike to 180 for a straight line.
+fn corner_angle(previous: IntPoint, current: IntPoint, next: IntPoint) -> f64 {
+    let u = to_coord(previous) - to_coord(current);
+    let v = to_coord(next) - to_coord(current);
+    let cross = u.x * v.y - u.y * v.x;
+    let dot = u.x * v.x + u.y * v.y;
+    cross.abs().atan2(dot).to_degrees()
+}
+
+/// Finds a point where two non-adjacent edges of a ring cross or touch.
+fn find_self_intersection(points: &[IntPoint]) -> Option<Coord<f64>> {
+    let n = points.len();
+    if n < 4 {
+        return None;
+    }
+    for i in 0..n {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        for j in i + 2..n {
+            // The last edge is adjacent to the first one.
+            if i == 0 && j == n - 1 {
+                continue;
+            }
+            let (c, d) = (points[j], points[(j + 1) % n]);
+            if segments_intersect(a, b, c, d) {
+                return Some(intersection_point(a, b, c, d));
+            }
+        }
+    }
+    None
+}
+
+fn orientation(a: IntPoint, b: IntPoint, c: IntPoint) -> i64 {
+    ((b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)).signum()
+}
+
+fn on_segment(a: IntPoint, b: IntPoint, p: IntPoint) -> bool {
+    p.0 >= a.0.min(b.0) && p.0 <= a.0.max(b.0) && p.1 >= a.1.min(b.1) && p.1 <= a.1.max(b.1)
+}
+
+fn segments_intersect(a: IntPoint, b: IntPoint, c: IntPoint, d: IntPoint) -> bool {
+    let o1 = orientation(a, b, c);
+    let o2 = orientation(a, b, d);
+    let o3 = orientation(c, d, a);
+    let o4 = orientation(c, d, b);
+    if o1 != o2 && o3 != o4 && o1 * o2 <= 0 && o3 * o4 <= 0 {
+        return true;
+    }
+    (o1 == 0 && on_segment(a, b, c))
+        || (o2 == 0 && on_segment(a, b, d))
+        || (o3 == 0 && on_segment(c, d, a))
+        || (o4 == 0 && on_segment(c, d, b))
+}
+
+/// Intersection of the lines through two segments, or an endpoint of the
+/// second segment when they are parallel.
+fn intersection_point(a: IntPoint, b: IntPoint, c: IntPoint, d: IntPoint) -> Coord<f64> {
+    let (a, b, c, d) = (to_coord(a), to_coord(b), to_coord(c), to_coord(d));
+    let r = b - a;
+    let s = d - c;
+    let denominator = r.x * s.y - r.y * s.x;
+    if denominator == 0.0 {
+        return c;
+    }
+    let t = ((c.x - a.x) * s.y - (c.y - a.y) * s.x) / denominator;
+    a + r * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_corner_and_edge_issues() {
+        // A triangle with a 45 degree corner at the origin, off the grid of 10
+        // at one vertex.
+        let points = vec![(0, 0), (100, 0), (100, 105)];
+        let options = LintOptions {
+            grid: 10,
+            ..LintOptions::default()
+        };
+        let mut issues = Vec::new();
+        check_points(
+            &points,
+            true,
+            &options,
+            EdgeAngles::Manhattan,
+            &mut |kind, _| issues.push(kind),
+        );
+        assert_eq!(
+            issues.iter().filter(|k| **k == LintKind::OffGrid).count(),
+            1
+        );
+        assert_eq!(
+            issues
+                .iter()
+                .filter(|k| **k == LintKind::AcuteAngle)
+                .count(),
+            2
+        );
+        assert_eq!(
+            issues
+                .iter()
+                .filter(|k| **k == LintKind::DisallowedAngle)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_detects_bowtie() {
+        let bowtie = vec![(0, 0), (10, 10), (10, 0), (0, 10)];
+        let location = find_self_intersection(&bowtie).unwrap();
+        assert_eq!(location, Coord { x: 5.0, y: 5.0 });
+        let square = vec![(0, 0), (10, 0), (10, 10), (0, 10)];
+        assert_eq!(find_self_intersection(&square), None);
+    }
+}
@@ -0,0 +1,97 @@
+use crate::Project;
+use geo::Coord;
+
+/// A distance measured between two points, in database units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ruler {
+    pub start: Coord<f64>,
+    pub end: Coord<f64>,
+}
+
+impl Ruler {
+    pub fn length(&self) -> f64 {
+        let delta = self.end - self.start;
+        delta.x.hypot(delta.y)
+    }
+}
+
+impl Project {
+    /// Formats a length given in database units as a physical distance, in
+    /// nanometers, micrometers or millimeters depending on its size. Falls
+    /// back to database units when the library does not specify them.
+    pub fn format_length(&self, length: f64) -> String {
+        format_length(length, self.units().db_unit())
+    }
+
+    /// Formats an area given in square database units, see [format_area].
+    pub fn format_area(&self, area: f64) -> String {
+        format_area(area, self.units().db_unit())
+    }
+}
+
+/// Formats a length given in database units of the given size in meters.
+pub fn format_length(length: f64, db_unit: f64) -> String {
+    if !(db_unit.is_finite() && db_unit > 0.0) {
+        return format!("{} dbu", trim_number(length, 0));
+    }
+    let meters = length * db_unit;
+    let (value, unit) = if meters < 1e-6 {
+        (meters * 1e9, "nm")
+    } else if meters < 1e-3 {
+        (meters * 1e6, "um")
+    } else {
+        (meters * 1e3, "mm")
+    };
+    // Three significant digits is plenty for reading off the viewer, but
+    // never drop digits before the decimal point.
+    let decimals = if value >= 100.0 {
+        0
+    } else if value >= 10.0 {
+        1
+    } else {
+        2
+    };
+    format!("{} {}", trim_number(value, decimals), unit)
+}
+
+/// Formats an area given in square database units, in the square of the
+/// unit that [format_length] would pick for its side.
+pub fn format_area(area: f64, db_unit: f64) -> String {
+    if !(db_unit.is_finite() && db_unit > 0.0) {
+        return format!("{} dbu²", area);
+    }
+    let square_meters = area * db_unit * db_unit;
+    let (value, unit) = if square_meters < 1e-12 {
+        (square_meters * 1e18, "nm²")
+    } else if square_meters < 1e-6 {
+        (square_meters * 1e12, "um²")
+    } else {
+        (square_meters * 1e6, "mm²")
+    };
+    let text = format!("{:.3}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    format!("{} {}", text, unit)
+}
+
+fn trim_number(value: f64, decimals: usize) -> String {
+    let text = format!("{:.*}", decimals, value);
+    if text.contains('.') {
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_lengths() {
+        assert_eq!(format_length(130.0, 1e-9), "130 nm");
+        assert_eq!(format_length(1500.0, 1e-9), "1.5 um");
+        assert_eq!(format_length(12345.0, 1e-9), "12.3 um");
+        assert_eq!(format_length(2.5e6, 1e-9), "2.5 mm");
+        assert_eq!(format_length(42.0, 0.0), "42 dbu");
+    }
+}
@@ -1,7 +1,44 @@
 mod cells;
+mod connectivity;
+mod cross_section;
+mod density;
+mod derived_layers;
+mod drc;
+mod edit;
+mod fill;
+mod flatten;
+mod hierarchy;
+mod inspect;
+mod instance_path;
 mod layer;
+mod layout_placement;
+mod lint;
+mod measure;
+mod polygon_ops;
 mod project;
+mod rule_file;
+mod search;
+#[cfg(test)]
+pub(crate) mod test_layouts;
+mod usage;
 
 pub use cells::*;
+pub use connectivity::*;
+pub use cross_section::*;
+pub use density::*;
+pub use derived_layers::*;
+pub use drc::*;
+pub use edit::*;
+pub use fill::*;
+pub use flatten::*;
+pub use hierarchy::*;
+pub use inspect::*;
 pub use layer::*;
+pub use layout_placement::*;
+pub use lint::*;
+pub use measure::*;
+pub use polygon_ops::*;
 pub use project::*;
+pub use rule_file::*;
+pub use search::*;
+pub use usage::*;
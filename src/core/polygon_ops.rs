@@ -0,0 +1,282 @@
+use geo::orient::Direction;
+use geo::BoundingRect;
+use geo::Coord;
+use geo::Line;
+use geo::LineString;
+use geo::Orient;
+use geo::Rect;
+use i_overlay::core::fill_rule::FillRule;
+use i_overlay::core::overlay_rule::OverlayRule;
+use i_overlay::float::single::SingleFloatOverlay;
+use i_overlay::mesh::outline::offset::OutlineOffset;
+use i_overlay::mesh::style::LineJoin;
+use i_overlay::mesh::style::OutlineStyle;
+
+type Polygon = geo::Polygon<f64>;
+type Contour = Vec<[f64; 2]>;
+
+/// Length of the segments approximating round corners, relative to their
+/// radius.
+const ROUND_CORNER_STEP: f64 = 0.1;
+
+/// Merges overlapping and abutting polygons into a minimal set of polygons,
+/// which may contain holes.
+pub fn union_polygons(polygons: &[Polygon]) -> Vec<Polygon> {
+    overlay(polygons, &[], OverlayRule::Union)
+}
+
+/// Intersects one set of polygons with another.
+pub fn intersect_polygons(subject: &[Polygon], clip: &[Polygon]) -> Vec<Polygon> {
+    overlay(subject, clip, OverlayRule::Intersect)
+}
+
+/// Removes the clip polygons from the subject polygons.
+pub fn difference_polygons(subject: &[Polygon], clip: &[Polygon]) -> Vec<Polygon> {
+    overlay(subject, clip, OverlayRule::Difference)
+}
+
+/// Keeps the areas covered by exactly one of the two sets of polygons.
+pub fn xor_polygons(subject: &[Polygon], clip: &[Polygon]) -> Vec<Polygon> {
+    overlay(subject, clip, OverlayRule::Xor)
+}
+
+/// How corners are treated when polygons are grown or shrunk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CornerStyle {
+    /// Corners stay sharp, as if each edge was moved outwards.
+    #[default]
+    Square,
+    /// Corners are rounded with an arc of the sizing distance.
+    Round,
+    /// Corners are cut off diagonally, which turns the corners of Manhattan
+    /// shapes into 45 degree edges. The cut of a right angle touches the arc
+    /// of [CornerStyle::Round], so that the sizing distance is kept in every
+    /// direction.
+    Octagonal,
+}
+
+/// Grows polygons outwards by the given distance, or shrinks them for a
+/// negative distance. Corners are kept square. Overlapping polygons are
+/// merged first so that shared edges do not offset into each other.
+pub fn offset_polygons(polygons: &[Polygon], distance: f64) -> Vec<Polygon> {
+    size_polygons(polygons, distance, CornerStyle::Square)
+}
+
+/// Like [offset_polygons], with the given treatment of corners that move
+/// away from the original shape: convex corners when growing and concave
+/// corners when shrinking.
+pub fn size_polygons(polygons: &[Polygon], distance: f64, corners: CornerStyle) -> Vec<Polygon> {
+    if distance == 0.0 {
+        return union_polygons(polygons);
+    }
+    // The outline builder expects clockwise exteriors and counter-clockwise
+    // holes, the opposite of the convention used by boolean operations.
+    let shapes: Vec<Vec<Contour>> = union_polygons(polygons)
+        .iter()
+        .map(|polygon| {
+            let polygon = polygon.orient(Direction::Reversed);
+            std::iter::once(polygon.exterior())
+                .chain(polygon.interiors())
+                .map(line_string_to_contour)
+                .collect()
+        })
+        .collect();
+    let join = match corners {
+        CornerStyle::Square => LineJoin::Miter(0.01),
+        CornerStyle::Round => LineJoin::Round(ROUND_CORNER_STEP),
+        // A miter limited to 135 degrees is cut off at tan(22.5°) times the
+        // distance along each edge, which for right angles is where the cut
+        // touches the arc. A bevel would cut across the corner of the offset
+        // edges, at only 0.71 times the distance from the original corner.
+        CornerStyle::Octagonal => LineJoin::Miter(0.75 * std::f64::consts::PI),
+    };
+    let style = OutlineStyle::new(distance).line_join(join);
+    shapes_to_polygons(shapes.outline(style))
+}
+
+/// Splits polygons with holes into several polygons without holes, since
+/// GDSII boundaries cannot represent holes. Each hole is opened by cutting
+/// the polygon along a vertical line through it.
+pub fn split_holes(polygon: &Polygon) -> Vec<Polygon> {
+    let Some(hole) = polygon.interiors().first() else {
+        return vec![polygon.clone()];
+    };
+    let Some(hole_bounds) = hole.bounding_rect() else {
+        return vec![polygon.clone()];
+    };
+    let split_x = (hole_bounds.min().x + hole_bounds.max().x) / 2.0;
+    cut_polygon(polygon, true, split_x)
+        .iter()
+        .flat_map(split_holes)
+        .collect()
+}
+
+/// Splits a polygon without holes into pieces with at most the given number
+/// of points, counting the closing point, by cutting it in half across its
+/// longer side. Cuts are made on whole coordinates.
+pub fn split_points(polygon: &Polygon, max_points: usize) -> Vec<Polygon> {
+    if polygon.exterior().0.len() <= max_points {
+        return vec![polygon.clone()];
+    }
+    let Some(bounds) = polygon.bounding_rect() else {
+        return vec![polygon.clone()];
+    };
+    let vertical = bounds.width() >= bounds.height();
+    let at = match vertical {
+        true => bounds.center().x.round(),
+        false => bounds.center().y.round(),
+    };
+    let pieces = cut_polygon(polygon, vertical, at);
+    if pieces.len() < 2 {
+        // Too narrow to cut on whole coordinates.
+        return pieces;
+    }
+    pieces
+        .iter()
+        .flat_map(split_holes)
+        .flat_map(|piece| split_points(&piece, max_points))
+        .collect()
+}
+
+/// Cuts a polygon in two along a vertical or horizontal line.
+fn cut_polygon(polygon: &Polygon, vertical: bool, at: f64) -> Vec<Polygon> {
+    let Some(bounds) = polygon.bounding_rect() else {
+        return vec![polygon.clone()];
+    };
+    let (min, max) = (
+        bounds.min() - (1.0, 1.0).into(),
+        bounds.max() + (1.0, 1.0).into(),
+    );
+    let halves = match vertical {
+        true => [
+            Rect::new(min, Coord { x: at, y: max.y }),
+            Rect::new(Coord { x: at, y: min.y }, max),
+        ],
+        false => [
+            Rect::new(min, Coord { x: max.x, y: at }),
+            Rect::new(Coord { x: min.x, y: at }, max),
+        ],
+    };
+    let subject = std::slice::from_ref(polygon);
+    halves
+        .iter()
+        .flat_map(|half| intersect_polygons(subject, &[half.to_polygon()]))
+        .collect()
+}
+
+/// Returns the point on the line segment closest to the given point.
+pub fn closest_point_on_line(point: Coord<f64>, line: &Line<f64>) -> Coord<f64> {
+    let delta = line.delta();
+    let length_squared = delta.x * delta.x + delta.y * delta.y;
+    if length_squared == 0.0 {
+        return line.start;
+    }
+    let offset = point - line.start;
+    let t = ((offset.x * delta.x + offset.y * delta.y) / length_squared).clamp(0.0, 1.0);
+    line.start + delta * t
+}
+
+fn overlay(subject: &[Polygon], clip: &[Polygon], rule: OverlayRule) -> Vec<Polygon> {
+    let subject = polygons_to_contours(subject);
+    let clip = polygons_to_contours(clip);
+    shapes_to_polygons(subject.overlay(&clip, rule, FillRule::NonZero))
+}
+
+fn shapes_to_polygons(shapes: Vec<Vec<Contour>>) -> Vec<Polygon> {
+    shapes
+        .into_iter()
+        .filter_map(|shape| {
+            let mut contours = shape.into_iter().map(contour_to_line_string);
+            let exterior = contours.next()?;
+            Some(Polygon::new(exterior, contours.collect()))
+        })
+        .collect()
+}
+
+/// Converts polygons into contours for i_overlay. The non-zero fill rule
+/// requires holes to wind opposite to exteriors, so the polygons are
+/// oriented first.
+fn polygons_to_contours(polygons: &[Polygon]) -> Vec<Contour> {
+    let mut contours = Vec::with_capacity(polygons.len());
+    for polygon in polygons {
+        let polygon = polygon.orient(Direction::Default);
+        contours.push(line_string_to_contour(polygon.exterior()));
+        for interior in polygon.interiors() {
+            contours.push(line_string_to_contour(interior));
+        }
+    }
+    contours
+}
+
+fn line_string_to_contour(ring: &LineString<f64>) -> Contour {
+    let mut contour: Contour = ring.coords().map(|c| [c.x, c.y]).collect();
+    // i_overlay expects open contours.
+    if contour.len() > 1 && contour.first() == contour.last() {
+        contour.pop();
+    }
+    contour
+}
+
+fn contour_to_line_string(contour: Contour) -> LineString<f64> {
+    LineString::from(contour.into_iter().map(|[x, y]| (x, y)).collect::<Vec<_>>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::Area;
+
+    fn square(min: f64, max: f64) -> LineString<f64> {
+        LineString::from(vec![
+            (min, min),
+            (max, min),
+            (max, max),
+            (min, max),
+            (min, min),
+        ])
+    }
+
+    #[test]
+    fn test_split_holes_preserves_area() {
+        let polygon = Polygon::new(square(0.0, 10.0), vec![square(4.0, 6.0)]);
+        let pieces = split_holes(&polygon);
+        assert!(pieces.len() >= 2);
+        assert!(pieces.iter().all(|piece| piece.interiors().is_empty()));
+        let area: f64 = pieces.iter().map(|piece| piece.unsigned_area()).sum();
+        assert!((area - 96.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_union_merges_overlapping_squares() {
+        let a = Polygon::new(square(0.0, 2.0), vec![]);
+        let b = Polygon::new(square(1.0, 3.0), vec![]);
+        let merged = union_polygons(&[a, b]);
+        assert_eq!(merged.len(), 1);
+        assert!((merged[0].unsigned_area() - 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_offset_grows_and_shrinks_squares() {
+        let polygon = Polygon::new(square(0.0, 10.0), vec![]);
+        let grown = offset_polygons(std::slice::from_ref(&polygon), 1.0);
+        assert_eq!(grown.len(), 1);
+        assert!((grown[0].unsigned_area() - 144.0).abs() < 1e-6);
+        let shrunk = offset_polygons(&[polygon], -1.0);
+        assert_eq!(shrunk.len(), 1);
+        assert!((shrunk[0].unsigned_area() - 64.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_sizing_corner_styles() {
+        let polygon = [Polygon::new(square(0.0, 10.0), vec![])];
+        let octagonal = size_polygons(&polygon, 1.0, CornerStyle::Octagonal);
+        // Each corner loses a triangle whose legs are 1 - tan(22.5°).
+        let leg = 1.0 - (std::f64::consts::PI / 8.0).tan();
+        assert!((octagonal[0].unsigned_area() - (144.0 - 2.0 * leg * leg)).abs() < 1e-6);
+        // The arcs are approximated by segments, so the area is slightly
+        // less than that of a rounded square.
+        let round = size_polygons(&polygon, 1.0, CornerStyle::Round);
+        let area = round[0].unsigned_area();
+        assert!(area < 140.0 + std::f64::consts::PI && area > 143.0);
+    }
+}
@@ -1,9 +1,21 @@
+use crate::core::append_derived_layers;
+use crate::core::boundary_polygon;
+use crate::core::closest_point_on_line;
+use crate::core::path_polygon;
+use crate::core::transform_bounds;
 use crate::core::ArrayProperties;
 use crate::core::Cell;
 use crate::core::CellDef;
 use crate::core::CellDefId;
 use crate::core::CellId;
+use crate::core::DerivedLayer;
+use crate::core::EditHistory;
+use crate::core::ElementMetadata;
+use crate::core::InstanceStep;
+use crate::core::Label;
 use crate::core::Layer;
+use crate::core::PathSpine;
+use crate::core::Shape;
 use crate::graphics::BoundingBox;
 use crate::rsutils::hsv_to_rgb;
 use crate::rsutils::IdMap;
@@ -13,17 +25,22 @@ use anyhow::Result;
 use gds21::GdsLibrary;
 use gds21::GdsPoint;
 use gds21::GdsStrans;
+use gds21::GdsUnits;
+use geo::AffineOps;
 use geo::AffineTransform;
+use geo::BoundingRect;
 use geo::Contains;
 use geo::Coord;
 use geo::Point;
 use indexmap::IndexMap;
+use indexmap::IndexSet;
 use nalgebra::Vector4;
-use rstar::Envelope;
-use rstar::PointDistance;
+use rstar::primitives::GeomWithData;
+use rstar::primitives::Rectangle;
 use rstar::RTree;
-use rstar::RTreeObject;
 use rstar::AABB;
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fmt::Debug;
 use std::fmt::Formatter;
 use std::fmt::{self};
@@ -37,7 +54,32 @@ pub struct Project {
     stats: LayoutStats,
     interner: StringInterner,
     bounds: BoundingBox,
-    rtree: RTree<PickResult>,
+    /// Placements of every drawn cell that has shapes of its own, see
+    /// [Project::placements].
+    placements: Vec<Placement>,
+    /// Every drawn instance in depth-first order, which placements and
+    /// picks refer to for their instance paths.
+    instance_nodes: Vec<InstanceNode>,
+    placement_tree: RTree<GeomWithData<Rectangle<[f64; 2]>, usize>>,
+    /// Whether [Layer::polygons] are filled in, see [Project::flatten_layers].
+    flattened: bool,
+    /// For each placement, the index in [Layer::polygons] of its first shape
+    /// on each layer that it draws on. Only filled in while flattened.
+    flat_offsets: Vec<Vec<(i16, usize)>>,
+    library_name: String,
+    units: GdsUnits,
+    pub(crate) derived_layers: Vec<DerivedLayer>,
+    /// Cell shown in place of the top cells, see [Project::set_context].
+    context: Option<CellDefId>,
+    /// Instances nested deeper than this are drawn as placeholders, see
+    /// [Project::set_display_depth].
+    display_depth: Option<usize>,
+    placeholders: Vec<Placeholder>,
+    placeholder_rtree: RTree<GeomWithData<Rectangle<[f64; 2]>, usize>>,
+    /// Cells that were not referenced when the file was loaded. Instances
+    /// deleted by an edit do not turn their cells into new top cells.
+    roots: Vec<CellDefId>,
+    pub(crate) history: EditHistory,
 }
 
 impl Project {
@@ -47,6 +89,10 @@ impl Project {
         let library =
             GdsLibrary::from_bytes(data).map_err(|e| anyhow!("Failed to parse GDSII: {}", e))?;
 
+        Ok(Self::from_library(&library))
+    }
+
+    pub fn from_library(library: &GdsLibrary) -> Project {
         let mut stats = LayoutStats {
             struct_count: library.structs.len(),
             polygon_count: 0,
@@ -87,17 +133,28 @@ impl Project {
         let add_cell = |cells: &mut IdMap<CellId, Cell>,
                         cell_defs: &mut IndexMap<CellDefId, CellDef>,
                         interner: &mut StringInterner,
+                        parent: CellDefId,
                         name: &str,
                         xy: &GdsPoint,
-                        strans: &Option<GdsStrans>| {
+                        strans: &Option<GdsStrans>,
+                        metadata: ElementMetadata| {
             let cell_def_id = CellDefId(interner.intern(name));
+            if let Some(strans) = strans {
+                if strans.mag.unwrap_or(1.0) != 1.0 {
+                    eprintln!("Magnification not supported.");
+                }
+                if strans.abs_mag || strans.abs_angle {
+                    eprintln!("Absolute transform not supported.");
+                }
+            }
             let cell = Cell {
                 cell_def_id,
+                parent,
                 xy: xy.clone(),
                 local_transform: strans.clone(),
                 visible: true,
-                world_transform: AffineTransform::identity(),
                 array: None,
+                metadata,
             };
             let cell_id = cells.insert(cell);
             cell_defs
@@ -116,6 +173,7 @@ impl Project {
         for cell in &library.structs {
             let cell_def_id = CellDefId(interner.intern(&cell.name));
             let mut cell_def = cell_defs.get(&cell_def_id).unwrap().clone();
+            cell_def.dates = cell.dates.clone();
             for elem in &cell.elems {
                 match elem {
                     gds21::GdsElement::GdsStructRef(sref) => {
@@ -123,9 +181,15 @@ impl Project {
                             &mut cells,
                             &mut cell_defs,
                             &mut interner,
+                            cell_def_id,
                             &sref.name,
                             &sref.xy,
                             &sref.strans,
+                            ElementMetadata {
+                                elflags: sref.elflags.clone(),
+                                plex: sref.plex.clone(),
+                                properties: sref.properties.clone(),
+                            },
                         ));
                     }
                     gds21::GdsElement::GdsArrayRef(aref) => {
@@ -133,9 +197,15 @@ impl Project {
                             &mut cells,
                             &mut cell_defs,
                             &mut interner,
+                            cell_def_id,
                             &aref.name,
                             &aref.xy[0],
                             &aref.strans,
+                            ElementMetadata {
+                                elflags: aref.elflags.clone(),
+                                plex: aref.plex.clone(),
+                                properties: aref.properties.clone(),
+                            },
                         );
 
                         cell_def.cell_elements.push(id);
@@ -147,35 +217,79 @@ impl Project {
                         let width = aref.xy[1].x as f64 - aref.xy[0].x as f64;
                         let height = aref.xy[2].y as f64 - aref.xy[0].y as f64;
 
-                        // TODO: array refs are not yet implemented, hide them for now
-
                         let cell = cells.get_mut(&id).unwrap();
-                        cell.visible = false;
                         cell.array = Some(ArrayProperties {
                             rows,
                             cols,
                             width,
                             height,
+                            xy: [aref.xy[0].clone(), aref.xy[1].clone(), aref.xy[2].clone()],
                         });
                     }
                     gds21::GdsElement::GdsBoundary(boundary) => {
-                        cell_def.boundary_elements.push(boundary.clone());
+                        if let Some(polygon) = boundary_polygon(boundary) {
+                            cell_def.shapes.push(Shape {
+                                layer: boundary.layer,
+                                datatype: boundary.datatype,
+                                polygon,
+                                path: None,
+                                metadata: ElementMetadata {
+                                    elflags: boundary.elflags.clone(),
+                                    plex: boundary.plex.clone(),
+                                    properties: boundary.properties.clone(),
+                                },
+                            });
+                        }
                     }
                     gds21::GdsElement::GdsPath(path) => {
-                        cell_def.path_elements.push(path.clone());
+                        if let Some(polygon) = path_polygon(path) {
+                            cell_def.shapes.push(Shape {
+                                layer: path.layer,
+                                datatype: path.datatype,
+                                polygon,
+                                path: Some(PathSpine {
+                                    xy: path.xy.clone(),
+                                    width: path.width,
+                                    path_type: path.path_type,
+                                    begin_extn: path.begin_extn,
+                                    end_extn: path.end_extn,
+                                }),
+                                metadata: ElementMetadata {
+                                    elflags: path.elflags.clone(),
+                                    plex: path.plex.clone(),
+                                    properties: path.properties.clone(),
+                                },
+                            });
+                        }
                     }
-                    gds21::GdsElement::GdsTextElem(_) => {
-                        // We do not support text elements yet, but they do
-                        // occur so let's not spam the console with warnings.
-                    }
-                    gds21::GdsElement::GdsNode(_) => {
-                        log::warn!("Node elements are not supported");
+                    gds21::GdsElement::GdsTextElem(text) => {
+                        // Text is not drawn, but kept so that it can be
+                        // searched for.
+                        cell_def.labels.push(Label {
+                            text: text.string.clone(),
+                            layer: text.layer,
+                            texttype: text.texttype,
+                            position: Coord {
+                                x: text.xy.x as f64,
+                                y: text.xy.y as f64,
+                            },
+                            presentation: text.presentation.clone(),
+                            path_type: text.path_type,
+                            width: text.width,
+                            strans: text.strans.clone(),
+                            metadata: ElementMetadata {
+                                elflags: text.elflags.clone(),
+                                plex: text.plex.clone(),
+                                properties: text.properties.clone(),
+                            },
+                        });
                     }
-                    gds21::GdsElement::GdsBox(_) => {
-                        log::warn!("Box elements are not supported");
+                    gds21::GdsElement::GdsNode(_) | gds21::GdsElement::GdsBox(_) => {
+                        cell_def.raw_elements.push(elem.clone());
                     }
                 }
             }
+            cell_def.update_shape_tree();
             cell_defs.insert(cell_def_id, cell_def);
         }
 
@@ -187,19 +301,32 @@ impl Project {
             layers: Vec::new(),
             highest_layer,
             bounds: BoundingBox::new(),
-            rtree: RTree::new(),
+            placements: Vec::new(),
+            instance_nodes: Vec::new(),
+            placement_tree: RTree::new(),
+            flattened: false,
+            flat_offsets: Vec::new(),
+            library_name: library.name.clone(),
+            units: library.units.clone(),
+            derived_layers: Vec::new(),
+            context: None,
+            display_depth: None,
+            placeholders: Vec::new(),
+            placeholder_rtree: RTree::new(),
+            roots: Vec::new(),
+            history: EditHistory::default(),
         };
 
-        let roots = project.find_roots();
-        for root in roots {
+        project.roots = project.find_roots();
+        for root in project.roots.clone() {
             let cell_def = project.cell_defs.get_mut(&root).unwrap();
             cell_def.root_instance = Some(project.cells.create_id());
         }
 
-        project.update_world_transforms();
+        project.update_cell_def_bounds();
         project.update_layers();
 
-        Ok(project)
+        project
     }
 
     pub fn stats(&self) -> &LayoutStats {
@@ -214,6 +341,53 @@ impl Project {
         self.interner.get(cell_def_id.0)
     }
 
+    /// Name of the GDSII library that this project was loaded from.
+    pub fn library_name(&self) -> &str {
+        &self.library_name
+    }
+
+    /// Database and user units of the GDSII library.
+    pub fn units(&self) -> &GdsUnits {
+        &self.units
+    }
+
+    pub fn find_cell_def(&self, name: &str) -> Option<CellDefId> {
+        let cell_def_id = CellDefId(self.interner.get_id(name)?);
+        self.cell_defs
+            .contains_key(&cell_def_id)
+            .then_some(cell_def_id)
+    }
+
+    pub(crate) fn cell(&self, cell_id: CellId) -> &Cell {
+        self.cells.get(&cell_id).unwrap()
+    }
+
+    /// Returns None for the root instances of top cells, which are not
+    /// stored.
+    pub(crate) fn find_cell(&self, cell_id: CellId) -> Option<&Cell> {
+        self.cells.get(&cell_id)
+    }
+
+    pub(crate) fn cell_mut(&mut self, cell_id: CellId) -> &mut Cell {
+        self.cells.get_mut(&cell_id).unwrap()
+    }
+
+    pub(crate) fn has_cell_def(&self, cell_def_id: CellDefId) -> bool {
+        self.cell_defs.contains_key(&cell_def_id)
+    }
+
+    pub(crate) fn cell_def(&self, cell_def_id: CellDefId) -> &CellDef {
+        &self.cell_defs[&cell_def_id]
+    }
+
+    pub(crate) fn cell_def_mut(&mut self, cell_def_id: CellDefId) -> &mut CellDef {
+        self.cell_defs.get_mut(&cell_def_id).unwrap()
+    }
+
+    pub(crate) fn cell_def_ids(&self) -> impl Iterator<Item = CellDefId> + '_ {
+        self.cell_defs.keys().copied()
+    }
+
     pub fn find_roots(&self) -> Vec<CellDefId> {
         self.cell_defs
             .iter()
@@ -222,65 +396,132 @@ impl Project {
             .collect()
     }
 
-    pub fn update_world_transforms(&mut self) {
-        let roots = self.find_roots();
-        let identity = &AffineTransform::identity();
-        for cell_def_id in roots {
-            let cell_ids = self.cell_defs[&cell_def_id].cell_elements.clone();
-            for cell_id in cell_ids {
-                self.update_world_transforms_recurse(cell_id, identity);
+    /// Returns the given cells along with every cell they reference, directly
+    /// or indirectly, without duplicates.
+    pub fn collect_dependencies(&self, cell_def_ids: &[CellDefId]) -> Vec<CellDefId> {
+        let mut result: IndexSet<CellDefId> = IndexSet::new();
+        let mut pending = cell_def_ids.to_vec();
+        while let Some(cell_def_id) = pending.pop() {
+            if !result.insert(cell_def_id) {
+                continue;
+            }
+            for cell_id in &self.cell_defs[&cell_def_id].cell_elements {
+                pending.push(self.cells.get(cell_id).unwrap().cell_def_id);
+            }
+        }
+        result.into_iter().collect()
+    }
+
+    /// Shows the given cell and its descendants as if it were the only top
+    /// cell, at its own origin, or the whole library for None. Rebuilds the
+    /// layers, keeping their visibility, color and merging.
+    pub fn set_context(&mut self, context: Option<CellDefId>) {
+        if let Some(cell_def_id) = context {
+            if self.cell_defs[&cell_def_id].root_instance.is_none() {
+                let root_instance = self.cells.create_id();
+                self.cell_defs.get_mut(&cell_def_id).unwrap().root_instance = Some(root_instance);
             }
         }
+        self.context = context;
+        self.refresh_layers();
     }
 
+    /// Returns the cell that is shown in place of the top cells, if any.
+    pub fn context(&self) -> Option<CellDefId> {
+        self.context
+    }
+
+    /// Cells drawn at the top level: the context if set, otherwise the roots.
+    pub fn top_cells(&self) -> Vec<CellDefId> {
+        match self.context {
+            Some(cell_def_id) => vec![cell_def_id],
+            None => self.roots.clone(),
+        }
+    }
+
+    /// Draws the full geometry of instances down to the given depth, and
+    /// every instance below that as a placeholder box. The top cells are at
+    /// depth 0. None draws everything.
+    pub fn set_display_depth(&mut self, depth: Option<usize>) {
+        self.display_depth = depth;
+        self.refresh_layers();
+    }
+
+    pub fn display_depth(&self) -> Option<usize> {
+        self.display_depth
+    }
+
+    /// Instances that were cut off by the display depth.
+    pub fn placeholders(&self) -> &[Placeholder] {
+        &self.placeholders
+    }
+
+    /// Placeholders that overlap the given bounds.
+    pub fn placeholders_in(&self, bounds: &BoundingBox) -> impl Iterator<Item = &Placeholder> {
+        let envelope =
+            AABB::from_corners([bounds.min_x, bounds.min_y], [bounds.max_x, bounds.max_y]);
+        self.placeholder_rtree
+            .locate_in_envelope_intersecting(&envelope)
+            .map(|item| &self.placeholders[item.data])
+    }
+
+    /// Finds the smallest placeholder containing the given point.
+    pub fn pick_placeholder(&self, x: f64, y: f64) -> Option<&Placeholder> {
+        self.placeholder_rtree
+            .locate_all_at_point(&[x, y])
+            .map(|item| &self.placeholders[item.data])
+            .min_by(|a, b| {
+                let area = |p: &Placeholder| p.bounds.width() * p.bounds.height();
+                area(a).total_cmp(&area(b))
+            })
+    }
+
+    /// Returns the cell of the innermost instance that a picked polygon
+    /// belongs to, or None if the polygon belongs to a top cell.
+    pub fn picked_cell_def(&self, pick: &PickResult) -> Option<CellDefId> {
+        Some(self.cells.get(&pick.cell_id)?.cell_def_id)
+    }
+
+    /// Rebuilds the layers and the placements of the drawn cells. Shapes
+    /// are only copied into the layers if they are flattened, see
+    /// [Self::flatten_layers].
     pub fn update_layers(&mut self) {
         self.layers.clear();
         for i in 0..=self.highest_layer {
             self.layers.push(Layer::new(i));
         }
 
-        let mut rtree_items = Vec::new();
-
+        let mut placements = Vec::new();
+        let mut placeholders = Vec::new();
+        let mut nodes = Vec::new();
         let identity = &AffineTransform::identity();
-        for cell_def_id in self.find_roots() {
-            let cell_def = self.cell_defs.get(&cell_def_id).unwrap();
-            let root_id = cell_def.root_instance.unwrap();
-            for boundary in &cell_def.boundary_elements {
-                let layer = &mut self.layers[boundary.layer as usize];
-                layer.add_boundary_element(boundary, identity);
-                rtree_items.push(PickResult {
-                    aabb: layer.polygons.last().unwrap().envelope(),
-                    layer: boundary.layer,
-                    polygon: layer.polygons.len() - 1,
-                    cell_id: root_id,
-                });
-            }
-            for path in &cell_def.path_elements {
-                let layer = &mut self.layers[path.layer as usize];
-                layer.add_path_element(path, identity);
-                rtree_items.push(PickResult {
-                    aabb: layer.polygons.last().unwrap().envelope(),
-                    layer: path.layer,
-                    polygon: layer.polygons.len() - 1,
-                    cell_id: root_id,
-                });
-            }
-            let cell_ids = self.cell_defs[&cell_def_id].cell_elements.clone();
-            for cell_id in cell_ids {
-                self.update_layers_recurse(cell_id, &mut rtree_items);
-            }
+        for cell_def_id in self.top_cells() {
+            let root_id = self.cell_defs[&cell_def_id].root_instance.unwrap();
+            self.update_layers_recurse(
+                root_id.into(),
+                cell_def_id,
+                identity,
+                None,
+                &mut nodes,
+                &mut placements,
+                &mut placeholders,
+            );
         }
+        self.placements = placements;
+        self.placeholders = placeholders;
+        self.instance_nodes = nodes;
+        self.update_shape_counts();
 
         let mut count = 0;
         for layer in &self.layers {
-            if !layer.polygons.is_empty() {
+            if !layer.is_empty() {
                 count += 1;
             }
         }
 
         let mut i = 0;
         for layer in &mut self.layers {
-            if layer.polygons.is_empty() {
+            if layer.is_empty() {
                 continue;
             }
             // Make the last layer white. To my eyes this looks somewhat better, aesthetically.
@@ -294,88 +535,373 @@ impl Project {
             i += 1;
         }
 
-        // Update bounds for each layer and the overall project
+        self.flat_offsets = Vec::new();
+        if self.flattened {
+            for placement in &self.placements {
+                let mut offsets: Vec<(i16, usize)> = Vec::new();
+                for shape in &self.cell_defs[&placement.cell_def_id].shapes {
+                    let layer = &mut self.layers[shape.layer as usize];
+                    if !offsets.iter().any(|&(index, _)| index == shape.layer) {
+                        offsets.push((shape.layer, layer.polygons.len()));
+                    }
+                    layer.add_polygon(&shape.polygon, &placement.transform);
+                }
+                self.flat_offsets.push(offsets);
+            }
+            append_derived_layers(&mut self.layers, &self.derived_layers, |_| true);
+        }
+
+        self.update_bounds();
+        self.placement_tree = RTree::bulk_load(
+            (0..self.placements.len())
+                .filter_map(|index| self.placement_item(index))
+                .collect(),
+        );
+        self.update_placeholder_rtree();
+    }
+
+    /// Updates the layers after an instance was moved, rotated or mirrored,
+    /// given the cells whose bounds changed as a result. Only the placements
+    /// below the instance are transformed again and their entries in the
+    /// spatial index replaced, rather than walking the whole hierarchy as
+    /// [Self::update_layers] does. Requires the layers not to be flattened.
+    pub(crate) fn update_moved_instance(&mut self, cell_id: CellId, stale: &HashSet<CellDefId>) {
+        // A cell placed more than once has an instance node per placement.
+        let moved: Vec<usize> = (0..self.instance_nodes.len())
+            .filter(|&node| self.instance_nodes[node].cell_id == cell_id)
+            .collect();
+        for node in moved {
+            let end = self.instance_nodes[node].end;
+            let parent = self.instance_nodes[node].parent.unwrap();
+            let parent_transform = self.node_transform(parent);
+            let mut transforms: Vec<AffineTransform> = Vec::with_capacity(end - node);
+            for current in &self.instance_nodes[node..end] {
+                let parent_transform = match current.parent {
+                    Some(parent) if parent >= node => &transforms[parent - node],
+                    _ => &parent_transform,
+                };
+                let transform = self
+                    .cell(current.cell_id)
+                    .element_placement(current.element)
+                    .compose(parent_transform);
+                transforms.push(transform);
+            }
+
+            let first = self.placements.partition_point(|p| p.node < node);
+            for index in first..self.placements.len() {
+                let current = self.placements[index].node;
+                if current >= end {
+                    break;
+                }
+                if let Some(item) = self.placement_item(index) {
+                    self.placement_tree.remove(&item);
+                }
+                self.placements[index].transform = transforms[current - node];
+                if let Some(item) = self.placement_item(index) {
+                    self.placement_tree.insert(item);
+                }
+            }
+            for placeholder in &mut self.placeholders {
+                if (node..end).contains(&placeholder.node) {
+                    if let Some(bounds) = &self.cell_defs[&placeholder.cell_def_id].bounds {
+                        let transform = &transforms[placeholder.node - node];
+                        placeholder.bounds = transform_bounds(bounds, transform);
+                    }
+                }
+            }
+        }
+        self.update_after_edit(stale);
+    }
+
+    /// Updates the layers after the shapes of a cell changed, given its shape
+    /// bounds before the edit and the cells whose bounds changed as a result.
+    /// The placements stay where they are, so only the entries of the cell's
+    /// placements in the spatial index are replaced. Requires the layers not
+    /// to be flattened, and the cell to have had shapes before if and only if
+    /// it still has.
+    pub(crate) fn update_reshaped_cell(
+        &mut self,
+        cell_def_id: CellDefId,
+        old_shape_bounds: Option<BoundingBox>,
+        stale: &HashSet<CellDefId>,
+    ) {
+        for index in 0..self.placements.len() {
+            let placement = &self.placements[index];
+            if placement.cell_def_id != cell_def_id {
+                continue;
+            }
+            if let Some(bounds) = &old_shape_bounds {
+                let bounds = transform_bounds(bounds, &placement.transform);
+                self.placement_tree.remove(&rtree_item(&bounds, index));
+            }
+            if let Some(item) = self.placement_item(index) {
+                self.placement_tree.insert(item);
+            }
+        }
+        self.update_shape_counts();
+        self.update_after_edit(stale);
+    }
+
+    /// Moves the placeholders of cells whose bounds changed, and updates the
+    /// bounds of the layers and the project.
+    fn update_after_edit(&mut self, stale: &HashSet<CellDefId>) {
+        let mut index = 0;
+        while index < self.placeholders.len() {
+            let placeholder = &self.placeholders[index];
+            if !stale.contains(&placeholder.cell_def_id) {
+                index += 1;
+                continue;
+            }
+            let transform = self.node_transform(placeholder.node);
+            match self.cell_def_bounds(placeholder.cell_def_id) {
+                Some(bounds) => {
+                    self.placeholders[index].bounds = transform_bounds(&bounds, &transform);
+                    index += 1;
+                }
+                // Cells without any geometry get no placeholder.
+                None => {
+                    self.placeholders.remove(index);
+                }
+            }
+        }
+        self.update_placeholder_rtree();
+        self.update_bounds();
+    }
+
+    /// Counts the shapes drawn on each layer, once per cell rather than once
+    /// per placement.
+    fn update_shape_counts(&mut self) {
+        let mut placement_counts: HashMap<CellDefId, usize> = HashMap::new();
+        for placement in &self.placements {
+            *placement_counts.entry(placement.cell_def_id).or_default() += 1;
+        }
+        for layer in &mut self.layers {
+            layer.shape_count = 0;
+        }
+        for (cell_def_id, count) in placement_counts {
+            for shape in &self.cell_defs[&cell_def_id].shapes {
+                self.layers[shape.layer as usize].shape_count += count;
+            }
+        }
+    }
+
+    /// Updates the bounds of each layer and of the project. Layers read from
+    /// the file are bounded by the shapes of each placement, so that their
+    /// bounds do not depend on whether they are flattened.
+    fn update_bounds(&mut self) {
+        for layer in &mut self.layers {
+            layer.bounds = BoundingBox::new();
+        }
+        let mut cell_layer_bounds: HashMap<CellDefId, Vec<(usize, BoundingBox)>> = HashMap::new();
+        for placement in &self.placements {
+            let layer_bounds = cell_layer_bounds
+                .entry(placement.cell_def_id)
+                .or_insert_with(|| shape_layer_bounds(&self.cell_defs[&placement.cell_def_id]));
+            for (layer, bounds) in layer_bounds.iter() {
+                self.layers[*layer]
+                    .bounds
+                    .encompass(&transform_bounds(bounds, &placement.transform));
+            }
+        }
         self.bounds = BoundingBox::new();
         for layer in &mut self.layers {
-            layer.update_bounds();
+            if layer.is_derived() {
+                layer.update_bounds();
+            }
             if !layer.bounds.is_empty() {
                 self.bounds.encompass(&layer.bounds);
             }
         }
+        for placeholder in &self.placeholders {
+            self.bounds.encompass(&placeholder.bounds);
+        }
+    }
+
+    /// Entry of a placement in the spatial index, or None if its cell has no
+    /// shapes with an extent.
+    fn placement_item(&self, index: usize) -> Option<GeomWithData<Rectangle<[f64; 2]>, usize>> {
+        let placement = &self.placements[index];
+        let bounds = self.cell_defs[&placement.cell_def_id].shape_bounds?;
+        let bounds = transform_bounds(&bounds, &placement.transform);
+        Some(rtree_item(&bounds, index))
+    }
 
-        self.rtree = RTree::bulk_load(rtree_items);
+    fn update_placeholder_rtree(&mut self) {
+        self.placeholder_rtree = RTree::bulk_load(
+            self.placeholders
+                .iter()
+                .enumerate()
+                .map(|(index, placeholder)| rtree_item(&placeholder.bounds, index))
+                .collect(),
+        );
     }
 
-    fn update_layers_recurse(&mut self, cell_id: CellId, rtree_items: &mut Vec<PickResult>) {
-        let cell = self.cells.get(&cell_id).unwrap();
-        if !cell.visible {
+    /// Transform from the cell of an instance node into world coordinates.
+    fn node_transform(&self, node: usize) -> AffineTransform {
+        let (_, path) = self.node_path(node);
+        self.instance_path_transform(&path)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn update_layers_recurse(
+        &self,
+        step: InstanceStep,
+        cell_def_id: CellDefId,
+        transform: &AffineTransform,
+        parent: Option<usize>,
+        nodes: &mut Vec<InstanceNode>,
+        placements: &mut Vec<Placement>,
+        placeholders: &mut Vec<Placeholder>,
+    ) {
+        let node = nodes.len();
+        let depth = parent.map_or(0, |parent| nodes[parent].depth + 1);
+        let cell_id = step.cell_id;
+        nodes.push(InstanceNode {
+            cell_id,
+            element: step.element,
+            cell_def_id,
+            parent,
+            depth,
+            end: node + 1,
+        });
+        if self
+            .display_depth
+            .is_some_and(|max_depth| depth > max_depth)
+        {
+            if let Some(bounds) = self.cell_def_bounds(cell_def_id) {
+                placeholders.push(Placeholder {
+                    cell_id,
+                    cell_def_id,
+                    bounds: transform_bounds(&bounds, transform),
+                    node,
+                });
+            }
             return;
         }
-        let transform = &cell.world_transform;
-        let cell_def = self.cell_defs.get(&cell.cell_def_id).unwrap();
-        for boundary in &cell_def.boundary_elements {
-            let layer = &mut self.layers[boundary.layer as usize];
-            layer.add_boundary_element(boundary, transform);
-            rtree_items.push(PickResult {
-                aabb: layer.polygons.last().unwrap().envelope(),
-                layer: boundary.layer,
-                polygon: layer.polygons.len() - 1,
-                cell_id,
-            });
-        }
-        for path in &cell_def.path_elements {
-            let layer = &mut self.layers[path.layer as usize];
-            layer.add_path_element(path, transform);
-            rtree_items.push(PickResult {
-                aabb: layer.polygons.last().unwrap().envelope(),
-                layer: path.layer,
-                polygon: layer.polygons.len() - 1,
+        let cell_def = &self.cell_defs[&cell_def_id];
+        if !cell_def.shapes.is_empty() {
+            placements.push(Placement {
                 cell_id,
+                cell_def_id,
+                transform: *transform,
+                node,
             });
         }
-        let cell_ids = self.cell_defs[&cell.cell_def_id].cell_elements.clone();
-        for cell_id in cell_ids {
-            self.update_layers_recurse(cell_id, rtree_items);
+        for &child_id in &cell_def.cell_elements {
+            let child = self.cell(child_id);
+            if !child.visible {
+                continue;
+            }
+            for element in child.elements() {
+                let child_transform = child.element_placement(element).compose(transform);
+                self.update_layers_recurse(
+                    InstanceStep {
+                        cell_id: child_id,
+                        element,
+                    },
+                    child.cell_def_id,
+                    &child_transform,
+                    Some(node),
+                    nodes,
+                    placements,
+                    placeholders,
+                );
+            }
         }
+        nodes[node].end = nodes.len();
     }
 
-    fn update_world_transforms_recurse(
-        &mut self,
-        cell_id: CellId,
-        parent_transform: &AffineTransform,
-    ) {
-        let cell = self.cells.get_mut(&cell_id).unwrap();
+    /// Every drawn placement of a cell that has shapes of its own, skipping
+    /// hidden instances and those below the display depth. Cells placed more
+    /// than once appear once per placement.
+    pub(crate) fn placements(&self) -> &[Placement] {
+        &self.placements
+    }
 
-        let translate = AffineTransform::translate(cell.xy.x as f64, cell.xy.y as f64);
-        let mut rotate = AffineTransform::identity();
-        let mut scale = AffineTransform::identity();
+    /// Corners and indices of the entries in the spatial index of the
+    /// placements, in no particular order.
+    #[cfg(test)]
+    pub(crate) fn placement_tree_items(&self) -> Vec<([f64; 2], [f64; 2], usize)> {
+        self.placement_tree
+            .iter()
+            .map(|item| (item.geom().lower(), item.geom().upper(), item.data))
+            .collect()
+    }
 
-        if let Some(local_transform) = &cell.local_transform {
-            if let Some(angle) = &local_transform.angle {
-                rotate = AffineTransform::rotate(*angle, Coord::zero());
-            }
-            if local_transform.reflected {
-                scale = AffineTransform::scale(1.0, -1.0, Coord::zero());
-            }
-            if local_transform.mag.unwrap_or(1.0) != 1.0 {
-                eprintln!("Magnification not supported.");
-            }
-            if local_transform.abs_mag || local_transform.abs_angle {
-                eprintln!("Absolute transform not supported.");
+    /// Instance drawn at the given position in depth-first order, see
+    /// [Placement::node].
+    pub(crate) fn instance_node(&self, node: usize) -> Option<&InstanceNode> {
+        self.instance_nodes.get(node)
+    }
+
+    /// Finds the top cell and the chain of instances leading to a node.
+    pub(crate) fn node_path(&self, node: usize) -> (CellDefId, Vec<InstanceStep>) {
+        let mut path = Vec::new();
+        let mut current = &self.instance_nodes[node];
+        while let Some(parent) = current.parent {
+            path.push(InstanceStep {
+                cell_id: current.cell_id,
+                element: current.element,
+            });
+            current = &self.instance_nodes[parent];
+        }
+        path.reverse();
+        (current.cell_def_id, path)
+    }
+
+    /// Calls the function with every polygon drawn on the layer at the given
+    /// position in [Self::layers], in world coordinates. Unless the layers
+    /// are flattened, the shapes are transformed one at a time while walking
+    /// [Self::placements], so that export does not need to flatten.
+    pub fn for_each_layer_polygon(&self, layer: usize, mut f: impl FnMut(&geo::Polygon<f64>)) {
+        if self.flattened {
+            self.layers[layer].render_polygons().iter().for_each(f);
+            return;
+        }
+        for placement in &self.placements {
+            for shape in &self.cell_defs[&placement.cell_def_id].shapes {
+                if shape.layer as usize == layer {
+                    f(&shape.polygon.affine_transform(&placement.transform));
+                }
             }
         }
+    }
 
-        let transform = scale
-            .compose(&rotate)
-            .compose(&translate)
-            .compose(parent_transform);
+    /// Returns the shapes of a cell in its own coordinate system.
+    pub(crate) fn cell_def_shapes(&self, cell_def_id: CellDefId) -> &[Shape] {
+        &self.cell_defs[&cell_def_id].shapes
+    }
 
-        cell.world_transform = transform;
+    /// Copies the shapes of every placement into [Layer::polygons] in world
+    /// coordinates and evaluates the derived layers over them. Analyses read
+    /// these, while drawing, picking and export work from the cells directly,
+    /// so this is left until it is needed. The layers stay flattened when
+    /// they are rebuilt, until [Self::release_flat_layers].
+    pub fn flatten_layers(&mut self) {
+        if self.flattened {
+            return;
+        }
+        self.flattened = true;
+        self.refresh_layers();
+    }
 
-        let cell_ids = self.cell_defs[&cell.cell_def_id].cell_elements.clone();
-        for cell_id in cell_ids {
-            self.update_world_transforms_recurse(cell_id, &transform);
+    /// Drops the polygons copied by [Self::flatten_layers] once no analysis
+    /// needs them anymore, so that later rebuilds only update the placements.
+    /// Merged layers and derived layers are computed from the flattened
+    /// polygons, so they are kept while either is in use.
+    pub fn release_flat_layers(&mut self) {
+        if !self.flattened || self.is_merged() || !self.derived_layers.is_empty() {
+            return;
         }
+        self.flattened = false;
+        self.flat_offsets = Vec::new();
+        for layer in &mut self.layers {
+            layer.polygons = Vec::new();
+        }
+    }
+
+    pub fn is_flattened(&self) -> bool {
+        self.flattened
     }
 
     pub fn layers(&self) -> &[Layer] {
@@ -386,27 +912,168 @@ impl Project {
         &mut self.layers
     }
 
+    /// Copies the visibility and color of each layer from the matching layer
+    /// of another project, e.g. an earlier version of the same file. Layers
+    /// read from the file match by number and derived layers by name, since
+    /// derived layers are numbered after the highest layer in the file.
+    pub fn copy_layer_styles(&mut self, layers: &[Layer]) {
+        for layer in &mut self.layers {
+            let old_layer = layers
+                .iter()
+                .find(|old_layer| match (&layer.name, &old_layer.name) {
+                    (Some(name), Some(old_name)) => name == old_name,
+                    (None, None) => layer.index() == old_layer.index(),
+                    _ => false,
+                });
+            if let Some(old_layer) = old_layer {
+                layer.visible = old_layer.visible;
+                layer.color = old_layer.color;
+            }
+        }
+    }
+
+    /// Computes the union of each layer, which is then used for rendering,
+    /// export and area statistics. Picking still uses the original polygons.
+    pub fn merge_layers(&mut self) {
+        self.flatten_layers();
+        for layer in &mut self.layers {
+            layer.merge();
+        }
+    }
+
+    pub fn unmerge_layers(&mut self) {
+        for layer in &mut self.layers {
+            layer.unmerge();
+        }
+    }
+
+    pub fn is_merged(&self) -> bool {
+        self.layers.iter().any(|layer| layer.is_merged())
+    }
+
+    pub(crate) fn push_layer(&mut self, layer: Layer) {
+        if !layer.bounds.is_empty() {
+            self.bounds.encompass(&layer.bounds);
+        }
+        self.layers.push(layer);
+    }
+
     pub fn bounds(&self) -> BoundingBox {
         self.bounds
     }
 
+    /// Finds the shape on the highest visible layer containing the given
+    /// point, by visiting the placements around it.
     pub fn pick_cell(&self, x: f64, y: f64) -> Option<PickResult> {
-        let point = Point::new(x, y);
-        let items = self.rtree.locate_all_at_point(&point);
-        let mut result: Option<PickResult> = None;
-        for item in items {
-            if let Some(ref result) = result {
-                if item.layer < result.layer {
+        let point = Coord { x, y };
+        let mut best: Option<(&Placement, usize, i16)> = None;
+        for item in self.placement_tree.locate_all_at_point(&[x, y]) {
+            let placement = &self.placements[item.data];
+            let Some(inverse) = placement.transform.inverse() else {
+                continue;
+            };
+            let local = inverse.apply(point);
+            let cell_def = &self.cell_defs[&placement.cell_def_id];
+            for shape_item in cell_def.shape_tree.locate_all_at_point(&[local.x, local.y]) {
+                let shape = &cell_def.shapes[shape_item.data];
+                if best.is_some_and(|(_, _, layer)| shape.layer < layer) {
                     continue;
                 }
+                if self.layers[shape.layer as usize].visible
+                    && shape.polygon.contains(&Point::from(local))
+                {
+                    best = Some((placement, shape_item.data, shape.layer));
+                }
             }
-            let layer = &self.layers[item.layer as usize];
-            let polygon = &layer.polygons[item.polygon];
-            if layer.visible && polygon.contains(&point) {
-                result = Some(item.clone());
+        }
+        let (placement, shape, layer) = best?;
+        let polygon = &self.cell_defs[&placement.cell_def_id].shapes[shape].polygon;
+        let (top, path) = self.node_path(placement.node);
+        Some(PickResult {
+            polygon: polygon.affine_transform(&placement.transform),
+            shape,
+            layer,
+            cell_id: placement.cell_id,
+            cell_def_id: placement.cell_def_id,
+            transform: placement.transform,
+            top,
+            path,
+            node: placement.node,
+        })
+    }
+
+    /// Finds the flattened polygon of a picked shape, which is how nets
+    /// refer to shapes. The shapes of each placement are flattened in order,
+    /// so this is the first polygon of the placement on the layer, offset by
+    /// the shapes before it in the cell. Requires the layers to be flattened.
+    pub fn flat_polygon_index(&self, pick: &PickResult) -> Option<usize> {
+        // Each node has at most one placement.
+        let placement = self.placements.partition_point(|p| p.node < pick.node);
+        if self.placements.get(placement)?.node != pick.node {
+            return None;
+        }
+        let &(_, first) = self
+            .flat_offsets
+            .get(placement)?
+            .iter()
+            .find(|&&(layer, _)| layer == pick.layer)?;
+        let shapes = &self.cell_defs[&pick.cell_def_id].shapes;
+        let before = shapes[..pick.shape]
+            .iter()
+            .filter(|shape| shape.layer == pick.layer)
+            .count();
+        Some(first + before)
+    }
+
+    /// Finds the polygon vertex on a visible layer nearest to the given point
+    /// within the given radius, or failing that, the nearest point on a
+    /// polygon edge.
+    pub fn snap_point(&self, x: f64, y: f64, radius: f64) -> Option<Coord<f64>> {
+        let point = Coord { x, y };
+        let search = BoundingBox {
+            min_x: x - radius,
+            min_y: y - radius,
+            max_x: x + radius,
+            max_y: y + radius,
+        };
+        let envelope =
+            AABB::from_corners([search.min_x, search.min_y], [search.max_x, search.max_y]);
+        let distance = |other: Coord<f64>| (other - point).x.hypot((other - point).y);
+        let mut vertex: Option<(f64, Coord<f64>)> = None;
+        let mut edge: Option<(f64, Coord<f64>)> = None;
+        for item in self
+            .placement_tree
+            .locate_in_envelope_intersecting(&envelope)
+        {
+            let placement = &self.placements[item.data];
+            let Some(inverse) = placement.transform.inverse() else {
+                continue;
+            };
+            let local = transform_bounds(&search, &inverse);
+            let local = AABB::from_corners([local.min_x, local.min_y], [local.max_x, local.max_y]);
+            let cell_def = &self.cell_defs[&placement.cell_def_id];
+            for shape_item in cell_def.shape_tree.locate_in_envelope_intersecting(&local) {
+                let shape = &cell_def.shapes[shape_item.data];
+                if !self.layers[shape.layer as usize].visible {
+                    continue;
+                }
+                let polygon = shape.polygon.affine_transform(&placement.transform);
+                for ring in std::iter::once(polygon.exterior()).chain(polygon.interiors()) {
+                    for line in ring.lines() {
+                        let d = distance(line.start);
+                        if d <= radius && vertex.is_none_or(|(best, _)| d < best) {
+                            vertex = Some((d, line.start));
+                        }
+                        let closest = closest_point_on_line(point, &line);
+                        let d = distance(closest);
+                        if d <= radius && edge.is_none_or(|(best, _)| d < best) {
+                            edge = Some((d, closest));
+                        }
+                    }
+                }
             }
         }
-        result
+        vertex.or(edge).map(|(_, coord)| coord)
     }
 }
 
@@ -422,46 +1089,308 @@ pub struct LayoutStats {
     pub box_count: usize,
 }
 
-#[derive(Clone)]
+/// Instance drawn as a box because it is nested deeper than the display
+/// depth, see [Project::set_display_depth].
+#[derive(Debug, Clone)]
+pub struct Placeholder {
+    pub cell_id: CellId,
+    pub cell_def_id: CellDefId,
+    /// World bounds of everything in the instance, regardless of visibility.
+    pub bounds: BoundingBox,
+    /// Index of the instance among the [InstanceNode]s of the project.
+    pub(crate) node: usize,
+}
+
+/// Placement of a cell with shapes, see [Project::placements].
+#[derive(Debug, Clone)]
+pub(crate) struct Placement {
+    /// The instance that was placed, or the root instance of a top cell.
+    pub cell_id: CellId,
+    pub cell_def_id: CellDefId,
+    /// Transform from the cell into world coordinates.
+    pub transform: AffineTransform,
+    /// Index of the instance among the [InstanceNode]s of the project.
+    pub node: usize,
+}
+
+/// Instance visited while collecting the placements. Nodes are stored in
+/// depth-first order, so the nodes below one directly follow it.
+#[derive(Debug, Clone)]
+pub(crate) struct InstanceNode {
+    /// The instance, or the root instance of a top cell.
+    pub cell_id: CellId,
+    /// Element of an array instance, with a node for each element.
+    pub element: Option<(i16, i16)>,
+    pub cell_def_id: CellDefId,
+    pub parent: Option<usize>,
+    /// Number of instances above this one, 0 for top cells.
+    pub depth: usize,
+    /// End of the range of nodes below this one.
+    pub end: usize,
+}
+
+fn rtree_item(bounds: &BoundingBox, index: usize) -> GeomWithData<Rectangle<[f64; 2]>, usize> {
+    let rectangle =
+        Rectangle::from_corners([bounds.min_x, bounds.min_y], [bounds.max_x, bounds.max_y]);
+    GeomWithData::new(rectangle, index)
+}
+
+/// Bounds of the shapes of a cell on each layer that it draws on, in the
+/// coordinate system of the cell.
+fn shape_layer_bounds(cell_def: &CellDef) -> Vec<(usize, BoundingBox)> {
+    let mut bounds: Vec<(usize, BoundingBox)> = Vec::new();
+    for shape in &cell_def.shapes {
+        let Some(rect) = shape.polygon.bounding_rect() else {
+            continue;
+        };
+        let layer = shape.layer as usize;
+        match bounds.iter_mut().find(|(index, _)| *index == layer) {
+            Some((_, layer_bounds)) => layer_bounds.encompass(&BoundingBox::from(rect)),
+            None => bounds.push((layer, BoundingBox::from(rect))),
+        }
+    }
+    bounds
+}
+
+#[derive(Clone, PartialEq)]
 pub struct PickResult {
-    aabb: AABB<Point<f64>>,
-    pub polygon: usize,
+    /// Outline of the shape in world coordinates.
+    pub polygon: geo::Polygon<f64>,
+    /// Index of the shape within its cell.
+    pub shape: usize,
     pub layer: i16,
     pub cell_id: CellId,
+    /// Cell that owns the shape.
+    pub cell_def_id: CellDefId,
+    /// Transform from the owning cell into world coordinates.
+    pub transform: AffineTransform,
+    /// Top cell that the shape was reached from.
+    pub top: CellDefId,
+    /// Instances leading from the top cell to the shape, empty if the shape
+    /// belongs to the top cell itself, see [Project::describe_instance_path].
+    pub path: Vec<InstanceStep>,
+    /// Index of the owning instance among the [InstanceNode]s of the project.
+    pub(crate) node: usize,
 }
 
 impl Debug for PickResult {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "{{ polygon {}, layer {}, cell_id {} }}",
-            self.polygon, self.layer, self.cell_id.0
+            "{{ shape {}, layer {}, cell_id {} }}",
+            self.shape, self.layer, self.cell_id.0
         )
     }
 }
 
-impl PartialEq for PickResult {
-    fn eq(&self, other: &Self) -> bool {
-        self.polygon == other.polygon && self.layer == other.layer && self.cell_id == other.cell_id
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::test_layouts::*;
+
+    fn test_project() -> Project {
+        project(vec![
+            gds_struct("bit", vec![rect(1, (0, 0), (10, 10))]),
+            gds_struct("pair", vec![rect(2, (0, 0), (30, 5)), sref("bit", (20, 0))]),
+            gds_struct(
+                "top",
+                vec![
+                    rect(1, (-5, -5), (0, 0)),
+                    sref("pair", (100, 0)),
+                    aref("bit", (0, 50), (2, 1), (15, 0)),
+                ],
+            ),
+        ])
+    }
+
+    /// Placements with their transforms rounded, and the sorted entries of
+    /// their spatial index.
+    type PlacementState = (Vec<(usize, [i64; 6])>, Vec<[i64; 5]>);
+
+    fn placement_state(project: &Project) -> PlacementState {
+        let round = |value: f64| (value * 1000.0).round() as i64;
+        let placements = project
+            .placements()
+            .iter()
+            .map(|p| {
+                let t = &p.transform;
+                let transform = [t.a(), t.b(), t.xoff(), t.d(), t.e(), t.yoff()].map(round);
+                (p.node, transform)
+            })
+            .collect();
+        let mut items: Vec<[i64; 5]> = project
+            .placement_tree_items()
+            .into_iter()
+            .map(|(lower, upper, index)| {
+                let [x0, y0, x1, y1] = [lower[0], lower[1], upper[0], upper[1]].map(round);
+                [x0, y0, x1, y1, index as i64]
+            })
+            .collect();
+        items.sort();
+        (placements, items)
     }
-}
 
-impl Eq for PickResult {}
+    #[test]
+    fn test_instance_nodes_are_depth_first() {
+        let project = test_project();
+        let names: Vec<_> = project
+            .instance_nodes
+            .iter()
+            .map(|node| {
+                (
+                    project.struct_name(node.cell_def_id),
+                    node.element,
+                    node.end,
+                )
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("top", None, 5),
+                ("pair", None, 3),
+                ("bit", None, 3),
+                ("bit", Some((0, 0)), 4),
+                ("bit", Some((1, 0)), 5),
+            ]
+        );
+        let nodes: Vec<usize> = project.placements().iter().map(|p| p.node).collect();
+        assert_eq!(nodes, vec![0, 1, 2, 3, 4]);
+        let (_, path) = project.node_path(4);
+        assert_eq!(path.len(), 1);
+        assert_eq!(path[0].element, Some((1, 0)));
+    }
 
-impl RTreeObject for PickResult {
-    type Envelope = AABB<Point<f64>>;
+    #[test]
+    fn test_context_replaces_top_cells() {
+        let mut project = test_project();
+        let top = project.find_cell_def("top").unwrap();
+        let pair = project.find_cell_def("pair").unwrap();
+        assert_eq!(project.top_cells(), vec![top]);
+        project.layers_mut()[2].visible = false;
 
-    fn envelope(&self) -> Self::Envelope {
-        self.aabb
+        project.set_context(Some(pair));
+        assert_eq!(project.context(), Some(pair));
+        assert_eq!(project.top_cells(), vec![pair]);
+        assert_eq!(project.placements().len(), 2);
+        let bounds = project.bounds();
+        assert_eq!((bounds.min_x, bounds.max_x), (0.0, 30.0));
+        assert!(!project.layers()[2].visible);
+
+        project.set_context(None);
+        assert_eq!(project.top_cells(), vec![top]);
+        assert_eq!(project.placements().len(), 5);
+        assert_eq!(project.find_roots(), vec![top]);
     }
-}
 
-impl PointDistance for PickResult {
-    fn distance_2(&self, point: &Point<f64>) -> f64 {
-        self.aabb.distance_2(point)
+    #[test]
+    fn test_display_depth_draws_placeholders() {
+        let mut project = test_project();
+        assert_eq!(project.hierarchy_depth(), 2);
+        assert!(project.placeholders().is_empty());
+
+        project.set_display_depth(Some(1));
+        assert_eq!(project.placements().len(), 4);
+        let bounds: Vec<_> = project.placeholders().iter().map(|p| p.bounds).collect();
+        assert_eq!(bounds.len(), 1);
+        assert_eq!(
+            (
+                bounds[0].min_x,
+                bounds[0].min_y,
+                bounds[0].max_x,
+                bounds[0].max_y
+            ),
+            (120.0, 0.0, 130.0, 10.0)
+        );
+        let bit = project.find_cell_def("bit").unwrap();
+        let picked = project.pick_placeholder(125.0, 5.0).unwrap();
+        assert_eq!(picked.cell_def_id, bit);
+        assert!(project.pick_placeholder(115.0, 5.0).is_none());
+
+        project.set_display_depth(Some(0));
+        assert_eq!(project.placements().len(), 1);
+        assert_eq!(project.placeholders().len(), 3);
+        let around_array = BoundingBox {
+            min_x: 0.0,
+            min_y: 40.0,
+            max_x: 50.0,
+            max_y: 70.0,
+        };
+        assert_eq!(project.placeholders_in(&around_array).count(), 2);
+
+        project.set_display_depth(None);
+        assert!(project.placeholders().is_empty());
+        assert_eq!(project.placements().len(), 5);
+    }
+
+    #[test]
+    fn test_layer_styles_carry_over() {
+        let mut old = test_project();
+        let gate = old.add_derived_layer("gate", "1 AND 2").unwrap();
+        let red = Vector4::new(1.0, 0.0, 0.0, 0.5);
+        old.layers_mut()[1].visible = false;
+        old.layers_mut()[gate as usize].color = red;
+
+        // A new layer moves the derived layer up by one.
+        let mut new = project(vec![gds_struct(
+            "top",
+            vec![rect(1, (0, 0), (5, 5)), rect(3, (0, 0), (5, 5))],
+        )]);
+        let new_gate = new.add_derived_layer("gate", "1 AND 3").unwrap();
+        assert_eq!(new_gate, gate + 1);
+        new.copy_layer_styles(old.layers());
+        assert!(!new.layers()[1].visible);
+        assert!(new.layers()[3].visible);
+        assert_eq!(new.layers()[new_gate as usize].color, red);
+        assert_ne!(new.layers()[gate as usize].color, red);
+    }
+
+    #[test]
+    fn test_flatten_and_release_layers() {
+        let mut project = test_project();
+        assert!(!project.is_flattened());
+        assert!(project.layers()[1].polygons.is_empty());
+        assert_eq!(project.layers()[1].shape_count, 4);
+
+        project.flatten_layers();
+        assert!(project.is_flattened());
+        assert_eq!(project.layers()[1].polygons.len(), 4);
+        assert_eq!(project.layers()[2].polygons.len(), 1);
+        let pick = project.pick_cell(20.0, 55.0).unwrap();
+        assert_eq!(project.flat_polygon_index(&pick), Some(3));
+
+        project.release_flat_layers();
+        assert!(!project.is_flattened());
+        assert!(project.layers()[1].polygons.is_empty());
+        assert_eq!(project.flat_polygon_index(&pick), None);
+
+        project.merge_layers();
+        project.release_flat_layers();
+        assert!(project.is_flattened());
     }
 
-    fn contains_point(&self, point: &Point<f64>) -> bool {
-        self.aabb.contains_point(point)
+    #[test]
+    fn test_moved_instance_matches_full_update() {
+        let mut project = test_project();
+        project.set_display_depth(Some(1));
+        let pair = project.find_cell_def("pair").unwrap();
+        let cell_id = project.cell_def(pair).instances[0];
+        project.cell_mut(cell_id).xy = GdsPoint::new(-40, 70);
+        let parent = project.cell(cell_id).parent;
+        let stale = project.update_cell_def_bounds_above(parent);
+        project.update_moved_instance(cell_id, &stale);
+
+        let moved = placement_state(&project);
+        let placeholders = |project: &Project| -> Vec<[f64; 4]> {
+            let bounds = project.placeholders().iter().map(|p| p.bounds);
+            bounds
+                .map(|b| [b.min_x, b.min_y, b.max_x, b.max_y])
+                .collect()
+        };
+        let moved_placeholders = placeholders(&project);
+        project.update_layers();
+        assert_eq!(moved, placement_state(&project));
+        assert_eq!(moved_placeholders, placeholders(&project));
+        assert_eq!(moved_placeholders, vec![[-20.0, 70.0, -10.0, 80.0]]);
     }
 }
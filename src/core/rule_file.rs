@@ -0,0 +1,63 @@
+use anyhow::anyhow;
+use anyhow::Error;
+use anyhow::Result;
+
+/// Line of a rule file, split into whitespace-separated fields, see
+/// [rule_lines].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleLine<'a> {
+    /// Line number, counting from 1.
+    pub number: usize,
+    pub fields: Vec<&'a str>,
+}
+
+impl RuleLine<'_> {
+    /// Returns an error pointing at this line.
+    pub fn error(&self, message: &str) -> Error {
+        anyhow!("Line {}: {}", self.number, message)
+    }
+
+    /// Reads a layer field, see [rule_lines].
+    pub fn layer(&self, field: &str, resolve: impl Fn(&str) -> Option<i16>) -> Result<i16> {
+        field
+            .parse()
+            .ok()
+            .or_else(|| resolve(field))
+            .ok_or_else(|| self.error(&format!("unknown layer '{}'", field)))
+    }
+}
+
+/// Splits the text of a rule file, such as DRC rules or a layer stack, into
+/// lines of fields. Text after `#` is a comment, and lines without fields
+/// are skipped. Layers are given by number, or by name for derived layers,
+/// which are looked up with a function given to [RuleLine::layer].
+pub fn rule_lines(text: &str) -> impl Iterator<Item = RuleLine<'_>> {
+    text.lines().enumerate().filter_map(|(index, line)| {
+        let line = line.split('#').next().unwrap_or("");
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        (!fields.is_empty()).then_some(RuleLine {
+            number: index + 1,
+            fields,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_lines_skip_comments() {
+        let text = "# header\n\nwidth 3 140  # metal\n  \nspacing metal1 2\n";
+        let lines: Vec<RuleLine> = rule_lines(text).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].number, 3);
+        assert_eq!(lines[0].fields, vec!["width", "3", "140"]);
+
+        let resolve = |name: &str| (name == "metal1").then_some(9);
+        assert_eq!(lines[1].layer("metal1", resolve).unwrap(), 9);
+        assert_eq!(lines[0].layer("3", resolve).unwrap(), 3);
+        let error = lines[1].layer("metal2", resolve).unwrap_err();
+        assert_eq!(error.to_string(), "Line 5: unknown layer 'metal2'");
+    }
+}
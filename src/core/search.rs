@@ -0,0 +1,266 @@
+use crate::core::placed_bounds;
+use crate::core::transform_bounds;
+use crate::core::CellDefId;
+use crate::core::InstanceStep;
+use crate::graphics::BoundingBox;
+use crate::rsutils::glob_match;
+use crate::rsutils::is_glob_pattern;
+use crate::Project;
+use anyhow::anyhow;
+use anyhow::Result;
+use geo::AffineTransform;
+use geo::Coord;
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Pattern that cell names and labels are matched against, see
+/// [SearchPattern::parse].
+#[derive(Debug, Clone)]
+pub enum SearchPattern {
+    /// Matches text containing the string.
+    Text(String),
+    /// Matches the whole text, see [glob_match].
+    Glob(String),
+    /// Matches text containing a match of the expression.
+    Regex(Regex),
+}
+
+impl SearchPattern {
+    /// Parses `/EXPR/` as a regular expression, text with `*` or `?` as a
+    /// glob and anything else as a string to look for within names.
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(anyhow!("Empty search pattern"));
+        }
+        if let Some(expr) = text
+            .strip_prefix('/')
+            .and_then(|rest| rest.strip_suffix('/'))
+        {
+            return Regex::new(expr)
+                .map(Self::Regex)
+                .map_err(|err| anyhow!("Invalid regular expression '{}': {}", expr, err));
+        }
+        match is_glob_pattern(text) {
+            true => Ok(Self::Glob(text.to_string())),
+            false => Ok(Self::Text(text.to_string())),
+        }
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            SearchPattern::Text(needle) => text.contains(needle.as_str()),
+            SearchPattern::Glob(pattern) => glob_match(pattern, text),
+            SearchPattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+/// Instance whose cell name matches a search, or a matching label within an
+/// instance, see [Project::search].
+#[derive(Debug, Clone)]
+pub struct SearchMatch {
+    pub top: CellDefId,
+    /// Instances from the top cell down to the one found, empty for the top
+    /// cell itself.
+    pub path: Vec<InstanceStep>,
+    /// Cell of the instance, which holds the label for label matches.
+    pub cell_def_id: CellDefId,
+    /// Text of the matching label, None if the cell name matched.
+    pub label: Option<String>,
+    /// World bounds of the instance, or the position of the label.
+    pub bounds: BoundingBox,
+}
+
+impl Project {
+    /// Finds every instance below [Self::top_cells] whose cell name matches,
+    /// and every matching label, in depth-first order. Stops after the given
+    /// number of matches. Hidden instances are included. An array reference
+    /// matches once as a whole, while the instances and labels within it
+    /// match once per element.
+    pub fn search(&self, pattern: &SearchPattern, max_count: usize) -> Vec<SearchMatch> {
+        let mut search = Search {
+            project: self,
+            pattern,
+            max_count,
+            contains_match: HashMap::new(),
+            matches: Vec::new(),
+        };
+        for top in self.top_cells() {
+            if pattern.matches(self.struct_name(top)) {
+                let bounds = self
+                    .cell_def_bounds(top)
+                    .unwrap_or_else(|| point_bounds(Coord::zero()));
+                search.push(top, top, &[], None, bounds);
+            }
+            search.visit(top, top, &mut Vec::new(), &AffineTransform::identity());
+        }
+        search.matches
+    }
+
+    /// Describes a match as its instance path, followed by the label if a
+    /// label matched, see [Self::describe_instance_path].
+    pub fn describe_search_match(&self, found: &SearchMatch) -> String {
+        let path = self.describe_instance_path(found.top, &found.path);
+        match &found.label {
+            Some(label) => format!("{} \"{}\"", path, label),
+            None => path,
+        }
+    }
+}
+
+struct Search<'a> {
+    project: &'a Project,
+    pattern: &'a SearchPattern,
+    max_count: usize,
+    /// Whether anything below a cell matches, so that the rest of the
+    /// hierarchy is skipped.
+    contains_match: HashMap<CellDefId, bool>,
+    matches: Vec<SearchMatch>,
+}
+
+impl Search<'_> {
+    fn visit(
+        &mut self,
+        top: CellDefId,
+        cell_def_id: CellDefId,
+        path: &mut Vec<InstanceStep>,
+        transform: &AffineTransform,
+    ) {
+        if self.matches.len() >= self.max_count || !self.contains_match(cell_def_id) {
+            return;
+        }
+        let project = self.project;
+        let cell_def = project.cell_def(cell_def_id);
+        for label in &cell_def.labels {
+            if self.pattern.matches(&label.text) {
+                let bounds = point_bounds(transform.apply(label.position));
+                self.push(top, cell_def_id, path, Some(label.text.clone()), bounds);
+            }
+        }
+        for &cell_id in &cell_def.cell_elements {
+            let cell = project.cell(cell_id);
+            if self.pattern.matches(project.struct_name(cell.cell_def_id)) {
+                let bounds = match project.cell_def_bounds(cell.cell_def_id) {
+                    Some(bounds) => transform_bounds(&placed_bounds(cell, &bounds), transform),
+                    None => point_bounds(cell.placement().compose(transform).apply(Coord::zero())),
+                };
+                path.push(cell_id.into());
+                self.push(top, cell.cell_def_id, path, None, bounds);
+                path.pop();
+            }
+            if !self.contains_match(cell.cell_def_id) {
+                continue;
+            }
+            for element in cell.elements() {
+                path.push(InstanceStep { cell_id, element });
+                self.visit(
+                    top,
+                    cell.cell_def_id,
+                    path,
+                    &cell.element_placement(element).compose(transform),
+                );
+                path.pop();
+            }
+        }
+    }
+
+    fn push(
+        &mut self,
+        top: CellDefId,
+        cell_def_id: CellDefId,
+        path: &[InstanceStep],
+        label: Option<String>,
+        bounds: BoundingBox,
+    ) {
+        if self.matches.len() < self.max_count {
+            self.matches.push(SearchMatch {
+                top,
+                path: path.to_vec(),
+                cell_def_id,
+                label,
+                bounds,
+            });
+        }
+    }
+
+    /// Whether a label in the cell, or an instance or label anywhere below it,
+    /// matches.
+    fn contains_match(&mut self, cell_def_id: CellDefId) -> bool {
+        if let Some(&found) = self.contains_match.get(&cell_def_id) {
+            return found;
+        }
+        let project = self.project;
+        let cell_def = project.cell_def(cell_def_id);
+        let found = cell_def
+            .labels
+            .iter()
+            .any(|label| self.pattern.matches(&label.text))
+            || cell_def.cell_elements.iter().any(|&cell_id| {
+                let child = project.cell(cell_id).cell_def_id;
+                self.pattern.matches(project.struct_name(child)) || self.contains_match(child)
+            });
+        self.contains_match.insert(cell_def_id, found);
+        found
+    }
+}
+
+fn point_bounds(point: Coord) -> BoundingBox {
+    BoundingBox {
+        min_x: point.x,
+        min_y: point.y,
+        max_x: point.x,
+        max_y: point.y,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::test_layouts::*;
+
+    #[test]
+    fn test_search_frames_whole_arrays() {
+        let project = project(vec![
+            gds_struct("via", vec![rect(2, (0, 0), (2, 2))]),
+            gds_struct("bit", vec![rect(1, (0, 0), (10, 10)), sref("via", (4, 4))]),
+            gds_struct("ram", vec![aref("bit", (100, 0), (3, 2), (10, 20))]),
+        ]);
+
+        let pattern = SearchPattern::parse("bit").unwrap();
+        let found = project.search(&pattern, 10);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].path[0].element, None);
+        let bounds = found[0].bounds;
+        assert_eq!(
+            (bounds.min_x, bounds.min_y, bounds.max_x, bounds.max_y),
+            (100.0, 0.0, 130.0, 30.0)
+        );
+
+        let pattern = SearchPattern::parse("via").unwrap();
+        let found = project.search(&pattern, 10);
+        assert_eq!(found.len(), 6);
+        assert_eq!(found[4].path[0].element, Some((1, 1)));
+        let bounds = found[4].bounds;
+        assert_eq!((bounds.min_x, bounds.min_y), (114.0, 24.0));
+        assert_eq!(project.search(&pattern, 2).len(), 2);
+    }
+
+    #[test]
+    fn test_parse_search_pattern() {
+        let text = SearchPattern::parse("alu").unwrap();
+        assert!(text.matches("core_alu_2"));
+        assert!(!text.matches("ALU"));
+
+        let glob = SearchPattern::parse("nand?_*").unwrap();
+        assert!(glob.matches("nand2_x1"));
+        assert!(!glob.matches("xnand2_x1"));
+
+        let regex = SearchPattern::parse(r"/^reg\[\d+\]$/").unwrap();
+        assert!(regex.matches("reg[12]"));
+        assert!(!regex.matches("reg[a]"));
+
+        assert!(SearchPattern::parse("/(/").is_err());
+        assert!(SearchPattern::parse("  ").is_err());
+    }
+}
@@ -0,0 +1,68 @@
+//! Small layouts built in memory for the tests.
+
+use crate::Project;
+use gds21::GdsArrayRef;
+use gds21::GdsBoundary;
+use gds21::GdsElement;
+use gds21::GdsLibrary;
+use gds21::GdsPoint;
+use gds21::GdsStruct;
+use gds21::GdsStructRef;
+
+/// Rectangle on a layer, given by two opposite corners.
+pub fn rect(layer: i16, (x0, y0): (i32, i32), (x1, y1): (i32, i32)) -> GdsElement {
+    GdsElement::GdsBoundary(GdsBoundary {
+        layer,
+        xy: [(x0, y0), (x1, y0), (x1, y1), (x0, y1), (x0, y0)]
+            .into_iter()
+            .map(|(x, y)| GdsPoint::new(x, y))
+            .collect(),
+        ..Default::default()
+    })
+}
+
+/// Instance of a cell at the given position.
+pub fn sref(name: &str, (x, y): (i32, i32)) -> GdsElement {
+    GdsElement::GdsStructRef(GdsStructRef {
+        name: name.to_string(),
+        xy: GdsPoint::new(x, y),
+        ..Default::default()
+    })
+}
+
+/// Array of a cell with its first element at the given position, spaced by
+/// the given pitch along x and y.
+pub fn aref(
+    name: &str,
+    (x, y): (i32, i32),
+    (cols, rows): (i16, i16),
+    (pitch_x, pitch_y): (i32, i32),
+) -> GdsElement {
+    GdsElement::GdsArrayRef(GdsArrayRef {
+        name: name.to_string(),
+        xy: [
+            GdsPoint::new(x, y),
+            GdsPoint::new(x + cols as i32 * pitch_x, y),
+            GdsPoint::new(x, y + rows as i32 * pitch_y),
+        ],
+        cols,
+        rows,
+        ..Default::default()
+    })
+}
+
+pub fn gds_struct(name: &str, elems: Vec<GdsElement>) -> GdsStruct {
+    GdsStruct {
+        name: name.to_string(),
+        elems,
+        ..Default::default()
+    }
+}
+
+pub fn project(structs: Vec<GdsStruct>) -> Project {
+    Project::from_library(&GdsLibrary {
+        name: "test".to_string(),
+        structs,
+        ..Default::default()
+    })
+}
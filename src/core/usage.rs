@@ -0,0 +1,149 @@
+use crate::core::CellDefId;
+use crate::Project;
+use geo::Area;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+/// How often a cell is used and how much area it accounts for, see
+/// [Project::cell_usage]. Areas are in square database units and count
+/// overlapping shapes twice.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellUsage {
+    pub cell_def_id: CellDefId,
+    pub name: String,
+    /// References to the cell from other cells, counting every element of
+    /// an array reference.
+    pub direct_instances: usize,
+    /// Number of copies of the cell once the top cells are flattened. Top
+    /// cells count once.
+    pub flattened_instances: usize,
+    /// Area of the shapes of the cell itself, without its instances.
+    pub own_area: f64,
+    /// Area that all copies of the cell's own shapes add to the flattened
+    /// layout.
+    pub total_area: f64,
+}
+
+impl CellUsage {
+    /// Whether no other cell places this one, as is the case for top cells
+    /// and for leftovers in the library.
+    pub fn is_unreferenced(&self) -> bool {
+        self.direct_instances == 0
+    }
+}
+
+impl Project {
+    /// Accounts for every cell of the library, ordered by the area it adds
+    /// to the flattened layout, largest first. Hidden instances are
+    /// counted, and only the hierarchy below [Self::top_cells] is flattened.
+    pub fn cell_usage(&self) -> Vec<CellUsage> {
+        let mut references: HashMap<CellDefId, Vec<(CellDefId, usize)>> = HashMap::new();
+        let mut direct_instances: HashMap<CellDefId, usize> = HashMap::new();
+        for cell_def_id in self.cell_def_ids() {
+            let children = self
+                .cell_def(cell_def_id)
+                .cell_elements
+                .iter()
+                .map(|&cell_id| {
+                    let cell = self.cell(cell_id);
+                    let copies = cell.array.as_ref().map_or(1, |array| {
+                        array.cols.max(1) as usize * array.rows.max(1) as usize
+                    });
+                    (cell.cell_def_id, copies)
+                })
+                .collect::<Vec<_>>();
+            for &(child, copies) in &children {
+                *direct_instances.entry(child).or_default() += copies;
+            }
+            references.insert(cell_def_id, children);
+        }
+        let flattened = count_flattened(&self.top_cells(), &references);
+
+        let mut usage: Vec<CellUsage> = self
+            .cell_def_ids()
+            .map(|cell_def_id| {
+                let own_area = self
+                    .cell_def_shapes(cell_def_id)
+                    .iter()
+                    .map(|shape| shape.polygon.unsigned_area())
+                    .sum::<f64>();
+                let flattened_instances = flattened.get(&cell_def_id).copied().unwrap_or(0);
+                CellUsage {
+                    cell_def_id,
+                    name: self.struct_name(cell_def_id).to_string(),
+                    direct_instances: direct_instances.get(&cell_def_id).copied().unwrap_or(0),
+                    flattened_instances,
+                    own_area,
+                    total_area: own_area * flattened_instances as f64,
+                }
+            })
+            .collect();
+        usage.sort_by(|a, b| {
+            b.total_area
+                .total_cmp(&a.total_area)
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        usage
+    }
+}
+
+/// Counts the copies of every cell below the given top cells, given the
+/// cells each cell references and how many copies of each.
+fn count_flattened(
+    tops: &[CellDefId],
+    references: &HashMap<CellDefId, Vec<(CellDefId, usize)>>,
+) -> HashMap<CellDefId, usize> {
+    // Visit parents before their children, so that a cell's count is final
+    // before it is passed down.
+    fn visit(
+        cell_def_id: CellDefId,
+        references: &HashMap<CellDefId, Vec<(CellDefId, usize)>>,
+        visited: &mut HashSet<CellDefId>,
+        order: &mut Vec<CellDefId>,
+    ) {
+        if !visited.insert(cell_def_id) {
+            return;
+        }
+        for &(child, _) in references.get(&cell_def_id).into_iter().flatten() {
+            visit(child, references, visited, order);
+        }
+        order.push(cell_def_id);
+    }
+    let mut visited = HashSet::new();
+    let mut order = Vec::new();
+    for &top in tops {
+        visit(top, references, &mut visited, &mut order);
+    }
+
+    let mut counts: HashMap<CellDefId, usize> = tops.iter().map(|&top| (top, 1)).collect();
+    for &cell_def_id in order.iter().rev() {
+        let count = counts.get(&cell_def_id).copied().unwrap_or(0);
+        for &(child, copies) in references.get(&cell_def_id).into_iter().flatten() {
+            *counts.entry(child).or_default() += count * copies;
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_flattened_multiplies_along_paths() {
+        // top places core twice and nand2 once; core places a 2x2 array of
+        // nand2 and one alu, which places nand2 three times.
+        let (top, core, alu, nand2) = (CellDefId(0), CellDefId(1), CellDefId(2), CellDefId(3));
+        let references = HashMap::from([
+            (top, vec![(core, 1), (core, 1), (nand2, 1)]),
+            (core, vec![(nand2, 4), (alu, 1)]),
+            (alu, vec![(nand2, 3)]),
+            (nand2, vec![]),
+        ]);
+        let counts = count_flattened(&[top], &references);
+        assert_eq!(counts[&top], 1);
+        assert_eq!(counts[&core], 2);
+        assert_eq!(counts[&alu], 2);
+        assert_eq!(counts[&nand2], 1 + 2 * 4 + 2 * 3);
+    }
+}
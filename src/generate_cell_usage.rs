@@ -0,0 +1,56 @@
+use crate::core::CellUsage;
+use crate::rsutils::json_number;
+use crate::rsutils::json_string;
+use std::fmt::Write;
+
+/// Formats cell usage as CSV with one row per cell, areas in square database
+/// units.
+pub fn generate_cell_usage_csv(usage: &[CellUsage]) -> String {
+    let mut csv = String::from(
+        "cell,direct_instances,flattened_instances,own_area,total_area,unreferenced\n",
+    );
+    for cell in usage {
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{}",
+            csv_field(&cell.name),
+            cell.direct_instances,
+            cell.flattened_instances,
+            cell.own_area,
+            cell.total_area,
+            cell.is_unreferenced()
+        );
+    }
+    csv
+}
+
+/// Formats cell usage as a JSON array with one object per cell, areas in
+/// square database units.
+pub fn generate_cell_usage_json(usage: &[CellUsage]) -> String {
+    let mut json = String::from("[\n");
+    for (index, cell) in usage.iter().enumerate() {
+        let _ = write!(
+            json,
+            "  {{\"cell\": {}, \"direct_instances\": {}, \"flattened_instances\": {}, \
+             \"own_area\": {}, \"total_area\": {}, \"unreferenced\": {}}}",
+            json_string(&cell.name),
+            cell.direct_instances,
+            cell.flattened_instances,
+            json_number(cell.own_area),
+            json_number(cell.total_area),
+            cell.is_unreferenced()
+        );
+        json.push_str(if index + 1 < usage.len() { ",\n" } else { "\n" });
+    }
+    json.push_str("]\n");
+    json
+}
+
+/// Quotes a field if it contains characters that are special in CSV.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
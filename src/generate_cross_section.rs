@@ -0,0 +1,71 @@
+use crate::core::CrossSection;
+use crate::core::Layer;
+use svg::node::element::Line;
+use svg::node::element::Rectangle;
+use svg::node::element::Title;
+use svg::Document;
+
+/// Heights are stretched so that the drawing is at least this tall relative
+/// to its width.
+pub(crate) const CROSS_SECTION_ASPECT: f64 = 0.25;
+
+/// Draws a cross-section as colored rectangles, with the distance along the
+/// cut line pointing right and height pointing up. The colors are taken from
+/// the given layers.
+pub fn generate_cross_section_svg(section: &CrossSection, layers: &[Layer]) -> String {
+    let scale = section.vertical_scale(CROSS_SECTION_ASPECT);
+    let width = section.length.max(1.0);
+    let height = ((section.top - section.bottom) * scale).max(1.0);
+    let padding = width * 0.02;
+
+    // SVG has y pointing down, so heights are negated.
+    let view_box = (
+        -padding,
+        -section.top * scale - padding,
+        width + 2.0 * padding,
+        height + 2.0 * padding,
+    );
+    let mut doc = Document::new()
+        .set("viewBox", view_box)
+        .set("style", "background-color: #2D2D2D");
+
+    for rect in &section.rects {
+        let color = layers
+            .get(rect.layer as usize)
+            .map(|layer| layer.color)
+            .unwrap_or(nalgebra::Vector4::new(0.5, 0.5, 0.5, 1.0));
+        let fill = format!(
+            "#{:02x}{:02x}{:02x}",
+            (color.x * 255.0) as u8,
+            (color.y * 255.0) as u8,
+            (color.z * 255.0) as u8
+        );
+        let title = Title::new(format!(
+            "Layer {}: {} to {}",
+            rect.layer, rect.from, rect.to
+        ));
+        doc = doc.add(
+            Rectangle::new()
+                .set("x", rect.from)
+                .set("y", -(rect.z + rect.thickness) * scale)
+                .set("width", rect.to - rect.from)
+                .set("height", rect.thickness * scale)
+                .set("fill", fill)
+                .set("opacity", 0.8)
+                .add(title),
+        );
+    }
+
+    // Ground line at height zero.
+    doc = doc.add(
+        Line::new()
+            .set("x1", 0.0)
+            .set("y1", 0.0)
+            .set("x2", width)
+            .set("y2", 0.0)
+            .set("stroke", "white")
+            .set("stroke-width", padding / 8.0),
+    );
+
+    doc.to_string()
+}
@@ -0,0 +1,72 @@
+use crate::core::DensityMap;
+use crate::rsutils::encode_png;
+use crate::rsutils::heatmap_color;
+use std::fmt::Write;
+
+/// Writes one line per window with its bounds and density, followed by
+/// whether it violates the configured limits.
+pub fn generate_density_csv(map: &DensityMap) -> String {
+    let mut csv = String::from("col,row,min_x,min_y,max_x,max_y,density,failing\n");
+    let violations = map.violations();
+    for row in 0..map.rows {
+        for col in 0..map.cols {
+            let bounds = map.window_bounds(col, row);
+            let failing = violations.iter().any(|v| v.col == col && v.row == row);
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{:.6},{}",
+                col,
+                row,
+                bounds.min_x,
+                bounds.min_y,
+                bounds.max_x,
+                bounds.max_y,
+                map.value(col, row),
+                failing
+            );
+        }
+    }
+    csv
+}
+
+/// Renders the density map as a heatmap image with one block of pixels per
+/// window, y pointing up. Failing windows are marked with a white border.
+pub fn generate_density_png(map: &DensityMap, size: u32) -> Vec<u8> {
+    let scale = (size as usize / map.cols.max(map.rows)).max(1);
+    let width = map.cols * scale;
+    let height = map.rows * scale;
+
+    let failing: Vec<bool> = {
+        let mut failing = vec![false; map.cols * map.rows];
+        for violation in map.violations() {
+            failing[violation.row * map.cols + violation.col] = true;
+        }
+        failing
+    };
+
+    let mut rgba = vec![0u8; width * height * 4];
+    for y in 0..height {
+        let row = map.rows - 1 - y / scale;
+        for x in 0..width {
+            let col = x / scale;
+            let on_border = scale >= 4
+                && [x % scale, y % scale]
+                    .iter()
+                    .any(|&i| i == 0 || i == scale - 1);
+            let (r, g, b) = if on_border && failing[row * map.cols + col] {
+                (1.0, 1.0, 1.0)
+            } else {
+                heatmap_color(map.value(col, row) as f32)
+            };
+            let pixel = &mut rgba[(y * width + x) * 4..][..4];
+            pixel.copy_from_slice(&[
+                (r * 255.0).round() as u8,
+                (g * 255.0).round() as u8,
+                (b * 255.0).round() as u8,
+                255,
+            ]);
+        }
+    }
+
+    encode_png(width as u32, height as u32, &rgba)
+}
@@ -0,0 +1,70 @@
+use crate::core::DrcReport;
+use crate::rsutils::json_number;
+use crate::rsutils::json_string;
+use std::fmt::Write;
+
+/// Formats a report as plain text, with a summary per rule followed by one
+/// line per violation.
+pub fn generate_drc_text(report: &DrcReport) -> String {
+    let mut text = String::new();
+    for (rule, count) in report.rules.iter().zip(report.counts()) {
+        let _ = writeln!(text, "{:<40} {} violations", rule.to_string(), count);
+    }
+    let _ = writeln!(text);
+    for (index, violation) in report.violations.iter().enumerate() {
+        let b = &violation.bounds;
+        let _ = writeln!(
+            text,
+            "#{} [{}] {} at ({}, {}) to ({}, {})",
+            index + 1,
+            report.rules[violation.rule],
+            violation.message,
+            b.min_x,
+            b.min_y,
+            b.max_x,
+            b.max_y
+        );
+    }
+    text
+}
+
+/// Formats a report as JSON, listing the rules with their violation counts
+/// and every violation with its bounding box.
+pub fn generate_drc_json(report: &DrcReport) -> String {
+    let mut json = String::from("{\n  \"rules\": [\n");
+    let counts = report.counts();
+    for (index, rule) in report.rules.iter().enumerate() {
+        let _ = write!(
+            json,
+            "    {{\"rule\": {}, \"violations\": {}}}",
+            json_string(&rule.to_string()),
+            counts[index]
+        );
+        json.push_str(if index + 1 < report.rules.len() {
+            ",\n"
+        } else {
+            "\n"
+        });
+    }
+    json.push_str("  ],\n  \"violations\": [\n");
+    for (index, violation) in report.violations.iter().enumerate() {
+        let b = &violation.bounds;
+        let _ = write!(
+            json,
+            "    {{\"rule\": {}, \"message\": {}, \"bounds\": [{}, {}, {}, {}]}}",
+            json_string(&report.rules[violation.rule].to_string()),
+            json_string(&violation.message),
+            json_number(b.min_x),
+            json_number(b.min_y),
+            json_number(b.max_x),
+            json_number(b.max_y)
+        );
+        json.push_str(if index + 1 < report.violations.len() {
+            ",\n"
+        } else {
+            "\n"
+        });
+    }
+    json.push_str("  ]\n}\n");
+    json
+}
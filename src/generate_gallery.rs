@@ -0,0 +1,167 @@
+#[cfg(not(target_arch = "wasm32"))]
+use crate::rsutils::glob_files;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::rsutils::is_glob_pattern;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::rsutils::walk_files;
+use std::collections::HashSet;
+use std::fmt::Write;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
+/// A single file in a gallery, which either loaded successfully or failed to
+/// parse.
+pub struct GalleryEntry {
+    pub name: String,
+    pub outcome: Result<GalleryItem, String>,
+}
+
+/// Relative paths to the exports of a layout, along with its statistics as
+/// label / value pairs.
+pub struct GalleryItem {
+    pub thumbnail: String,
+    pub svg: String,
+    pub png: String,
+    pub stats: Vec<(String, String)>,
+}
+
+const STYLE: &str = r#"
+body { background: #1a1a1a; color: #ddd; font-family: sans-serif; margin: 2em; }
+.gallery { display: grid; grid-template-columns: repeat(auto-fill, minmax(280px, 1fr)); gap: 1em; }
+.card { background: #2d2d2d; border-radius: 6px; padding: 12px; }
+.card img { width: 100%; height: 200px; object-fit: contain; background: #2d2d2d; }
+.card h2 { font-size: 1em; margin: 0.5em 0; word-break: break-all; }
+.card table { font-size: 0.8em; width: 100%; }
+.card td:last-child { text-align: right; }
+.card a { color: #8cf; }
+.error { border: 1px solid #c44; }
+.error pre { color: #f88; white-space: pre-wrap; font-size: 0.8em; }
+"#;
+
+/// Generates a static HTML page with one card per entry.
+pub fn generate_gallery_html(title: &str, entries: &[GalleryEntry]) -> String {
+    let mut html = String::new();
+    let failures = entries.iter().filter(|e| e.outcome.is_err()).count();
+
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape_html(title),
+        STYLE
+    );
+    let _ = writeln!(
+        html,
+        "<h1>{}</h1>\n<p>{} layouts, {} failed to load</p>\n<div class=\"gallery\">",
+        escape_html(title),
+        entries.len(),
+        failures
+    );
+
+    for entry in entries {
+        let name = escape_html(&entry.name);
+        match &entry.outcome {
+            Ok(item) => {
+                let _ = writeln!(
+                    html,
+                    "<div class=\"card\">\n<a href=\"{svg}\"><img src=\"{thumb}\" alt=\"{name}\"></a>\n<h2>{name}</h2>",
+                    svg = escape_html(&item.svg),
+                    thumb = escape_html(&item.thumbnail),
+                    name = name
+                );
+                let _ = writeln!(
+                    html,
+                    "<a href=\"{}\">SVG</a> | <a href=\"{}\">PNG</a>\n<table>",
+                    escape_html(&item.svg),
+                    escape_html(&item.png)
+                );
+                for (label, value) in &item.stats {
+                    let _ = writeln!(
+                        html,
+                        "<tr><td>{}</td><td>{}</td></tr>",
+                        escape_html(label),
+                        escape_html(value)
+                    );
+                }
+                html.push_str("</table>\n</div>\n");
+            }
+            Err(error) => {
+                let _ = writeln!(
+                    html,
+                    "<div class=\"card error\">\n<h2>{}</h2>\n<pre>{}</pre>\n</div>",
+                    name,
+                    escape_html(error)
+                );
+            }
+        }
+    }
+
+    html.push_str("</div>\n</body>\n</html>\n");
+    html
+}
+
+/// Turns the name of a layout into a file name stem for its exports, which
+/// differs from the stems already used even on case-insensitive file
+/// systems. Returns e.g. `a_b_c` for `a/b.c.gds`, or `a_b_c_2` if that is
+/// taken.
+pub fn gallery_stem(name: &str, used: &mut HashSet<String>) -> String {
+    let base = match name.len().checked_sub(4) {
+        Some(end) if name.is_char_boundary(end) && name[end..].eq_ignore_ascii_case(".gds") => {
+            &name[..end]
+        }
+        _ => name,
+    };
+    let base = base.replace(['/', '.'], "_");
+    let mut stem = base.clone();
+    let mut count = 1;
+    while !used.insert(stem.to_lowercase()) {
+        count += 1;
+        stem = format!("{}_{}", base, count);
+    }
+    stem
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Finds the GDS files to include in a gallery. The input can be a directory,
+/// which is searched recursively, a glob pattern, or a single file.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn find_layout_files(input: &Path) -> std::io::Result<Vec<PathBuf>> {
+    if input.is_dir() {
+        let files = walk_files(input)?
+            .into_iter()
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("gds"))
+            })
+            .collect();
+        return Ok(files);
+    }
+
+    let input_str = input.to_string_lossy();
+    if is_glob_pattern(&input_str) {
+        return glob_files(&input_str);
+    }
+
+    Ok(vec![input.to_path_buf()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gallery_stems_are_unique() {
+        let mut used = HashSet::new();
+        assert_eq!(gallery_stem("a/b_c.gds", &mut used), "a_b_c");
+        assert_eq!(gallery_stem("a_b/c.gds", &mut used), "a_b_c_2");
+        assert_eq!(gallery_stem("A_B_C.GDS", &mut used), "A_B_C_3");
+        assert_eq!(gallery_stem("chip.v2.gds", &mut used), "chip_v2");
+    }
+}
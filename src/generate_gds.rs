@@ -0,0 +1,339 @@
+use crate::core::split_holes;
+use crate::core::split_points;
+use crate::core::CellDefId;
+use crate::core::FillResult;
+use crate::core::FlatCell;
+use crate::core::Layer;
+use crate::Project;
+use anyhow::anyhow;
+use anyhow::Result;
+use gds21::GdsArrayRef;
+use gds21::GdsBoundary;
+use gds21::GdsElement;
+use gds21::GdsLibrary;
+use gds21::GdsPath;
+use gds21::GdsPoint;
+use gds21::GdsStrans;
+use gds21::GdsStruct;
+use gds21::GdsStructRef;
+use gds21::GdsTextElem;
+use geo::AffineTransform;
+
+type Polygon = geo::Polygon<f64>;
+
+/// Points that fit in the XY record of a boundary, including the closing
+/// point.
+const MAX_BOUNDARY_POINTS: usize = 8191;
+
+/// Writes a flattened cell to a GDSII library. Cells referenced below the
+/// depth limit are written along with their own dependencies.
+pub fn generate_flat_gds(project: &Project, flat: &FlatCell) -> Result<Vec<u8>> {
+    let mut library = new_library(project);
+
+    let mut flat_struct = GdsStruct::new(project.struct_name(flat.cell_def_id));
+    append_layers(&mut flat_struct, &flat.layers);
+    for reference in &flat.references {
+        let (xy, strans) = transform_to_placement(&reference.transform);
+        flat_struct
+            .elems
+            .push(GdsElement::GdsStructRef(GdsStructRef {
+                name: project.struct_name(reference.cell_def_id).to_string(),
+                xy,
+                strans,
+                ..Default::default()
+            }));
+    }
+    library.structs.push(flat_struct);
+
+    let referenced: Vec<CellDefId> = flat.references.iter().map(|r| r.cell_def_id).collect();
+    for cell_def_id in project.collect_dependencies(&referenced) {
+        let gds_struct = cell_def_to_gds_struct(project, cell_def_id, |name| name.to_string());
+        library.structs.push(gds_struct);
+    }
+
+    write_library(&library)
+}
+
+/// Writes a cell and every cell it references, directly or indirectly, to a
+/// standalone GDSII library with the original hierarchy and units. The
+/// optional prefix is prepended to all struct names, which avoids collisions
+/// when the result is later merged into another library.
+pub fn generate_extracted_gds(
+    project: &Project,
+    cell_def_id: CellDefId,
+    prefix: Option<&str>,
+) -> Result<Vec<u8>> {
+    let mut library = new_library(project);
+    let prefix = prefix.unwrap_or("");
+    for id in project.collect_dependencies(&[cell_def_id]) {
+        let gds_struct = cell_def_to_gds_struct(project, id, |name| format!("{}{}", prefix, name));
+        library.structs.push(gds_struct);
+    }
+    write_library(&library)
+}
+
+/// Writes a cell and its dependencies along with the given fill shapes. The
+/// fill goes into a struct of its own, named after the top cell, which is
+/// placed in the top cell so that it can be told apart from the design.
+pub fn generate_fill_gds(project: &Project, fill: &FillResult, datatype: i16) -> Result<Vec<u8>> {
+    let mut library = new_library(project);
+    let top_name = project.struct_name(fill.cell_def_id);
+    let mut fill_name = format!("{}_FILL", top_name);
+    let mut suffix = 1;
+    while project.find_cell_def(&fill_name).is_some() {
+        suffix += 1;
+        fill_name = format!("{}_FILL{}", top_name, suffix);
+    }
+
+    for id in project.collect_dependencies(&[fill.cell_def_id]) {
+        let mut gds_struct = cell_def_to_gds_struct(project, id, |name| name.to_string());
+        if id == fill.cell_def_id {
+            gds_struct
+                .elems
+                .push(GdsElement::GdsStructRef(GdsStructRef {
+                    name: fill_name.clone(),
+                    xy: GdsPoint::new(0, 0),
+                    ..Default::default()
+                }));
+        }
+        library.structs.push(gds_struct);
+    }
+
+    let mut fill_struct = GdsStruct::new(fill_name);
+    for polygon in &fill.polygons {
+        for boundary in polygon_to_boundaries(polygon, fill.layer, datatype) {
+            fill_struct.elems.push(GdsElement::GdsBoundary(boundary));
+        }
+    }
+    library.structs.push(fill_struct);
+
+    write_library(&library)
+}
+
+pub(crate) fn new_library(project: &Project) -> GdsLibrary {
+    let mut library = GdsLibrary::new(project.library_name());
+    library.units = project.units().clone();
+    library
+}
+
+pub(crate) fn write_library(library: &GdsLibrary) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    library
+        .write(&mut bytes)
+        .map_err(|e| anyhow!("Failed to write GDSII: {}", e))?;
+    Ok(bytes)
+}
+
+/// Appends the polygons of each layer to a struct as boundary elements.
+pub(crate) fn append_layers(gds_struct: &mut GdsStruct, layers: &[Layer]) {
+    for layer in layers {
+        for polygon in &layer.polygons {
+            for boundary in polygon_to_boundaries(polygon, layer.index(), 0) {
+                gds_struct.elems.push(GdsElement::GdsBoundary(boundary));
+            }
+        }
+    }
+}
+
+/// Converts a polygon into boundary elements, splitting it up if it has
+/// holes or more points than a boundary can hold. Coordinates are rounded to
+/// the database grid.
+pub(crate) fn polygon_to_boundaries(
+    polygon: &Polygon,
+    layer: i16,
+    datatype: i16,
+) -> Vec<GdsBoundary> {
+    split_holes(polygon)
+        .iter()
+        .flat_map(|piece| split_points(piece, MAX_BOUNDARY_POINTS))
+        .filter(|piece| piece.exterior().0.len() >= 4)
+        .map(|piece| GdsBoundary {
+            layer,
+            datatype,
+            xy: piece
+                .exterior()
+                .coords()
+                .map(|c| GdsPoint::new(c.x.round() as i32, c.y.round() as i32))
+                .collect(),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Reconstructs a GDSII struct from a cell definition, renaming the struct
+/// and its references with the given function.
+pub(crate) fn cell_def_to_gds_struct(
+    project: &Project,
+    cell_def_id: CellDefId,
+    rename: impl Fn(&str) -> String,
+) -> GdsStruct {
+    let cell_def = project.cell_def(cell_def_id);
+    let mut gds_struct = GdsStruct::new(rename(project.struct_name(cell_def_id)));
+    gds_struct.dates = cell_def.dates.clone();
+
+    for shape in &cell_def.shapes {
+        let element = match &shape.path {
+            Some(path) => GdsElement::GdsPath(GdsPath {
+                layer: shape.layer,
+                datatype: shape.datatype,
+                xy: path.xy.clone(),
+                width: path.width,
+                path_type: path.path_type,
+                begin_extn: path.begin_extn,
+                end_extn: path.end_extn,
+                elflags: shape.metadata.elflags.clone(),
+                plex: shape.metadata.plex.clone(),
+                properties: shape.metadata.properties.clone(),
+            }),
+            None => GdsElement::GdsBoundary(GdsBoundary {
+                layer: shape.layer,
+                datatype: shape.datatype,
+                xy: shape.boundary_xy(),
+                elflags: shape.metadata.elflags.clone(),
+                plex: shape.metadata.plex.clone(),
+                properties: shape.metadata.properties.clone(),
+            }),
+        };
+        gds_struct.elems.push(element);
+    }
+    for &cell_id in &cell_def.cell_elements {
+        let cell = project.cell(cell_id);
+        let name = rename(project.struct_name(cell.cell_def_id));
+        let element = match &cell.array {
+            Some(array) => GdsElement::GdsArrayRef(GdsArrayRef {
+                name,
+                // Moving an instance only changes the first point.
+                xy: array.xy.clone().map(|point| {
+                    GdsPoint::new(
+                        point.x + cell.xy.x - array.xy[0].x,
+                        point.y + cell.xy.y - array.xy[0].y,
+                    )
+                }),
+                cols: array.cols,
+                rows: array.rows,
+                strans: cell.local_transform.clone(),
+                elflags: cell.metadata.elflags.clone(),
+                plex: cell.metadata.plex.clone(),
+                properties: cell.metadata.properties.clone(),
+            }),
+            None => GdsElement::GdsStructRef(GdsStructRef {
+                name,
+                xy: cell.xy.clone(),
+                strans: cell.local_transform.clone(),
+                elflags: cell.metadata.elflags.clone(),
+                plex: cell.metadata.plex.clone(),
+                properties: cell.metadata.properties.clone(),
+            }),
+        };
+        gds_struct.elems.push(element);
+    }
+    for label in &cell_def.labels {
+        gds_struct.elems.push(GdsElement::GdsTextElem(GdsTextElem {
+            string: label.text.clone(),
+            layer: label.layer,
+            texttype: label.texttype,
+            xy: GdsPoint::new(
+                label.position.x.round() as i32,
+                label.position.y.round() as i32,
+            ),
+            presentation: label.presentation.clone(),
+            path_type: label.path_type,
+            width: label.width,
+            strans: label.strans.clone(),
+            elflags: label.metadata.elflags.clone(),
+            plex: label.metadata.plex.clone(),
+            properties: label.metadata.properties.clone(),
+        }));
+    }
+    gds_struct
+        .elems
+        .extend(cell_def.raw_elements.iter().cloned());
+
+    gds_struct
+}
+
+/// Decomposes an affine transform into a GDSII reference position and
+/// transformation, assuming it consists of reflection, rotation, uniform
+/// scaling and translation.
+pub(crate) fn transform_to_placement(transform: &AffineTransform) -> (GdsPoint, Option<GdsStrans>) {
+    let xy = GdsPoint::new(
+        transform.xoff().round() as i32,
+        transform.yoff().round() as i32,
+    );
+    let determinant = transform.a() * transform.e() - transform.b() * transform.d();
+    let reflected = determinant < 0.0;
+    let angle = transform.d().atan2(transform.a()).to_degrees();
+    let mag = transform.a().hypot(transform.d());
+
+    let angle = (angle.abs() > 1e-9).then_some(angle);
+    let mag = ((mag - 1.0).abs() > 1e-9).then_some(mag);
+    if !reflected && angle.is_none() && mag.is_none() {
+        return (xy, None);
+    }
+
+    let strans = GdsStrans {
+        reflected,
+        abs_mag: false,
+        abs_angle: false,
+        mag,
+        angle,
+    };
+    (xy, Some(strans))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::test_layouts::*;
+    use geo::Area;
+    use geo::LineString;
+
+    #[test]
+    fn test_extracted_cells_are_renamed() {
+        let project = project(vec![
+            gds_struct("bit", vec![rect(1, (0, 0), (10, 10))]),
+            gds_struct("ram", vec![aref("bit", (5, 0), (4, 2), (10, 20))]),
+            gds_struct("top", vec![sref("ram", (100, 0)), sref("bit", (0, 0))]),
+        ]);
+        let ram = project.find_cell_def("ram").unwrap();
+        let bytes = generate_extracted_gds(&project, ram, Some("x_")).unwrap();
+        let library = GdsLibrary::from_bytes(bytes).unwrap();
+
+        let mut names: Vec<&str> = library.structs.iter().map(|s| s.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["x_bit", "x_ram"]);
+        let ram = library.structs.iter().find(|s| s.name == "x_ram").unwrap();
+        let GdsElement::GdsArrayRef(array) = &ram.elems[0] else {
+            panic!("expected an array reference");
+        };
+        assert_eq!(array.name, "x_bit");
+        assert_eq!((array.cols, array.rows), (4, 2));
+        assert_eq!(array.xy[0], GdsPoint::new(5, 0));
+    }
+
+    #[test]
+    fn test_long_boundaries_are_split() {
+        // A comb with 3000 teeth has 12002 points.
+        let mut coords = vec![(0.0, 0.0), (6000.0, 0.0)];
+        for tooth in (0..3000).rev() {
+            let x = tooth as f64 * 2.0;
+            coords.extend([(x + 2.0, 10.0), (x + 1.0, 10.0), (x + 1.0, 5.0), (x, 5.0)]);
+        }
+        let polygon = Polygon::new(LineString::from(coords), vec![]);
+        assert!(polygon.exterior().0.len() > MAX_BOUNDARY_POINTS);
+
+        let boundaries = polygon_to_boundaries(&polygon, 1, 0);
+        assert!(boundaries.len() >= 2);
+        assert!(boundaries
+            .iter()
+            .all(|boundary| boundary.xy.len() <= MAX_BOUNDARY_POINTS));
+        let area: f64 = boundaries
+            .iter()
+            .map(|boundary| {
+                let coords = boundary.xy.iter().map(|p| (p.x as f64, p.y as f64));
+                Polygon::new(LineString::from_iter(coords), vec![]).unsigned_area()
+            })
+            .sum();
+        assert_eq!(area, polygon.unsigned_area());
+    }
+}
@@ -0,0 +1,208 @@
+use crate::core::Layer;
+use crate::generate_svg::SvgOrientation;
+use crate::graphics::BoundingBox;
+use crate::rsutils::encode_png;
+use crate::Project;
+use geo::LineString;
+
+type Polygon = geo::Polygon<f64>;
+
+/// Number of vertical samples taken per pixel row when rasterizing.
+const SUBSAMPLES: usize = 4;
+
+/// Options for [generate_png].
+#[derive(Debug, Clone)]
+pub struct PngOptions {
+    /// Length of the longer side of the image, in pixels.
+    pub size: u32,
+    pub orientation: SvgOrientation,
+    pub background: [u8; 3],
+}
+
+impl Default for PngOptions {
+    fn default() -> Self {
+        Self {
+            size: 256,
+            orientation: SvgOrientation::Transposed,
+            background: [0x2d, 0x2d, 0x2d],
+        }
+    }
+}
+
+/// Rasterizes all visible layers into a PNG image, using the layer colors and
+/// alpha values. Intended for thumbnails, so polygons are filled with a
+/// simple scanline algorithm that approximates coverage for anti-aliasing.
+pub fn generate_png(layers: &[Layer], options: &PngOptions) -> Vec<u8> {
+    let layers: Vec<&Layer> = layers.iter().collect();
+    rasterize(&layers, options, |layer, add| {
+        layer.render_polygons().iter().for_each(add)
+    })
+}
+
+/// Like [generate_png] for the layers of a project, reading the shapes of
+/// each placement in turn rather than requiring the layers to be flattened,
+/// see [Project::for_each_layer_polygon].
+pub fn generate_project_png(project: &Project, options: &PngOptions) -> Vec<u8> {
+    let layers: Vec<&Layer> = project.layers().iter().collect();
+    rasterize(&layers, options, |layer, add| {
+        project.for_each_layer_polygon(layer.index() as usize, add)
+    })
+}
+
+fn rasterize(
+    layers: &[&Layer],
+    options: &PngOptions,
+    for_each_polygon: impl Fn(&Layer, &mut dyn FnMut(&Polygon)),
+) -> Vec<u8> {
+    let mut bounds = BoundingBox::new();
+    for layer in layers {
+        if layer.visible && !layer.bounds.is_empty() {
+            bounds.encompass(&layer.bounds);
+        }
+    }
+    if bounds.is_empty() {
+        bounds = BoundingBox {
+            min_x: 0.0,
+            min_y: 0.0,
+            max_x: 1.0,
+            max_y: 1.0,
+        };
+    }
+    let padding = (bounds.width() + bounds.height()) * 0.05;
+    let bounds = bounds.inflate(padding);
+
+    let (u0, v0) = options.orientation.map(bounds.min_x, bounds.min_y);
+    let (u1, v1) = options.orientation.map(bounds.max_x, bounds.max_y);
+    let (min_u, min_v) = (u0.min(u1), v0.min(v1));
+    let (extent_u, extent_v) = ((u1 - u0).abs(), (v1 - v0).abs());
+
+    let scale = options.size as f64 / extent_u.max(extent_v);
+    let width = ((extent_u * scale).round() as u32).max(1);
+    let height = ((extent_v * scale).round() as u32).max(1);
+
+    let mut pixels = vec![[0.0f32; 3]; (width * height) as usize];
+    let background = options.background.map(|c| c as f32 / 255.0);
+    pixels.fill(background);
+
+    let mut coverage = vec![0.0f32; (width * height) as usize];
+    for layer in layers.iter().filter(|layer| layer.visible) {
+        coverage.fill(0.0);
+        let mut raster = Rasterizer {
+            width,
+            height,
+            coverage: &mut coverage,
+        };
+        for_each_polygon(layer, &mut |polygon| {
+            let mut edges = Vec::new();
+            let rings = std::iter::once(polygon.exterior()).chain(polygon.interiors());
+            for ring in rings {
+                append_edges(&mut edges, ring, |x, y| {
+                    let (u, v) = options.orientation.map(x, y);
+                    ((u - min_u) * scale, (v - min_v) * scale)
+                });
+            }
+            raster.fill(&edges);
+        });
+
+        let color = [layer.color.x, layer.color.y, layer.color.z];
+        for (pixel, cov) in pixels.iter_mut().zip(&coverage) {
+            let alpha = layer.color.w * cov.min(1.0);
+            for c in 0..3 {
+                pixel[c] += (color[c] - pixel[c]) * alpha;
+            }
+        }
+    }
+
+    let rgba: Vec<u8> = pixels
+        .iter()
+        .flat_map(|p| {
+            let [r, g, b] = p.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+            [r, g, b, 255]
+        })
+        .collect();
+
+    encode_png(width, height, &rgba)
+}
+
+/// An edge of a polygon in pixel space.
+struct Edge {
+    x0: f64,
+    y0: f64,
+    x1: f64,
+    y1: f64,
+}
+
+fn append_edges(
+    edges: &mut Vec<Edge>,
+    ring: &LineString<f64>,
+    to_pixels: impl Fn(f64, f64) -> (f64, f64),
+) {
+    let points: Vec<(f64, f64)> = ring.points().map(|p| to_pixels(p.x(), p.y())).collect();
+    for pair in points.windows(2) {
+        let ((x0, y0), (x1, y1)) = (pair[0], pair[1]);
+        if y0 != y1 {
+            edges.push(Edge { x0, y0, x1, y1 });
+        }
+    }
+}
+
+struct Rasterizer<'a> {
+    width: u32,
+    height: u32,
+    coverage: &'a mut [f32],
+}
+
+impl Rasterizer<'_> {
+    /// Accumulates the coverage of the given closed edge loop using the
+    /// even-odd rule.
+    fn fill(&mut self, edges: &[Edge]) {
+        if edges.is_empty() {
+            return;
+        }
+        let min_y = edges
+            .iter()
+            .map(|e| e.y0.min(e.y1))
+            .fold(f64::MAX, f64::min);
+        let max_y = edges
+            .iter()
+            .map(|e| e.y0.max(e.y1))
+            .fold(f64::MIN, f64::max);
+        let first_row = (min_y.floor().max(0.0)) as u32;
+        let last_row = (max_y.ceil().min(self.height as f64)) as u32;
+
+        let sample_weight = 1.0 / SUBSAMPLES as f32;
+        let mut crossings = Vec::new();
+        for row in first_row..last_row {
+            for sample in 0..SUBSAMPLES {
+                let y = row as f64 + (sample as f64 + 0.5) / SUBSAMPLES as f64;
+                crossings.clear();
+                for e in edges {
+                    if (e.y0 <= y) != (e.y1 <= y) {
+                        let t = (y - e.y0) / (e.y1 - e.y0);
+                        crossings.push(e.x0 + t * (e.x1 - e.x0));
+                    }
+                }
+                crossings.sort_by(|a, b| a.total_cmp(b));
+                for span in crossings.chunks_exact(2) {
+                    self.add_span(row, span[0], span[1], sample_weight);
+                }
+            }
+        }
+    }
+
+    fn add_span(&mut self, row: u32, x0: f64, x1: f64, weight: f32) {
+        let x0 = x0.clamp(0.0, self.width as f64);
+        let x1 = x1.clamp(0.0, self.width as f64);
+        if x1 <= x0 {
+            return;
+        }
+        let line = &mut self.coverage[(row * self.width) as usize..][..self.width as usize];
+        let first = x0.floor() as usize;
+        let last = (x1.ceil() as usize).min(line.len());
+        for (col, cov) in line.iter_mut().enumerate().take(last).skip(first) {
+            let left = x0.max(col as f64);
+            let right = x1.min(col as f64 + 1.0);
+            *cov += weight * (right - left) as f32;
+        }
+    }
+}
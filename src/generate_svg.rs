@@ -1,34 +1,210 @@
 use crate::core::Layer;
 use crate::graphics::BoundingBox;
+use crate::Project;
+use geo::BooleanOps;
+use geo::BoundingRect;
+use geo::Coord;
+use geo::LineString;
+use geo::Rect;
+use std::borrow::Cow;
+use std::str::FromStr;
 use svg::node::element::Group;
 use svg::node::element::Path;
+use svg::node::element::Rectangle;
+use svg::node::element::Text;
 use svg::Document;
+use svg::Node;
 
-const PRECISION: f64 = 0.0001;
+type Polygon = geo::Polygon<f64>;
 
-pub fn generate_svg(layers: &[Layer]) -> String {
-    // Get the overall bounding box
-    let mut bounds = BoundingBox::new();
-    for layer in layers {
-        if !layer.bounds.is_empty() {
-            bounds.encompass(&layer.bounds);
+/// Controls how world coordinates are mapped onto the SVG canvas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SvgOrientation {
+    /// Swaps x and y, which matches the orientation of the interactive viewer.
+    #[default]
+    Transposed,
+    /// The natural GDS orientation, with x pointing right and y pointing up.
+    YUp,
+    /// Uses world coordinates verbatim, which leaves the layout upside down.
+    YDown,
+}
+
+impl FromStr for SvgOrientation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "transposed" => Ok(Self::Transposed),
+            "y-up" => Ok(Self::YUp),
+            "y-down" => Ok(Self::YDown),
+            _ => Err(format!(
+                "Unknown orientation '{}', expected transposed, y-up or y-down",
+                s
+            )),
+        }
+    }
+}
+
+impl SvgOrientation {
+    pub(crate) fn map(&self, x: f64, y: f64) -> (f64, f64) {
+        match self {
+            Self::Transposed => (y, x),
+            Self::YUp => (x, -y),
+            Self::YDown => (x, y),
+        }
+    }
+
+    fn map_bounds(&self, bounds: &BoundingBox) -> BoundingBox {
+        let (x0, y0) = self.map(bounds.min_x, bounds.min_y);
+        let (x1, y1) = self.map(bounds.max_x, bounds.max_y);
+        BoundingBox {
+            min_x: x0.min(x1),
+            min_y: y0.min(y1),
+            max_x: x0.max(x1),
+            max_y: y0.max(y1),
         }
     }
+}
+
+/// Options for [generate_svg_with_options]. The defaults are used by
+/// [generate_svg].
+#[derive(Debug, Clone)]
+pub struct SvgOptions {
+    /// Layer indices to include, or all layers if None.
+    pub layers: Option<Vec<i16>>,
+    /// World-space window to crop to. Polygons are clipped against it and it
+    /// becomes the viewBox, without padding.
+    pub window: Option<BoundingBox>,
+    pub orientation: SvgOrientation,
+    /// CSS color for the document background, or None for transparency.
+    pub background: Option<String>,
+    pub opacity: f64,
+    /// CSS color for polygon outlines, or None to disable stroking.
+    pub stroke: Option<String>,
+    /// Width of the polygon and placeholder outlines, in world units.
+    pub stroke_width: f64,
+    /// Coordinates are rounded to a multiple of this value.
+    pub precision: f64,
+}
 
-    // Add padding
-    let padding = (bounds.width() + bounds.height()) * 0.05;
-    let padded_bounds = bounds.inflate(padding);
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            layers: None,
+            window: None,
+            orientation: SvgOrientation::Transposed,
+            background: Some("#2D2D2D".to_string()),
+            opacity: 0.5,
+            stroke: None,
+            stroke_width: 1.0,
+            precision: 0.0001,
+        }
+    }
+}
+
+impl SvgOptions {
+    fn includes_layer(&self, index: i16) -> bool {
+        match &self.layers {
+            Some(layers) => layers.contains(&index),
+            None => true,
+        }
+    }
+}
+
+pub fn generate_svg(layers: &[Layer]) -> String {
+    generate_svg_with_options(layers, &SvgOptions::default())
+}
+
+pub fn generate_svg_with_options(layers: &[Layer], options: &SvgOptions) -> String {
+    generate_svg_with_placeholders(layers, &[], options)
+}
+
+/// Like [generate_svg_with_options], additionally drawing the given boxes as
+/// outlines labelled with the given names, such as the placeholders of
+/// instances below the display depth.
+pub fn generate_svg_with_placeholders(
+    layers: &[Layer],
+    placeholders: &[(BoundingBox, String)],
+    options: &SvgOptions,
+) -> String {
+    let layers: Vec<&Layer> = layers
+        .iter()
+        .filter(|layer| options.includes_layer(layer.index()))
+        .collect();
+    build_svg(&layers, placeholders, options, |layer, add| {
+        layer.render_polygons().iter().for_each(add)
+    })
+}
+
+/// Like [generate_svg_with_placeholders] for the layers of a project, reading
+/// the shapes of each placement in turn rather than requiring the layers to
+/// be flattened, see [Project::for_each_layer_polygon].
+pub fn generate_project_svg(
+    project: &Project,
+    placeholders: &[(BoundingBox, String)],
+    options: &SvgOptions,
+) -> String {
+    let layers: Vec<&Layer> = project
+        .layers()
+        .iter()
+        .filter(|layer| options.includes_layer(layer.index()))
+        .collect();
+    build_svg(&layers, placeholders, options, |layer, add| {
+        project.for_each_layer_polygon(layer.index() as usize, add)
+    })
+}
+
+fn build_svg(
+    layers: &[&Layer],
+    placeholders: &[(BoundingBox, String)],
+    options: &SvgOptions,
+    for_each_polygon: impl Fn(&Layer, &mut dyn FnMut(&Polygon)),
+) -> String {
+    // Get the overall bounding box, adding padding unless a window was given
+    let bounds = match options.window {
+        Some(window) => window,
+        None => {
+            let mut bounds = BoundingBox::new();
+            for layer in layers {
+                if !layer.bounds.is_empty() {
+                    bounds.encompass(&layer.bounds);
+                }
+            }
+            for (placeholder, _) in placeholders {
+                bounds.encompass(placeholder);
+            }
+            let padding = (bounds.width() + bounds.height()) * 0.05;
+            bounds.inflate(padding)
+        }
+    };
+
+    let view_bounds = options.orientation.map_bounds(&bounds);
     let view_box = (
-        padded_bounds.min_y,
-        padded_bounds.min_x,
-        padded_bounds.height(),
-        padded_bounds.width(),
+        view_bounds.min_x,
+        view_bounds.min_y,
+        view_bounds.width(),
+        view_bounds.height(),
     );
 
     // Create SVG document
-    let mut doc = Document::new()
-        .set("viewBox", view_box)
-        .set("style", "background-color: #2D2D2D");
+    let mut doc = Document::new().set("viewBox", view_box);
+    if let Some(background) = &options.background {
+        doc = doc.set("style", format!("background-color: {}", background));
+    }
+
+    let clip_polygon = options.window.map(|window| {
+        Rect::new(
+            Coord {
+                x: window.min_x,
+                y: window.min_y,
+            },
+            Coord {
+                x: window.max_x,
+                y: window.max_y,
+            },
+        )
+        .to_polygon()
+    });
 
     // Add each layer as a group
     for layer in layers {
@@ -40,65 +216,260 @@ pub fn generate_svg(layers: &[Layer]) -> String {
             (layer.color.z * 255.0) as u8
         );
 
-        let mut group = Group::new().set("fill", color).set("opacity", 0.5);
+        let mut group = Group::new()
+            .set("id", layer_id(layer))
+            .set("fill", color)
+            .set("fill-rule", "evenodd")
+            .set("opacity", options.opacity);
 
-        for polygon in &layer.polygons {
-            let path_data = polygon_to_path_data(polygon);
-            let path = Path::new().set("d", path_data).set("stroke", "none");
-            group = group.add(path);
+        match &options.stroke {
+            Some(stroke) => {
+                group = group
+                    .set("stroke", stroke.as_str())
+                    .set("stroke-width", options.stroke_width)
+            }
+            None => group = group.set("stroke", "none"),
         }
 
+        for_each_polygon(layer, &mut |polygon| {
+            for polygon in clip_polygon_to_window(polygon, clip_polygon.as_ref()) {
+                let path_data = polygon_to_path_data(&polygon, options);
+                group.append(Path::new().set("d", path_data));
+            }
+        });
+
         doc = doc.add(group);
     }
 
+    if !placeholders.is_empty() {
+        doc = doc.add(placeholder_group(placeholders, options));
+    }
+
     doc.to_string()
 }
 
-fn polygon_to_path_data(polygon: &geo::Polygon<f64>) -> String {
+/// Id of the group of a layer: `layer-` followed by the layer number, or by
+/// the name of a derived layer, since derived layers are numbered after the
+/// layers in the file and their numbers change when the file does.
+fn layer_id(layer: &Layer) -> String {
+    match &layer.name {
+        Some(name) => format!("layer-{}", name),
+        None => format!("layer-{}", layer.index()),
+    }
+}
+
+/// Outlines and names of placeholders, left out if outside of the window.
+fn placeholder_group(placeholders: &[(BoundingBox, String)], options: &SvgOptions) -> Group {
+    let stroke = options.stroke.as_deref().unwrap_or("#CCCCCC");
+    let mut group = Group::new()
+        .set("id", "placeholders")
+        .set("fill", "none")
+        .set("stroke", stroke)
+        .set("stroke-width", options.stroke_width);
+    for (bounds, name) in placeholders {
+        if let Some(window) = &options.window {
+            let outside = bounds.max_x < window.min_x
+                || bounds.min_x > window.max_x
+                || bounds.max_y < window.min_y
+                || bounds.min_y > window.max_y;
+            if outside {
+                continue;
+            }
+        }
+        let view = options.orientation.map_bounds(bounds);
+        let x = round_to_precision(view.min_x, options.precision);
+        let y = round_to_precision(view.min_y, options.precision);
+        let width = round_to_precision(view.width(), options.precision);
+        let height = round_to_precision(view.height(), options.precision);
+        group = group.add(
+            Rectangle::new()
+                .set("x", x)
+                .set("y", y)
+                .set("width", width)
+                .set("height", height),
+        );
+
+        // Fit the name within the width of the box, and a tenth of its height.
+        let font_size = (height / 10.0).min(width / (name.len().max(1) as f64 * 0.7));
+        let text = Text::new(name.as_str())
+            .set("x", x + font_size / 2.0)
+            .set("y", y + font_size * 1.5)
+            .set("font-size", font_size)
+            .set("font-family", "monospace")
+            .set("fill", stroke)
+            .set("stroke", "none");
+        group = group.add(text);
+    }
+    group
+}
+
+fn clip_polygon_to_window<'a>(
+    polygon: &'a Polygon,
+    window: Option<&Polygon>,
+) -> Vec<Cow<'a, Polygon>> {
+    let Some(window) = window else {
+        return vec![Cow::Borrowed(polygon)];
+    };
+    let (Some(bbox), Some(window_bbox)) = (polygon.bounding_rect(), window.bounding_rect()) else {
+        return vec![];
+    };
+
+    // Avoid the expensive boolean operation for trivially accepted or
+    // rejected polygons.
+    if bbox.min().x >= window_bbox.min().x
+        && bbox.min().y >= window_bbox.min().y
+        && bbox.max().x <= window_bbox.max().x
+        && bbox.max().y <= window_bbox.max().y
+    {
+        return vec![Cow::Borrowed(polygon)];
+    }
+    if bbox.max().x < window_bbox.min().x
+        || bbox.max().y < window_bbox.min().y
+        || bbox.min().x > window_bbox.max().x
+        || bbox.min().y > window_bbox.max().y
+    {
+        return vec![];
+    }
+
+    polygon
+        .intersection(window)
+        .into_iter()
+        .map(Cow::Owned)
+        .collect()
+}
+
+fn polygon_to_path_data(polygon: &Polygon, options: &SvgOptions) -> String {
     let mut path_data = String::new();
+    append_ring(&mut path_data, polygon.exterior(), options);
+    for interior in polygon.interiors() {
+        append_ring(&mut path_data, interior, options);
+    }
+    path_data
+}
+
+fn append_ring(path_data: &mut String, ring: &LineString<f64>, options: &SvgOptions) {
+    let mut points = ring.points();
+    let Some(first) = points.next() else {
+        return;
+    };
 
-    if let Some(point) = polygon.exterior().points().next() {
+    let (x, y) = options.orientation.map(first.x(), first.y());
+    path_data.push_str(&format!(
+        "M {} {} ",
+        round_to_precision(x, options.precision),
+        round_to_precision(y, options.precision)
+    ));
+
+    for point in points {
+        let (x, y) = options.orientation.map(point.x(), point.y());
         path_data.push_str(&format!(
-            "M {} {} ",
-            round_to_precision(point.y()),
-            round_to_precision(point.x())
+            "L {} {} ",
+            round_to_precision(x, options.precision),
+            round_to_precision(y, options.precision)
         ));
-
-        for point in polygon.exterior().points().skip(1) {
-            path_data.push_str(&format!(
-                "L {} {} ",
-                round_to_precision(point.y()),
-                round_to_precision(point.x())
-            ));
-        }
     }
 
     path_data.push('Z');
+}
 
-    // Add interior rings (holes)
-    for interior in polygon.interiors() {
-        if let Some(point) = interior.points().next() {
-            path_data.push_str(&format!(
-                "M {} {} ",
-                round_to_precision(point.y()),
-                round_to_precision(point.x())
-            ));
-
-            for point in interior.points().skip(1) {
-                path_data.push_str(&format!(
-                    "L {} {} ",
-                    round_to_precision(point.y()),
-                    round_to_precision(point.x())
-                ));
-            }
+fn round_to_precision(value: f64, precision: f64) -> f64 {
+    if precision <= 0.0 {
+        return value;
+    }
+    (value / precision).round() * precision
+}
 
-            path_data.push('Z');
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square_layer(size: f64) -> Layer {
+        let mut layer = Layer::new(3);
+        let ring = vec![(0.0, 0.0), (size, 0.0), (size, size), (0.0, size)];
+        layer
+            .polygons
+            .push(Polygon::new(LineString::from(ring), vec![]));
+        layer.update_bounds();
+        layer
     }
 
-    path_data
-}
+    #[test]
+    fn test_orientation_from_str() {
+        assert_eq!("y-up".parse(), Ok(SvgOrientation::YUp));
+        assert_eq!("transposed".parse(), Ok(SvgOrientation::Transposed));
+        assert!("sideways".parse::<SvgOrientation>().is_err());
+    }
+
+    #[test]
+    fn test_layer_filter_and_group_names() {
+        let layers = [square_layer(10.0)];
+        let svg = generate_svg(&layers);
+        assert!(svg.contains("id=\"layer-3\""));
+
+        let options = SvgOptions {
+            layers: Some(vec![1, 2]),
+            ..Default::default()
+        };
+        let svg = generate_svg_with_options(&layers, &options);
+        assert!(!svg.contains("layer-3"));
+
+        let mut derived = square_layer(10.0);
+        derived.name = Some("gate".to_string());
+        let svg = generate_svg(&[derived]);
+        assert!(svg.contains("id=\"layer-gate\""));
+    }
+
+    #[test]
+    fn test_window_clips_polygons() {
+        let layers = [square_layer(10.0)];
+        let options = SvgOptions {
+            window: Some(BoundingBox {
+                min_x: 5.0,
+                min_y: 5.0,
+                max_x: 20.0,
+                max_y: 20.0,
+            }),
+            orientation: SvgOrientation::YDown,
+            precision: 1.0,
+            ..Default::default()
+        };
+        let svg = generate_svg_with_options(&layers, &options);
+        assert!(svg.contains("viewBox=\"5 5 15 15\""));
+        assert!(svg.contains("M 5 5"));
+        assert!(!svg.contains("M 0 0"));
+    }
 
-fn round_to_precision(value: f64) -> f64 {
-    (value / PRECISION).round() * PRECISION
+    #[test]
+    fn test_placeholders_are_labelled() {
+        let layers = [square_layer(10.0)];
+        let placeholders = [(
+            BoundingBox {
+                min_x: 20.0,
+                min_y: 0.0,
+                max_x: 40.0,
+                max_y: 10.0,
+            },
+            "nand2".to_string(),
+        )];
+        let options = SvgOptions {
+            orientation: SvgOrientation::YDown,
+            ..Default::default()
+        };
+        let svg = generate_svg_with_placeholders(&layers, &placeholders, &options);
+        assert!(svg.contains("id=\"placeholders\""));
+        assert!(svg.contains("nand2"));
+        assert!(svg.contains("width=\"20\""));
+
+        let options = SvgOptions {
+            window: Some(BoundingBox {
+                min_x: 0.0,
+                min_y: 0.0,
+                max_x: 10.0,
+                max_y: 10.0,
+            }),
+            ..options
+        };
+        let svg = generate_svg_with_placeholders(&layers, &placeholders, &options);
+        assert!(!svg.contains("nand2"));
+    }
 }
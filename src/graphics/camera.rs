@@ -20,6 +20,7 @@ impl fmt::Debug for Camera {
     }
 }
 
+#[derive(Clone)]
 pub struct Camera {
     pub position: Point,
     pub up: Vec3,
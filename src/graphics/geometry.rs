@@ -11,14 +11,26 @@ impl IdMapKey for GeometryId {
     }
 }
 
+/// Vertex attributes, by location. Shaders may declare any of them.
+pub(super) const ATTRIBUTES: [&str; 3] = ["position", "instance_x", "instance_y"];
+
+/// Floats per instance: the two rows of a 2x3 affine transform.
+const INSTANCE_STRIDE: usize = 6;
+
 pub struct Geometry {
     pub positions: Vec<f32>,
     pub indices: Vec<u32>,
+    /// Transforms of the instances to draw, as consecutive rows of 2x3
+    /// affine matrices, which shaders read as `instance_x` and
+    /// `instance_y`. If empty, the geometry is drawn once without them.
+    pub instances: Vec<f32>,
     vao: Option<glow::VertexArray>,
     positions_vbo: Option<glow::Buffer>,
     indices_vbo: Option<glow::Buffer>,
+    instances_vbo: Option<glow::Buffer>,
     positions_uploaded: bool,
     indices_uploaded: bool,
+    instances_uploaded: bool,
 }
 
 impl Geometry {
@@ -26,23 +38,47 @@ impl Geometry {
         Self {
             positions: Vec::new(),
             indices: Vec::new(),
+            instances: Vec::new(),
             vao: None,
             positions_vbo: None,
             indices_vbo: None,
+            instances_vbo: None,
             positions_uploaded: false,
             indices_uploaded: false,
+            instances_uploaded: false,
         }
     }
 
+    /// Appends an instance with the given 2x3 affine transform rows.
+    pub fn push_instance(&mut self, row_x: [f32; 3], row_y: [f32; 3]) {
+        self.instances.extend(row_x);
+        self.instances.extend(row_y);
+    }
+
+    /// Replaces the instance transforms, which are uploaded again before the
+    /// next draw.
+    pub fn set_instances(&mut self, instances: Vec<f32>) {
+        self.instances = instances;
+        self.instances_uploaded = false;
+    }
+
+    pub fn instance_count(&self) -> usize {
+        self.instances.len() / INSTANCE_STRIDE
+    }
+
     pub(super) fn destroy(&mut self, gl: &glow::Context) {
         unsafe {
             if let Some(vao) = self.vao.take() {
                 gl.delete_vertex_array(vao)
             }
-            [&mut self.positions_vbo, &mut self.indices_vbo]
-                .iter_mut()
-                .filter_map(|vbo| vbo.take())
-                .for_each(|vbo| gl.delete_buffer(vbo));
+            [
+                &mut self.positions_vbo,
+                &mut self.indices_vbo,
+                &mut self.instances_vbo,
+            ]
+            .iter_mut()
+            .filter_map(|vbo| vbo.take())
+            .for_each(|vbo| gl.delete_buffer(vbo));
         }
     }
 
@@ -94,6 +130,37 @@ impl Geometry {
         self.indices_uploaded = true;
     }
 
+    pub(super) fn upload_instances(&mut self, gl: &glow::Context) {
+        self.instances_uploaded = true;
+        if self.instances.is_empty() {
+            return;
+        }
+
+        if self.vao.is_none() {
+            self.create(gl);
+        }
+
+        unsafe {
+            gl.bind_vertex_array(self.vao);
+            if self.instances_vbo.is_none() {
+                self.instances_vbo =
+                    Some(gl.create_buffer().expect("Failed to create instances VBO"));
+            }
+            gl.bind_buffer(glow::ARRAY_BUFFER, self.instances_vbo);
+            gl.buffer_data_u8_slice(
+                glow::ARRAY_BUFFER,
+                bytemuck::cast_slice(&self.instances),
+                glow::STATIC_DRAW,
+            );
+            let stride = (INSTANCE_STRIDE * 4) as i32;
+            for (location, offset) in [(1, 0), (2, 12)] {
+                gl.enable_vertex_attrib_array(location);
+                gl.vertex_attrib_pointer_f32(location, 3, glow::FLOAT, false, stride, offset);
+                gl.vertex_attrib_divisor(location, 1);
+            }
+        }
+    }
+
     pub(super) fn bind(&mut self, gl: &glow::Context) {
         if !self.positions_uploaded {
             self.upload_positions(gl);
@@ -101,6 +168,9 @@ impl Geometry {
         if !self.indices_uploaded {
             self.upload_indices(gl);
         }
+        if !self.instances_uploaded {
+            self.upload_instances(gl);
+        }
         unsafe {
             gl.bind_vertex_array(self.vao);
         }
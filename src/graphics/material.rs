@@ -1,3 +1,4 @@
+use crate::graphics::geometry::ATTRIBUTES;
 use crate::rsutils::IdMapKey;
 use glow::HasContext;
 use indexmap::IndexMap;
@@ -76,6 +77,12 @@ impl Material {
                 shaders.push(shader);
             }
 
+            // Attribute locations must match the vertex arrays of Geometry,
+            // which GLSL ES 1.0 cannot declare in the shader itself.
+            for (location, name) in ATTRIBUTES.iter().enumerate() {
+                gl.bind_attrib_location(program, location as u32, name);
+            }
+
             // Link program
             gl.link_program(program);
             if !gl.get_program_link_status(program) {
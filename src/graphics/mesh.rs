@@ -144,12 +144,22 @@ impl Mesh {
 
             geometry.bind(gl);
 
-            gl.draw_elements(
-                glow::TRIANGLES,
-                geometry.indices.len() as i32,
-                glow::UNSIGNED_INT,
-                0,
-            );
+            if geometry.instances.is_empty() {
+                gl.draw_elements(
+                    glow::TRIANGLES,
+                    geometry.indices.len() as i32,
+                    glow::UNSIGNED_INT,
+                    0,
+                );
+            } else {
+                gl.draw_elements_instanced(
+                    glow::TRIANGLES,
+                    geometry.indices.len() as i32,
+                    glow::UNSIGNED_INT,
+                    0,
+                    geometry.instance_count() as i32,
+                );
+            }
         }
     }
 }
@@ -66,6 +66,25 @@ impl Scene {
         }
     }
 
+    /// Removes a mesh along with its geometry, which must not be shared with
+    /// other meshes.
+    pub fn remove_mesh(&mut self, gl: &glow::Context, id: MeshId) {
+        let Some(mesh) = self.meshes.remove(&id) else {
+            log::error!("Scene: remove_mesh called with non-existent id");
+            return;
+        };
+        if let Some(mut geometry) = self.geometries.remove(&mesh.geometry_id) {
+            geometry.destroy(gl);
+        }
+    }
+
+    /// Removes a material that is no longer used by any mesh.
+    pub fn remove_material(&mut self, gl: &glow::Context, id: MaterialId) {
+        if let Some(mut material) = self.materials.remove(&id) {
+            material.destroy(gl);
+        }
+    }
+
     pub fn destroy(&mut self, gl: &glow::Context) {
         // Destroy all geometries
         for geometry in self.geometries.values_mut() {
@@ -1,8 +1,16 @@
 #![allow(dead_code)]
 
 mod app_controller;
+mod app_overlays;
 mod app_shaders;
 mod core;
+mod generate_cell_usage;
+mod generate_cross_section;
+mod generate_density;
+mod generate_drc_report;
+mod generate_gallery;
+mod generate_gds;
+mod generate_png;
 mod generate_svg;
 mod graphics;
 mod rsutils;
@@ -15,9 +23,70 @@ mod components;
 
 #[cfg(not(target_arch = "wasm32"))]
 pub use app_window::spawn_window;
+#[cfg(not(target_arch = "wasm32"))]
+pub use app_window::WindowOptions;
 
+pub use core::split_derived_layer_definition;
+pub use core::CellDefId;
+pub use core::CellUsage;
+pub use core::ChildGroup;
+pub use core::Connectivity;
+pub use core::CornerStyle;
+pub use core::CrossSection;
+pub use core::DensityMap;
+pub use core::DensityOptions;
+pub use core::DerivedLayer;
+pub use core::DrcReport;
+pub use core::DrcRule;
+pub use core::EdgeAngles;
+pub use core::Edit;
+pub use core::EditChange;
+pub use core::EditOutcome;
+pub use core::ElementMetadata;
+pub use core::FillOptions;
+pub use core::FillResult;
+pub use core::FlatCell;
+pub use core::FlattenOptions;
+pub use core::InstanceInfo;
+pub use core::LayerExpr;
+pub use core::LayerStack;
+pub use core::LayoutPlacement;
+pub use core::LintKind;
+pub use core::LintOptions;
+pub use core::Net;
+pub use core::Netlist;
+pub use core::Placeholder;
 pub use core::Project;
+pub use core::SearchMatch;
+pub use core::SearchPattern;
+pub use core::ShapeInfo;
+pub use generate_cell_usage::generate_cell_usage_csv;
+pub use generate_cell_usage::generate_cell_usage_json;
+pub use generate_cross_section::generate_cross_section_svg;
+pub use generate_density::generate_density_csv;
+pub use generate_density::generate_density_png;
+pub use generate_drc_report::generate_drc_json;
+pub use generate_drc_report::generate_drc_text;
+pub use generate_gallery::gallery_stem;
+pub use generate_gallery::generate_gallery_html;
+pub use generate_gallery::GalleryEntry;
+pub use generate_gallery::GalleryItem;
+pub use generate_gds::generate_extracted_gds;
+pub use generate_gds::generate_fill_gds;
+pub use generate_gds::generate_flat_gds;
+pub use generate_png::generate_png;
+pub use generate_png::generate_project_png;
+pub use generate_png::PngOptions;
+pub use generate_svg::generate_project_svg;
 pub use generate_svg::generate_svg;
+pub use generate_svg::generate_svg_with_options;
+pub use generate_svg::generate_svg_with_placeholders;
+pub use generate_svg::SvgOptions;
+pub use generate_svg::SvgOrientation;
+pub use graphics::BoundingBox;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use generate_gallery::find_layout_files;
 
 #[cfg(target_arch = "wasm32")]
 pub use components::App;
@@ -22,6 +22,12 @@ pub fn rgb_to_hex(r: f32, g: f32, b: f32) -> String {
     )
 }
 
+/// Maps a value in range [0, 1] to a color running from blue through green
+/// and yellow to red.
+pub fn heatmap_color(t: f32) -> (f32, f32, f32) {
+    hsv_to_rgb(0.66 * (1.0 - t.clamp(0.0, 1.0)), 0.9, 0.95)
+}
+
 pub fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (f32, f32, f32) {
     let h = h * 6.0;
     let i = h.floor();
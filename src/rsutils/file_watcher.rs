@@ -0,0 +1,65 @@
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use std::time::SystemTime;
+
+/// Polls the modification time of a file on a background thread and invokes
+/// a callback (on that thread) whenever the file settles after a change.
+///
+/// Polling is used rather than OS notifications because many tools write
+/// their output by replacing the file, which invalidates inode-based watches.
+/// The watcher stops when dropped.
+pub struct FileWatcher {
+    stop: Arc<AtomicBool>,
+}
+
+impl FileWatcher {
+    pub fn new<F>(path: impl Into<PathBuf>, interval: Duration, mut callback: F) -> Self
+    where
+        F: FnMut(&Path) + Send + 'static,
+    {
+        let path = path.into();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_clone = stop.clone();
+
+        thread::spawn(move || {
+            let mut last_seen = file_signature(&path);
+            while !stop_clone.load(Ordering::Relaxed) {
+                thread::sleep(interval);
+                let current = file_signature(&path);
+                if current.is_none() || current == last_seen {
+                    continue;
+                }
+
+                // Wait for the writer to finish before reporting the change.
+                thread::sleep(interval);
+                if file_signature(&path) != current {
+                    continue;
+                }
+
+                last_seen = current;
+                if !stop_clone.load(Ordering::Relaxed) {
+                    callback(&path);
+                }
+            }
+        });
+
+        Self { stop }
+    }
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn file_signature(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
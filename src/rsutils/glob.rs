@@ -0,0 +1,120 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
+/// Matches text against a shell-style pattern.
+///
+/// `?` matches any single character and `*` matches any run of characters
+/// other than `/`, while `**` also matches across `/` and `**/` matches zero
+/// or more directories.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob_match_chars(&pattern, &text)
+}
+
+/// Returns true if the string contains any glob metacharacters.
+pub fn is_glob_pattern(s: &str) -> bool {
+    s.contains(['*', '?'])
+}
+
+fn glob_match_chars(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') => {
+            let crosses_separators = pattern.get(1) == Some(&'*');
+            let rest = if crosses_separators {
+                &pattern[2..]
+            } else {
+                &pattern[1..]
+            };
+            // `**/` also matches no directory at all.
+            if crosses_separators
+                && rest.first() == Some(&'/')
+                && glob_match_chars(&rest[1..], text)
+            {
+                return true;
+            }
+            for i in 0..=text.len() {
+                if glob_match_chars(rest, &text[i..]) {
+                    return true;
+                }
+                if i < text.len() && text[i] == '/' && !crosses_separators {
+                    return false;
+                }
+            }
+            false
+        }
+        Some('?') => !text.is_empty() && glob_match_chars(&pattern[1..], &text[1..]),
+        Some(c) => text.first() == Some(c) && glob_match_chars(&pattern[1..], &text[1..]),
+    }
+}
+
+/// Recursively collects the files below the given directory, sorted by path.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn walk_files(root: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Expands a glob pattern such as `blocks/**/*.gds` into matching file paths.
+/// Walking starts from the longest leading directory without metacharacters.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn glob_files(pattern: &str) -> std::io::Result<Vec<PathBuf>> {
+    let pattern = pattern.replace('\\', "/");
+    let pattern = pattern.strip_prefix("./").unwrap_or(&pattern);
+    let root: Vec<&str> = pattern
+        .split('/')
+        .take_while(|component| !is_glob_pattern(component))
+        .collect();
+    let root = if root.is_empty() {
+        ".".to_string()
+    } else {
+        root.join("/")
+    };
+    let root_path = Path::new(&root);
+    if !root_path.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let matches = walk_files(root_path)?
+        .into_iter()
+        .filter(|path| {
+            let path = path.to_string_lossy().replace('\\', "/");
+            let path = path.strip_prefix("./").unwrap_or(&path);
+            glob_match(pattern, path)
+        })
+        .collect();
+    Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.gds", "chip.gds"));
+        assert!(!glob_match("*.gds", "chip.svg"));
+        assert!(!glob_match("*.gds", "blocks/chip.gds"));
+        assert!(glob_match("blocks/**/*.gds", "blocks/io/pad.gds"));
+        assert!(glob_match("blocks/**/*.gds", "blocks/pad.gds"));
+        assert!(glob_match("nand?_*", "nand2_x1"));
+        assert!(!glob_match("nand?", "nand"));
+    }
+}
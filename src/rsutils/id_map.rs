@@ -56,6 +56,11 @@ impl<K: IdMapKey + Copy + Hash + Eq, V> IdMap<K, V> {
         self.items.insert(id, value);
     }
 
+    /// Removes an item, preserving the order of the remaining items.
+    pub fn remove(&mut self, id: &K) -> Option<V> {
+        self.items.shift_remove(id)
+    }
+
     pub fn clear(&mut self) {
         self.items.clear();
         self.next_id = 1;
@@ -1,13 +1,25 @@
 pub mod colors;
-pub mod string_interner;
+pub mod glob;
 pub mod id_map;
+pub mod json;
+pub mod png;
+pub mod string_interner;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod file_watcher;
 
 #[cfg(target_arch = "wasm32")]
 pub mod resize_observer;
 
 pub use colors::*;
-pub use string_interner::*;
+pub use glob::*;
 pub use id_map::*;
+pub use json::*;
+pub use png::*;
+pub use string_interner::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use file_watcher::*;
 
 #[cfg(target_arch = "wasm32")]
 pub use resize_observer::*;
@@ -0,0 +1,78 @@
+use miniz_oxide::deflate::compress_to_vec_zlib;
+
+/// Deflate level of the image data, from 0 (stored) to 10 (smallest).
+const COMPRESSION_LEVEL: u8 = 6;
+
+/// Encodes 8-bit RGBA pixels as a PNG file.
+pub fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    assert_eq!(rgba.len(), (width * height * 4) as usize);
+
+    let mut png = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[8, 6, 0, 0, 0]); // 8-bit RGBA, no interlacing
+    write_chunk(&mut png, b"IHDR", &header);
+
+    // Each scanline is prefixed with a filter type byte (0 = none).
+    let row_len = (width * 4) as usize;
+    let mut raw = Vec::with_capacity((row_len + 1) * height as usize);
+    for row in rgba.chunks(row_len) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+    write_chunk(
+        &mut png,
+        b"IDAT",
+        &compress_to_vec_zlib(&raw, COMPRESSION_LEVEL),
+    );
+
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+    }
+
+    #[test]
+    fn test_encode_png() {
+        let png = encode_png(2, 1, &[255, 0, 0, 255, 0, 0, 255, 255]);
+        assert_eq!(&png[1..4], b"PNG");
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+
+        // The IDAT chunk follows the signature and the 25 byte IHDR chunk.
+        let idat = &png[33..png.len() - 12];
+        let length = u32::from_be_bytes(idat[..4].try_into().unwrap()) as usize;
+        assert_eq!(&idat[4..8], b"IDAT");
+        let raw = miniz_oxide::inflate::decompress_to_vec_zlib(&idat[8..8 + length]).unwrap();
+        assert_eq!(raw, [0, 255, 0, 0, 255, 0, 0, 255, 255]);
+    }
+}